@@ -0,0 +1,52 @@
+import unittest
+import base_test
+import json
+import urllib
+
+class AssignmentRequestTest(base_test.BaseTest):
+    def setUp(self):
+        super(AssignmentRequestTest, self).setUp()
+        self._org = self.post('/api/orgs', {"name":"approval_org", "url":"https://myorg.com"})
+        self._user = self.post('/api/orgs/%s/principals' % self._org["id"], {"username":"approval_user", "organization_id":self._org["id"]})
+        self._manager = self.post('/api/orgs/%s/principals' % self._org["id"], {"username":"approval_manager", "organization_id":self._org["id"]})
+        self._realm = self.post('/api/realms', {"id":"approval_realm"})
+        self._approver = self.post('/api/orgs/%s/roles' % self._org["id"], {"name":"approver", "organization_id":self._org["id"], "realm_id":self._realm["id"]})
+        self._teller = self.post('/api/orgs/%s/roles' % self._org["id"], {"name":"teller", "organization_id":self._org["id"], "realm_id":self._realm["id"]})
+        self.put('/api/orgs/%s' % self._org["id"], {"name":"approval_org", "url":"https://myorg.com", "approver_role_id":self._approver["id"]})
+        self.put('/api/orgs/%s/roles/%s/principals/%s?max=10&constraints=&expired_at=%s' % (self._org["id"], self._approver["id"], self._manager["id"], urllib.quote('2033-6-17T00:00:00+05:30', safe='')), {})
+
+    def tearDown(self):
+        self.delete('/api/orgs/%s/roles/%s/principals/%s' % (self._org["id"], self._teller["id"], self._user["id"]))
+        self.delete('/api/orgs/%s/roles/%s/principals/%s' % (self._org["id"], self._approver["id"], self._manager["id"]))
+        self.delete('/api/orgs/%s/roles/%s' % (self._org["id"], self._teller["id"]))
+        self.delete('/api/orgs/%s/roles/%s' % (self._org["id"], self._approver["id"]))
+        self.delete('/api/realms/%s' % self._realm["id"])
+        self.delete('/api/orgs/%s/principals/%s' % (self._org["id"], self._user["id"]))
+        self.delete('/api/orgs/%s/principals/%s' % (self._org["id"], self._manager["id"]))
+        self.delete('/api/orgs/%s' % self._org["id"])
+
+    def test_approve(self):
+        request = self.post('/api/orgs/%s/assignment_requests' % self._org["id"], {"principal_id":self._user["id"], "role_id":self._teller["id"], "effective_at": "2019-01-01T00:00:00", "expired_at": "2030-01-01T00:00:00"})
+        self.assertEquals("PENDING", request["status"])
+        requests = self.get('/api/orgs/%s/assignment_requests' % self._org["id"])
+        self.assertEquals(1, len(requests))
+        # assignee cannot approve its own request
+        self._principal = self._user
+        with self.assertRaises(Exception):
+            self.put('/api/orgs/%s/assignment_requests/%s/approve' % (self._org["id"], request["id"]), {})
+        self._principal = self._manager
+        request = self.put('/api/orgs/%s/assignment_requests/%s/approve?comment=ok' % (self._org["id"], request["id"]), {})
+        self.assertEquals("APPROVED", request["status"])
+        self.assertEquals(self._manager["id"], request["reviewed_by"])
+        requests = self.get('/api/orgs/%s/assignment_requests' % self._org["id"])
+        self.assertEquals(0, len(requests))
+
+    def test_reject(self):
+        request = self.post('/api/orgs/%s/assignment_requests' % self._org["id"], {"principal_id":self._user["id"], "role_id":self._teller["id"], "effective_at": "2019-01-01T00:00:00", "expired_at": "2030-01-01T00:00:00"})
+        self._principal = self._manager
+        request = self.put('/api/orgs/%s/assignment_requests/%s/reject?comment=no' % (self._org["id"], request["id"]), {})
+        self.assertEquals("REJECTED", request["status"])
+        self.assertEquals("no", request["review_comment"])
+
+if __name__ == '__main__':
+    unittest.main()
@@ -0,0 +1,38 @@
+import unittest
+import base_test
+import json
+
+class AttributeTest(base_test.BaseTest):
+    def setUp(self):
+        super(AttributeTest, self).setUp()
+        self._org = self.post('/api/orgs', {"name":"attribute_org", "url":"https://myorg.com"})
+        self._group = self.post('/api/orgs/%s/groups' % self._org["id"], {"name":"attribute_group", "organization_id":self._org["id"]})
+        self._user = self.post('/api/orgs/%s/principals' % self._org["id"], {"username":"attribute_user", "organization_id":self._org["id"]})
+
+    def tearDown(self):
+        self.delete('/api/orgs/%s/principals/%s' % (self._org["id"], self._user["id"]))
+        self.delete('/api/orgs/%s/groups/%s' % (self._org["id"], self._group["id"]))
+        self.delete('/api/orgs/%s' % self._org["id"])
+
+    def test_principal_attributes(self):
+        attr = self.put('/api/orgs/%s/principals/%s/attributes/region' % (self._org["id"], self._user["id"]), {"value":"Midwest"})
+        self.assertEquals("Midwest", attr["value"])
+        self.put('/api/orgs/%s/principals/%s/attributes/level' % (self._org["id"], self._user["id"]), {"value":3})
+        attrs = self.get('/api/orgs/%s/principals/%s/attributes' % (self._org["id"], self._user["id"]))
+        self.assertEquals(2, len(attrs))
+        self.assertEquals("level", attrs[0]["name"])
+        self.assertEquals(3, attrs[0]["value"])
+        resp = self.delete('/api/orgs/%s/principals/%s/attributes/level' % (self._org["id"], self._user["id"]))
+        self.assertEquals(1, resp, json.dumps(resp))
+        self.delete('/api/orgs/%s/principals/%s/attributes/region' % (self._org["id"], self._user["id"]))
+
+    def test_group_org_attributes(self):
+        self.put('/api/orgs/%s/groups/%s/attributes/clearance' % (self._org["id"], self._group["id"]), {"value":True})
+        self.put('/api/orgs/%s/attributes/region' % self._org["id"], {"value":"Midwest"})
+        self.assertEquals(True, self.get('/api/orgs/%s/groups/%s/attributes' % (self._org["id"], self._group["id"]))[0]["value"])
+        self.assertEquals("Midwest", self.get('/api/orgs/%s/attributes' % self._org["id"])[0]["value"])
+        self.delete('/api/orgs/%s/groups/%s/attributes/clearance' % (self._org["id"], self._group["id"]))
+        self.delete('/api/orgs/%s/attributes/region' % self._org["id"])
+
+if __name__ == '__main__':
+    unittest.main()
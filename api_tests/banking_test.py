@@ -157,6 +157,55 @@ class BankingTest(base_test.BaseTest):
         resp = self.get('/api/security?resource=GeneralLedger&action=DELETE')
         self.assertEquals("Allow", resp)
 
+    def test_explain_ali_accountant_may_not_read_deposit_account(self):
+        self._principal = self._ali
+        trace = self.get('/api/security/explain?resource=DepositAccount&action=READ')
+        self.assertEquals("Deny", trace["decision"])
+        self.assertTrue(trace["license_matched"])
+        self.assertTrue("accountant" in [role["name"] for role in trace["roles"]])
+
+    def test_explain_tom_teller_may_read_deposit_account(self):
+        self._principal = self._tom
+        trace = self.get('/api/security/explain?resource=DepositAccount&action=READ')
+        self.assertEquals("Allow", trace["decision"])
+        self.assertTrue("Matched" in [claim["outcome"] for claim in trace["claims"]])
+
+    def test_batch_check_for_tom_teller(self):
+        self._principal = self._tom
+        resp = self.post('/api/security/batch', [
+            {"action":"READ", "resource":"DepositAccount"},
+            {"action":"DELETE", "resource":"DepositAccount"},
+            {"action":"READ", "resource":"GeneralLedger"}])
+        self.assertEquals(["Allow", "Deny", "Deny"], [item["decision"] for item in resp])
+
+    def test_json_check_for_tom_teller(self):
+        self._principal = self._tom
+        resp = self.post('/api/security/check', {"action":"READ", "resource":"DepositAccount", "context":{"amount":250, "regions":["Midwest"], "account":{"type":"savings"}}})
+        self.assertEquals("Allow", resp)
+        try:
+            resp = self.post('/api/security/check', {"action":"DELETE", "resource":"DepositAccount"})
+            self.assertTrue(False)
+        except Exception as e:
+            None
+
+    def test_cassy_csr_permissions(self):
+        permissions = self.get('/api/orgs/%s/principals/%s/permissions?realm=%s' % (self._org["id"], self._cassy["id"], self._realm["id"]))
+        sources = [claim["source"] for rp in permissions for claim in rp["claims"]]
+        self.assertTrue("via role csr" in sources)
+        self.assertTrue("via role csr inherited from parent teller" in sources)
+
+    def test_diff_tom_teller_and_cassy_csr(self):
+        diff = self.get('/api/orgs/%s/principals/%s/diff/%s?realm=%s' % (self._org["id"], self._tom["id"], self._cassy["id"], self._realm["id"]))
+        self.assertEquals([], diff["only_in_a"])
+        self.assertEquals(["(CREATE|DELETE)"], [t["action"] for t in diff["only_in_b"]])
+        self.assertEquals(["(READ|UPDATE)"], [t["action"] for t in diff["common"]])
+        diff = self.get('/api/orgs/%s/roles/%s/diff/%s?realm=%s' % (self._org["id"], self._csr["id"], self._teller["id"], self._realm["id"]))
+        self.assertEquals(["(CREATE|DELETE)"], [t["action"] for t in diff["only_in_a"]])
+
+    def test_who_can_delete_deposit_account(self):
+        access = self.get('/api/realms/%s/resources/%s/access?action=DELETE' % (self._realm["id"], self._deposit_account["id"]))
+        self.assertEquals([self._cassy["id"]], [a["principal_id"] for a in access])
+
 if __name__ == '__main__':
     unittest.main()
 
@@ -0,0 +1,31 @@
+import unittest
+import base_test
+import json
+import datetime
+
+class BreakGlassTest(base_test.BaseTest):
+    def setUp(self):
+        super(BreakGlassTest, self).setUp()
+        self._org = self.post('/api/orgs', {"name":"break_glass_org", "url":"https://myorg.com"})
+        self._user = self.post('/api/orgs/%s/principals' % self._org["id"], {"username":"oncall", "organization_id":self._org["id"]})
+        self._realm = self.post('/api/realms', {"id":"break_glass_realm"})
+        self._role = self.post('/api/orgs/%s/roles' % self._org["id"], {"name":"emergency_admin", "organization_id":self._org["id"], "realm_id":self._realm["id"], "break_glass":True})
+        self._principal = self._user
+
+    def tearDown(self):
+        self.delete('/api/orgs/%s/roles/%s/principals/%s' % (self._org["id"], self._role["id"], self._user["id"]))
+        self.delete('/api/orgs/%s/roles/%s' % (self._org["id"], self._role["id"]))
+        self.delete('/api/realms/%s' % self._realm["id"])
+        self.delete('/api/orgs/%s/principals/%s' % (self._org["id"], self._user["id"]))
+        self.delete('/api/orgs/%s' % self._org["id"])
+
+    def test_break_glass(self):
+        expired_at = (datetime.datetime.utcnow() + datetime.timedelta(hours=1)).strftime('%Y-%m-%dT%H:%M:%S')
+        # justification is mandatory
+        with self.assertRaises(Exception):
+            self.post('/api/security/break-glass', {"role_id":self._role["id"], "justification":"", "expired_at":expired_at})
+        access = self.post('/api/security/break-glass', {"role_id":self._role["id"], "justification":"database outage", "expired_at":expired_at})
+        self.assertEquals(self._user["id"], access["principal_id"], json.dumps(access))
+
+if __name__ == '__main__':
+    unittest.main()
@@ -0,0 +1,37 @@
+import unittest
+import base_test
+import json
+
+class DelegationTest(base_test.BaseTest):
+    def setUp(self):
+        super(DelegationTest, self).setUp()
+        self._org = self.post('/api/orgs', {"name":"delegation_org", "url":"https://myorg.com"})
+        self._delegator = self.post('/api/orgs/%s/principals' % self._org["id"], {"username":"delegator", "organization_id":self._org["id"]})
+        self._delegatee = self.post('/api/orgs/%s/principals' % self._org["id"], {"username":"delegatee", "organization_id":self._org["id"]})
+        self._realm = self.post('/api/realms', {"id":"delegation_realm"})
+        self._role = self.post('/api/orgs/%s/roles' % self._org["id"], {"name":"approver", "organization_id":self._org["id"], "realm_id":self._realm["id"]})
+        # delegations are created by the delegator itself
+        self._principal = self._delegator
+
+    def tearDown(self):
+        self.delete('/api/orgs/%s/delegations/%s' % (self._org["id"], self._delegation["id"]))
+        self.delete('/api/orgs/%s/roles/%s' % (self._org["id"], self._role["id"]))
+        self.delete('/api/realms/%s' % self._realm["id"])
+        self.delete('/api/orgs/%s/principals/%s' % (self._org["id"], self._delegator["id"]))
+        self.delete('/api/orgs/%s/principals/%s' % (self._org["id"], self._delegatee["id"]))
+        self.delete('/api/orgs/%s' % self._org["id"])
+
+    def test_create(self):
+        self._delegation = self.post('/api/orgs/%s/delegations' % self._org["id"], {"organization_id":self._org["id"], "delegator_id":self._delegator["id"], "delegatee_id":self._delegatee["id"], "role_ids":[self._role["id"]], "effective_at": "2019-01-01T00:00:00", "expired_at": "2030-01-01T00:00:00"})
+        self.assertEquals([self._role["id"]], self._delegation["role_ids"])
+        #
+        delegations = self.get('/api/orgs/%s/delegations' % self._org["id"])
+        self.assertEquals(1, len(delegations))
+
+    def test_update(self):
+        self._delegation = self.post('/api/orgs/%s/delegations' % self._org["id"], {"organization_id":self._org["id"], "delegator_id":self._delegator["id"], "delegatee_id":self._delegatee["id"], "role_ids":[self._role["id"]], "effective_at": "2019-01-01T00:00:00", "expired_at": "2030-01-01T00:00:00"})
+        delegation = self.put('/api/orgs/%s/delegations/%s' % (self._org["id"], self._delegation["id"]), {"organization_id":self._org["id"], "delegator_id":self._delegator["id"], "delegatee_id":self._delegatee["id"], "role_ids":[self._role["id"]], "constraints": "amount < 1000", "effective_at": "2019-01-01T00:00:00", "expired_at": "2030-01-01T00:00:00"})
+        self.assertEquals("amount < 1000", delegation["constraints"])
+
+if __name__ == '__main__':
+    unittest.main()
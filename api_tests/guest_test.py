@@ -0,0 +1,36 @@
+import unittest
+import base_test
+import json
+
+class GuestTest(base_test.BaseTest):
+    def setUp(self):
+        super(GuestTest, self).setUp()
+        self._vendor = self.post('/api/orgs', {"name":"guest_vendor_org", "url":"https://vendor.com"})
+        self._customer = self.post('/api/orgs', {"name":"guest_customer_org", "url":"https://customer.com"})
+        self._consultant = self.post('/api/orgs/%s/principals' % self._vendor["id"], {"username":"consultant", "organization_id":self._vendor["id"]})
+
+    def tearDown(self):
+        self.delete('/api/orgs/%s/principals/%s' % (self._vendor["id"], self._consultant["id"]))
+        self.delete('/api/orgs/%s' % self._customer["id"])
+        self.delete('/api/orgs/%s' % self._vendor["id"])
+
+    def test_create_delete(self):
+        guest = self.post('/api/orgs/%s/guests' % self._customer["id"], {"principal_id":self._consultant["id"], "description":"audit engagement", "effective_at": "2019-01-01T00:00:00", "expired_at": "2030-01-01T00:00:00"})
+        self.assertEquals(self._customer["id"], guest["organization_id"])
+        self.assertEquals(self._consultant["id"], guest["principal_id"])
+        loaded = self.get('/api/orgs/%s/guests/%s' % (self._customer["id"], guest["id"]))
+        self.assertEquals(guest["id"], loaded["id"])
+        #
+        guests = self.get('/api/orgs/%s/guests' % self._customer["id"])
+        self.assertEquals(1, len(guests))
+        resp = self.delete('/api/orgs/%s/guests/%s' % (self._customer["id"], guest["id"]))
+        self.assertEquals(1, resp, json.dumps(resp))
+        guests = self.get('/api/orgs/%s/guests' % self._customer["id"])
+        self.assertEquals(0, len(guests))
+
+    def test_home_org(self):
+        with self.assertRaises(Exception):
+            self.post('/api/orgs/%s/guests' % self._vendor["id"], {"principal_id":self._consultant["id"], "effective_at": "2019-01-01T00:00:00", "expired_at": "2030-01-01T00:00:00"})
+
+if __name__ == '__main__':
+    unittest.main()
@@ -35,6 +35,12 @@ class OrgTest(base_test.BaseTest):
         self.assertEquals("my_org", org["name"])
         self.assertEquals("https://myorg.com", org["url"])
         self.assertEquals("my desc", org["description"])
+    def test_org_chain(self):
+        self._org = self.post('/api/orgs', {"name":"my_holding", "url":"https://myorg.com"})
+        sub = self.post('/api/orgs', {"name":"my_subsidiary", "url":"https://myorg.com", "parent_id": self._org["id"]})
+        org = self.get('/api/orgs/%s' % sub["id"])
+        self.assertEquals([sub["id"], self._org["id"]], org["org_chain"])
+        self.delete('/api/orgs/%s' % sub["id"])
 
 if __name__ == '__main__':
     unittest.main()
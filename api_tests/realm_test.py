@@ -32,5 +32,10 @@ class RealmTest(base_test.BaseTest):
         json_resp = self.get('/api/realms/my_realm')
         self.assertEquals('{"id": "my_realm", "description": "my desc"}', json.dumps(json_resp))
 
+    def test_update_scope_matching(self):
+        json_resp = self.post('/api/realms', {"id":"my_realm"})
+        json_resp = self.put('/api/realms/my_realm', {"id":"my_realm", "scope_matching": "Hierarchical"})
+        self.assertEquals('{"id": "my_realm", "scope_matching": "Hierarchical"}', json.dumps(json_resp))
+
 if __name__ == '__main__':
     unittest.main()
@@ -49,5 +49,14 @@ class ResourceInstanceTest(base_test.BaseTest):
         self.assertEquals("scope", instance["scope"])
         self.assertEquals("COMPLETED", instance["status"])
 
+    def test_attributes(self):
+        self._instance = self.post('/api/realms/%s/resources/%s/instances' % (self._realm["id"], self._resource["id"]), {"scope":"scope", "license_policy_id":self._license["id"], "ref_id": "my-ref", "status": "PENDING"})
+        attr = self.put('/api/realms/%s/resources/%s/instances/%s/attributes/amount' % (self._realm["id"], self._resource["id"], self._instance["id"]), {"value":250})
+        self.assertEquals(250, attr["value"])
+        attrs = self.get('/api/realms/%s/resources/%s/instances/%s/attributes' % (self._realm["id"], self._resource["id"], self._instance["id"]))
+        self.assertEquals(1, len(attrs), json.dumps(attrs))
+        resp = self.delete('/api/realms/%s/resources/%s/instances/%s/attributes/amount' % (self._realm["id"], self._resource["id"], self._instance["id"]))
+        self.assertEquals(1, resp, json.dumps(resp))
+
 if __name__ == '__main__':
     unittest.main()
@@ -45,5 +45,15 @@ class RoleTest(base_test.BaseTest):
         self.assertEquals("my_role", role["name"])
         self.assertEquals("my desc", role["description"])
 
+    def test_max_assignees(self):
+        self._role = self.post('/api/orgs/%s/roles' % self._org["id"], {"name":"owner_role", "organization_id":self._org["id"], "realm_id":self._realm["id"], "max_assignees": 1})
+        self.assertEquals(1, self._role["max_assignees"])
+        #
+        self._rolep = self.post('/api/orgs/%s/roles' % self._org["id"], {"name":"child_role", "organization_id":self._org["id"], "realm_id":self._realm["id"], "parent_id": self._role["id"]})
+        self.assertEquals(None, self._rolep["max_assignees"])
+        #
+        role = self.put('/api/orgs/%s/roles/%s' % (self._org["id"], self._role["id"]), {"name":"owner_role", "organization_id":self._org["id"], "realm_id":self._realm["id"], "max_assignees": 2})
+        self.assertEquals(2, role["max_assignees"])
+
 if __name__ == '__main__':
     unittest.main()
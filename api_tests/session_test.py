@@ -0,0 +1,40 @@
+import unittest
+import base_test
+import json
+import urllib
+
+class SessionTest(base_test.BaseTest):
+    def setUp(self):
+        super(SessionTest, self).setUp()
+        self._org = self.post('/api/orgs', {"name":"session_org", "url":"https://myorg.com"})
+        self._user = self.post('/api/orgs/%s/principals' % self._org["id"], {"username":"session_user", "organization_id":self._org["id"]})
+        self._realm = self.post('/api/realms', {"id":"session_realm"})
+        self._admin = self.post('/api/orgs/%s/roles' % self._org["id"], {"name":"admin", "organization_id":self._org["id"], "realm_id":self._realm["id"]})
+        self._auditor = self.post('/api/orgs/%s/roles' % self._org["id"], {"name":"auditor", "organization_id":self._org["id"], "realm_id":self._realm["id"]})
+        self.put('/api/orgs/%s/roles/%s/principals/%s?max=10&constraints=&expired_at=%s' % (self._org["id"], self._admin["id"], self._user["id"], urllib.quote('2033-6-17T00:00:00+05:30', safe='')), {})
+        self._principal = self._user
+
+    def tearDown(self):
+        self.delete('/api/orgs/%s/roles/%s/principals/%s' % (self._org["id"], self._admin["id"], self._user["id"]))
+        self.delete('/api/orgs/%s/roles/%s' % (self._org["id"], self._admin["id"]))
+        self.delete('/api/orgs/%s/roles/%s' % (self._org["id"], self._auditor["id"]))
+        self.delete('/api/realms/%s' % self._realm["id"])
+        self.delete('/api/orgs/%s/principals/%s' % (self._org["id"], self._user["id"]))
+        self.delete('/api/orgs/%s' % self._org["id"])
+
+    def test_open_close(self):
+        session = self.post('/api/security/sessions', {"role_ids":[self._admin["id"]], "expired_at": "2030-01-01T00:00:00"})
+        self.assertEquals([self._admin["id"]], session["role_ids"])
+        self.assertEquals(self._user["id"], session["principal_id"])
+        loaded = self.get('/api/security/sessions/%s' % session["id"])
+        self.assertEquals(session["id"], loaded["id"])
+        # auditor role is not assigned to the principal
+        with self.assertRaises(Exception):
+            self.put('/api/security/sessions/%s/roles/%s' % (session["id"], self._auditor["id"]), {})
+        session = self.delete('/api/security/sessions/%s/roles/%s' % (session["id"], self._admin["id"]))
+        self.assertEquals([], session["role_ids"])
+        resp = self.delete('/api/security/sessions/%s' % session["id"])
+        self.assertEquals(1, resp, json.dumps(resp))
+
+if __name__ == '__main__':
+    unittest.main()
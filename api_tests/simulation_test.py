@@ -0,0 +1,42 @@
+import unittest
+import base_test
+import json
+
+class SimulationTest(base_test.BaseTest):
+    def setUp(self):
+        super(SimulationTest, self).setUp()
+        self._org = self.post('/api/orgs', {"name":"simulation_org", "url":"https://myorg.com"})
+        self._user = self.post('/api/orgs/%s/principals' % self._org["id"], {"username":"simulation_user", "organization_id":self._org["id"]})
+        self._admin = self._principal
+        self._realm = self.post('/api/realms', {"id":"simulation_realm"})
+        self._role = self.post('/api/orgs/%s/roles' % self._org["id"], {"name":"teller", "organization_id":self._org["id"], "realm_id":self._realm["id"]})
+        self._resource = self.post('/api/realms/%s/resources' % self._realm["id"], {"resource_name":"DepositAccount", "realm_id":self._realm["id"]})
+        self._claim = self.post('/api/realms/%s/resources/%s/claims' % (self._realm["id"], self._resource["id"]), {"action":"(READ|UPDATE)", "realm_id":self._realm["id"]})
+        self.put('/api/realms/%s/resources/%s/claims/%s/roles/%s' % (self._realm["id"], self._resource["id"], self._claim["id"], self._role["id"]), {})
+
+    def tearDown(self):
+        self.delete('/api/realms/%s/resources/%s/claims/%s/roles/%s' % (self._realm["id"], self._resource["id"], self._claim["id"], self._role["id"]))
+        self.delete('/api/realms/%s/resources/%s/claims/%s' % (self._realm["id"], self._resource["id"], self._claim["id"]))
+        self.delete('/api/realms/%s/resources/%s' % (self._realm["id"], self._resource["id"]))
+        self.delete('/api/orgs/%s/roles/%s' % (self._org["id"], self._role["id"]))
+        self.delete('/api/realms/%s' % self._realm["id"])
+        self._principal = self._admin
+        self.delete('/api/orgs/%s/principals/%s' % (self._org["id"], self._user["id"]))
+        self.delete('/api/orgs/%s' % self._org["id"])
+
+    def test_simulate(self):
+        self._principal = self._user
+        mutations = [{"type":"AddPrincipalToRole", "principal_id":self._user["id"], "role_id":self._role["id"]}, {"type":"ChangeClaimAction", "claim_id":self._claim["id"], "action":"READ"}]
+        requests = [{"action":"READ", "resource":"DepositAccount"}, {"action":"UPDATE", "resource":"DepositAccount"}]
+        results = self.post('/api/security/simulate', {"mutations":mutations, "requests":requests})
+        self.assertEquals(2, len(results), json.dumps(results))
+        self.assertEquals("Deny", results[0]["before"])
+        self.assertEquals("Allow", results[0]["after"])
+        self.assertTrue(results[0]["changed"])
+        self.assertEquals("Deny", results[1]["after"])
+        # mutations are not saved
+        results = self.post('/api/security/simulate', {"requests":requests})
+        self.assertEquals("Deny", results[0]["after"])
+
+if __name__ == '__main__':
+    unittest.main()
@@ -0,0 +1,38 @@
+import unittest
+import base_test
+import json
+
+class SodRuleTest(base_test.BaseTest):
+    def setUp(self):
+        super(SodRuleTest, self).setUp()
+        self._org = self.post('/api/orgs', {"name":"sod_org", "url":"https://myorg.com"})
+        self._principal = self.post('/api/orgs/%s/principals' % self._org["id"], {"username":"sod_principal", "organization_id":self._org["id"]})
+        self._realm = self.post('/api/realms', {"id":"sod_realm"})
+        self._teller = self.post('/api/orgs/%s/roles' % self._org["id"], {"name":"teller", "organization_id":self._org["id"], "realm_id":self._realm["id"]})
+        self._manager = self.post('/api/orgs/%s/roles' % self._org["id"], {"name":"accountant_manager", "organization_id":self._org["id"], "realm_id":self._realm["id"]})
+
+    def tearDown(self):
+        self.delete('/api/orgs/%s/roles/%s/principals/%s' % (self._org["id"], self._teller["id"], self._principal["id"]))
+        self.delete('/api/orgs/%s/sod_rules/%s' % (self._org["id"], self._rule["id"]))
+        self.delete('/api/orgs/%s/roles/%s' % (self._org["id"], self._teller["id"]))
+        self.delete('/api/orgs/%s/roles/%s' % (self._org["id"], self._manager["id"]))
+        self.delete('/api/realms/%s' % self._realm["id"])
+        self.delete('/api/orgs/%s/principals/%s' % (self._org["id"], self._principal["id"]))
+        self.delete('/api/orgs/%s' % self._org["id"])
+
+    def test_create(self):
+        self._rule = self.post('/api/orgs/%s/sod_rules' % self._org["id"], {"name":"teller_manager", "realm_id":self._realm["id"], "organization_id":self._org["id"], "role_ids":[self._teller["id"], self._manager["id"]], "cardinality":2, "dynamic":False})
+        self.assertEquals("teller_manager", self._rule["name"])
+        #
+        rules = self.get('/api/orgs/%s/sod_rules' % self._org["id"])
+        self.assertEquals(1, len(rules))
+
+    def test_violation(self):
+        self._rule = self.post('/api/orgs/%s/sod_rules' % self._org["id"], {"name":"teller_manager", "realm_id":self._realm["id"], "organization_id":self._org["id"], "role_ids":[self._teller["id"], self._manager["id"]], "cardinality":2, "dynamic":False})
+        resp = self.put('/api/orgs/%s/roles/%s/principals/%s' % (self._org["id"], self._teller["id"], self._principal["id"]), {})
+        self.assertEquals(1, resp, json.dumps(resp))
+        with self.assertRaises(Exception):
+            self.put('/api/orgs/%s/roles/%s/principals/%s' % (self._org["id"], self._manager["id"], self._principal["id"]), {})
+
+if __name__ == '__main__':
+    unittest.main()
@@ -0,0 +1,35 @@
+CREATE TABLE IF NOT EXISTS new_rbac_realms (
+  id VARCHAR(100) NOT NULL PRIMARY KEY,
+  description TEXT,
+  created_by VARCHAR(36),
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  updated_by VARCHAR(36),
+  updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO new_rbac_realms SELECT id, description, created_by, created_at, updated_by, updated_at FROM rbac_realms;
+DROP TABLE rbac_realms;
+ALTER TABLE new_rbac_realms RENAME TO rbac_realms;
+
+CREATE TABLE IF NOT EXISTS new_rbac_claims (
+  id VARCHAR(36) NOT NULL PRIMARY KEY,
+  realm_id VARCHAR(100) NOT NULL,
+  resource_id VARCHAR(36) NOT NULL,
+  action VARCHAR(100) NOT NULL,
+  effect VARCHAR(50) DEFAULT 'Allow',
+  description TEXT,
+  created_by VARCHAR(36),
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  updated_by VARCHAR(36),
+  updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT rbac_claims_claim_fk FOREIGN KEY (realm_id)
+        REFERENCES rbac_realms(id),
+  CONSTRAINT rbac_claims_resource_fk FOREIGN KEY (resource_id)
+        REFERENCES rbac_resources(id)
+);
+
+INSERT INTO new_rbac_claims SELECT id, realm_id, resource_id, action, effect, description, created_by, created_at, updated_by, updated_at FROM rbac_claims;
+DROP TABLE rbac_claims;
+ALTER TABLE new_rbac_claims RENAME TO rbac_claims;
+
+CREATE UNIQUE INDEX IF NOT EXISTS rbac_claims_resource_ndx ON rbac_claims(realm_id, resource_id, action);
@@ -0,0 +1,2 @@
+ALTER TABLE rbac_realms ADD COLUMN combining_algorithm VARCHAR(50);
+ALTER TABLE rbac_claims ADD COLUMN priority INTEGER;
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS rbac_sod_rules_org_ndx;
+DROP TABLE IF EXISTS rbac_sod_rules;
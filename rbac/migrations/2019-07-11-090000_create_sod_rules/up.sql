@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS rbac_sod_rules (
+  id VARCHAR(36) NOT NULL PRIMARY KEY,
+  realm_id VARCHAR(36) NOT NULL,
+  organization_id VARCHAR(36) NOT NULL,
+  name VARCHAR(100) NOT NULL,
+  description TEXT,
+  role_ids TEXT NOT NULL,
+  cardinality INTEGER NOT NULL,
+  dynamic BOOLEAN NOT NULL DEFAULT 0,
+  created_by VARCHAR(36),
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  updated_by VARCHAR(36),
+  updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT rbac_sod_rules_org_fk FOREIGN KEY (organization_id)
+        REFERENCES rbac_organizations(id)
+);
+
+CREATE INDEX IF NOT EXISTS rbac_sod_rules_org_ndx ON rbac_sod_rules(organization_id);
@@ -0,0 +1,25 @@
+CREATE TABLE IF NOT EXISTS new_rbac_roles (
+  id VARCHAR(36) NOT NULL PRIMARY KEY,
+  parent_id VARCHAR(36),
+  realm_id VARCHAR(100) NOT NULL,
+  organization_id VARCHAR(36) NOT NULL,
+  name VARCHAR(150) NOT NULL,
+  description TEXT,
+  created_by VARCHAR(36),
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  updated_by VARCHAR(36),
+  updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT rbac_role_parent_fk FOREIGN KEY (parent_id)
+        REFERENCES rbac_roles(id),
+  CONSTRAINT rbac_role_realm_fk FOREIGN KEY (realm_id)
+        REFERENCES rbac_realms(id),
+  CONSTRAINT rbac_role_org_fk FOREIGN KEY (organization_id)
+        REFERENCES rbac_organizations(id)
+);
+
+INSERT INTO new_rbac_roles SELECT id, parent_id, realm_id, organization_id, name, description, created_by, created_at, updated_by, updated_at FROM rbac_roles;
+DROP TABLE rbac_roles;
+ALTER TABLE new_rbac_roles RENAME TO rbac_roles;
+
+CREATE UNIQUE INDEX IF NOT EXISTS rbac_roles_name_ndx ON rbac_roles(name, realm_id, organization_id);
+CREATE INDEX IF NOT EXISTS rbac_roles_parent_ndx ON rbac_roles(parent_id, realm_id, organization_id);
@@ -0,0 +1 @@
+ALTER TABLE rbac_roles ADD COLUMN max_assignees INTEGER;
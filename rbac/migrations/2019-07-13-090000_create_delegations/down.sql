@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS rbac_delegations_delegator_ndx;
+DROP INDEX IF EXISTS rbac_delegations_delegatee_ndx;
+DROP TABLE IF EXISTS rbac_delegations;
@@ -0,0 +1,24 @@
+CREATE TABLE IF NOT EXISTS rbac_delegations (
+  id VARCHAR(36) NOT NULL PRIMARY KEY,
+  organization_id VARCHAR(36) NOT NULL,
+  delegator_id VARCHAR(36) NOT NULL,
+  delegatee_id VARCHAR(36) NOT NULL,
+  role_ids TEXT,
+  claim_ids TEXT,
+  delegation_constraints TEXT,
+  effective_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  expired_at TIMESTAMP NOT NULL,
+  created_by VARCHAR(36),
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  updated_by VARCHAR(36),
+  updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT rbac_delegations_org_fk FOREIGN KEY (organization_id)
+        REFERENCES rbac_organizations(id),
+  CONSTRAINT rbac_delegations_delegator_fk FOREIGN KEY (delegator_id)
+        REFERENCES rbac_principals(id),
+  CONSTRAINT rbac_delegations_delegatee_fk FOREIGN KEY (delegatee_id)
+        REFERENCES rbac_principals(id)
+);
+
+CREATE INDEX IF NOT EXISTS rbac_delegations_delegator_ndx ON rbac_delegations(delegator_id);
+CREATE INDEX IF NOT EXISTS rbac_delegations_delegatee_ndx ON rbac_delegations(delegatee_id);
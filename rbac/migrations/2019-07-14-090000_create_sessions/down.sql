@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS rbac_sessions_principal_ndx;
+DROP TABLE IF EXISTS rbac_sessions;
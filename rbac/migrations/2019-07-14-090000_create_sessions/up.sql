@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS rbac_sessions (
+  id VARCHAR(36) NOT NULL PRIMARY KEY,
+  realm_id VARCHAR(100) NOT NULL,
+  organization_id VARCHAR(36) NOT NULL,
+  principal_id VARCHAR(36) NOT NULL,
+  role_ids TEXT,
+  expired_at TIMESTAMP NOT NULL,
+  created_by VARCHAR(36),
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  updated_by VARCHAR(36),
+  updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT rbac_sessions_realm_fk FOREIGN KEY (realm_id)
+        REFERENCES rbac_realms(id),
+  CONSTRAINT rbac_sessions_org_fk FOREIGN KEY (organization_id)
+        REFERENCES rbac_organizations(id),
+  CONSTRAINT rbac_sessions_principal_fk FOREIGN KEY (principal_id)
+        REFERENCES rbac_principals(id)
+);
+
+CREATE INDEX IF NOT EXISTS rbac_sessions_principal_ndx ON rbac_sessions(principal_id);
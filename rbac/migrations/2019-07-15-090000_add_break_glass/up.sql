@@ -0,0 +1,2 @@
+ALTER TABLE rbac_roles ADD COLUMN break_glass BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE rbac_audit_records ADD COLUMN severity VARCHAR(20);
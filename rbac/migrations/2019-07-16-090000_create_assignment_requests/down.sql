@@ -0,0 +1,23 @@
+DROP INDEX IF EXISTS rbac_assignment_requests_org_status_ndx;
+DROP TABLE IF EXISTS rbac_assignment_requests;
+
+CREATE TABLE IF NOT EXISTS new_rbac_organizations (
+  id VARCHAR(36) NOT NULL PRIMARY KEY,
+  parent_id VARCHAR(36),
+  name VARCHAR(150) NOT NULL,
+  url VARCHAR(200) NOT NULL,
+  description TEXT,
+  created_by VARCHAR(36),
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  updated_by VARCHAR(36),
+  updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT rbac_organizations_parent_fk FOREIGN KEY (parent_id)
+        REFERENCES rbac_organizations(id)
+);
+
+INSERT INTO new_rbac_organizations SELECT id, parent_id, name, url, description, created_by, created_at, updated_by, updated_at FROM rbac_organizations;
+DROP TABLE rbac_organizations;
+ALTER TABLE new_rbac_organizations RENAME TO rbac_organizations;
+
+CREATE UNIQUE INDEX IF NOT EXISTS rbac_organizations_name_ndx ON rbac_organizations(name);
+CREATE INDEX IF NOT EXISTS rbac_organizations_parent_ndx ON rbac_organizations(parent_id);
@@ -0,0 +1,29 @@
+CREATE TABLE IF NOT EXISTS rbac_assignment_requests (
+  id VARCHAR(36) NOT NULL PRIMARY KEY,
+  organization_id VARCHAR(36) NOT NULL,
+  principal_id VARCHAR(36) NOT NULL,
+  role_id VARCHAR(36),
+  claim_id VARCHAR(36),
+  scope VARCHAR(100) NOT NULL DEFAULT '',
+  assignment_constraints TEXT,
+  effective_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  expired_at TIMESTAMP NOT NULL,
+  approver_role_id VARCHAR(36) NOT NULL,
+  status VARCHAR(20) NOT NULL,
+  reviewed_by VARCHAR(36),
+  review_comment TEXT,
+  created_by VARCHAR(36),
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  updated_by VARCHAR(36),
+  updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT rbac_assignment_requests_org_fk FOREIGN KEY (organization_id)
+        REFERENCES rbac_organizations(id),
+  CONSTRAINT rbac_assignment_requests_principal_fk FOREIGN KEY (principal_id)
+        REFERENCES rbac_principals(id),
+  CONSTRAINT rbac_assignment_requests_approver_fk FOREIGN KEY (approver_role_id)
+        REFERENCES rbac_roles(id)
+);
+
+CREATE INDEX IF NOT EXISTS rbac_assignment_requests_org_status_ndx ON rbac_assignment_requests(organization_id, status);
+
+ALTER TABLE rbac_organizations ADD COLUMN approver_role_id VARCHAR(36);
@@ -0,0 +1,33 @@
+CREATE TABLE IF NOT EXISTS new_rbac_resources (
+  id VARCHAR(36) NOT NULL PRIMARY KEY,
+  realm_id VARCHAR(100) NOT NULL,
+  resource_name VARCHAR(50) NOT NULL,
+  description TEXT,
+  allowable_actions TEXT,
+  created_by VARCHAR(36),
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  updated_by VARCHAR(36),
+  updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT rbac_resources_realm_fk FOREIGN KEY (realm_id)
+        REFERENCES rbac_realms(id)
+);
+
+INSERT INTO new_rbac_resources SELECT id, realm_id, resource_name, description, allowable_actions, created_by, created_at, updated_by, updated_at FROM rbac_resources;
+DROP TABLE rbac_resources;
+ALTER TABLE new_rbac_resources RENAME TO rbac_resources;
+
+CREATE UNIQUE INDEX IF NOT EXISTS rbac_resources_type_ndx ON rbac_resources(realm_id, resource_name);
+
+CREATE TABLE IF NOT EXISTS new_rbac_realms (
+  id VARCHAR(100) NOT NULL PRIMARY KEY,
+  description TEXT,
+  created_by VARCHAR(36),
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  updated_by VARCHAR(36),
+  updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  combining_algorithm VARCHAR(50)
+);
+
+INSERT INTO new_rbac_realms SELECT id, description, created_by, created_at, updated_by, updated_at, combining_algorithm FROM rbac_realms;
+DROP TABLE rbac_realms;
+ALTER TABLE new_rbac_realms RENAME TO rbac_realms;
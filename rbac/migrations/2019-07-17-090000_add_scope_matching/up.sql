@@ -0,0 +1,2 @@
+ALTER TABLE rbac_realms ADD COLUMN scope_matching VARCHAR(50);
+ALTER TABLE rbac_resources ADD COLUMN scope_matching VARCHAR(50);
@@ -0,0 +1,3 @@
+-- parent can have multiple subsidiaries so the index is not made unique again
+DROP INDEX IF EXISTS rbac_organizations_parent_ndx;
+CREATE INDEX IF NOT EXISTS rbac_organizations_parent_ndx ON rbac_organizations(parent_id);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS rbac_organizations_parent_ndx;
+CREATE INDEX IF NOT EXISTS rbac_organizations_parent_ndx ON rbac_organizations(parent_id);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS rbac_guest_memberships_principal_ndx;
+DROP INDEX IF EXISTS rbac_guest_memberships_org_principal_ndx;
+DROP TABLE IF EXISTS rbac_guest_memberships;
@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS rbac_guest_memberships (
+  id VARCHAR(36) NOT NULL PRIMARY KEY,
+  organization_id VARCHAR(36) NOT NULL,
+  principal_id VARCHAR(36) NOT NULL,
+  description TEXT,
+  effective_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  expired_at TIMESTAMP NOT NULL,
+  created_by VARCHAR(36),
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  updated_by VARCHAR(36),
+  updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT rbac_guest_memberships_org_fk FOREIGN KEY (organization_id)
+        REFERENCES rbac_organizations(id),
+  CONSTRAINT rbac_guest_memberships_principal_fk FOREIGN KEY (principal_id)
+        REFERENCES rbac_principals(id)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS rbac_guest_memberships_org_principal_ndx ON rbac_guest_memberships(organization_id, principal_id);
+CREATE INDEX IF NOT EXISTS rbac_guest_memberships_principal_ndx ON rbac_guest_memberships(principal_id);
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS rbac_attributes_owner_name_ndx;
+DROP TABLE IF EXISTS rbac_attributes;
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS rbac_attributes (
+  id VARCHAR(36) NOT NULL PRIMARY KEY,
+  owner_id VARCHAR(36) NOT NULL,
+  owner_type VARCHAR(50) NOT NULL,
+  name VARCHAR(100) NOT NULL,
+  value TEXT NOT NULL,
+  value_type VARCHAR(20) NOT NULL,
+  created_by VARCHAR(36),
+  created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+  updated_by VARCHAR(36),
+  updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS rbac_attributes_owner_name_ndx ON rbac_attributes(owner_type, owner_id, name);
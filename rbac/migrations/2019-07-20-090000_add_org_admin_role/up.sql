@@ -0,0 +1 @@
+ALTER TABLE rbac_organizations ADD COLUMN admin_role_id VARCHAR(36);
@@ -10,6 +10,7 @@
 #[macro_use] extern crate diesel_migrations;
 #[macro_use] extern crate log;
 #[macro_use] extern crate env_logger;
+#[macro_use] extern crate lazy_static;
 
 extern crate chrono;
 extern crate dotenv;
@@ -64,12 +65,16 @@ fn rocket() -> rocket::Rocket {
                realm::create_resource,
                realm::update_resource,
                realm::get_resource,
+               realm::get_resource_access,
                realm::delete_resource,
                realm::get_instances,
                realm::create_instance,
                realm::update_instance,
                realm::get_instance,
                realm::delete_instance,
+               realm::get_instance_attributes,
+               realm::save_instance_attribute,
+               realm::delete_instance_attribute,
                realm::get_quotas,
                realm::create_quota,
                realm::update_quota,
@@ -83,6 +88,8 @@ fn rocket() -> rocket::Rocket {
                realm::delete_claim,
                realm::add_principal_to_claim,
                realm::delete_principal_from_claim,
+               realm::add_guest_to_claim,
+               realm::delete_guest_from_claim,
                realm::add_role_to_claim,
                realm::delete_role_from_claim,
                realm::add_license_to_claim,
@@ -105,6 +112,7 @@ fn rocket() -> rocket::Rocket {
                organization::create_role,
                organization::update_role,
                organization::get_role,
+               organization::diff_roles,
                organization::delete_role,
                organization::add_principal_to_role,
                organization::delete_principal_from_role,
@@ -114,14 +122,58 @@ fn rocket() -> rocket::Rocket {
                organization::create_principal,
                organization::update_principal,
                organization::get_principal,
+               organization::get_principal_permissions,
+               organization::diff_principals,
+               organization::get_principal_groups,
                organization::delete_principal,
                organization::get_licenses_by_org,
                organization::create_license,
                organization::update_license,
                organization::get_license,
-               organization::delete_license
+               organization::delete_license,
+               organization::get_sod_rules_by_org,
+               organization::create_sod_rule,
+               organization::update_sod_rule,
+               organization::get_sod_rule,
+               organization::delete_sod_rule,
+               organization::get_delegations_by_org,
+               organization::create_delegation,
+               organization::update_delegation,
+               organization::get_delegation,
+               organization::delete_delegation,
+               organization::get_pending_assignment_requests,
+               organization::create_assignment_request,
+               organization::get_assignment_request,
+               organization::approve_assignment_request,
+               organization::reject_assignment_request,
+               organization::get_guests_by_org,
+               organization::create_guest,
+               organization::get_guest,
+               organization::delete_guest,
+               organization::get_org_attributes,
+               organization::save_org_attribute,
+               organization::delete_org_attribute,
+               organization::get_group_attributes,
+               organization::save_group_attribute,
+               organization::delete_group_attribute,
+               organization::get_principal_attributes,
+               organization::save_principal_attribute,
+               organization::delete_principal_attribute
+                   ])
+        .mount("/api/security", routes![
+               security::check,
+               security::check_json,
+               security::explain,
+               security::check_batch,
+               security::simulate,
+               security::open_session,
+               security::get_session,
+               security::activate_session_role,
+               security::deactivate_session_role,
+               security::close_session,
+               security::break_glass,
+               security::cache_stats
                    ])
-        .mount("/api/security", routes![security::check])
         .register(catchers![not_found])
         .manage(new_pool())
         .manage(Mutex::new(HashMap::<String, String>::new()))
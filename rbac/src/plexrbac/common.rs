@@ -1,9 +1,11 @@
 //#![crate_name = "doc"]
 //![feature(proc_macro_hygiene, decl_macro, never_type)]
 extern crate url;
+extern crate serde_json;
 
 use std::collections::HashMap;
 use plexrbac::utils::evaluator;
+use plexrbac::utils::text;
 
 use rocket::request::{self, Request, FromRequest};
 use rocket::outcome::Outcome::*;
@@ -35,15 +37,7 @@ impl<'a, 'r> FromRequest<'a, 'r> for SecurityContext {
         let dict: HashMap<_, _> = parsed_url.query_pairs().into_owned().collect();
         let mut ctx = SecurityContext::new(realm, principal);
         for (k,v) in dict {
-            if let Ok(f) = v.parse::<f64>() {
-                ctx.add(k.as_str(), ValueWrapper::Float(f));
-            } else if let Ok(i) = v.parse::<i64>() {
-                ctx.add(k.as_str(), ValueWrapper::Int(i));
-            } else if v == "true" || v == "false" {
-                ctx.add(k.as_str(), ValueWrapper::Bool(v == "true"));
-            } else {
-                ctx.add(k.as_str(), ValueWrapper::String(v));
-            }
+            ctx.add(k.as_str(), ValueWrapper::parse(v));
         } 
         Success(ctx)
     }
@@ -75,7 +69,10 @@ pub enum Constants {
     Principal,
     Role,
     Group,
+    Organization,
+    ResourceInstance,
     LicensePolicy,
+    GuestMembership,
     Allow,
     Deny
 }
@@ -86,6 +83,96 @@ impl std::fmt::Display for Constants {
     }
 }
 
+/// CombiningAlgorithm defines how effects of multiple matching claims are combined
+#[derive(Debug, Clone, PartialEq)]
+pub enum CombiningAlgorithm {
+    DenyOverrides,
+    PermitOverrides,
+    FirstApplicable,
+    Priority,
+}
+
+impl CombiningAlgorithm {
+    /// Parses algorithm by name, e.g. DenyOverrides or deny-overrides, where empty name defaults
+    /// to first-applicable and unknown names are rejected
+    pub fn from(value: &str) -> Result<CombiningAlgorithm, RbacError> {
+        match value.to_lowercase().replace("-", "").replace("_", "").as_str() {
+            "denyoverrides" => Ok(CombiningAlgorithm::DenyOverrides),
+            "permitoverrides" => Ok(CombiningAlgorithm::PermitOverrides),
+            "priority" => Ok(CombiningAlgorithm::Priority),
+            "firstapplicable" | "" => Ok(CombiningAlgorithm::FirstApplicable),
+            _ => Err(RbacError::Custom(format!("Unknown combining algorithm '{}'", value))),
+        }
+    }
+}
+
+impl std::fmt::Display for CombiningAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// ScopeMatching defines how scope of a grant is matched against requested scope
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScopeMatching {
+    Exact,
+    Hierarchical,
+    Glob,
+}
+
+impl ScopeMatching {
+    /// Parses strategy by name, e.g. Hierarchical or glob, where empty name defaults to exact
+    /// and unknown names are rejected
+    pub fn from(value: &str) -> Result<ScopeMatching, RbacError> {
+        match value.to_lowercase().as_str() {
+            "hierarchical" => Ok(ScopeMatching::Hierarchical),
+            "glob" => Ok(ScopeMatching::Glob),
+            "exact" | "" => Ok(ScopeMatching::Exact),
+            _ => Err(RbacError::Custom(format!("Unknown scope matching '{}'", value))),
+        }
+    }
+
+    /// Returns true if granted scope covers requested scope, where hierarchical scopes such as
+    /// US cover descendant paths such as US/Midwest/Chicago and glob scopes additionally match
+    /// wildcards such as * or US/*/Chicago
+    pub fn matches(&self, granted: &str, requested: &str) -> bool {
+        if granted == requested {
+            return true;
+        }
+        let covers_path = granted.len() > 0 && requested.starts_with(format!("{}/", granted.trim_end_matches('/')).as_str());
+        match self {
+            ScopeMatching::Exact => false,
+            ScopeMatching::Hierarchical => covers_path,
+            ScopeMatching::Glob => covers_path || text::glob_match(granted, requested),
+        }
+    }
+
+    /// Returns SQLite GLOB patterns of requested scopes other than the granted scope itself
+    /// that are covered by the granted scope, e.g. US/* for hierarchical scope US, where
+    /// special characters of the scope other than wildcards of glob scopes are escaped
+    pub fn glob_patterns(&self, granted: &str) -> Vec<String> {
+        let escape = |scope: &str, wildcards: bool| scope.chars().map(|c| match c {
+            '*' if wildcards => c.to_string(),
+            '*' | '?' | '[' => format!("[{}]", c),
+            _ => c.to_string(),
+        }).collect::<String>();
+        let mut patterns = vec![];
+        if *self != ScopeMatching::Exact && granted.len() > 0 {
+            patterns.push(format!("{}/*", escape(granted.trim_end_matches('/'), false)));
+        }
+        if *self == ScopeMatching::Glob && granted.contains('*') {
+            patterns.push(escape(granted, true));
+        }
+        patterns
+    }
+}
+
+impl std::fmt::Display for ScopeMatching {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
 /// Status
 #[derive(Debug, Clone, PartialEq)]
 pub enum Status {
@@ -93,6 +180,8 @@ pub enum Status {
     PENDING,
     FAILED,
     COMPLETED,
+    APPROVED,
+    REJECTED,
     UNKNOWN
 }
 
@@ -148,13 +237,70 @@ impl std::fmt::Display for ActionType {
 }
 
 
-/// ValueWrapper is used to wrap values inside hashmap
-#[derive(Debug, Clone, PartialEq)]
+/// ValueWrapper is used to wrap values inside hashmap, where a list is passed to constraints
+/// as a tuple and a nested map is passed as properties with dotted names, e.g. order.amount
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
 pub enum ValueWrapper {
     Bool(bool),
     String(String),
     Int(i64),
     Float(f64),
+    Tuple(Vec<ValueWrapper>),
+    Map(HashMap<String, ValueWrapper>),
+}
+
+impl ValueWrapper {
+    /// Converts string value into int, float, bool or string value
+    pub fn parse(v: String) -> ValueWrapper {
+        if let Ok(i) = v.parse::<i64>() {
+            ValueWrapper::Int(i)
+        } else if let Ok(f) = v.parse::<f64>() {
+            ValueWrapper::Float(f)
+        } else if v == "true" || v == "false" {
+            ValueWrapper::Bool(v == "true")
+        } else {
+            ValueWrapper::String(v)
+        }
+    }
+
+    /// Converts string value into value of given type, e.g. Int or Bool
+    pub fn parse_as(value_type: &str, v: &str) -> Option<ValueWrapper> {
+        match value_type {
+            "Bool" => v.parse::<bool>().ok().map(|b| ValueWrapper::Bool(b)),
+            "Int" => v.parse::<i64>().ok().map(|i| ValueWrapper::Int(i)),
+            "Float" => v.parse::<f64>().ok().map(|f| ValueWrapper::Float(f)),
+            "String" => Some(ValueWrapper::String(v.to_string())),
+            "Tuple" => serde_json::from_str::<Vec<ValueWrapper>>(v).ok().map(|t| ValueWrapper::Tuple(t)),
+            "Map" => serde_json::from_str::<HashMap<String, ValueWrapper>>(v).ok().map(|m| ValueWrapper::Map(m)),
+            _ => None,
+        }
+    }
+
+    /// Returns name of the type of value
+    pub fn value_type(&self) -> String {
+        match self {
+            ValueWrapper::Bool(_) => "Bool".to_string(),
+            ValueWrapper::String(_) => "String".to_string(),
+            ValueWrapper::Int(_) => "Int".to_string(),
+            ValueWrapper::Float(_) => "Float".to_string(),
+            ValueWrapper::Tuple(_) => "Tuple".to_string(),
+            ValueWrapper::Map(_) => "Map".to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ValueWrapper {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ValueWrapper::Bool(b) => write!(f, "{}", b),
+            ValueWrapper::String(s) => write!(f, "{}", s),
+            ValueWrapper::Int(i) => write!(f, "{}", i),
+            ValueWrapper::Float(n) => write!(f, "{}", n),
+            // lists and maps are formatted as JSON
+            _ => write!(f, "{}", serde_json::to_string(self).map_err(|_| std::fmt::Error)?),
+        }
+    }
 }
 
 
@@ -169,6 +315,8 @@ pub enum RbacError {
     Security(String),
     Evaluation(String),
     QuotaExceeded(String),
+    CyclicHierarchy(String),
+    SodViolation(String),
     Custom(String),
 }
 
@@ -181,6 +329,8 @@ impl fmt::Display for RbacError {
             RbacError::Security(ref e) => e.fmt(f),
             RbacError::Evaluation(ref e) => e.fmt(f),
             RbacError::QuotaExceeded(ref e) => e.fmt(f),
+            RbacError::CyclicHierarchy(ref e) => e.fmt(f),
+            RbacError::SodViolation(ref e) => e.fmt(f),
             RbacError::Custom(ref e) => e.fmt(f),
         }
     }
@@ -195,6 +345,8 @@ impl error::Error for RbacError {
             RbacError::Security(ref e) => e.as_str(),
             RbacError::Evaluation(ref e) => e.as_str(),
             RbacError::QuotaExceeded(ref e) => e.as_str(),
+            RbacError::CyclicHierarchy(ref e) => e.as_str(),
+            RbacError::SodViolation(ref e) => e.as_str(),
             RbacError::Custom(ref e) => e.as_str(),
         }
     }
@@ -207,6 +359,8 @@ impl error::Error for RbacError {
             RbacError::Security(_) => None,
             RbacError::Evaluation(_) => None,
             RbacError::QuotaExceeded(_) => None,
+            RbacError::CyclicHierarchy(_) => None,
+            RbacError::SodViolation(_) => None,
             RbacError::Custom(_) => None,
         }
     }
@@ -216,6 +370,7 @@ impl error::Error for RbacError {
 #[cfg(test)]
 mod tests {
     use plexrbac::common::*;
+    use std::collections::HashMap;
     use chrono::{Utc, Datelike};
 
     #[test]
@@ -232,6 +387,43 @@ mod tests {
         assert_eq!("Device".to_string(), ResourceType::Device.to_string());
     }
 
+    #[test]
+    fn test_combining_algorithm() {
+        assert_eq!(Ok(CombiningAlgorithm::DenyOverrides), CombiningAlgorithm::from("DenyOverrides"));
+        assert_eq!(Ok(CombiningAlgorithm::DenyOverrides), CombiningAlgorithm::from("deny-overrides"));
+        assert_eq!(Ok(CombiningAlgorithm::PermitOverrides), CombiningAlgorithm::from("permit_overrides"));
+        assert_eq!(Ok(CombiningAlgorithm::Priority), CombiningAlgorithm::from("Priority"));
+        assert_eq!(Ok(CombiningAlgorithm::FirstApplicable), CombiningAlgorithm::from("first-applicable"));
+        assert_eq!(Ok(CombiningAlgorithm::FirstApplicable), CombiningAlgorithm::from(""));
+        assert!(CombiningAlgorithm::from("deny-override").is_err());
+        assert_eq!("DenyOverrides".to_string(), CombiningAlgorithm::DenyOverrides.to_string());
+    }
+
+    #[test]
+    fn test_scope_matching() {
+        assert_eq!(Ok(ScopeMatching::Hierarchical), ScopeMatching::from("hierarchical"));
+        assert_eq!(Ok(ScopeMatching::Glob), ScopeMatching::from("Glob"));
+        assert_eq!(Ok(ScopeMatching::Exact), ScopeMatching::from("exact"));
+        assert_eq!(Ok(ScopeMatching::Exact), ScopeMatching::from(""));
+        assert!(ScopeMatching::from("hierachical").is_err());
+        assert!(ScopeMatching::Exact.matches("US", "US"));
+        assert!(!ScopeMatching::Exact.matches("US", "US/Midwest"));
+        assert!(ScopeMatching::Hierarchical.matches("US", "US/Midwest/Chicago"));
+        assert!(!ScopeMatching::Hierarchical.matches("US", "USA"));
+        assert!(!ScopeMatching::Hierarchical.matches("", "US"));
+        assert!(!ScopeMatching::Hierarchical.matches("*", "US"));
+        assert!(ScopeMatching::Glob.matches("*", "US/Midwest"));
+        assert!(ScopeMatching::Glob.matches("US/*/Chicago", "US/Midwest/Chicago"));
+        assert!(ScopeMatching::Glob.matches("US", "US/Midwest"));
+        assert!(!ScopeMatching::Glob.matches("US/*/Chicago", "US/Midwest/Detroit"));
+        assert_eq!(Vec::<String>::new(), ScopeMatching::Exact.glob_patterns("US"));
+        assert_eq!(vec!["US/*".to_string()], ScopeMatching::Hierarchical.glob_patterns("US/"));
+        assert_eq!(Vec::<String>::new(), ScopeMatching::Hierarchical.glob_patterns(""));
+        assert_eq!(vec!["US[?]/*".to_string()], ScopeMatching::Hierarchical.glob_patterns("US?"));
+        assert_eq!(vec!["US/[*]/*".to_string(), "US/*".to_string()], ScopeMatching::Glob.glob_patterns("US/*"));
+        assert_eq!("Hierarchical".to_string(), ScopeMatching::Hierarchical.to_string());
+    }
+
     #[test]
     fn test_action() {
         assert_eq!("READ".to_string(), ActionType::READ.to_string());
@@ -253,6 +445,8 @@ mod tests {
         assert_eq!("PENDING".to_string(), Status::PENDING.to_string());
         assert_eq!("FAILED".to_string(), Status::FAILED.to_string());
         assert_eq!("COMPLETED".to_string(), Status::COMPLETED.to_string());
+        assert_eq!("APPROVED".to_string(), Status::APPROVED.to_string());
+        assert_eq!("REJECTED".to_string(), Status::REJECTED.to_string());
         assert_eq!("UNKNOWN".to_string(), Status::UNKNOWN.to_string());
     }
 
@@ -262,6 +456,8 @@ mod tests {
         assert_eq!("test".to_string(), RbacError::Security("test".to_string()).to_string());
         assert_eq!("test".to_string(), RbacError::Evaluation("test".to_string()).to_string());
         assert_eq!("test".to_string(), RbacError::QuotaExceeded("test".to_string()).to_string());
+        assert_eq!("test".to_string(), RbacError::CyclicHierarchy("test".to_string()).to_string());
+        assert_eq!("test".to_string(), RbacError::SodViolation("test".to_string()).to_string());
         assert_eq!("test".to_string(), RbacError::Custom("test".to_string()).to_string());
     }
 
@@ -283,6 +479,41 @@ mod tests {
         assert!(ctx.evaluate("11").is_err());
     }
 
+    #[test]
+    fn test_typed_value() {
+        for v in vec![ValueWrapper::Bool(true), ValueWrapper::Int(5), ValueWrapper::Float(0.5), ValueWrapper::String("Midwest".into())] {
+            assert_eq!(Some(v.clone()), ValueWrapper::parse_as(v.value_type().as_str(), v.to_string().as_str()));
+        }
+        assert_eq!(None, ValueWrapper::parse_as("Int", "five"));
+        assert_eq!(ValueWrapper::Int(10), ValueWrapper::parse("10".into()));
+        assert_eq!(ValueWrapper::Float(10.5), ValueWrapper::parse("10.5".into()));
+        assert_eq!(ValueWrapper::Bool(true), ValueWrapper::parse("true".into()));
+        assert_eq!(None, ValueWrapper::parse_as("Date", "2019-01-01"));
+        let mut order = HashMap::new();
+        order.insert("amount".to_string(), ValueWrapper::Int(250));
+        order.insert("tags".to_string(), ValueWrapper::Tuple(vec![ValueWrapper::String("travel".into()), ValueWrapper::Float(1.5)]));
+        let order = ValueWrapper::Map(order);
+        assert_eq!(Some(order.clone()), ValueWrapper::parse_as(order.value_type().as_str(), order.to_string().as_str()));
+
+        let mut ctx =  SecurityContext::new("org", "user");
+        ctx.add("principal.region".into(), ValueWrapper::String("Midwest".to_string()));
+        ctx.add("principal.level".into(), ValueWrapper::Int(3));
+        assert_eq!(ctx.evaluate(r#"principal.region == "Midwest" && principal.level > 2"#), Ok(true));
+    }
+
+    #[test]
+    fn test_nested_evaluate() {
+        let mut order = HashMap::new();
+        order.insert("amount".to_string(), ValueWrapper::Int(250));
+        order.insert("currency".to_string(), ValueWrapper::String("100".to_string()));
+        let mut ctx =  SecurityContext::new("org", "user");
+        ctx.add("order".into(), ValueWrapper::Map(order));
+        ctx.add("regions".into(), ValueWrapper::Tuple(vec![ValueWrapper::String("Midwest".into()), ValueWrapper::String("Northeast".into())]));
+        assert_eq!(ctx.evaluate(r#"order.amount == 250 && order.currency == "100""#), Ok(true));
+        assert_eq!(ctx.evaluate(r#"contains(regions, "Midwest")"#), Ok(true));
+        assert_eq!(ctx.evaluate(r#"contains(regions, "West")"#), Ok(false));
+    }
+
     #[test]
     fn test_regex_match() {
         let mut ctx =  SecurityContext::new("org", "user");
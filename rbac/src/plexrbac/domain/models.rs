@@ -2,8 +2,13 @@
 
 use plexrbac::persistence::models::*;
 use plexrbac::common::Constants;
+use plexrbac::common::Status;
+use plexrbac::common::CombiningAlgorithm;
+use plexrbac::common::RbacError;
+use plexrbac::common::ScopeMatching;
+use plexrbac::common::ValueWrapper;
 use chrono::{NaiveDateTime, Utc};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 //////////////////////////////////////////////////////////////////////////////////////////////
 ///
@@ -18,24 +23,53 @@ pub struct SecurityRealm {
     pub id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub combining_algorithm: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope_matching: Option<String>,
 }
 
 impl SecurityRealm {
     /// Creates instance from persistent realm
     pub fn from(realm: &PSecurityRealm) -> SecurityRealm {
-        SecurityRealm::new(realm.id.as_str(), realm.description.clone())
+        let mut obj = SecurityRealm::new(realm.id.as_str(), realm.description.clone());
+        obj.combining_algorithm = realm.combining_algorithm.clone();
+        obj.scope_matching = realm.scope_matching.clone();
+        obj
     }
 
     /// Creates instance of persistent realm
     pub fn to(&self) -> PSecurityRealm {
-        PSecurityRealm::new(self.id.as_str(), self.description.clone())
+        let mut obj = PSecurityRealm::new(self.id.as_str(), self.description.clone(), self.combining_algorithm.clone());
+        obj.scope_matching = self.scope_matching.clone();
+        obj
     }
 
     /// Creates new instance of realm
     pub fn new(id: &str, description: Option<String>) -> SecurityRealm {
         SecurityRealm{
             id: id.to_string(),
-            description: description.clone()
+            description: description.clone(),
+            combining_algorithm: None,
+            scope_matching: None,
+        }
+    }
+
+    /// Returns algorithm for combining effects of matching claims, defaults to first-applicable
+    pub fn combining_algorithm(&self) -> Result<CombiningAlgorithm, RbacError> {
+        if let Some(ref algorithm) = self.combining_algorithm {
+            CombiningAlgorithm::from(algorithm.as_str())
+        } else {
+            Ok(CombiningAlgorithm::FirstApplicable)
+        }
+    }
+
+    /// Returns strategy for matching scopes of grants, defaults to exact
+    pub fn scope_matching(&self) -> Result<ScopeMatching, RbacError> {
+        if let Some(ref matching) = self.scope_matching {
+            ScopeMatching::from(matching.as_str())
+        } else {
+            Ok(ScopeMatching::Exact)
         }
     }
 }
@@ -58,6 +92,10 @@ pub struct Organization {
     pub url: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub approver_role_id: Option<String>,   // Role whose holders approve assignment requests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub admin_role_id: Option<String>,      // Role whose holders administer the organization
     #[serde(skip_serializing, skip_deserializing)]
     pub groups: HashMap<String, Group>,
     #[serde(skip_serializing, skip_deserializing)]
@@ -68,6 +106,8 @@ pub struct Organization {
     pub resources: Vec<Resource>,
     #[serde(skip_serializing, skip_deserializing)]
     pub license_policy: Option<LicensePolicy>,
+    #[serde(skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub org_chain: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(skip_deserializing)]
     pub created_by: Option<String>,
@@ -105,11 +145,14 @@ impl Organization {
             name: org.name.clone(),
             url: org.url.clone(),
             description: org.description.clone(),
+            approver_role_id: org.approver_role_id.clone(),
+            admin_role_id: org.admin_role_id.clone(),
             groups: HashMap::new(),
             roles: HashMap::new(),
             claims: vec![],
             resources: vec![],
             license_policy: None,
+            org_chain: vec![],
             created_at: org.created_at.clone(),
             created_by: org.created_by.clone(),
             updated_at:org.updated_at.clone(), 
@@ -119,7 +162,10 @@ impl Organization {
 
     /// Creates instance of persistent organization
     pub fn to(&self) -> POrganization {
-        POrganization::new(self.id.as_str(), self.parent_id.clone(), self.name.as_str(), self.url.as_str(), self.description.clone())
+        let mut org = POrganization::new(self.id.as_str(), self.parent_id.clone(), self.name.as_str(), self.url.as_str(), self.description.clone());
+        org.approver_role_id = self.approver_role_id.clone();
+        org.admin_role_id = self.admin_role_id.clone();
+        org
     }
 
     pub fn new(id: &str, parent_id: Option<String>, name: &str, url: &str, description: Option<String>) -> Organization {
@@ -129,11 +175,14 @@ impl Organization {
             name: name.to_string(),
             url: url.to_string(),
             description: description,
+            approver_role_id: None,
+            admin_role_id: None,
             groups: HashMap::new(),
             roles: HashMap::new(),
             claims: vec![],
             resources: vec![],
             license_policy: None,
+            org_chain: vec![],
             created_at: Some(Utc::now().naive_utc()),
             created_by: None,
             updated_at: Some(Utc::now().naive_utc()),
@@ -158,6 +207,8 @@ pub struct Principal {
     pub claims: Vec<ClaimClaimable>,
     #[serde(skip_serializing, skip_deserializing)]
     pub resources: Vec<Resource>,
+    #[serde(skip_serializing, skip_deserializing)]
+    pub attributes: HashMap<String, ValueWrapper>,
     pub created_by: Option<String>,
     pub created_at: Option<NaiveDateTime>,
     pub updated_by: Option<String>,
@@ -195,6 +246,7 @@ impl Principal {
             roles: HashMap::new(),
             claims: vec![],
             resources: vec![],
+            attributes: HashMap::new(),
             created_at: principal.created_at.clone(),
             created_by: principal.created_by.clone(),
             updated_at: principal.updated_at.clone(),
@@ -217,6 +269,7 @@ impl Principal {
             roles: HashMap::new(),
             claims: vec![],
             resources: vec![],
+            attributes: HashMap::new(),
             created_at: Some(Utc::now().naive_utc()),
             created_by: None,
             updated_at: Some(Utc::now().naive_utc()),
@@ -301,6 +354,7 @@ pub struct Resource {
     pub resource_name: String,
     pub description: Option<String>,
     pub allowable_actions: Option<String>,
+    pub scope_matching: Option<String>,
     #[serde(skip_serializing, skip_deserializing)]
     pub instances: HashMap<String, ResourceInstance>,
     #[serde(skip_serializing, skip_deserializing)]
@@ -326,6 +380,7 @@ impl Resource {
             resource_name: resource.resource_name.clone(),
             description: resource.description.clone(),
             allowable_actions: resource.allowable_actions.clone(),
+            scope_matching: resource.scope_matching.clone(),
             instances: HashMap::new(),
             quotas: HashMap::new(),
             created_at: resource.created_at.clone(),
@@ -337,7 +392,9 @@ impl Resource {
 
     /// Creates instance of persistent resource
     pub fn to(&self) -> PResource {
-        PResource::new(self.id.as_str(), self.realm_id.as_str(), self.resource_name.as_str(), self.description.clone(), self.allowable_actions.clone())
+        let mut obj = PResource::new(self.id.as_str(), self.realm_id.as_str(), self.resource_name.as_str(), self.description.clone(), self.allowable_actions.clone());
+        obj.scope_matching = self.scope_matching.clone();
+        obj
     }
 
     pub fn new(id: &str, realm_id: &str, resource_name: &str, description: Option<String>, allowable_actions: Option<String>) -> Resource {
@@ -347,6 +404,7 @@ impl Resource {
             resource_name: resource_name.to_string(),
             description: description,
             allowable_actions: allowable_actions,
+            scope_matching: None,
             instances: HashMap::new(),
             quotas: HashMap::new(),
             created_at: Some(Utc::now().naive_utc()),
@@ -355,6 +413,16 @@ impl Resource {
             updated_by: None
         }
     }
+
+    /// Returns strategy for matching scopes of the resource, where resources without strategy
+    /// use given strategy of the realm
+    pub fn scope_matching(&self, realm_matching: &ScopeMatching) -> Result<ScopeMatching, RbacError> {
+        if let Some(ref matching) = self.scope_matching {
+            ScopeMatching::from(matching.as_str())
+        } else {
+            Ok(realm_matching.clone())
+        }
+    }
 }
 
 /// ResourceInstance represents an instance of target object in case number of objects need constraints
@@ -492,6 +560,9 @@ pub struct Role {
     pub organization_id: String,
     pub name: String,
     pub description: Option<String>,
+    pub max_assignees: Option<i32>,     // Maximum number of principals that can hold the role
+    #[serde(default)]
+    pub break_glass: bool,              // Role is granted temporarily via break-glass for emergency access
     #[serde(skip_serializing, skip_deserializing)]
     pub claims: Vec<ClaimClaimable>,    // All claims mapped to role
     pub constraints: Option<String>,
@@ -521,6 +592,8 @@ impl Role {
             realm_id: role.realm_id.clone(),
             name: role.name.clone(),
             description: role.description.clone(),
+            max_assignees: role.max_assignees,
+            break_glass: role.break_glass,
             claims: vec![],
             constraints: None,
             created_at: role.created_at.clone(),
@@ -532,7 +605,10 @@ impl Role {
 
     /// Creates instance of persistent role
     pub fn to(&self) -> PRole {
-        PRole::new(self.id.as_str(), self.realm_id.as_str(), self.organization_id.as_str(), self.name.as_str(), self.description.clone(), self.parent_id.clone())
+        let mut role = PRole::new(self.id.as_str(), self.realm_id.as_str(), self.organization_id.as_str(), self.name.as_str(), self.description.clone(), self.parent_id.clone());
+        role.max_assignees = self.max_assignees;
+        role.break_glass = self.break_glass;
+        role
     }
 
     pub fn new(id: &str, realm_id: &str, organization_id: &str, name: &str, description: Option<String>, parent_id: Option<String>) -> Role {
@@ -543,6 +619,8 @@ impl Role {
             realm_id: realm_id.to_string(),
             name: name.to_string(),
             description: description,
+            max_assignees: None,
+            break_glass: false,
             claims: vec![],
             constraints: None,
             created_at: Some(Utc::now().naive_utc()),
@@ -553,6 +631,24 @@ impl Role {
     }
 }
 
+/// RoleSource defines how a role was resolved for the principal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RoleSource {
+    Direct,
+    Group(String), // group name
+    Inherited(String), // name of role held by the principal
+}
+
+impl std::fmt::Display for RoleSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RoleSource::Direct => write!(f, "direct"),
+            RoleSource::Group(group) => write!(f, "via group {}", group),
+            RoleSource::Inherited(child) => write!(f, "inherited from {}", child),
+        }
+    }
+}
+
 /// ClaimClaimable defines mapping of claim and claimable
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ClaimClaimable {
@@ -560,6 +656,7 @@ pub enum ClaimClaimable {
     LicensePolicy(Claim, String, String, String), // realm, scope, constraints
     Role(Claim, String, String, String, String), // realm, role-id, scope, constraints
     Principal(Claim, String, String, String, String), // realm, principal-id, scope, constraints
+    Delegation(Claim, String, String, String, String, String), // realm, delegation-id, delegator-id, scope, constraints
 }
 
 impl std::fmt::Display for ClaimClaimable {
@@ -569,6 +666,7 @@ impl std::fmt::Display for ClaimClaimable {
             ClaimClaimable::LicensePolicy(claim, _realm, scope, constraints) => write!(f, "\n\tlicense-claim: {}\t\tscope: {}, constraints: {}", claim, scope, constraints),
             ClaimClaimable::Role(claim, _, _realm, scope, constraints) => write!(f, "\n\trole-claim: {}\t\tscope: {}, constraints: {}", claim, scope, constraints),
             ClaimClaimable::Principal(claim, _, _realm, scope, constraints) => write!(f, "\n\tprincipal-claim: {}\t\tscope: {}, constraints: {}", claim, scope, constraints),
+            ClaimClaimable::Delegation(claim, _, _, delegator_id, scope, constraints) => write!(f, "\n\tdelegated-claim: {}\t\tdelegator: {}, scope: {}, constraints: {}", claim, delegator_id, scope, constraints),
         }
     }
 }
@@ -588,6 +686,101 @@ impl ClaimResource {
     }
 }
 
+/// EffectiveClaim defines claim available to the principal along with its provenance, e.g.
+/// direct, via role X or via role X inherited from parent Y
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EffectiveClaim {
+    pub claim_id: String,
+    pub action: String,
+    pub effect: String,
+    pub constraints: String,
+    pub source: String,
+}
+
+/// PrincipalAccess defines access of a principal to a resource via a claim, the access is
+/// conditional when constraints of the claim must be evaluated at runtime
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrincipalAccess {
+    pub principal_id: String,
+    pub username: String,
+    pub organization_id: String,
+    pub claim_id: String,
+    pub action: String,
+    pub effect: String,
+    pub scope: String,
+    pub constraints: String,
+    pub conditional: bool,
+}
+
+impl PrincipalAccess {
+    pub fn new(principal: &Principal, cr: &ClaimResource) -> PrincipalAccess {
+        PrincipalAccess {
+            principal_id: principal.id.clone(),
+            username: principal.username.clone(),
+            organization_id: principal.organization_id.clone(),
+            claim_id: cr.claim.id.clone(),
+            action: cr.claim.action.clone(),
+            effect: cr.claim.effect(),
+            scope: cr.scope.clone(),
+            constraints: cr.constraints.clone(),
+            conditional: cr.constraints.len() > 0,
+        }
+    }
+}
+
+/// ResourcePermission groups effective claims of the principal by resource and scope
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourcePermission {
+    pub resource_id: String,
+    pub resource_name: String,
+    pub scope: String,
+    pub claims: Vec<EffectiveClaim>,
+}
+
+/// PermissionTuple defines resource, action, scope, constraints and effect of an effective claim
+/// that are compared by permission diff
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct PermissionTuple {
+    pub resource_name: String,
+    pub action: String,
+    pub scope: String,
+    pub constraints: String,
+    pub effect: String,
+}
+
+impl PermissionTuple {
+    pub fn new(resource_name: &str, action: &str, scope: &str, constraints: &str, effect: &str) -> PermissionTuple {
+        PermissionTuple {
+            resource_name: resource_name.to_string(),
+            action: action.to_string(),
+            scope: scope.to_string(),
+            constraints: constraints.to_string(),
+            effect: effect.to_string(),
+        }
+    }
+}
+
+/// PermissionDiff defines difference between effective permissions of two principals or roles
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PermissionDiff {
+    pub only_in_a: Vec<PermissionTuple>,
+    pub only_in_b: Vec<PermissionTuple>,
+    pub common: Vec<PermissionTuple>,
+}
+
+impl PermissionDiff {
+    /// Compares permission tuples of a and b, where duplicate tuples are compared only once
+    pub fn new(a: &Vec<PermissionTuple>, b: &Vec<PermissionTuple>) -> PermissionDiff {
+        let a = a.iter().cloned().collect::<BTreeSet<PermissionTuple>>();
+        let b = b.iter().cloned().collect::<BTreeSet<PermissionTuple>>();
+        PermissionDiff {
+            only_in_a: a.difference(&b).cloned().collect(),
+            only_in_b: b.difference(&a).cloned().collect(),
+            common: a.intersection(&b).cloned().collect(),
+        }
+    }
+}
+
 /// Claim defines mapping of target resource that needs protection and action that can be performed
 /// on those resources.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -600,6 +793,7 @@ pub struct Claim {
     pub action: String,
     pub effect: Option<String>,
     pub description: Option<String>,
+    pub priority: Option<i32>,
     pub created_by: Option<String>,
     pub created_at: Option<NaiveDateTime>,
     pub updated_by: Option<String>,
@@ -622,6 +816,7 @@ impl Claim {
             action: claim.action.clone(),
             effect: claim.effect.clone(),
             description: claim.description.clone(),
+            priority: claim.priority.clone(),
             created_at: claim.created_at.clone(),
             created_by: claim.created_by.clone(),
             updated_at: claim.updated_at.clone(),
@@ -631,7 +826,7 @@ impl Claim {
 
     /// Creates instance of persistent claim
     pub fn to(&self) -> PClaim {
-        PClaim::new(self.id.as_str(), self.realm_id.as_str(), self.resource_id.as_str(), self.action.as_str(), self.effect().as_str(), self.description.clone())
+        PClaim::new(self.id.as_str(), self.realm_id.as_str(), self.resource_id.as_str(), self.action.as_str(), self.effect().as_str(), self.description.clone(), self.priority.clone())
     }
 
     pub fn new(id: &str, realm_id: &str, resource_id: &str, action: &str, effect: &str, description: Option<String>) -> Claim {
@@ -642,6 +837,7 @@ impl Claim {
             action: action.to_string(),
             effect: Some(effect.to_string()),
             description: description,
+            priority: None,
             created_at: Some(Utc::now().naive_utc()),
             created_by: None,
             updated_at: Some(Utc::now().naive_utc()),
@@ -657,6 +853,11 @@ impl Claim {
         }
         Constants::Allow.to_string()
     }
+
+    /// Returns priority of claim that is used by priority combining algorithm, defaults to 0
+    pub fn priority(&self) -> i32 {
+        self.priority.unwrap_or(0)
+    }
 }
 
 /// LicensePolicy defines what an organization can access
@@ -717,12 +918,488 @@ impl LicensePolicy {
     }
 }
 
+/// SodRule defines separation-of-duty rule, which is violated when a principal is assigned (static)
+/// or activates (dynamic) cardinality or more roles out of the role-ids
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SodRule {
+    #[serde(skip_deserializing)]
+    pub id: String,
+    pub realm_id: String,
+    pub organization_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub role_ids: Vec<String>,
+    pub cardinality: i32,
+    pub dynamic: bool,
+    pub created_by: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_by: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl SodRule {
+    /// Creates instance from persistent sod-rule
+    pub fn from(rule: &PSodRule) -> SodRule {
+        SodRule {
+            id: rule.id.clone(),
+            realm_id: rule.realm_id.clone(),
+            organization_id: rule.organization_id.clone(),
+            name: rule.name.clone(),
+            description: rule.description.clone(),
+            role_ids: rule.role_ids.split(',').map(|s| s.trim().to_string()).filter(|s| s.len() > 0).collect(),
+            cardinality: rule.cardinality,
+            dynamic: rule.dynamic,
+            created_at: rule.created_at.clone(),
+            created_by: rule.created_by.clone(),
+            updated_at: rule.updated_at.clone(),
+            updated_by: rule.updated_by.clone(),
+        }
+    }
+
+    /// Creates instance of persistent sod-rule
+    pub fn to(&self) -> PSodRule {
+        PSodRule::new(self.id.as_str(), self.realm_id.as_str(), self.organization_id.as_str(), self.name.as_str(), self.description.clone(), self.role_ids.join(",").as_str(), self.cardinality, self.dynamic)
+    }
+
+    pub fn new(id: &str, realm_id: &str, organization_id: &str, name: &str, description: Option<String>, role_ids: Vec<String>, cardinality: i32, dynamic: bool) -> SodRule {
+        SodRule {
+            id: id.to_string(),
+            realm_id: realm_id.to_string(),
+            organization_id: organization_id.to_string(),
+            name: name.to_string(),
+            description: description,
+            role_ids: role_ids,
+            cardinality: cardinality,
+            dynamic: dynamic,
+            created_at: Some(Utc::now().naive_utc()),
+            created_by: None,
+            updated_at: Some(Utc::now().naive_utc()),
+            updated_by: None
+        }
+    }
+
+    /// Returns true if given roles contain cardinality or more roles of this rule
+    pub fn violated_by(&self, role_ids: &HashSet<String>) -> bool {
+        let held = self.role_ids.iter().filter(|id| role_ids.contains(*id)).count() as i32;
+        held >= self.cardinality
+    }
+}
+
+/// Delegation defines temporary delegation of claims from delegator to delegatee, where the
+/// delegation is limited to claims of given roles or given claims held by the delegator
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Delegation {
+    #[serde(skip_deserializing)]
+    pub id: String,
+    pub organization_id: String,
+    pub delegator_id: String,
+    pub delegatee_id: String,
+    #[serde(default)]
+    pub role_ids: Vec<String>,
+    #[serde(default)]
+    pub claim_ids: Vec<String>,
+    pub constraints: Option<String>,
+    pub effective_at: NaiveDateTime,
+    pub expired_at: NaiveDateTime,
+    pub created_by: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_by: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl Delegation {
+    /// Creates instance from persistent delegation
+    pub fn from(delegation: &PDelegation) -> Delegation {
+        let split = |ids: &Option<String>| ids.clone().unwrap_or_default().split(',').map(|s| s.trim().to_string()).filter(|s| s.len() > 0).collect::<Vec<String>>();
+        Delegation {
+            id: delegation.id.clone(),
+            organization_id: delegation.organization_id.clone(),
+            delegator_id: delegation.delegator_id.clone(),
+            delegatee_id: delegation.delegatee_id.clone(),
+            role_ids: split(&delegation.role_ids),
+            claim_ids: split(&delegation.claim_ids),
+            constraints: delegation.delegation_constraints.clone().filter(|c| c.len() > 0),
+            effective_at: delegation.effective_at.clone(),
+            expired_at: delegation.expired_at.clone(),
+            created_at: delegation.created_at.clone(),
+            created_by: delegation.created_by.clone(),
+            updated_at: delegation.updated_at.clone(),
+            updated_by: delegation.updated_by.clone(),
+        }
+    }
+
+    /// Creates instance of persistent delegation
+    pub fn to(&self) -> PDelegation {
+        PDelegation::new(self.id.as_str(), self.organization_id.as_str(), self.delegator_id.as_str(), self.delegatee_id.as_str(), self.role_ids.join(",").as_str(), self.claim_ids.join(",").as_str(), self.constraints.clone().unwrap_or_default().as_str(), self.effective_at.clone(), self.expired_at.clone())
+    }
+
+    pub fn new(id: &str, organization_id: &str, delegator_id: &str, delegatee_id: &str, role_ids: Vec<String>, claim_ids: Vec<String>, constraints: Option<String>, effective_at: NaiveDateTime, expired_at: NaiveDateTime) -> Delegation {
+        Delegation {
+            id: id.to_string(),
+            organization_id: organization_id.to_string(),
+            delegator_id: delegator_id.to_string(),
+            delegatee_id: delegatee_id.to_string(),
+            role_ids: role_ids,
+            claim_ids: claim_ids,
+            constraints: constraints,
+            effective_at: effective_at,
+            expired_at: expired_at,
+            created_at: Some(Utc::now().naive_utc()),
+            created_by: None,
+            updated_at: Some(Utc::now().naive_utc()),
+            updated_by: None
+        }
+    }
+}
+
+/// Session defines roles that a principal activated out of its assigned roles so that only
+/// claims of the activated roles (and their parent roles) are considered while checking access
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    #[serde(skip_deserializing)]
+    pub id: String,
+    #[serde(default)]
+    pub realm_id: String,
+    #[serde(default)]
+    pub organization_id: String,
+    #[serde(default)]
+    pub principal_id: String,
+    #[serde(default)]
+    pub role_ids: Vec<String>,
+    pub expired_at: NaiveDateTime,
+    pub created_by: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_by: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl Session {
+    /// Creates instance from persistent session
+    pub fn from(session: &PSession) -> Session {
+        Session {
+            id: session.id.clone(),
+            realm_id: session.realm_id.clone(),
+            organization_id: session.organization_id.clone(),
+            principal_id: session.principal_id.clone(),
+            role_ids: session.role_ids.clone().unwrap_or_default().split(',').map(|s| s.trim().to_string()).filter(|s| s.len() > 0).collect::<Vec<String>>(),
+            expired_at: session.expired_at.clone(),
+            created_at: session.created_at.clone(),
+            created_by: session.created_by.clone(),
+            updated_at: session.updated_at.clone(),
+            updated_by: session.updated_by.clone(),
+        }
+    }
+
+    /// Creates instance of persistent session
+    pub fn to(&self) -> PSession {
+        PSession::new(self.id.as_str(), self.realm_id.as_str(), self.organization_id.as_str(), self.principal_id.as_str(), self.role_ids.join(",").as_str(), self.expired_at.clone())
+    }
+
+    pub fn new(id: &str, realm_id: &str, organization_id: &str, principal_id: &str, role_ids: Vec<String>, expired_at: NaiveDateTime) -> Session {
+        Session {
+            id: id.to_string(),
+            realm_id: realm_id.to_string(),
+            organization_id: organization_id.to_string(),
+            principal_id: principal_id.to_string(),
+            role_ids: role_ids,
+            expired_at: expired_at,
+            created_at: Some(Utc::now().naive_utc()),
+            created_by: None,
+            updated_at: Some(Utc::now().naive_utc()),
+            updated_by: None
+        }
+    }
+
+    /// Returns true if session has not expired yet
+    pub fn is_active(&self) -> bool {
+        self.expired_at > Utc::now().naive_utc()
+    }
+}
+
+/// GuestMembership links a principal, e.g. a consultant or vendor, into an organization other
+/// than its own organization (host) so that it can be granted roles and groups of the host
+/// organization and its access is checked against license-policy of the host organization
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GuestMembership {
+    #[serde(skip_deserializing)]
+    pub id: String,
+    #[serde(default)]
+    pub organization_id: String,
+    pub principal_id: String,
+    pub description: Option<String>,
+    pub effective_at: NaiveDateTime,
+    pub expired_at: NaiveDateTime,
+    pub created_by: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_by: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl GuestMembership {
+    /// Creates instance from persistent guest membership
+    pub fn from(membership: &PGuestMembership) -> GuestMembership {
+        GuestMembership {
+            id: membership.id.clone(),
+            organization_id: membership.organization_id.clone(),
+            principal_id: membership.principal_id.clone(),
+            description: membership.description.clone(),
+            effective_at: membership.effective_at.clone(),
+            expired_at: membership.expired_at.clone(),
+            created_at: membership.created_at.clone(),
+            created_by: membership.created_by.clone(),
+            updated_at: membership.updated_at.clone(),
+            updated_by: membership.updated_by.clone(),
+        }
+    }
+
+    /// Creates instance of persistent guest membership
+    pub fn to(&self) -> PGuestMembership {
+        PGuestMembership::new(self.id.as_str(), self.organization_id.as_str(), self.principal_id.as_str(), self.description.clone(), self.effective_at.clone(), self.expired_at.clone())
+    }
+
+    pub fn new(id: &str, organization_id: &str, principal_id: &str, description: Option<String>, effective_at: NaiveDateTime, expired_at: NaiveDateTime) -> GuestMembership {
+        GuestMembership {
+            id: id.to_string(),
+            organization_id: organization_id.to_string(),
+            principal_id: principal_id.to_string(),
+            description: description,
+            effective_at: effective_at,
+            expired_at: expired_at,
+            created_at: Some(Utc::now().naive_utc()),
+            created_by: None,
+            updated_at: Some(Utc::now().naive_utc()),
+            updated_by: None
+        }
+    }
+
+    /// Returns true if membership is currently effective
+    pub fn is_active(&self) -> bool {
+        let now = Utc::now().naive_utc();
+        self.effective_at <= now && self.expired_at >= now
+    }
+}
+
+/// Attribute defines typed attribute of a principal, group, organization or resource instance
+/// such as region or clearance level, which is injected into context of permission requests
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attribute {
+    #[serde(skip_deserializing)]
+    pub id: String,
+    #[serde(default)]
+    pub owner_id: String,
+    #[serde(default)]
+    pub owner_type: String,
+    #[serde(default)]
+    pub name: String,
+    pub value: ValueWrapper,
+    pub created_by: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_by: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl Attribute {
+    /// Reserved namespace of attributes within context of permission requests
+    pub const PRINCIPAL_PREFIX: &'static str = "principal.";
+
+    /// Reserved namespace of attributes of the resource instance within context of permission requests
+    pub const RESOURCE_PREFIX: &'static str = "resource.";
+
+    /// Creates instance from persistent attribute
+    pub fn from(attribute: &PAttribute) -> Attribute {
+        Attribute {
+            id: attribute.id.clone(),
+            owner_id: attribute.owner_id.clone(),
+            owner_type: attribute.owner_type.clone(),
+            name: attribute.name.clone(),
+            value: ValueWrapper::parse_as(attribute.value_type.as_str(), attribute.value.as_str()).unwrap_or_else(|| ValueWrapper::String(attribute.value.clone())),
+            created_at: attribute.created_at.clone(),
+            created_by: attribute.created_by.clone(),
+            updated_at: attribute.updated_at.clone(),
+            updated_by: attribute.updated_by.clone(),
+        }
+    }
+
+    /// Creates instance of persistent attribute
+    pub fn to(&self) -> PAttribute {
+        PAttribute::new(self.id.as_str(), self.owner_id.as_str(), self.owner_type.as_str(), self.name.as_str(), self.value.to_string().as_str(), self.value.value_type().as_str())
+    }
+
+    pub fn new(id: &str, owner_id: &str, owner_type: &str, name: &str, value: ValueWrapper) -> Attribute {
+        Attribute {
+            id: id.to_string(),
+            owner_id: owner_id.to_string(),
+            owner_type: owner_type.to_string(),
+            name: name.to_string(),
+            value: value,
+            created_at: Some(Utc::now().naive_utc()),
+            created_by: None,
+            updated_at: Some(Utc::now().naive_utc()),
+            updated_by: None
+        }
+    }
+}
+
+/// AssignmentRequest defines pending assignment of a role or a claim to a principal, which
+/// becomes effective only after a principal holding the approver role approves it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssignmentRequest {
+    #[serde(skip_deserializing)]
+    pub id: String,
+    #[serde(default)]
+    pub organization_id: String,
+    pub principal_id: String,
+    pub role_id: Option<String>,
+    pub claim_id: Option<String>,
+    #[serde(default)]
+    pub scope: String,
+    pub constraints: Option<String>,
+    pub effective_at: NaiveDateTime,
+    pub expired_at: NaiveDateTime,
+    #[serde(skip_deserializing)]
+    pub approver_role_id: String,
+    #[serde(skip_deserializing)]
+    pub status: String,
+    #[serde(skip_deserializing)]
+    pub reviewed_by: Option<String>,
+    #[serde(skip_deserializing)]
+    pub review_comment: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_by: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl AssignmentRequest {
+    /// Creates instance from persistent assignment-request
+    pub fn from(request: &PAssignmentRequest) -> AssignmentRequest {
+        AssignmentRequest {
+            id: request.id.clone(),
+            organization_id: request.organization_id.clone(),
+            principal_id: request.principal_id.clone(),
+            role_id: request.role_id.clone(),
+            claim_id: request.claim_id.clone(),
+            scope: request.scope.clone(),
+            constraints: request.assignment_constraints.clone().filter(|c| c.len() > 0),
+            effective_at: request.effective_at.clone(),
+            expired_at: request.expired_at.clone(),
+            approver_role_id: request.approver_role_id.clone(),
+            status: request.status.clone(),
+            reviewed_by: request.reviewed_by.clone(),
+            review_comment: request.review_comment.clone(),
+            created_at: request.created_at.clone(),
+            created_by: request.created_by.clone(),
+            updated_at: request.updated_at.clone(),
+            updated_by: request.updated_by.clone(),
+        }
+    }
+
+    /// Creates instance of persistent assignment-request
+    pub fn to(&self) -> PAssignmentRequest {
+        let mut request = PAssignmentRequest::new(self.id.as_str(), self.organization_id.as_str(), self.principal_id.as_str(), self.role_id.clone(), self.claim_id.clone(), self.scope.as_str(), self.constraints.clone().unwrap_or_default().as_str(), self.effective_at.clone(), self.expired_at.clone(), self.approver_role_id.as_str(), self.status.as_str());
+        request.reviewed_by = self.reviewed_by.clone();
+        request.review_comment = self.review_comment.clone();
+        request
+    }
+
+    /// Creates pending request for assigning role to the principal
+    pub fn for_role(organization_id: &str, principal_id: &str, role_id: &str, constraints: Option<String>, effective_at: NaiveDateTime, expired_at: NaiveDateTime, approver_role_id: &str) -> AssignmentRequest {
+        AssignmentRequest::new(organization_id, principal_id, Some(role_id.to_string()), None, "", constraints, effective_at, expired_at, approver_role_id)
+    }
+
+    /// Creates pending request for assigning claim to the principal
+    pub fn for_claim(organization_id: &str, principal_id: &str, claim_id: &str, scope: &str, constraints: Option<String>, effective_at: NaiveDateTime, expired_at: NaiveDateTime, approver_role_id: &str) -> AssignmentRequest {
+        AssignmentRequest::new(organization_id, principal_id, None, Some(claim_id.to_string()), scope, constraints, effective_at, expired_at, approver_role_id)
+    }
+
+    fn new(organization_id: &str, principal_id: &str, role_id: Option<String>, claim_id: Option<String>, scope: &str, constraints: Option<String>, effective_at: NaiveDateTime, expired_at: NaiveDateTime, approver_role_id: &str) -> AssignmentRequest {
+        AssignmentRequest {
+            id: "".to_string(),
+            organization_id: organization_id.to_string(),
+            principal_id: principal_id.to_string(),
+            role_id: role_id,
+            claim_id: claim_id,
+            scope: scope.to_string(),
+            constraints: constraints,
+            effective_at: effective_at,
+            expired_at: expired_at,
+            approver_role_id: approver_role_id.to_string(),
+            status: Status::PENDING.to_string(),
+            reviewed_by: None,
+            review_comment: None,
+            created_at: Some(Utc::now().naive_utc()),
+            created_by: None,
+            updated_at: Some(Utc::now().naive_utc()),
+            updated_by: None
+        }
+    }
+}
+
+/// BreakGlass defines emergency access to a break-glass role that is granted to a principal
+/// until given time along with mandatory justification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BreakGlass {
+    #[serde(default)]
+    pub principal_id: String,
+    pub role_id: String,
+    pub justification: String,
+    pub expired_at: NaiveDateTime,
+}
+
+impl BreakGlass {
+    /// Maximum hours that break-glass access can be granted for
+    pub const MAX_HOURS: i64 = 24;
+
+    pub fn new(principal_id: &str, role_id: &str, justification: &str, expired_at: NaiveDateTime) -> BreakGlass {
+        BreakGlass {
+            principal_id: principal_id.to_string(),
+            role_id: role_id.to_string(),
+            justification: justification.to_string(),
+            expired_at: expired_at,
+        }
+    }
+}
+
+/// PolicyMutation defines a proposed change to the policy that is applied as an overlay for
+/// what-if simulation without being saved in the database
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum PolicyMutation {
+    AddPrincipalToRole {
+        principal_id: String,
+        role_id: String,
+        #[serde(default)]
+        constraints: String,
+    },
+    MapRoleToClaim {
+        role_id: String,
+        claim_id: String,
+        #[serde(default)]
+        scope: String,
+        #[serde(default)]
+        constraints: String,
+    },
+    MapPrincipalToClaim {
+        principal_id: String,
+        claim_id: String,
+        #[serde(default)]
+        scope: String,
+        #[serde(default)]
+        constraints: String,
+    },
+    ChangeClaimAction {
+        claim_id: String,
+        action: String,
+    },
+}
+
 #[cfg(test)]
 mod tests {
     extern crate uuid as uuu;
     use self::uuu::Uuid;
     use plexrbac::domain::models::*;
     use chrono::{NaiveDate, Utc};
+    use std::collections::HashSet;
 
     #[test]
     fn test_create_realm() {
@@ -783,4 +1460,59 @@ mod tests {
         let license_policy = PLicensePolicy::new(Uuid::new_v4().to_hyphenated().to_string().as_str(), "99", "mylicense_policy", None, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0));
         assert_eq!("mylicense_policy", license_policy.name);
     }
+
+    #[test]
+    fn test_sod_rule_violation() {
+        let rule = SodRule::new("", "realm", "99", "teller-manager", None, vec!["teller".into(), "manager".into(), "auditor".into()], 2, false);
+        let mut roles = HashSet::new();
+        roles.insert("teller".to_string());
+        roles.insert("clerk".to_string());
+        assert!(!rule.violated_by(&roles));
+        roles.insert("auditor".to_string());
+        assert!(rule.violated_by(&roles));
+        assert_eq!(rule.role_ids, SodRule::from(&rule.to()).role_ids);
+    }
+
+    #[test]
+    fn test_create_delegation() {
+        let delegation = Delegation::new("", "99", "11", "12", vec!["1".into()], vec![], Some("amount < 100".into()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0));
+        let loaded = Delegation::from(&delegation.to());
+        assert_eq!(vec!["1".to_string()], loaded.role_ids);
+        assert_eq!(0, loaded.claim_ids.len());
+        assert_eq!(Some("amount < 100".to_string()), loaded.constraints);
+    }
+
+    #[test]
+    fn test_create_assignment_request() {
+        let request = AssignmentRequest::for_claim("99", "11", "1", "com.abc", None, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0), "2");
+        let loaded = AssignmentRequest::from(&request.to());
+        assert_eq!("PENDING", loaded.status);
+        assert_eq!(None, loaded.role_id);
+        assert_eq!(Some("1".to_string()), loaded.claim_id);
+        assert_eq!("com.abc", loaded.scope);
+    }
+
+    #[test]
+    fn test_permission_diff() {
+        let a = vec![PermissionTuple::new("Account", "READ", "", "", "Allow"), PermissionTuple::new("Account", "UPDATE", "", "", "Allow"), PermissionTuple::new("Account", "READ", "", "", "Allow")];
+        let b = vec![PermissionTuple::new("Account", "READ", "", "", "Allow"), PermissionTuple::new("Account", "UPDATE", "", "amount < 100", "Allow")];
+        let diff = PermissionDiff::new(&a, &b);
+        assert_eq!(vec![PermissionTuple::new("Account", "UPDATE", "", "", "Allow")], diff.only_in_a);
+        assert_eq!(vec![PermissionTuple::new("Account", "UPDATE", "", "amount < 100", "Allow")], diff.only_in_b);
+        assert_eq!(vec![PermissionTuple::new("Account", "READ", "", "", "Allow")], diff.common);
+        // opposite effects are different permissions
+        let diff = PermissionDiff::new(&vec![PermissionTuple::new("Account", "DELETE", "", "", "Allow")], &vec![PermissionTuple::new("Account", "DELETE", "", "", "Deny")]);
+        assert_eq!(1, diff.only_in_a.len());
+        assert_eq!(1, diff.only_in_b.len());
+        assert_eq!(0, diff.common.len());
+    }
+
+    #[test]
+    fn test_create_session() {
+        let session = Session::new("", "realm1", "99", "11", vec!["1".into(), "2".into()], NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0));
+        let loaded = Session::from(&session.to());
+        assert_eq!(vec!["1".to_string(), "2".to_string()], loaded.role_ids);
+        assert!(loaded.is_active());
+        assert!(!Session::new("", "realm1", "99", "11", vec![], Utc::now().naive_utc()).is_active());
+    }
 }
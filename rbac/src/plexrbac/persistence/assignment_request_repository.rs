@@ -0,0 +1,272 @@
+//#![crate_name = "doc"]
+extern crate uuid as uuu;
+
+use diesel::prelude::*;
+use super::schema::{rbac_assignment_requests, rbac_role_roleables, rbac_claim_claimables};
+use super::models::{PAssignmentRequest, PRoleRoleable, PClaimClaimable};
+use chrono::{Utc};
+use plexrbac::domain::models::AssignmentRequest;
+use plexrbac::common::Constants;
+use plexrbac::common::SecurityContext;
+use plexrbac::common::RbacError;
+use plexrbac::common::Status;
+use super::cache::CLAIM_CACHE;
+use self::uuu::Uuid;
+
+//////////////////////////////////////////////////////////////////////////////////////////////
+/// AssignmentRequestRepository defines methods for accessing and persisting requests for
+/// assigning roles or claims to principals that need to be approved before they are effective
+///
+pub struct AssignmentRequestRepository<'a> {
+    pub data_source: &'a dyn super::data_source::DataSource,
+    pub audit_record_repository: super::audit_record_repository::AuditRecordRepository<'a>,
+}
+
+impl<'a> AssignmentRequestRepository<'a> {
+    /// Creates assignment-request in pending state
+    pub fn create(&self, ctx: &SecurityContext, request: &AssignmentRequest) -> Result<AssignmentRequest, RbacError> {
+        self.validate(ctx, request)?;
+        let mut db_obj = request.to();
+        db_obj.id = Uuid::new_v4().to_hyphenated().to_string();
+        db_obj.status = Status::PENDING.to_string();
+        db_obj.reviewed_by = None;
+        db_obj.review_comment = None;
+        db_obj.created_at = Some(Utc::now().naive_utc());
+        db_obj.created_by = Some(ctx.principal_id.clone());
+        db_obj.updated_at = Some(Utc::now().naive_utc());
+        db_obj.updated_by = Some(ctx.principal_id.clone());
+        if let Err(err) = self._create(&db_obj) {
+            return Err(RbacError::Persistence(err.to_string()));
+        }
+        self.audit(ctx, format!("Requested assignment {:?}", db_obj), "CREATE");
+        Ok(AssignmentRequest::from(&db_obj))
+    }
+
+    /// Updates status of the assignment-request along with reviewer and comment
+    pub fn update(&self, ctx: &SecurityContext, request: &AssignmentRequest) -> Result<AssignmentRequest, RbacError> {
+        match self._get(request.organization_id.as_str(), request.id.as_str()) {
+            Some(mut db_obj) => {
+                db_obj.status = request.status.clone();
+                db_obj.reviewed_by = request.reviewed_by.clone();
+                db_obj.review_comment = request.review_comment.clone();
+                db_obj.updated_at = Some(Utc::now().naive_utc());
+                db_obj.updated_by = Some(ctx.principal_id.clone());
+                if let Err(err) = self._update(&db_obj) {
+                    return Err(RbacError::Persistence(err.to_string()));
+                }
+                self.audit(ctx, format!("Updated assignment request {:?}", db_obj), "UPDATE");
+                Ok(AssignmentRequest::from(&db_obj))
+            }
+            None => Err(RbacError::NotFound(format!("Assignment request not found {:?}", request)))
+        }
+    }
+
+    /// Approves pending assignment-request by assigning its role or claim to the principal and
+    /// saving reviewer and comment of the request within a single transaction, so that the
+    /// assignment is never saved without the approval or vice versa
+    pub fn approve(&self, ctx: &SecurityContext, request: &AssignmentRequest) -> Result<AssignmentRequest, RbacError> {
+        let constraints = request.constraints.clone().unwrap_or_default();
+        let principal = Constants::Principal.to_string();
+        let now = Utc::now().naive_utc();
+        let connection = match self.data_source.new_connection() {
+            Ok(connection) => connection,
+            Err(err) => return Err(RbacError::Persistence(err.to_string())),
+        };
+        let result = connection.transaction::<usize, diesel::result::Error, _>(|| {
+            let n = diesel::update(rbac_assignment_requests::table
+                    .filter(rbac_assignment_requests::organization_id.eq(request.organization_id.clone()))
+                    .filter(rbac_assignment_requests::id.eq(request.id.clone()))
+                    .filter(rbac_assignment_requests::status.eq(Status::PENDING.to_string())))
+                .set((rbac_assignment_requests::status.eq(Status::APPROVED.to_string()),
+                      rbac_assignment_requests::reviewed_by.eq(request.reviewed_by.clone()),
+                      rbac_assignment_requests::review_comment.eq(request.review_comment.clone()),
+                      rbac_assignment_requests::updated_by.eq(Some(ctx.principal_id.clone())),
+                      rbac_assignment_requests::updated_at.eq(Some(now))))
+                .execute(&*connection)?;
+            if n == 0 {
+                return Ok(n);
+            }
+            if let Some(ref role_id) = request.role_id {
+                let rr = PRoleRoleable::new(role_id.as_str(), request.principal_id.as_str(), principal.as_str(), constraints.as_str(), request.effective_at, request.expired_at);
+                diesel::insert_into(rbac_role_roleables::table).values(&rr).execute(&*connection)?;
+            } else if let Some(ref claim_id) = request.claim_id {
+                let cc = PClaimClaimable::new(claim_id.as_str(), request.principal_id.as_str(), principal.as_str(), request.scope.as_str(), constraints.as_str(), request.effective_at, request.expired_at);
+                diesel::insert_into(rbac_claim_claimables::table).values(&cc).execute(&*connection)?;
+            }
+            Ok(n)
+        });
+        match result {
+            Ok(0) => Err(RbacError::Custom(format!("Assignment request {} is not pending", request.id))),
+            Ok(_) => {
+                CLAIM_CACHE.invalidate_principal(request.principal_id.as_str());
+                self.audit(ctx, format!("Assigned {:?}/{:?} to principal {} for request {}", request.role_id, request.claim_id, request.principal_id, request.id), "CREATE");
+                self.get(ctx, request.organization_id.as_str(), request.id.as_str()).ok_or_else(|| RbacError::NotFound(format!("Assignment request not found {:?}", request)))
+            },
+            Err(err) => Err(RbacError::Persistence(err.to_string())),
+        }
+    }
+
+    /// Retrieves assignment-request by id from the database
+    pub fn get(&self, _ctx: &SecurityContext, org_id: &str, id: &str) -> Option<AssignmentRequest> {
+        match self._get(org_id, id) {
+            Some(request) => Some(AssignmentRequest::from(&request)),
+            _ => None,
+        }
+    }
+
+    /// Returns all assignment-requests of the organization
+    pub fn get_by_org(&self, _ctx: &SecurityContext, org_id: &str) -> Vec<AssignmentRequest> {
+        self._get_by_org_and_status(org_id, None).iter().map(|r| AssignmentRequest::from(&r)).collect::<Vec<AssignmentRequest>>()
+    }
+
+    /// Returns assignment-requests of the organization that are waiting for approval
+    pub fn get_pending_by_org(&self, _ctx: &SecurityContext, org_id: &str) -> Vec<AssignmentRequest> {
+        self._get_by_org_and_status(org_id, Some(Status::PENDING.to_string())).iter().map(|r| AssignmentRequest::from(&r)).collect::<Vec<AssignmentRequest>>()
+    }
+
+    /// Deletes assignment-request by id from the database
+    pub fn delete(&self, ctx: &SecurityContext, org_id: &str, id: &str) -> Result<usize, RbacError> {
+        match self._delete(org_id, id) {
+            Ok(n) => {
+                self.audit(ctx, format!("Deleted assignment request {}", id), "DELETE");
+                Ok(n)
+            },
+            Err(err) => Err(RbacError::Persistence(err.to_string())),
+        }
+    }
+
+    ///////////////////////////////////// PRIVATE METHODS ////////////////////////////////
+    fn audit(&self, ctx: &SecurityContext, message: String, action: &str) {
+        let _ = self.audit_record_repository.create_with(message.as_str(), action, format!("{:?}", ctx).as_str(), ctx.principal_id.clone());
+        info!("{}", message);
+    }
+
+    /// Verifies that request assigns either a role or a claim, defines approver role and has
+    /// valid date range
+    fn validate(&self, ctx: &SecurityContext, request: &AssignmentRequest) -> Result<(), RbacError> {
+        let err = if request.role_id.is_some() == request.claim_id.is_some() {
+            Some(format!("Assignment request for {} must define either role or claim", request.principal_id))
+        } else if request.approver_role_id.len() == 0 {
+            Some(format!("Assignment request for {} must define approver role", request.principal_id))
+        } else if request.effective_at >= request.expired_at {
+            Some(format!("Assignment request for {} expires before it's effective", request.principal_id))
+        } else {
+            None
+        };
+        if let Some(err) = err {
+            self.audit(ctx, format!("Invalid assignment request {} -- {:?}", err, request), "CREATE");
+            return Err(RbacError::Custom(err));
+        }
+        Ok(())
+    }
+
+    /// Creates instance of assignment-request
+    fn _create(&self, request: &PAssignmentRequest) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::insert_into(rbac_assignment_requests::table).values(request).execute(&*connection)
+    }
+
+    /// Updates previous instance of the assignment-request
+    fn _update(&self, request: &PAssignmentRequest) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::update(rbac_assignment_requests::table.find(request.id.clone())).set(request).
+            execute(&*connection)
+    }
+
+    /// Deletes instance of the assignment-request by id from the database
+    fn _delete(&self, org_id: &str, id: &str) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::delete(rbac_assignment_requests::table
+                .filter(rbac_assignment_requests::organization_id.eq(org_id.to_string()))
+                .filter(rbac_assignment_requests::id.eq(id.to_string())))
+                .execute(&*connection)
+    }
+
+    /// Retrieves instance of the assignment-request by id from the database
+    fn _get(&self, org_id: &str, id: &str) -> Option<PAssignmentRequest> {
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_assignment_requests::table
+                .filter(rbac_assignment_requests::organization_id.eq(org_id.to_string()))
+                .filter(rbac_assignment_requests::id.eq(id.to_string()))
+                .load::<PAssignmentRequest>(&*connection) {
+                    Ok(v) => {
+                        if let Some(r) = v.first() {
+                            Some(r.clone())
+                        } else {
+                            None
+                        }
+                    }
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Retrieves assignment-requests by organization-id and optionally by status
+    fn _get_by_org_and_status(&self, organization_id: &str, status: Option<String>) -> Vec<PAssignmentRequest> {
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_assignment_requests::table
+                .filter(rbac_assignment_requests::organization_id.eq(organization_id.to_string()))
+                .order(rbac_assignment_requests::created_at.asc())
+                .load::<PAssignmentRequest>(&*connection) {
+                Ok(v) => v.into_iter().filter(|r| status.as_ref().map_or(true, |s| r.status == *s)).collect(),
+                _ => vec![],
+            }
+        } else {
+            vec![]
+        }
+    }
+
+    /// Removes all instances of the assignment-request from the database for testing
+    pub fn clear(&self) {
+        let connection = self.data_source.new_connection().unwrap();
+        let _ = diesel::delete(rbac_assignment_requests::table).execute(&*connection);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use plexrbac::persistence::locator::RepositoryLocator;
+    use plexrbac::persistence::data_source::DefaultDataSource;
+    use plexrbac::domain::models::AssignmentRequest;
+    use plexrbac::common::SecurityContext;
+    use chrono::{NaiveDate, Utc};
+
+    #[test]
+    fn test_create_update() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_assignment_request_repository();
+        repo.clear();
+
+        let mut request = repo.create(&ctx, &AssignmentRequest::for_role("99", "11", "1", None, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0), "2")).unwrap();
+        let loaded = repo.get(&ctx, "99", request.id.as_str()).unwrap();
+        assert_eq!(format!("{:?}", request), format!("{:?}", loaded));
+        assert_eq!(1, repo.get_pending_by_org(&ctx, "99").len());
+
+        request.status = "APPROVED".into();
+        request.reviewed_by = Some("12".into());
+        repo.update(&ctx, &request).unwrap();
+        assert_eq!(0, repo.get_pending_by_org(&ctx, "99").len());
+        assert_eq!(1, repo.get_by_org(&ctx, "99").len());
+        repo.delete(&ctx, "99", request.id.as_str()).unwrap();
+        assert!(repo.get(&ctx, "99", request.id.as_str()).is_none());
+    }
+
+    #[test]
+    fn test_create_invalid() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_assignment_request_repository();
+
+        let mut request = AssignmentRequest::for_role("99", "11", "1", None, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0), "2");
+        request.claim_id = Some("3".into());
+        assert!(repo.create(&ctx, &request).is_err());
+        assert!(repo.create(&ctx, &AssignmentRequest::for_role("99", "11", "1", None, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0), "")).is_err());
+        assert!(repo.create(&ctx, &AssignmentRequest::for_role("99", "11", "1", None, NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0), Utc::now().naive_utc(), "2")).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//#![crate_name = "doc"]
+extern crate uuid as uuu;
+
+use diesel::prelude::*;
+use super::schema::rbac_attributes;
+use super::models::{PAttribute};
+use chrono::{Utc};
+use plexrbac::domain::models::Attribute;
+use plexrbac::common::{Constants, SecurityContext};
+use plexrbac::common::RbacError;
+use plexrbac::utils::text;
+use super::cache::CLAIM_CACHE;
+use self::uuu::Uuid;
+
+//////////////////////////////////////////////////////////////////////////////////////////////
+/// AttributeRepository defines methods for accessing and persisting typed attributes of
+/// principals, groups and organizations
+///
+pub struct AttributeRepository<'a> {
+    pub data_source: &'a dyn super::data_source::DataSource,
+    pub audit_record_repository: super::audit_record_repository::AuditRecordRepository<'a>,
+}
+
+impl<'a> AttributeRepository<'a> {
+    /// Creates or updates attribute of the owner by name
+    pub fn save(&self, ctx: &SecurityContext, attribute: &Attribute) -> Result<Attribute, RbacError> {
+        if !text::regex_match(r"^[A-Za-z_][A-Za-z0-9_]*$", attribute.name.as_str()) {
+            return Err(RbacError::Custom(format!("Invalid attribute name '{}', only letters, digits and underscore are allowed", attribute.name)));
+        }
+        let mut db_obj = attribute.to();
+        db_obj.updated_at = Some(Utc::now().naive_utc());
+        db_obj.updated_by = Some(ctx.principal_id.clone());
+        if let Some(existing) = self._get(attribute.owner_type.as_str(), attribute.owner_id.as_str(), attribute.name.as_str()) {
+            db_obj.id = existing.id.clone();
+            db_obj.created_at = existing.created_at.clone();
+            db_obj.created_by = existing.created_by.clone();
+            if let Err(err) = self._update(&db_obj) {
+                return Err(RbacError::Persistence(err.to_string()));
+            }
+            self.audit(ctx, format!("Updated attribute {:?}", db_obj), "UPDATE");
+        } else {
+            db_obj.id = Uuid::new_v4().to_hyphenated().to_string();
+            db_obj.created_at = Some(Utc::now().naive_utc());
+            db_obj.created_by = Some(ctx.principal_id.clone());
+            if let Err(err) = self._create(&db_obj) {
+                return Err(RbacError::Persistence(err.to_string()));
+            }
+            self.audit(ctx, format!("Adding new attribute {:?}", db_obj), "CREATE");
+        }
+        self.invalidate(&db_obj);
+        Ok(Attribute::from(&db_obj))
+    }
+
+    /// Returns attributes of the owner sorted by name
+    pub fn get_by_owner(&self, _ctx: &SecurityContext, owner_type: &str, owner_id: &str) -> Vec<Attribute> {
+        self._get_by_owners(owner_type, vec![owner_id.to_string()]).iter().map(|a| Attribute::from(&a)).collect::<Vec<Attribute>>()
+    }
+
+    /// Deletes attribute of the owner by name
+    pub fn delete(&self, ctx: &SecurityContext, owner_type: &str, owner_id: &str, name: &str) -> Result<usize, RbacError> {
+        let existing = self._get(owner_type, owner_id, name);
+        match self._delete(owner_type, owner_id, name) {
+            Ok(n) => {
+                if let Some(attribute) = existing {
+                    self.audit(ctx, format!("Deleted attribute {:?}", attribute), "DELETE");
+                    self.invalidate(&attribute);
+                }
+                Ok(n)
+            },
+            Err(err) => Err(RbacError::Persistence(err.to_string())),
+        }
+    }
+
+    /// Returns attributes of given owners sorted by name
+    pub fn _get_by_owners(&self, owner_type: &str, owner_ids: Vec<String>) -> Vec<PAttribute> {
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_attributes::table
+                .filter(rbac_attributes::owner_type.eq(owner_type.to_string()))
+                .filter(rbac_attributes::owner_id.eq_any(owner_ids))
+                .order(rbac_attributes::name.asc())
+                .load::<PAttribute>(&*connection) {
+                Ok(v) => v,
+                _ => vec![],
+            }
+        } else {
+            vec![]
+        }
+    }
+
+    ///////////////////////////////////// PRIVATE METHODS ////////////////////////////////
+    fn audit(&self, ctx: &SecurityContext, message: String, action: &str) {
+        let _ = self.audit_record_repository.create_with(message.as_str(), action, format!("{:?}", ctx).as_str(), ctx.principal_id.clone());
+        info!("{}", message);
+    }
+
+    /// Invalidates cached principals that depend on the attribute
+    fn invalidate(&self, attribute: &PAttribute) {
+        if attribute.owner_type == Constants::Principal.to_string() {
+            CLAIM_CACHE.invalidate_principal(attribute.owner_id.as_str());
+        } else if attribute.owner_type == Constants::Group.to_string() {
+            CLAIM_CACHE.invalidate_group(attribute.owner_id.as_str());
+        } else if attribute.owner_type == Constants::Organization.to_string() {
+            CLAIM_CACHE.invalidate_org(attribute.owner_id.as_str());
+        }
+    }
+
+    /// Creates instance of attribute
+    fn _create(&self, attribute: &PAttribute) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::insert_into(rbac_attributes::table).values(attribute).execute(&*connection)
+    }
+
+    /// Updates instance of attribute
+    fn _update(&self, attribute: &PAttribute) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::update(rbac_attributes::table.find(attribute.id.clone())).set(attribute).execute(&*connection)
+    }
+
+    /// Deletes instance of the attribute by owner and name from the database
+    fn _delete(&self, owner_type: &str, owner_id: &str, name: &str) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::delete(rbac_attributes::table
+                .filter(rbac_attributes::owner_type.eq(owner_type.to_string()))
+                .filter(rbac_attributes::owner_id.eq(owner_id.to_string()))
+                .filter(rbac_attributes::name.eq(name.to_string())))
+                .execute(&*connection)
+    }
+
+    /// Retrieves instance of the attribute by owner and name from the database
+    fn _get(&self, owner_type: &str, owner_id: &str, name: &str) -> Option<PAttribute> {
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_attributes::table
+                .filter(rbac_attributes::owner_type.eq(owner_type.to_string()))
+                .filter(rbac_attributes::owner_id.eq(owner_id.to_string()))
+                .filter(rbac_attributes::name.eq(name.to_string()))
+                .load::<PAttribute>(&*connection) {
+                Ok(v) => {
+                    if let Some(a) = v.first() {
+                        Some(a.clone())
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Removes all instances of the attribute from the database for testing
+    pub fn clear(&self) {
+        let connection = self.data_source.new_connection().unwrap();
+        let _ = diesel::delete(rbac_attributes::table).execute(&*connection);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use plexrbac::persistence::locator::RepositoryLocator;
+    use plexrbac::persistence::data_source::DefaultDataSource;
+    use plexrbac::domain::models::Attribute;
+    use plexrbac::common::{SecurityContext, ValueWrapper};
+
+    #[test]
+    fn test_save_delete() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_attribute_repository();
+        repo.clear();
+
+        let region = repo.save(&ctx, &Attribute::new("", "11", "Principal", "region", ValueWrapper::String("Midwest".into()))).unwrap();
+        let level = repo.save(&ctx, &Attribute::new("", "11", "Principal", "level", ValueWrapper::Int(3))).unwrap();
+        assert_eq!(ValueWrapper::Int(3), level.value);
+        assert!(repo.save(&ctx, &Attribute::new("", "11", "Principal", "bad name", ValueWrapper::Bool(true))).is_err());
+
+        // saving existing attribute updates its value and type
+        let updated = repo.save(&ctx, &Attribute::new("", "11", "Principal", "level", ValueWrapper::Float(3.5))).unwrap();
+        assert_eq!(level.id, updated.id);
+        let loaded = repo.get_by_owner(&ctx, "Principal", "11");
+        assert_eq!(vec!["level".to_string(), "region".to_string()], loaded.iter().map(|a| a.name.clone()).collect::<Vec<String>>());
+        assert_eq!(ValueWrapper::Float(3.5), loaded[0].value);
+        assert_eq!(region.value, loaded[1].value);
+        assert_eq!(0, repo.get_by_owner(&ctx, "Group", "11").len());
+
+        assert_eq!(1, repo.delete(&ctx, "Principal", "11", "region").unwrap());
+        assert_eq!(1, repo.get_by_owner(&ctx, "Principal", "11").len());
+    }
+}
@@ -30,6 +30,22 @@ impl<'a> AuditRecordRepository<'a> {
         }
     }
 
+    /// Returns latest audit records with given severity
+    pub fn latest_by_severity(&self, severity: &str, max: i64) -> Vec<PAuditRecord> {
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_audit_records::table
+                .filter(rbac_audit_records::severity.eq(severity.to_string()))
+                .limit(max)
+                .order(rbac_audit_records::created_at.desc())
+                .load::<PAuditRecord>(&*connection) {
+                Ok(v) => v,
+                _ => vec![],
+            }
+        } else {
+            vec![]
+        }
+    }
+
     /// Creates new instance of the audit-record
     pub fn create_with(&self, message: &str, action: &str, context: &str, created_by: String) -> Result<usize, diesel::result::Error> {
         self.create(
@@ -38,11 +54,21 @@ impl<'a> AuditRecordRepository<'a> {
                  message: message.to_string(),
                  action: Some(action.to_string()),
                  context: Some(context.to_string()),
+                 severity: None,
                  created_at: Some(Utc::now().naive_utc()),
                  created_by: Some(created_by.clone()),
             })
     }
 
+    /// Creates new instance of the audit-record with given severity, e.g. HIGH for records
+    /// that need to be reviewed such as break-glass access
+    pub fn create_with_severity(&self, message: &str, action: &str, context: &str, created_by: String, severity: &str) -> Result<usize, diesel::result::Error> {
+        let mut rec = PAuditRecord::new(message, Some(action.to_string()), Some(context.to_string()));
+        rec.severity = Some(severity.to_string());
+        rec.created_by = Some(created_by);
+        self.create(&rec)
+    }
+
     /// Creates new instance of the audit-record
     pub fn create(&self, rec: &PAuditRecord) -> Result<usize, diesel::result::Error> {
         let connection = self.data_source.new_connection()?;
@@ -96,4 +122,18 @@ mod tests {
         let results = repo.latest(10);
         assert_eq!(2, results.len());
     }
+
+    #[test]
+    fn test_get_latest_by_severity() {
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_audit_record_repository();
+        repo.clear();
+
+        repo.create_with("mymessage1", "CREATE", "mcontext", "myid".into()).unwrap();
+        repo.create_with_severity("mymessage2", "BREAK_GLASS", "mcontext", "myid".into(), "HIGH").unwrap();
+        let results = repo.latest_by_severity("HIGH", 10);
+        assert_eq!(1, results.len());
+        assert_eq!("mymessage2", results[0].message);
+    }
 }
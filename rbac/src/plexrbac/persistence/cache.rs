@@ -0,0 +1,273 @@
+//#![crate_name = "doc"]
+
+use plexrbac::domain::models::{Principal, ClaimClaimable};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use chrono::{NaiveDateTime, Utc};
+
+lazy_static! {
+    /// Process-wide cache of security claims that is shared by all repositories
+    pub static ref CLAIM_CACHE: ClaimCache = ClaimCache::new(Duration::from_secs(60));
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////
+/// CacheStats defines hit/miss statistics of claim cache
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    pub invalidations: usize,
+    pub principal_entries: usize,
+    pub org_entries: usize,
+}
+
+//////////////////////////////////////////////////////////////////////////////////////////////
+/// ClaimCache caches populated principals keyed by realm, org and principal-id and claims of
+/// organizations keyed by realm and org-id. Entries expire after ttl so that effective/expired
+/// dates of associations are honored, and repositories invalidate affected entries upon writes.
+/// A principal entry expires no later than the earliest expiration of its time-bounded grants.
+///
+pub struct ClaimCache {
+    ttl: Duration,
+    principals: Mutex<HashMap<(String, String, String), (Principal, Instant)>>,
+    orgs: Mutex<HashMap<(String, String), (Vec<ClaimClaimable>, Instant)>>,
+    ancestors: Mutex<HashMap<String, Vec<String>>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    invalidations: AtomicUsize,
+}
+
+impl ClaimCache {
+    /// Creates instance of claim cache
+    pub fn new(ttl: Duration) -> ClaimCache {
+        ClaimCache {
+            ttl: ttl,
+            principals: Mutex::new(HashMap::new()),
+            orgs: Mutex::new(HashMap::new()),
+            ancestors: Mutex::new(HashMap::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            invalidations: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns populated principal if it's cached
+    pub fn get_principal(&self, realm_id: &str, organization_id: &str, principal_id: &str) -> Option<Principal> {
+        let key = (realm_id.to_string(), organization_id.to_string(), principal_id.to_string());
+        let mut principals = self.principals.lock().unwrap();
+        let found = match principals.get(&key) {
+            Some((principal, expires_at)) if Instant::now() < *expires_at => Some(principal.clone()),
+            _ => None,
+        };
+        if found.is_some() {
+            self.hits.fetch_add(1, Ordering::SeqCst);
+        } else {
+            principals.remove(&key);
+            self.misses.fetch_add(1, Ordering::SeqCst);
+        }
+        found
+    }
+
+    /// Adds populated principal to the cache
+    pub fn put_principal(&self, realm_id: &str, principal: &Principal) {
+        self.put_principal_until(realm_id, principal, None);
+    }
+
+    /// Adds populated principal to the cache until ttl elapses or its grants expire at given
+    /// time whichever is earlier, the principal isn't cached if its grants already expired
+    pub fn put_principal_until(&self, realm_id: &str, principal: &Principal, expired_at: Option<NaiveDateTime>) {
+        let mut lifetime = self.ttl;
+        if let Some(expired_at) = expired_at {
+            match (expired_at - Utc::now().naive_utc()).to_std() {
+                Ok(remaining) if remaining < lifetime => lifetime = remaining,
+                Ok(_) => (),
+                Err(_) => return,
+            }
+        }
+        let key = (realm_id.to_string(), principal.organization_id.clone(), principal.id.clone());
+        self.principals.lock().unwrap().insert(key, (principal.clone(), Instant::now() + lifetime));
+    }
+
+    /// Returns claims of organization if they are cached
+    pub fn get_org_claims(&self, realm_id: &str, organization_id: &str) -> Option<Vec<ClaimClaimable>> {
+        let key = (realm_id.to_string(), organization_id.to_string());
+        let mut orgs = self.orgs.lock().unwrap();
+        let found = match orgs.get(&key) {
+            Some((claims, cached_at)) if cached_at.elapsed() < self.ttl => Some(claims.clone()),
+            _ => None,
+        };
+        if found.is_some() {
+            self.hits.fetch_add(1, Ordering::SeqCst);
+        } else {
+            orgs.remove(&key);
+            self.misses.fetch_add(1, Ordering::SeqCst);
+        }
+        found
+    }
+
+    /// Adds claims of organization to the cache
+    pub fn put_org_claims(&self, realm_id: &str, organization_id: &str, claims: &Vec<ClaimClaimable>) {
+        let key = (realm_id.to_string(), organization_id.to_string());
+        self.orgs.lock().unwrap().insert(key, (claims.clone(), Instant::now()));
+    }
+
+    /// Records ancestors of organization so that changes to an ancestor also invalidate entries
+    /// of the organization that inherits from it
+    pub fn put_org_ancestors(&self, organization_id: &str, ancestor_ids: &Vec<String>) {
+        self.ancestors.lock().unwrap().insert(organization_id.to_string(), ancestor_ids.clone());
+    }
+
+    /// Invalidates all entries of the principal along with principals that have delegated claims
+    /// because they depend on claims of their delegators
+    pub fn invalidate_principal(&self, principal_id: &str) {
+        self.principals.lock().unwrap().retain(|(_, _, id), (principal, _)| id != principal_id && !ClaimCache::has_delegated_claims(principal));
+        self.invalidations.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Invalidates principals that are assigned given role directly, via group or parent
+    pub fn invalidate_role(&self, role_id: &str) {
+        self.principals.lock().unwrap().retain(|_, (principal, _)| !principal.roles.contains_key(role_id) && !ClaimCache::has_delegated_claims(principal));
+        self.invalidations.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Invalidates principals that are members of given group
+    pub fn invalidate_group(&self, group_id: &str) {
+        self.principals.lock().unwrap().retain(|_, (principal, _)| !principal.groups.contains_key(group_id) && !ClaimCache::has_delegated_claims(principal));
+        self.invalidations.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Invalidates claims of organization, organizations that inherit from it and all of their principals
+    pub fn invalidate_org(&self, organization_id: &str) {
+        let mut affected = vec![organization_id.to_string()];
+        {
+            let mut ancestors = self.ancestors.lock().unwrap();
+            for (org_id, ancestor_ids) in ancestors.iter() {
+                if ancestor_ids.iter().any(|id| id == organization_id) {
+                    affected.push(org_id.clone());
+                }
+            }
+            ancestors.retain(|org_id, _| !affected.contains(org_id));
+        }
+        self.principals.lock().unwrap().retain(|(_, org_id, _), _| !affected.contains(org_id));
+        self.orgs.lock().unwrap().retain(|(_, org_id), _| !affected.contains(org_id));
+        self.invalidations.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Invalidates all entries that belong to the security realm
+    pub fn invalidate_realm(&self, realm_id: &str) {
+        self.principals.lock().unwrap().retain(|(id, _, _), _| id != realm_id);
+        self.orgs.lock().unwrap().retain(|(id, _), _| id != realm_id);
+        self.invalidations.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Invalidates all entries
+    pub fn invalidate_all(&self) {
+        self.principals.lock().unwrap().clear();
+        self.orgs.lock().unwrap().clear();
+        self.ancestors.lock().unwrap().clear();
+        self.invalidations.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn has_delegated_claims(principal: &Principal) -> bool {
+        principal.claims.iter().any(|cc| match cc {
+            ClaimClaimable::Delegation(_, _, _, _, _, _) => true,
+            _ => false,
+        })
+    }
+
+    /// Returns hit/miss statistics of the cache
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::SeqCst),
+            misses: self.misses.load(Ordering::SeqCst),
+            invalidations: self.invalidations.load(Ordering::SeqCst),
+            principal_entries: self.principals.lock().unwrap().len(),
+            org_entries: self.orgs.lock().unwrap().len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plexrbac::persistence::cache::ClaimCache;
+    use plexrbac::domain::models::*;
+    use std::time::Duration;
+    use chrono::Utc;
+
+    #[test]
+    fn test_principal_cache() {
+        let cache = ClaimCache::new(Duration::from_secs(60));
+        let mut principal = Principal::new("p1", "org1", "tom", None);
+        principal.roles.insert("r1".into(), Role::new("r1", "realm1", "org1", "teller", None, None));
+        assert_eq!(None, cache.get_principal("realm1", "org1", "p1"));
+        cache.put_principal("realm1", &principal);
+        assert_eq!(Some(principal.clone()), cache.get_principal("realm1", "org1", "p1"));
+        assert_eq!(None, cache.get_principal("realm2", "org1", "p1"));
+
+        cache.invalidate_role("r2");
+        assert!(cache.get_principal("realm1", "org1", "p1").is_some());
+        cache.invalidate_role("r1");
+        assert_eq!(None, cache.get_principal("realm1", "org1", "p1"));
+
+        cache.put_principal("realm1", &principal);
+        cache.invalidate_org("org1");
+        assert_eq!(None, cache.get_principal("realm1", "org1", "p1"));
+
+        // principals with delegated claims are invalidated along with any other principal
+        let mut delegatee = Principal::new("p2", "org1", "cassy", None);
+        delegatee.claims.push(ClaimClaimable::Delegation(Claim::new("c1", "realm1", "res1", "READ", "allow", None), "realm1".into(), "d1".into(), "p1".into(), "".into(), "".into()));
+        cache.put_principal("realm1", &delegatee);
+        cache.invalidate_principal("p1");
+        assert_eq!(None, cache.get_principal("realm1", "org1", "p2"));
+
+        let stats = cache.stats();
+        assert_eq!(2, stats.hits);
+        assert_eq!(5, stats.misses);
+        assert_eq!(4, stats.invalidations);
+        assert_eq!(0, stats.principal_entries);
+    }
+
+    #[test]
+    fn test_principal_cache_until_grants_expire() {
+        let cache = ClaimCache::new(Duration::from_secs(60));
+        let principal = Principal::new("p1", "org1", "tom", None);
+        cache.put_principal_until("realm1", &principal, Some(Utc::now().naive_utc() - chrono::Duration::seconds(1)));
+        assert_eq!(None, cache.get_principal("realm1", "org1", "p1"));
+        cache.put_principal_until("realm1", &principal, Some(Utc::now().naive_utc() + chrono::Duration::milliseconds(50)));
+        assert!(cache.get_principal("realm1", "org1", "p1").is_some());
+        std::thread::sleep(Duration::from_millis(100));
+        assert_eq!(None, cache.get_principal("realm1", "org1", "p1"));
+        cache.put_principal_until("realm1", &principal, Some(Utc::now().naive_utc() + chrono::Duration::days(1)));
+        assert!(cache.get_principal("realm1", "org1", "p1").is_some());
+    }
+
+    #[test]
+    fn test_org_claims_cache() {
+        let cache = ClaimCache::new(Duration::from_secs(0));
+        let claims = vec![ClaimClaimable::Realm(Claim::new("c1", "realm1", "res1", "READ", "allow", None), "realm1".into())];
+        cache.put_org_claims("realm1", "org1", &claims);
+        // expired immediately because of zero ttl
+        assert_eq!(None, cache.get_org_claims("realm1", "org1"));
+
+        let cache = ClaimCache::new(Duration::from_secs(60));
+        cache.put_org_claims("realm1", "org1", &claims);
+        assert_eq!(Some(claims.clone()), cache.get_org_claims("realm1", "org1"));
+        cache.invalidate_realm("realm1");
+        assert_eq!(None, cache.get_org_claims("realm1", "org1"));
+        assert_eq!(0, cache.stats().org_entries);
+
+        // claims of subsidiaries are invalidated along with their parent organization
+        cache.put_org_claims("realm1", "org1", &claims);
+        cache.put_org_claims("realm1", "org2", &claims);
+        cache.put_org_claims("realm1", "org3", &claims);
+        cache.put_org_ancestors("org2", &vec!["org1".to_string()]);
+        cache.put_org_ancestors("org3", &vec!["org2".to_string(), "org1".to_string()]);
+        cache.invalidate_org("org2");
+        assert!(cache.get_org_claims("realm1", "org1").is_some());
+        assert_eq!(None, cache.get_org_claims("realm1", "org2"));
+        assert_eq!(None, cache.get_org_claims("realm1", "org3"));
+    }
+}
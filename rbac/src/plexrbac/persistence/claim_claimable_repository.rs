@@ -7,6 +7,7 @@ use super::models::PClaimClaimable;
 use plexrbac::common::SecurityContext;
 use plexrbac::common::RbacError;
 use chrono::{NaiveDateTime, Utc};
+use super::cache::CLAIM_CACHE;
 
 //////////////////////////////////////////////////////////////////////////////////////////////
 /// ClaimClaimableRepository defines association between claim an claimable -- principal, group,
@@ -44,6 +45,31 @@ impl<'a> ClaimClaimableRepository<'a> {
         }
     }
 
+    /// Adds guest membership to claim, which grants claim to the guest principal only within
+    /// the host organization of the membership
+    pub fn add_guest_membership_to_claim(&self, ctx: &SecurityContext, membership_id: &str, claim_id: &str, scope: &str, claim_constraints: &str, effective_at: NaiveDateTime, expired_at: NaiveDateTime) -> Result<usize, RbacError> {
+        let cc = PClaimClaimable::new(claim_id, membership_id, Constants::GuestMembership.to_string().as_str(), scope, claim_constraints, effective_at, expired_at);
+        match self.create(&cc) {
+            Ok(n) => {
+                self.audit(ctx, format!("Adding guest membership to claim {:?}", cc), "CREATE");
+                Ok(n)
+            },
+            Err(err) => Err(RbacError::Persistence(err.to_string())),
+        }
+    }
+
+    /// Removes guest membership from claim
+    pub fn delete_guest_membership_from_claim(&self, ctx: &SecurityContext, membership_id: &str, claim_id: &str) -> Result<usize, RbacError> {
+        let cc = PClaimClaimable::new(claim_id, membership_id, Constants::GuestMembership.to_string().as_str(), "", "", Utc::now().naive_utc(), Utc::now().naive_utc());
+        match self.delete(&cc) {
+            Ok(n) => {
+                self.audit(ctx, format!("Removing guest membership from claim {:?}", cc), "DELETE");
+                Ok(n)
+            },
+            Err(err) => Err(RbacError::Persistence(err.to_string())),
+        }
+    }
+
     /// Adds role to claim
     pub fn add_role_to_claim(&self, ctx: &SecurityContext, role_id: &str, claim_id: &str, scope: &str, claim_constraints: &str, effective_at: NaiveDateTime, expired_at: NaiveDateTime) -> Result<usize, RbacError> {
         let cc = PClaimClaimable::new(claim_id, role_id, Constants::Role.to_string().as_str(), scope, claim_constraints, effective_at, expired_at);
@@ -210,20 +236,35 @@ impl<'a> ClaimClaimableRepository<'a> {
     /// Creates new assocoation between claim and claimable
     pub fn create(&self, association: &PClaimClaimable) -> Result<usize, diesel::result::Error> {
         let connection = self.data_source.new_connection()?;
-        diesel::insert_into(rbac_claim_claimables::table).values(association).execute(&*connection)
+        let n = diesel::insert_into(rbac_claim_claimables::table).values(association).execute(&*connection)?;
+        self.invalidate(association);
+        Ok(n)
     }
 
     /// Deletes assocoation between claim and claimable
     pub fn delete(&self, association: &PClaimClaimable) -> Result<usize, diesel::result::Error> {
         let connection = self.data_source.new_connection()?;
-        diesel::delete(rbac_claim_claimables::table)
+        let n = diesel::delete(rbac_claim_claimables::table)
             .filter(rbac_claim_claimables::claim_id.eq(association.claim_id.clone()))
             .filter(rbac_claim_claimables::claimable_id.eq(association.claimable_id.clone()))
             .filter(rbac_claim_claimables::claimable_type.eq(association.claimable_type.clone()))
-            .execute(&*connection)
+            .execute(&*connection)?;
+        self.invalidate(association);
+        Ok(n)
     }
 
     ///////////////////////////////////// PRIVATE METHODS ////////////////////////////////
+    fn invalidate(&self, association: &PClaimClaimable) {
+        if association.claimable_type == Constants::Principal.to_string() {
+            CLAIM_CACHE.invalidate_principal(association.claimable_id.as_str());
+        } else if association.claimable_type == Constants::Role.to_string() {
+            CLAIM_CACHE.invalidate_role(association.claimable_id.as_str());
+        } else {
+            // license-policy claims are shared by all principals of the organization
+            CLAIM_CACHE.invalidate_all();
+        }
+    }
+
     fn audit(&self, ctx: &SecurityContext, message: String, action: &str) {
         let _ = self.audit_record_repository.create_with(message.as_str(), action, format!("{:?}", ctx).as_str(), ctx.principal_id.clone());
         info!("{}", message);
@@ -233,6 +274,7 @@ impl<'a> ClaimClaimableRepository<'a> {
     pub fn clear(&self) {
         let connection = self.data_source.new_connection().unwrap();
         let _ = diesel::delete(rbac_claim_claimables::table).execute(&*connection);
+        CLAIM_CACHE.invalidate_all();
     }
 
 }
@@ -7,6 +7,7 @@ use super::models::{PClaim};
 use plexrbac::domain::models::Claim;
 use plexrbac::common::SecurityContext;
 use plexrbac::common::RbacError;
+use super::cache::CLAIM_CACHE;
 use chrono::{Utc};
 use self::uuu::Uuid;
 use std::collections::HashMap;
@@ -32,6 +33,7 @@ impl<'a> ClaimRepository<'a> {
             return Err(RbacError::Persistence(err.to_string()));
         }
         self.audit(ctx, format!("Adding new claim {:?}", db_obj), "CREATE");
+        CLAIM_CACHE.invalidate_realm(db_obj.realm_id.as_str());
         Ok(Claim::from(&db_obj))
     }
 
@@ -42,12 +44,14 @@ impl<'a> ClaimRepository<'a> {
                 db_obj.action = claim.action.clone();
                 db_obj.effect = claim.effect.clone();
                 db_obj.description = claim.description.clone();
+                db_obj.priority = claim.priority.clone();
                 db_obj.updated_at = Some(Utc::now().naive_utc());
                 db_obj.updated_by = Some(ctx.principal_id.clone());
                 if let Err(err) = self._update(&db_obj) {
                     return Err(RbacError::Persistence(err.to_string()));
                 }
                 self.audit(ctx, format!("Updated claim {:?}", db_obj), "CREATE");
+                CLAIM_CACHE.invalidate_realm(db_obj.realm_id.as_str());
                 Ok(Claim::from(&db_obj))
             }
             None => Err(RbacError::NotFound(format!("Claim not found {:?}", claim)))
@@ -102,6 +106,7 @@ impl<'a> ClaimRepository<'a> {
         match self._delete(realm_id, resource_id, id) {
             Ok(n) => {
                 self.audit(ctx, format!("Deleted claim {:?}", id), "DELETE");
+                CLAIM_CACHE.invalidate_realm(realm_id);
                 Ok(n)
             },
             Err(err) => Err(RbacError::Persistence(err.to_string())),
@@ -0,0 +1,273 @@
+//#![crate_name = "doc"]
+extern crate uuid as uuu;
+
+use diesel::prelude::*;
+use super::schema::rbac_delegations;
+use super::models::{PDelegation};
+use chrono::{Utc};
+use plexrbac::domain::models::Delegation;
+use plexrbac::common::SecurityContext;
+use plexrbac::common::RbacError;
+use super::cache::CLAIM_CACHE;
+use self::uuu::Uuid;
+
+//////////////////////////////////////////////////////////////////////////////////////////////
+/// DelegationRepository defines methods for accessing and persisting delegations that allow a
+/// principal to temporarily delegate its claims to another principal
+///
+pub struct DelegationRepository<'a> {
+    pub data_source: &'a dyn super::data_source::DataSource,
+    pub audit_record_repository: super::audit_record_repository::AuditRecordRepository<'a>,
+}
+
+impl<'a> DelegationRepository<'a> {
+    /// Creates delegation
+    pub fn create(&self, ctx: &SecurityContext, delegation: &Delegation) -> Result<Delegation, RbacError> {
+        self.validate(ctx, delegation, "CREATE")?;
+        let mut db_obj = delegation.to();
+        db_obj.id = Uuid::new_v4().to_hyphenated().to_string();
+        db_obj.created_at = Some(Utc::now().naive_utc());
+        db_obj.created_by = Some(ctx.principal_id.clone());
+        db_obj.updated_at = Some(Utc::now().naive_utc());
+        db_obj.updated_by = Some(ctx.principal_id.clone());
+        if let Err(err) = self._create(&db_obj) {
+            return Err(RbacError::Persistence(err.to_string()));
+        }
+        self.audit(ctx, format!("Adding new delegation {:?}", db_obj), "CREATE");
+        CLAIM_CACHE.invalidate_principal(db_obj.delegatee_id.as_str());
+        Ok(Delegation::from(&db_obj))
+    }
+
+    /// Updates the delegation
+    pub fn update(&self, ctx: &SecurityContext, delegation: &Delegation) -> Result<Delegation, RbacError> {
+        self.validate(ctx, delegation, "UPDATE")?;
+        match self._get(delegation.organization_id.as_str(), delegation.id.as_str()) {
+            Some(mut db_obj) => {
+                let updated = delegation.to();
+                let previous_delegatee_id = db_obj.delegatee_id.clone();
+                db_obj.delegatee_id = updated.delegatee_id.clone();
+                db_obj.role_ids = updated.role_ids.clone();
+                db_obj.claim_ids = updated.claim_ids.clone();
+                db_obj.delegation_constraints = updated.delegation_constraints.clone();
+                db_obj.effective_at = updated.effective_at.clone();
+                db_obj.expired_at = updated.expired_at.clone();
+                db_obj.updated_at = Some(Utc::now().naive_utc());
+                db_obj.updated_by = Some(ctx.principal_id.clone());
+                if let Err(err) = self._update(&db_obj) {
+                    return Err(RbacError::Persistence(err.to_string()));
+                }
+                self.audit(ctx, format!("Updated delegation {:?}", db_obj), "UPDATE");
+                CLAIM_CACHE.invalidate_principal(previous_delegatee_id.as_str());
+                CLAIM_CACHE.invalidate_principal(db_obj.delegatee_id.as_str());
+                Ok(Delegation::from(&db_obj))
+            }
+            None => Err(RbacError::NotFound(format!("Delegation not found {:?}", delegation)))
+        }
+    }
+
+    /// Retrieves delegation by id from the database
+    pub fn get(&self, _ctx: &SecurityContext, org_id: &str, id: &str) -> Option<Delegation> {
+        match self._get(org_id, id) {
+            Some(delegation) => Some(Delegation::from(&delegation)),
+            _ => None,
+        }
+    }
+
+    /// Returns all delegations of the organization
+    pub fn get_by_org(&self, _ctx: &SecurityContext, org_id: &str) -> Vec<Delegation> {
+        self._get_by_org(org_id).iter().map(|d| Delegation::from(&d)).collect::<Vec<Delegation>>()
+    }
+
+    /// Returns delegations that are currently effective for given delegatee
+    pub fn get_by_delegatee(&self, delegatee_id: &str) -> Vec<Delegation> {
+        let now = Utc::now().naive_utc();
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_delegations::table
+                .filter(rbac_delegations::delegatee_id.eq(delegatee_id.to_string()))
+                .filter(rbac_delegations::effective_at.le(now))
+                .filter(rbac_delegations::expired_at.ge(now))
+                .load::<PDelegation>(&*connection) {
+                Ok(v) => v.iter().map(|d| Delegation::from(&d)).collect::<Vec<Delegation>>(),
+                _ => vec![],
+            }
+        } else {
+            vec![]
+        }
+    }
+
+    /// Returns delegations that are currently effective for given delegators
+    pub fn get_by_delegators(&self, delegator_ids: Vec<String>) -> Vec<Delegation> {
+        let now = Utc::now().naive_utc();
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_delegations::table
+                .filter(rbac_delegations::delegator_id.eq_any(delegator_ids))
+                .filter(rbac_delegations::effective_at.le(now))
+                .filter(rbac_delegations::expired_at.ge(now))
+                .load::<PDelegation>(&*connection) {
+                Ok(v) => v.iter().map(|d| Delegation::from(&d)).collect::<Vec<Delegation>>(),
+                _ => vec![],
+            }
+        } else {
+            vec![]
+        }
+    }
+
+    /// Deletes delegation by id from the database
+    pub fn delete(&self, ctx: &SecurityContext, org_id: &str, id: &str) -> Result<usize, RbacError> {
+        let existing = self._get(org_id, id);
+        match self._delete(org_id, id) {
+            Ok(n) => {
+                self.audit(ctx, format!("Deleted delegation {:?}", existing), "DELETE");
+                if let Some(delegation) = existing {
+                    CLAIM_CACHE.invalidate_principal(delegation.delegatee_id.as_str());
+                }
+                Ok(n)
+            },
+            Err(err) => Err(RbacError::Persistence(err.to_string())),
+        }
+    }
+
+    ///////////////////////////////////// PRIVATE METHODS ////////////////////////////////
+    fn audit(&self, ctx: &SecurityContext, message: String, action: &str) {
+        let _ = self.audit_record_repository.create_with(message.as_str(), action, format!("{:?}", ctx).as_str(), ctx.principal_id.clone());
+        info!("{}", message);
+    }
+
+    /// Verifies that delegation is between different principals, delegates at least one role or
+    /// claim and has valid date range
+    fn validate(&self, ctx: &SecurityContext, delegation: &Delegation, action: &str) -> Result<(), RbacError> {
+        let err = if delegation.delegator_id == delegation.delegatee_id {
+            Some(format!("Principal {} cannot delegate to itself", delegation.delegator_id))
+        } else if delegation.role_ids.len() == 0 && delegation.claim_ids.len() == 0 {
+            Some(format!("Delegation from {} to {} must define roles or claims", delegation.delegator_id, delegation.delegatee_id))
+        } else if delegation.effective_at >= delegation.expired_at {
+            Some(format!("Delegation from {} to {} expires before it's effective", delegation.delegator_id, delegation.delegatee_id))
+        } else {
+            None
+        };
+        if let Some(err) = err {
+            self.audit(ctx, format!("Invalid delegation {} -- {:?}", err, delegation), action);
+            return Err(RbacError::Custom(err));
+        }
+        Ok(())
+    }
+
+    /// Creates instance of delegation
+    fn _create(&self, delegation: &PDelegation) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::insert_into(rbac_delegations::table).values(delegation).execute(&*connection)
+    }
+
+    /// Updates previous instance of the delegation
+    fn _update(&self, delegation: &PDelegation) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::update(rbac_delegations::table.find(delegation.id.clone())).set(delegation).
+            execute(&*connection)
+    }
+
+    /// Deletes instance of the delegation by id from the database
+    fn _delete(&self, org_id: &str, id: &str) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::delete(rbac_delegations::table
+                .filter(rbac_delegations::organization_id.eq(org_id.to_string()))
+                .filter(rbac_delegations::id.eq(id.to_string())))
+                .execute(&*connection)
+    }
+
+    /// Retrieves instance of the delegation by id from the database
+    fn _get(&self, org_id: &str, id: &str) -> Option<PDelegation> {
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_delegations::table
+                .filter(rbac_delegations::organization_id.eq(org_id.to_string()))
+                .filter(rbac_delegations::id.eq(id.to_string()))
+                .load::<PDelegation>(&*connection) {
+                    Ok(v) => {
+                        if let Some(d) = v.first() {
+                            Some(d.clone())
+                        } else {
+                            None
+                        }
+                    }
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Retrieves delegations by organization-id
+    fn _get_by_org(&self, organization_id: &str) -> Vec<PDelegation> {
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_delegations::table
+                .filter(rbac_delegations::organization_id.eq(organization_id.to_string()))
+                .load::<PDelegation>(&*connection) {
+                Ok(v) => v,
+                _ => vec![],
+            }
+        } else {
+            vec![]
+        }
+    }
+
+    /// Removes all instances of the delegation from the database for testing
+    pub fn clear(&self) {
+        let connection = self.data_source.new_connection().unwrap();
+        let _ = diesel::delete(rbac_delegations::table).execute(&*connection);
+        CLAIM_CACHE.invalidate_all();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use plexrbac::persistence::locator::RepositoryLocator;
+    use plexrbac::persistence::data_source::DefaultDataSource;
+    use plexrbac::domain::models::Delegation;
+    use plexrbac::common::SecurityContext;
+    use chrono::{NaiveDate, Utc};
+
+    #[test]
+    fn test_create() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_delegation_repository();
+        repo.clear();
+
+        let delegation = repo.create(&ctx, &Delegation::new("", "99", "11", "12", vec!["1".into()], vec![], None, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+        let loaded = repo.get(&ctx, "99", delegation.id.as_str()).unwrap();
+        assert_eq!(format!("{:?}", delegation), format!("{:?}", loaded));
+        assert_eq!(1, repo.get_by_delegatee("12").len());
+        assert_eq!(1, repo.get_by_delegators(vec!["11".into()]).len());
+    }
+
+    #[test]
+    fn test_create_invalid() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_delegation_repository();
+
+        assert!(repo.create(&ctx, &Delegation::new("", "99", "11", "11", vec!["1".into()], vec![], None, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).is_err());
+        assert!(repo.create(&ctx, &Delegation::new("", "99", "11", "12", vec![], vec![], None, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).is_err());
+        assert!(repo.create(&ctx, &Delegation::new("", "99", "11", "12", vec!["1".into()], vec![], None, NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0), Utc::now().naive_utc())).is_err());
+    }
+
+    #[test]
+    fn test_update_delete() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_delegation_repository();
+        repo.clear();
+
+        let mut delegation = repo.create(&ctx, &Delegation::new("", "99", "11", "12", vec![], vec!["1".into()], None, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+        // delegation that is not effective yet
+        delegation.effective_at = NaiveDate::from_ymd(2099, 1, 1).and_hms(0, 0, 0);
+        repo.update(&ctx, &delegation).unwrap();
+        assert_eq!(0, repo.get_by_delegatee("12").len());
+        assert_eq!(1, repo.get_by_org(&ctx, "99").len());
+        repo.delete(&ctx, "99", delegation.id.as_str()).unwrap();
+        assert!(repo.get(&ctx, "99", delegation.id.as_str()).is_none());
+    }
+}
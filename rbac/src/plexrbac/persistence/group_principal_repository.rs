@@ -5,6 +5,7 @@ use super::schema::rbac_group_principals;
 use super::models::{PGroupPrincipal};
 use plexrbac::common::SecurityContext;
 use plexrbac::common::RbacError;
+use super::cache::CLAIM_CACHE;
 
 //////////////////////////////////////////////////////////////////////////////////////////////
 /// GroupPrincipalRepository is used to store Many-to-Many association between organization 
@@ -16,7 +17,8 @@ pub struct GroupPrincipalRepository<'a> {
 }
 
 impl<'a> GroupPrincipalRepository<'a> {
-    /// Adds principal to group
+    /// Adds principal to group without checking roles of the group, see
+    /// RoleRoleableRepository::add_principal_to_group for checking separation-of-duty rules
     pub fn add_principal_to_group(&self, ctx: &SecurityContext, group_id: &str, principal_id: &str) -> Result<usize, RbacError> {
         let gp = PGroupPrincipal::new(group_id, principal_id);
         match self.create(&gp) {
@@ -87,15 +89,19 @@ impl<'a> GroupPrincipalRepository<'a> {
 
     pub fn create(&self, association: &PGroupPrincipal) -> Result<usize, diesel::result::Error> {
         let connection = self.data_source.new_connection()?;
-        diesel::insert_into(rbac_group_principals::table).values(association).execute(&*connection)
+        let n = diesel::insert_into(rbac_group_principals::table).values(association).execute(&*connection)?;
+        CLAIM_CACHE.invalidate_principal(association.principal_id.as_str());
+        Ok(n)
     }
 
     pub fn delete(&self, association: &PGroupPrincipal) -> Result<usize, diesel::result::Error> {
         let connection = self.data_source.new_connection()?;
-        diesel::delete(rbac_group_principals::table)
+        let n = diesel::delete(rbac_group_principals::table)
             .filter(rbac_group_principals::group_id.eq(association.group_id.clone()))
             .filter(rbac_group_principals::principal_id.eq(association.principal_id.clone()))
-            .execute(&*connection)
+            .execute(&*connection)?;
+        CLAIM_CACHE.invalidate_principal(association.principal_id.as_str());
+        Ok(n)
     }
 
     ///////////////////////////////////// PRIVATE METHODS ////////////////////////////////
@@ -108,6 +114,7 @@ impl<'a> GroupPrincipalRepository<'a> {
     pub fn clear(&self) {
         let connection = self.data_source.new_connection().unwrap();
         let _ = diesel::delete(rbac_group_principals::table).execute(&*connection);
+        CLAIM_CACHE.invalidate_all();
     }
 
 }
@@ -7,9 +7,10 @@ use super::models::{PGroup};
 use plexrbac::domain::models::Group;
 use plexrbac::common::SecurityContext;
 use plexrbac::common::RbacError;
+use super::cache::CLAIM_CACHE;
 use chrono::{Utc};
 use self::uuu::Uuid;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 //////////////////////////////////////////////////////////////////////////////////////////////
 /// GroupRepository defines methods for accessing and persisting groups
@@ -28,6 +29,7 @@ impl<'a> GroupRepository<'a> {
         db_obj.created_by = Some(ctx.principal_id.clone());
         db_obj.updated_at = Some(Utc::now().naive_utc());
         db_obj.updated_by = Some(ctx.principal_id.clone());
+        self.validate_parent(ctx, &db_obj)?;
         if let Err(err) = self._create(&db_obj) {
             return Err(RbacError::Persistence(err.to_string()));
         }
@@ -43,10 +45,12 @@ impl<'a> GroupRepository<'a> {
                 db_obj.description = group.description.clone();
                 db_obj.updated_at = Some(Utc::now().naive_utc());
                 db_obj.updated_by = Some(ctx.principal_id.clone());
+                self.validate_parent(ctx, &db_obj)?;
                 if let Err(err) = self._update(&db_obj) {
                     return Err(RbacError::Persistence(err.to_string()));
                 }
                 self.audit(ctx, format!("Updated group {:?}", db_obj), "UPDATE");
+                CLAIM_CACHE.invalidate_org(db_obj.organization_id.as_str());
                 Ok(Group::from(&db_obj))
             }
             None => Err(RbacError::NotFound(format!("Group not found {:?}", group)))
@@ -82,6 +86,7 @@ impl<'a> GroupRepository<'a> {
         match self._delete(org_id, id) {
             Ok(n) => {
                 self.audit(ctx, format!("Deleted group {:?}", id), "DELETE");
+                CLAIM_CACHE.invalidate_org(org_id);
                 Ok(n)
             },
             Err(err) => Err(RbacError::Persistence(err.to_string())),
@@ -105,6 +110,22 @@ impl<'a> GroupRepository<'a> {
     }
 
     ///////////////////////////////////// PRIVATE METHODS ////////////////////////////////
+    /// Verifies that parent chain of the group does not loop back to itself, where the chain
+    /// is followed across organizations so that inherited parents are checked as well
+    fn validate_parent(&self, ctx: &SecurityContext, group: &PGroup) -> Result<(), RbacError> {
+        let mut visited = HashSet::new();
+        visited.insert(group.id.clone());
+        let mut next = group.parent_id.clone();
+        while let Some(parent_id) = next {
+            if !visited.insert(parent_id.clone()) {
+                self.audit(ctx, format!("Rejected group {:?} because parent chain contains cycle at {}", group, parent_id), "UPDATE");
+                return Err(RbacError::CyclicHierarchy(format!("Parent chain of group {} contains cycle at {}", group.id, parent_id)));
+            }
+            next = self.__get(parent_id.as_str()).ok().and_then(|parent| parent.parent_id);
+        }
+        Ok(())
+    }
+
     fn audit(&self, ctx: &SecurityContext, message: String, action: &str) {
         let _ = self.audit_record_repository.create_with(message.as_str(), action, format!("{:?}", ctx).as_str(), ctx.principal_id.clone());
         info!("{}", message);
@@ -128,6 +149,20 @@ impl<'a> GroupRepository<'a> {
         }
     }
 
+    /// Returns all groups that inherit from given parent group-ids
+    pub fn _get_by_parent_ids(&self, parent_ids: Vec<String>) -> Vec<PGroup> {
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_groups::table
+                .filter(rbac_groups::parent_id.eq_any(parent_ids))
+                .load::<PGroup>(&*connection) {
+                Ok(v) => v,
+                _ => vec![],
+            }
+        } else {
+            vec![]
+        }
+    }
+
     /// Returns groups by group-ids
     fn _get_by_group_ids(&self, group_ids: Vec<String>) -> Vec<PGroup> {
         if let Ok(connection) = self.data_source.new_connection() {
@@ -213,6 +248,7 @@ mod tests {
     use plexrbac::persistence::data_source::DefaultDataSource;
     use plexrbac::domain::models::Group;
     use plexrbac::common::SecurityContext;
+    use plexrbac::common::RbacError;
 
     #[test]
     fn test_create() {
@@ -229,6 +265,35 @@ mod tests {
         assert_eq!(group_str, format!("{:?}", loaded));
     }
 
+    #[test]
+    fn test_update_with_cyclic_parent() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_group_repository();
+        repo.clear();
+
+        let engineering = repo.create(&ctx, &Group::new("", "2", "Engineering", None, None)).unwrap();
+        let platform = repo.create(&ctx, &Group::new("", "2", "Platform", None, Some(engineering.id.clone()))).unwrap();
+        let sre = repo.create(&ctx, &Group::new("", "2", "SRE", None, Some(platform.id.clone()))).unwrap();
+
+        let mut loaded = repo.get(&ctx, "2", engineering.id.as_str()).unwrap();
+        loaded.parent_id = Some(sre.id.clone());
+        match repo.update(&ctx, &loaded) {
+            Err(RbacError::CyclicHierarchy(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(None, repo.get(&ctx, "2", engineering.id.as_str()).unwrap().parent_id);
+
+        // cycle through group of another organization
+        let holding = repo.create(&ctx, &Group::new("", "3", "Holding", None, Some(sre.id.clone()))).unwrap();
+        loaded.parent_id = Some(holding.id.clone());
+        match repo.update(&ctx, &loaded) {
+            Err(RbacError::CyclicHierarchy(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
     #[test]
     fn test_update() {
         let ctx = SecurityContext::new("myorg", "myid");
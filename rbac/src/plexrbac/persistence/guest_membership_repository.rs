@@ -0,0 +1,202 @@
+//#![crate_name = "doc"]
+extern crate uuid as uuu;
+
+use diesel::prelude::*;
+use super::schema::rbac_guest_memberships;
+use super::models::{PGuestMembership};
+use chrono::{Utc};
+use plexrbac::domain::models::GuestMembership;
+use plexrbac::common::SecurityContext;
+use plexrbac::common::RbacError;
+use super::cache::CLAIM_CACHE;
+use self::uuu::Uuid;
+
+//////////////////////////////////////////////////////////////////////////////////////////////
+/// GuestMembershipRepository defines methods for accessing and persisting guest memberships
+/// that link principals into organizations other than their own organization
+///
+pub struct GuestMembershipRepository<'a> {
+    pub data_source: &'a dyn super::data_source::DataSource,
+    pub audit_record_repository: super::audit_record_repository::AuditRecordRepository<'a>,
+}
+
+impl<'a> GuestMembershipRepository<'a> {
+    /// Creates guest membership
+    pub fn create(&self, ctx: &SecurityContext, membership: &GuestMembership) -> Result<GuestMembership, RbacError> {
+        if membership.expired_at <= membership.effective_at {
+            return Err(RbacError::Custom(format!("Guest membership of principal {} expires before it's effective", membership.principal_id)));
+        }
+        if self._get_by_org_principal(membership.organization_id.as_str(), membership.principal_id.as_str()).is_some() {
+            self.audit(ctx, format!("Guest membership already exist for {:?}", membership), "CREATE");
+            return Err(RbacError::Duplicate(format!("Guest membership already exist for {:?}", membership)));
+        }
+        let mut db_obj = membership.to();
+        db_obj.id = Uuid::new_v4().to_hyphenated().to_string();
+        db_obj.created_at = Some(Utc::now().naive_utc());
+        db_obj.created_by = Some(ctx.principal_id.clone());
+        db_obj.updated_at = Some(Utc::now().naive_utc());
+        db_obj.updated_by = Some(ctx.principal_id.clone());
+        if let Err(err) = self._create(&db_obj) {
+            return Err(RbacError::Persistence(err.to_string()));
+        }
+        self.audit(ctx, format!("Adding new guest membership {:?}", db_obj), "CREATE");
+        CLAIM_CACHE.invalidate_principal(db_obj.principal_id.as_str());
+        Ok(GuestMembership::from(&db_obj))
+    }
+
+    /// Retrieves guest membership by id from the database
+    pub fn get(&self, _ctx: &SecurityContext, org_id: &str, id: &str) -> Option<GuestMembership> {
+        match self._get(org_id, id) {
+            Some(membership) => Some(GuestMembership::from(&membership)),
+            _ => None,
+        }
+    }
+
+    /// Returns all guest memberships of the host organization
+    pub fn get_by_org(&self, _ctx: &SecurityContext, org_id: &str) -> Vec<GuestMembership> {
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_guest_memberships::table
+                .filter(rbac_guest_memberships::organization_id.eq(org_id.to_string()))
+                .load::<PGuestMembership>(&*connection) {
+                Ok(v) => v.iter().map(|m| GuestMembership::from(&m)).collect::<Vec<GuestMembership>>(),
+                _ => vec![],
+            }
+        } else {
+            vec![]
+        }
+    }
+
+    /// Returns guest membership of the principal in the host organization if it's currently effective
+    pub fn get_active(&self, _ctx: &SecurityContext, org_id: &str, principal_id: &str) -> Option<GuestMembership> {
+        self._get_by_org_principal(org_id, principal_id).map(|m| GuestMembership::from(&m)).filter(|m| m.is_active())
+    }
+
+    /// Deletes guest membership by id from the database, which revokes access of the principal
+    /// to the host organization
+    pub fn delete(&self, ctx: &SecurityContext, org_id: &str, id: &str) -> Result<usize, RbacError> {
+        let existing = self._get(org_id, id);
+        match self._delete(org_id, id) {
+            Ok(n) => {
+                self.audit(ctx, format!("Deleted guest membership {:?}", existing), "DELETE");
+                if let Some(membership) = existing {
+                    CLAIM_CACHE.invalidate_principal(membership.principal_id.as_str());
+                }
+                Ok(n)
+            },
+            Err(err) => Err(RbacError::Persistence(err.to_string())),
+        }
+    }
+
+    ///////////////////////////////////// PRIVATE METHODS ////////////////////////////////
+    fn audit(&self, ctx: &SecurityContext, message: String, action: &str) {
+        let _ = self.audit_record_repository.create_with(message.as_str(), action, format!("{:?}", ctx).as_str(), ctx.principal_id.clone());
+        info!("{}", message);
+    }
+
+    /// Creates instance of guest membership
+    fn _create(&self, membership: &PGuestMembership) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::insert_into(rbac_guest_memberships::table).values(membership).execute(&*connection)
+    }
+
+    /// Deletes instance of the guest membership by id from the database
+    fn _delete(&self, org_id: &str, id: &str) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::delete(rbac_guest_memberships::table
+                .filter(rbac_guest_memberships::organization_id.eq(org_id.to_string()))
+                .filter(rbac_guest_memberships::id.eq(id.to_string())))
+                .execute(&*connection)
+    }
+
+    /// Retrieves instance of the guest membership by id from the database
+    fn _get(&self, org_id: &str, id: &str) -> Option<PGuestMembership> {
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_guest_memberships::table
+                .filter(rbac_guest_memberships::organization_id.eq(org_id.to_string()))
+                .filter(rbac_guest_memberships::id.eq(id.to_string()))
+                .load::<PGuestMembership>(&*connection) {
+                Ok(v) => {
+                    if let Some(m) = v.first() {
+                        Some(m.clone())
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Retrieves instance of the guest membership by host organization and principal from the database
+    fn _get_by_org_principal(&self, org_id: &str, principal_id: &str) -> Option<PGuestMembership> {
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_guest_memberships::table
+                .filter(rbac_guest_memberships::organization_id.eq(org_id.to_string()))
+                .filter(rbac_guest_memberships::principal_id.eq(principal_id.to_string()))
+                .load::<PGuestMembership>(&*connection) {
+                Ok(v) => {
+                    if let Some(m) = v.first() {
+                        Some(m.clone())
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Removes all instances of the guest membership from the database for testing
+    pub fn clear(&self) {
+        let connection = self.data_source.new_connection().unwrap();
+        let _ = diesel::delete(rbac_guest_memberships::table).execute(&*connection);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use plexrbac::persistence::locator::RepositoryLocator;
+    use plexrbac::persistence::data_source::DefaultDataSource;
+    use plexrbac::domain::models::GuestMembership;
+    use plexrbac::common::SecurityContext;
+    use chrono::{NaiveDate, Utc, Duration};
+
+    #[test]
+    fn test_create_delete() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_guest_membership_repository();
+        repo.clear();
+
+        let membership = repo.create(&ctx, &GuestMembership::new("", "99", "11", Some("consultant".into()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+        let loaded = repo.get(&ctx, "99", membership.id.as_str()).unwrap();
+        assert_eq!(format!("{:?}", membership), format!("{:?}", loaded));
+        assert!(repo.create(&ctx, &GuestMembership::new("", "99", "11", None, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).is_err());
+        assert_eq!(1, repo.get_by_org(&ctx, "99").len());
+        assert!(repo.get_active(&ctx, "99", "11").is_some());
+        assert!(repo.get_active(&ctx, "98", "11").is_none());
+        repo.delete(&ctx, "99", membership.id.as_str()).unwrap();
+        assert!(repo.get(&ctx, "99", membership.id.as_str()).is_none());
+        assert!(repo.get_active(&ctx, "99", "11").is_none());
+    }
+
+    #[test]
+    fn test_expired() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_guest_membership_repository();
+        repo.clear();
+
+        assert!(repo.create(&ctx, &GuestMembership::new("", "99", "11", None, Utc::now().naive_utc(), Utc::now().naive_utc() - Duration::days(1))).is_err());
+        let _ = repo.create(&ctx, &GuestMembership::new("", "99", "11", None, Utc::now().naive_utc() - Duration::days(10), Utc::now().naive_utc() - Duration::days(1))).unwrap();
+        assert!(repo.get_active(&ctx, "99", "11").is_none());
+        assert_eq!(1, repo.get_by_org(&ctx, "99").len());
+    }
+}
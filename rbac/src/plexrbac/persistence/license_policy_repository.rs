@@ -8,6 +8,7 @@ use chrono::{Utc};
 use plexrbac::domain::models::LicensePolicy;
 use plexrbac::common::SecurityContext;
 use plexrbac::common::RbacError;
+use super::cache::CLAIM_CACHE;
 use self::uuu::Uuid;
 
 //////////////////////////////////////////////////////////////////////////////////////////////
@@ -28,7 +29,7 @@ impl<'a> LicensePolicyRepository<'a> {
         db_obj.created_by = Some(ctx.principal_id.clone());
         db_obj.updated_at = Some(Utc::now().naive_utc());
         db_obj.updated_by = Some(ctx.principal_id.clone());
-        if self._get_by_org(policy.organization_id.as_str()).len() > 0 {
+        if self._get_by_org(policy.organization_id.as_str()).iter().any(|p| p.name == policy.name) {
             self.audit(ctx, format!("License policy already exist for {:?}", policy), "CREATE");
             return Err(RbacError::Duplicate(format!("License policy already exist for {:?}", policy)));
         }
@@ -37,6 +38,7 @@ impl<'a> LicensePolicyRepository<'a> {
             return Err(RbacError::Persistence(err.to_string()));
         }
         self.audit(ctx, format!("Adding new license-policy {:?}", policy), "CREATE");
+        CLAIM_CACHE.invalidate_org(db_obj.organization_id.as_str());
         Ok(LicensePolicy::from(&db_obj))
     }
 
@@ -53,10 +55,7 @@ impl<'a> LicensePolicyRepository<'a> {
                     return Err(RbacError::Persistence(err.to_string()));
                 }
                 self.audit(ctx, format!("Updated license-policy {:?}", policy), "UPDATE");
-                if self._get_by_org(policy.organization_id.as_str()).len() > 1 {
-                    self.audit(ctx, format!("Multiple license policies exist for {:?}", policy), "CREATE");
-                    return Err(RbacError::Duplicate(format!("Multiple license policies exist for {:?}", policy)));
-                }
+                CLAIM_CACHE.invalidate_org(db_obj.organization_id.as_str());
                 Ok(LicensePolicy::from(&db_obj))
             }
             None => Err(RbacError::NotFound(format!("License policy not found {:?}", policy)))
@@ -72,7 +71,8 @@ impl<'a> LicensePolicyRepository<'a> {
         }
     }
 
-    /// Returns groups by group-ids
+    /// Returns all currently effective license-policies of the organization ordered by
+    /// effective date and id so that overlapping policies are always applied in the same order
     pub fn get_by_org(&self, _ctx: &SecurityContext, org_id: &str) -> Vec<LicensePolicy> {
         self._get_by_org(org_id).iter().map(|l| LicensePolicy::from(&l)).collect::<Vec<LicensePolicy>>()
     }
@@ -82,6 +82,7 @@ impl<'a> LicensePolicyRepository<'a> {
         match self._delete(org_id, id) {
             Ok(n) => {
                 self.audit(ctx, format!("Deleted license policy {:?}", id), "DELETE");
+                CLAIM_CACHE.invalidate_org(org_id);
                 Ok(n)
             },
             Err(err) => Err(RbacError::Persistence(err.to_string())),
@@ -152,6 +153,7 @@ impl<'a> LicensePolicyRepository<'a> {
                 .filter(rbac_license_policies::organization_id.eq(organization_id.to_string()))
                 .filter(rbac_license_policies::effective_at.le(now))
                 .filter(rbac_license_policies::expired_at.ge(now))
+                .order((rbac_license_policies::effective_at.asc(), rbac_license_policies::id.asc()))
                 .load::<PLicensePolicy>(&*connection) {
                 Ok(v) => v,
                 _ => vec![],
@@ -182,7 +184,7 @@ mod tests {
     use plexrbac::persistence::data_source::DefaultDataSource;
     use plexrbac::domain::models::LicensePolicy;
     use plexrbac::common::SecurityContext;
-    use chrono::{NaiveDate, Utc};
+    use chrono::{NaiveDate, Utc, Duration};
 
     #[test]
     fn test_create() {
@@ -223,10 +225,14 @@ mod tests {
         let repo = locator.new_license_policy_repository();
         repo.clear();
 
-        let _ = repo.create(&ctx, &LicensePolicy::new("", "99", "default-policy1", Some("desc".to_string()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+        let policy1 = repo.create(&ctx, &LicensePolicy::new("", "99", "default-policy1", Some("desc".to_string()), Utc::now().naive_utc() - Duration::days(1), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+        let policy2 = repo.create(&ctx, &LicensePolicy::new("", "99", "default-policy2", Some("desc".to_string()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
         assert!(repo.create(&ctx, &LicensePolicy::new("", "99", "default-policy2", Some("desc".to_string()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).is_err());
+        let _ = repo.create(&ctx, &LicensePolicy::new("", "99", "expired-policy", Some("desc".to_string()), Utc::now().naive_utc() - Duration::days(10), Utc::now().naive_utc() - Duration::days(1))).unwrap();
         let policies = repo.get_by_org(&ctx, "99");
-        assert_eq!(1, policies.len());
+        assert_eq!(2, policies.len());
+        assert_eq!(policy1.id, policies[0].id);
+        assert_eq!(policy2.id, policies[1].id);
     }
 
     #[test]
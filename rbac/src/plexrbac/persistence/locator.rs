@@ -90,6 +90,36 @@ impl<'a> RepositoryLocator<'a> {
         RepositoryLocator::build_license_policy_repository(self.data_source)
     }
 
+    /// Creates instance of sod-rule repository for persisting separation-of-duty rules
+    pub fn new_sod_rule_repository(&self) -> super::sod_rule_repository::SodRuleRepository {
+        RepositoryLocator::build_sod_rule_repository(self.data_source)
+    }
+
+    /// Creates instance of delegation repository for persisting temporary delegation of claims
+    pub fn new_delegation_repository(&self) -> super::delegation_repository::DelegationRepository {
+        RepositoryLocator::build_delegation_repository(self.data_source)
+    }
+
+    /// Creates instance of session repository for persisting roles activated by principals
+    pub fn new_session_repository(&self) -> super::session_repository::SessionRepository {
+        RepositoryLocator::build_session_repository(self.data_source)
+    }
+
+    /// Creates instance of assignment-request repository for persisting assignments that need approval
+    pub fn new_assignment_request_repository(&self) -> super::assignment_request_repository::AssignmentRequestRepository {
+        RepositoryLocator::build_assignment_request_repository(self.data_source)
+    }
+
+    /// Creates instance of guest-membership repository for persisting principals linked into other organizations
+    pub fn new_guest_membership_repository(&self) -> super::guest_membership_repository::GuestMembershipRepository {
+        RepositoryLocator::build_guest_membership_repository(self.data_source)
+    }
+
+    /// Creates instance of attribute repository for persisting attributes of principals, groups and organizations
+    pub fn new_attribute_repository(&self) -> super::attribute_repository::AttributeRepository {
+        RepositoryLocator::build_attribute_repository(self.data_source)
+    }
+
     /// Creates instance of rbac repository
     pub fn new_persistence_manager(&self) -> super::manager::PersistenceManager {
         RepositoryLocator::build_persistence_manager(self.data_source)
@@ -150,7 +180,15 @@ impl<'a> RepositoryLocator<'a> {
 
     /// Creates instance of role-roleable repository for defining association objects that can be tied with the role object.
     pub fn build_role_roleable_repository(data_source: &dyn DataSource) -> super::role_roleable_repository::RoleRoleableRepository {
-        super::role_roleable_repository::RoleRoleableRepository{data_source: data_source, audit_record_repository: RepositoryLocator::build_audit_record_repository(data_source)}
+        super::role_roleable_repository::RoleRoleableRepository{
+            data_source: data_source,
+            role_repository: RepositoryLocator::build_role_repository(data_source),
+            group_repository: RepositoryLocator::build_group_repository(data_source),
+            org_repository: RepositoryLocator::build_org_repository(data_source),
+            group_principal_repository: RepositoryLocator::build_group_principal_repository(data_source),
+            sod_rule_repository: RepositoryLocator::build_sod_rule_repository(data_source),
+            audit_record_repository: RepositoryLocator::build_audit_record_repository(data_source),
+        }
     }
 
     /// Creates instance of claim repository for persisting mapping of resource and actions
@@ -169,6 +207,36 @@ impl<'a> RepositoryLocator<'a> {
         super::license_policy_repository::LicensePolicyRepository{data_source: data_source, audit_record_repository: RepositoryLocator::build_audit_record_repository(data_source)}
     }
 
+    /// Creates instance of sod-rule repository for persisting separation-of-duty rules
+    pub fn build_sod_rule_repository(data_source: &dyn DataSource) -> super::sod_rule_repository::SodRuleRepository {
+        super::sod_rule_repository::SodRuleRepository{data_source: data_source, audit_record_repository: RepositoryLocator::build_audit_record_repository(data_source)}
+    }
+
+    /// Creates instance of delegation repository for persisting temporary delegation of claims
+    pub fn build_delegation_repository(data_source: &dyn DataSource) -> super::delegation_repository::DelegationRepository {
+        super::delegation_repository::DelegationRepository{data_source: data_source, audit_record_repository: RepositoryLocator::build_audit_record_repository(data_source)}
+    }
+
+    /// Creates instance of session repository for persisting roles activated by principals
+    pub fn build_session_repository(data_source: &dyn DataSource) -> super::session_repository::SessionRepository {
+        super::session_repository::SessionRepository{data_source: data_source, audit_record_repository: RepositoryLocator::build_audit_record_repository(data_source)}
+    }
+
+    /// Creates instance of assignment-request repository for persisting assignments that need approval
+    pub fn build_assignment_request_repository(data_source: &dyn DataSource) -> super::assignment_request_repository::AssignmentRequestRepository {
+        super::assignment_request_repository::AssignmentRequestRepository{data_source: data_source, audit_record_repository: RepositoryLocator::build_audit_record_repository(data_source)}
+    }
+
+    /// Creates instance of guest-membership repository for persisting principals linked into other organizations
+    pub fn build_guest_membership_repository(data_source: &dyn DataSource) -> super::guest_membership_repository::GuestMembershipRepository {
+        super::guest_membership_repository::GuestMembershipRepository{data_source: data_source, audit_record_repository: RepositoryLocator::build_audit_record_repository(data_source)}
+    }
+
+    /// Creates instance of attribute repository for persisting attributes of principals, groups and organizations
+    pub fn build_attribute_repository(data_source: &dyn DataSource) -> super::attribute_repository::AttributeRepository {
+        super::attribute_repository::AttributeRepository{data_source: data_source, audit_record_repository: RepositoryLocator::build_audit_record_repository(data_source)}
+    }
+
     /// Creates instance of rbac repository
     pub fn build_persistence_manager(data_source: &dyn DataSource) -> super::manager::PersistenceManager {
         super::manager::PersistenceManager{
@@ -185,6 +253,12 @@ impl<'a> RepositoryLocator<'a> {
             claim_repository: RepositoryLocator::build_claim_repository(data_source),
             claim_claimable_repository: RepositoryLocator::build_claim_claimable_repository(data_source),
             license_policy_repository: RepositoryLocator::build_license_policy_repository(data_source),
+            sod_rule_repository: RepositoryLocator::build_sod_rule_repository(data_source),
+            delegation_repository: RepositoryLocator::build_delegation_repository(data_source),
+            session_repository: RepositoryLocator::build_session_repository(data_source),
+            assignment_request_repository: RepositoryLocator::build_assignment_request_repository(data_source),
+            guest_membership_repository: RepositoryLocator::build_guest_membership_repository(data_source),
+            attribute_repository: RepositoryLocator::build_attribute_repository(data_source),
             audit_record_repository: RepositoryLocator::build_audit_record_repository(data_source),
         }
     }
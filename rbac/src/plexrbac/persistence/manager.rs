@@ -4,10 +4,16 @@ use plexrbac::domain::models::*;
 use plexrbac::common::Constants;
 use plexrbac::common::Status;
 use plexrbac::common::SecurityContext;
-use chrono::{NaiveDate, Utc};
+use plexrbac::common::ScopeMatching;
+use plexrbac::common::ValueWrapper;
+use chrono::{NaiveDate, NaiveDateTime, Utc, Duration};
 use log::{info, warn};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use plexrbac::common::RbacError;
+use plexrbac::utils::text;
+use plexrbac::utils::evaluator;
+use super::cache::CLAIM_CACHE;
+use super::models::{PRoleRoleable, PClaimClaimable};
 
 //////////////////////////////////////////////////////////////////////////////////////////////
 /// PersistenceManager defines high-level methods for accessing rbac entities
@@ -27,6 +33,12 @@ pub struct PersistenceManager<'a> {
     pub claim_repository: super::claim_repository::ClaimRepository<'a>,
     pub claim_claimable_repository: super::claim_claimable_repository::ClaimClaimableRepository<'a>,
     pub license_policy_repository: super::license_policy_repository::LicensePolicyRepository<'a>,
+    pub sod_rule_repository: super::sod_rule_repository::SodRuleRepository<'a>,
+    pub delegation_repository: super::delegation_repository::DelegationRepository<'a>,
+    pub session_repository: super::session_repository::SessionRepository<'a>,
+    pub assignment_request_repository: super::assignment_request_repository::AssignmentRequestRepository<'a>,
+    pub guest_membership_repository: super::guest_membership_repository::GuestMembershipRepository<'a>,
+    pub attribute_repository: super::attribute_repository::AttributeRepository<'a>,
     pub audit_record_repository: super::audit_record_repository::AuditRecordRepository<'a>,
 }
 
@@ -43,6 +55,13 @@ impl<'a> PersistenceManager<'a> {
         self.license_policy_repository.create(&ctx, &LicensePolicy::new("", org.id.as_str(), "default-policy", None, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)))
     }
 
+    ////////////////////////////////// SOD RULE CRUD OPERATIONS //////////////////////////////
+    /// Adds separation-of-duty rule for given roles
+    pub fn new_sod_rule_with(&self, ctx: &SecurityContext, realm: &SecurityRealm, org: &Organization, name: &str, roles: Vec<&Role>, cardinality: i32, dynamic: bool) -> Result<SodRule, RbacError> {
+        let role_ids = roles.iter().map(|r| r.id.clone()).collect::<Vec<String>>();
+        self.sod_rule_repository.create(ctx, &SodRule::new("", realm.id.as_str(), org.id.as_str(), name, None, role_ids, cardinality, dynamic))
+    }
+
     ////////////////////////////////// RESOURCE CRUD OPERATIONS //////////////////////////////
     /// Creates resource
     pub fn new_resource_with(&self, ctx: &SecurityContext, realm: &SecurityRealm, resource_name: &str) -> Result<Resource, RbacError> {
@@ -56,30 +75,68 @@ impl<'a> PersistenceManager<'a> {
         self.new_resource_instance(ctx, principal.id.as_str(), &mut instance)
     }
 
+    /// Creates resource instance, which is counted against the quota of the most specific scope
+    /// where quotas of all currently effective license-policies of the organization for that
+    /// scope are added together and the instance is attributed to the earliest effective policy
     pub fn new_resource_instance(&self, ctx: &SecurityContext, principal_id: &str, instance: &mut ResourceInstance) -> Result<ResourceInstance, RbacError> {
         if let Some(principal) = self.principal_repository.get(ctx, principal_id) {
-            if let Some(policy) = self.license_policy_repository.get_by_org(&ctx, principal.organization_id.as_str()).first() {
-                instance.license_policy_id = policy.id.clone();
-                if let Some(quota) = self.resource_quota_repository.get_by_resource_scope(instance.resource_id.as_str(), instance.scope.as_str()).first() {
-                    let count = self.resource_instance_repository.count_by_resource(instance.resource_id.as_str(), instance.scope.as_str(), Status::COMPLETED.to_string().as_str()) + self.resource_instance_repository.count_recent_by_resource(instance.resource_id.as_str(), instance.scope.as_str(), Status::INFLIGHT.to_string().as_str());
-                    if count >= quota.max_value as i64 {
-                        self.audit(ctx, format!("Reached quota limit for resource instance {:?}  -- {:?}", instance, quota), "CREATE");
-                        return Err(RbacError::QuotaExceeded(format!("Reached quota limit for resource instance {:?} -- {:?}", instance, quota)));
-                    }
-                } else {
+            let policies = self.get_effective_license_policies(ctx, principal.organization_id.as_str());
+            if policies.is_empty() {
+                return Err(RbacError::Persistence(format!("License policy not found for principal {:?} while adding resource instance {:?}", principal, instance)));
+            }
+            let matching = self.get_resource_scope_matching(ctx, instance.resource_id.as_str());
+            let quotas = self.resource_quota_repository.get_by_resource_matching(instance.resource_id.as_str(), instance.scope.as_str(), &matching);
+            let quota_scope = match quotas.iter().find(|q| policies.iter().any(|p| p.id == q.license_policy_id)) {
+                Some(quota) => quota.scope.clone(),
+                None => {
                     self.audit(ctx, format!("Reached quota limit for resource instance {:?}", instance), "CREATE");
                     return Err(RbacError::QuotaExceeded(format!("Reached limit for {:?} -- quota not found", instance)));
                 }
-                //
-                self.resource_instance_repository._create(ctx, instance)
-            } else {
-                Err(RbacError::Persistence(format!("License policy not found for principal {:?} while adding resource instance {:?}", principal, instance)))
+            };
+            // one quota per policy for the most specific scope, in the order of policies
+            let policy_quotas = policies.iter().filter_map(|p| quotas.iter().find(|q| q.license_policy_id == p.id && q.scope == quota_scope)).collect::<Vec<_>>();
+            let max_value = policy_quotas.iter().fold(0, |sum, q| sum + q.max_value as i64);
+            instance.license_policy_id = policy_quotas[0].license_policy_id.clone();
+            // instances of the organization are counted against the quota for all scopes that are
+            // covered by the quota
+            let recent = Utc::now().naive_utc() - Duration::seconds(3600);
+            let policy_ids = policies.iter().map(|p| p.id.clone()).collect::<Vec<String>>();
+            let count = self.resource_instance_repository.count_by_resource_matching(instance.resource_id.as_str(), policy_ids.clone(), quota_scope.as_str(), Status::COMPLETED.to_string().as_str(), &matching, None) + self.resource_instance_repository.count_by_resource_matching(instance.resource_id.as_str(), policy_ids, quota_scope.as_str(), Status::INFLIGHT.to_string().as_str(), &matching, Some(recent));
+            if count >= max_value {
+                self.audit(ctx, format!("Reached quota limit for resource instance {:?}  -- {:?}", instance, policy_quotas), "CREATE");
+                return Err(RbacError::QuotaExceeded(format!("Reached quota limit for resource instance {:?} -- {:?}", instance, policy_quotas)));
             }
+            //
+            self.resource_instance_repository._create(ctx, instance)
         } else {
             Err(RbacError::NotFound(format!("Principal not found {:?} while adding resource instance {:?}", principal_id, instance)))
         }
     }
 
+    /// Returns attributes of resource instance identified by resource name and reference id,
+    /// i.e., id, ref_id, scope, status and created_by of the instance along with its custom
+    /// attributes, where custom attributes cannot override attributes of the instance itself.
+    /// Only instances attributed to effective license-policies of the organization are found
+    pub fn get_resource_instance_attributes(&self, ctx: &SecurityContext, realm_id: &str, organization_id: &str, resource_name: &str, ref_id: &str) -> Result<HashMap<String, ValueWrapper>, RbacError> {
+        let resource = self.resource_repository.get_by_realm(ctx, realm_id).values().find(|r| r.resource_name == resource_name).cloned()
+            .ok_or_else(|| RbacError::NotFound(format!("Resource {} not found within realm {}", resource_name, realm_id)))?;
+        let policy_ids = self.get_effective_license_policies(ctx, organization_id).iter().map(|p| p.id.clone()).collect::<Vec<String>>();
+        let instance = self.resource_instance_repository.get_by_ref_id(ctx, resource.id.as_str(), policy_ids, ref_id)
+            .ok_or_else(|| RbacError::NotFound(format!("Instance {} of resource {} not found", ref_id, resource_name)))?;
+        let mut attributes = HashMap::new();
+        for attribute in self.attribute_repository.get_by_owner(ctx, Constants::ResourceInstance.to_string().as_str(), instance.id.as_str()) {
+            attributes.insert(attribute.name, attribute.value);
+        }
+        attributes.insert("id".to_string(), ValueWrapper::String(instance.id.clone()));
+        attributes.insert("ref_id".to_string(), ValueWrapper::String(instance.ref_id.clone()));
+        attributes.insert("scope".to_string(), ValueWrapper::String(instance.scope.clone()));
+        attributes.insert("status".to_string(), ValueWrapper::String(instance.status.clone()));
+        if let Some(created_by) = instance.created_by {
+            attributes.insert("created_by".to_string(), ValueWrapper::String(created_by));
+        }
+        Ok(attributes)
+    }
+
     ////////////////////////////////// RESOURCE QUOTA CRUD OPERATIONS //////////////////////////////
     /// Creates resource_quota
     pub fn new_resource_quota_with(&self, ctx: &SecurityContext, resource: &Resource, principal: &Principal, scope: &str, max_value: i32) -> Result<ResourceQuota, RbacError> {
@@ -87,9 +144,21 @@ impl<'a> PersistenceManager<'a> {
         self.new_resource_quota(ctx, principal.id.as_str(), &mut quota)
     }
 
+    /// Creates resource quota for the given license-policy, which must be effective for the
+    /// organization of the principal (or inherited by it), or for the earliest effective policy
+    /// when the quota doesn't name a policy
     pub fn new_resource_quota(&self, ctx: &SecurityContext, principal_id: &str, quota: &mut ResourceQuota) -> Result<ResourceQuota, RbacError> {
         if let Some(principal) = self.principal_repository.get(ctx, principal_id) {
-            if let Some(policy) = self.license_policy_repository.get_by_org(ctx, principal.organization_id.as_str()).first() {
+            let policies = self.get_effective_license_policies(ctx, principal.organization_id.as_str());
+            let policy = if quota.license_policy_id.is_empty() {
+                policies.first()
+            } else {
+                match policies.iter().find(|p| p.id == quota.license_policy_id) {
+                    Some(policy) => Some(policy),
+                    None => return Err(RbacError::Custom(format!("License policy {} is not effective for {:?} while adding resource quota {:?}", quota.license_policy_id, principal_id, quota))),
+                }
+            };
+            if let Some(policy) = policy {
                 quota.license_policy_id = policy.id.clone();
                 self.resource_quota_repository.create(ctx, quota)
             } else {
@@ -145,16 +214,282 @@ impl<'a> PersistenceManager<'a> {
         self.principal_repository.create(ctx, &Principal::new("", org.id.as_str(), username, None))
     }
 
-    /// Retrieves principal by user-id from the database
+    /// Retrieves principal by user-id from the database, roles/groups/claims of the principal
+    /// are served from the claim cache when available
     pub fn get_principal(&self, ctx: &SecurityContext, realm_id: &str, principal_id: &str) -> Option<Principal> {
         if let Some(mut principal) = self.principal_repository.get(&ctx, principal_id) {
-            self.populate_principal(ctx, realm_id, &mut principal);
+            self.load_principal(ctx, realm_id, &mut principal);
+            Some(principal)
+        } else {
+            None
+        }
+    }
+
+    /// Retrieves principal within context of given organization, which is either home
+    /// organization of the principal or an organization where the principal is an active guest.
+    /// The roles, groups and claims of the principal are then resolved against that organization
+    pub fn get_principal_in_org(&self, ctx: &SecurityContext, realm_id: &str, principal_id: &str, organization_id: &str) -> Result<Principal, RbacError> {
+        let mut principal = self.principal_repository.get(&ctx, principal_id).ok_or_else(|| RbacError::NotFound(format!("Principal not found {}", principal_id)))?;
+        if principal.organization_id != organization_id {
+            if self.guest_membership_repository.get_active(ctx, organization_id, principal_id).is_none() {
+                let message = format!("Principal {} is not a guest of organization {}", principal_id, organization_id);
+                self.audit(ctx, message.clone(), "GET");
+                return Err(RbacError::Security(message));
+            }
+            principal.organization_id = organization_id.to_string();
+        }
+        self.load_principal(ctx, realm_id, &mut principal);
+        Ok(principal)
+    }
+
+    /// Retrieves principal populated against current policy along with given mutations applied
+    /// as an overlay, the mutations are neither saved in the database nor in the claim cache
+    pub fn get_principal_with_mutations(&self, ctx: &SecurityContext, realm_id: &str, principal_id: &str, mutations: &Vec<PolicyMutation>) -> Option<Principal> {
+        if mutations.len() == 0 {
+            return self.get_principal(ctx, realm_id, principal_id);
+        }
+        if let Some(mut principal) = self.principal_repository.get(&ctx, principal_id) {
+            self.populate_principal(ctx, realm_id, &mut principal, mutations);
             Some(principal)
         } else {
             None
         }
     }
 
+    /// Verifies that given mutations would be accepted if they were saved, i.e., principals
+    /// added to roles don't exceed limits of the roles or violate static separation-of-duty
+    /// rules along with other roles of the principal including roles added by the mutations
+    pub fn validate_mutations(&self, ctx: &SecurityContext, mutations: &Vec<PolicyMutation>) -> Result<(), RbacError> {
+        let mut added: Vec<(String, Vec<String>)> = vec![];
+        for mutation in mutations {
+            if let PolicyMutation::AddPrincipalToRole{principal_id, role_id, ..} = mutation {
+                match added.iter().position(|(id, _)| id == principal_id) {
+                    Some(i) => added[i].1.push(role_id.clone()),
+                    None => added.push((principal_id.clone(), vec![role_id.clone()])),
+                }
+            }
+        }
+        for (principal_id, role_ids) in added {
+            let principal = self.principal_repository.get(ctx, principal_id.as_str()).ok_or_else(|| RbacError::NotFound(format!("Principal not found {}", principal_id)))?;
+            self.role_roleable_repository.validate_principal_roles(ctx, principal.organization_id.as_str(), principal.id.as_str(), &role_ids, &vec![])?;
+        }
+        Ok(())
+    }
+
+    /// Returns groups of the principal including groups inherited via parent groups
+    pub fn get_effective_groups(&self, ctx: &SecurityContext, principal_id: &str) -> Option<Vec<Group>> {
+        if let Some(principal) = self.principal_repository.get(&ctx, principal_id) {
+            let mut groups = self.resolve_groups(ctx, &principal);
+            groups.sort_by(|a, b| a.name.cmp(&b.name));
+            Some(groups)
+        } else {
+            None
+        }
+    }
+
+    ////////////////////////////////// SESSION OPERATIONS //////////////////////////////
+    /// Opens session for the principal of the context that activates given subset of its
+    /// assigned roles
+    pub fn open_session(&self, ctx: &SecurityContext, realm_id: &str, principal_id: &str, role_ids: Vec<String>, expired_at: NaiveDateTime) -> Result<Session, RbacError> {
+        if ctx.principal_id != principal_id {
+            let message = format!("Principal {} cannot open session for principal {}", ctx.principal_id, principal_id);
+            self.audit(ctx, message.clone(), "CREATE");
+            return Err(RbacError::Security(message));
+        }
+        let principal = self.get_principal(ctx, realm_id, principal_id).ok_or_else(|| RbacError::NotFound(format!("Principal not found {}", principal_id)))?;
+        let mut session = Session::new("", realm_id, principal.organization_id.as_str(), principal_id, vec![], expired_at);
+        for role_id in role_ids {
+            if !session.role_ids.contains(&role_id) {
+                session.role_ids.push(role_id);
+            }
+        }
+        self.validate_session(ctx, &principal, &session)?;
+        self.session_repository.create(ctx, &session)
+    }
+
+    /// Returns session that belongs to the principal of the context
+    pub fn get_session(&self, ctx: &SecurityContext, session_id: &str) -> Result<Session, RbacError> {
+        let session = self.session_repository.get(ctx, session_id).ok_or_else(|| RbacError::NotFound(format!("Session not found or expired {}", session_id)))?;
+        if session.principal_id != ctx.principal_id {
+            let message = format!("Session {} does not belong to principal {}", session_id, ctx.principal_id);
+            self.audit(ctx, message.clone(), "GET");
+            return Err(RbacError::Security(message));
+        }
+        Ok(session)
+    }
+
+    /// Activates given roles within the session
+    pub fn activate_session_roles(&self, ctx: &SecurityContext, session_id: &str, role_ids: Vec<String>) -> Result<Session, RbacError> {
+        let mut session = self.get_session(ctx, session_id)?;
+        let principal = self.get_principal(ctx, session.realm_id.as_str(), session.principal_id.as_str()).ok_or_else(|| RbacError::NotFound(format!("Principal not found {}", session.principal_id)))?;
+        for role_id in role_ids {
+            if !session.role_ids.contains(&role_id) {
+                session.role_ids.push(role_id);
+            }
+        }
+        self.validate_session(ctx, &principal, &session)?;
+        self.session_repository.update(ctx, &session)
+    }
+
+    /// Deactivates given roles within the session
+    pub fn deactivate_session_roles(&self, ctx: &SecurityContext, session_id: &str, role_ids: Vec<String>) -> Result<Session, RbacError> {
+        let mut session = self.get_session(ctx, session_id)?;
+        session.role_ids.retain(|role_id| !role_ids.contains(role_id));
+        self.session_repository.update(ctx, &session)
+    }
+
+    /// Closes the session
+    pub fn close_session(&self, ctx: &SecurityContext, session_id: &str) -> Result<usize, RbacError> {
+        self.get_session(ctx, session_id)?;
+        self.session_repository.delete(ctx, session_id)
+    }
+
+    /// Returns principal where roles and claims of roles are limited to roles activated by the
+    /// session along with their parent roles, claims mapped directly or delegated to the
+    /// principal are not affected by the session
+    pub fn get_principal_with_session(&self, ctx: &SecurityContext, realm_id: &str, principal_id: &str, session_id: &str) -> Result<Principal, RbacError> {
+        let principal = self.get_principal(ctx, realm_id, principal_id).ok_or_else(|| RbacError::NotFound(format!("Principal not found {}", principal_id)))?;
+        self.apply_session(ctx, realm_id, principal, session_id)
+    }
+
+    /// Limits roles and role claims of already populated principal to roles activated by the session
+    pub fn apply_session(&self, ctx: &SecurityContext, realm_id: &str, mut principal: Principal, session_id: &str) -> Result<Principal, RbacError> {
+        let session = self.session_repository.get(ctx, session_id).ok_or_else(|| RbacError::NotFound(format!("Session not found or expired {}", session_id)))?;
+        if session.realm_id != realm_id || session.principal_id != principal.id {
+            return Err(RbacError::Security(format!("Session {} does not belong to principal {} of realm {}", session_id, principal.id, realm_id)));
+        }
+        let active = self.get_session_role_ids(&principal, &session);
+        principal.roles.retain(|role_id, _| active.contains(role_id));
+        principal.claims.retain(|cc| match cc {
+            ClaimClaimable::Role(_, _, role_id, _, _) => active.contains(role_id),
+            _ => true,
+        });
+        Ok(principal)
+    }
+
+    ////////////////////////////////// DELEGATION OPERATIONS //////////////////////////////
+    /// Creates delegation, which is allowed only to the delegator itself or an administrator of
+    /// the organization acting on behalf of the delegator. The delegated roles must not exceed
+    /// limits of the roles or violate static separation-of-duty rules along with other roles of
+    /// the delegatee
+    pub fn delegate(&self, ctx: &SecurityContext, delegation: &Delegation) -> Result<Delegation, RbacError> {
+        self.validate_delegation(ctx, delegation)?;
+        self.delegation_repository.create(ctx, delegation)
+    }
+
+    /// Updates delegation, where the delegator of existing delegation cannot be changed
+    pub fn update_delegation(&self, ctx: &SecurityContext, delegation: &Delegation) -> Result<Delegation, RbacError> {
+        let existing = self.delegation_repository.get(ctx, delegation.organization_id.as_str(), delegation.id.as_str()).ok_or_else(|| RbacError::NotFound(format!("Delegation not found {:?}", delegation)))?;
+        let mut delegation = delegation.clone();
+        delegation.delegator_id = existing.delegator_id;
+        self.validate_delegation(ctx, &delegation)?;
+        self.delegation_repository.update(ctx, &delegation)
+    }
+
+    ////////////////////////////////// GUEST MEMBERSHIP OPERATIONS //////////////////////////////
+    /// Adds principal of another organization as guest of the host organization, roles and
+    /// groups of the host organization can then be assigned to the guest principal
+    pub fn add_guest(&self, ctx: &SecurityContext, membership: &GuestMembership) -> Result<GuestMembership, RbacError> {
+        let org_id = membership.organization_id.as_str();
+        let principal = self.principal_repository.get(ctx, membership.principal_id.as_str()).ok_or_else(|| RbacError::NotFound(format!("Principal not found {}", membership.principal_id)))?;
+        if principal.organization_id == org_id {
+            return Err(RbacError::Duplicate(format!("Principal {} is already member of organization {}", principal.id, org_id)));
+        }
+        if self.org_repository.get(ctx, org_id).is_none() {
+            return Err(RbacError::NotFound(format!("Organization not found {}", org_id)));
+        }
+        self.guest_membership_repository.create(ctx, membership)
+    }
+
+    ////////////////////////////////// ASSIGNMENT REQUEST OPERATIONS //////////////////////////////
+    /// Creates pending request for assigning role or claim to the principal, which is assigned
+    /// only after a principal holding the approver role of the organization approves it
+    pub fn request_assignment(&self, ctx: &SecurityContext, request: &AssignmentRequest) -> Result<AssignmentRequest, RbacError> {
+        let org_id = request.organization_id.as_str();
+        if self.principal_repository.get(ctx, request.principal_id.as_str()).filter(|p| p.organization_id == org_id).is_none() {
+            return Err(RbacError::NotFound(format!("Principal {} not found within organization {}", request.principal_id, org_id)));
+        }
+        let org = self.org_repository.get(ctx, org_id).ok_or_else(|| RbacError::NotFound(format!("Organization not found {}", org_id)))?;
+        let approver_role_id = org.approver_role_id.ok_or_else(|| RbacError::Custom(format!("Organization {} does not define approver role for assignment requests", org_id)))?;
+        if self.role_repository.get(ctx, org_id, approver_role_id.as_str()).is_none() {
+            return Err(RbacError::NotFound(format!("Approver role {} not found within organization {}", approver_role_id, org_id)));
+        }
+        if let Some(ref role_id) = request.role_id {
+            if self.role_repository.get(ctx, org_id, role_id.as_str()).is_none() {
+                return Err(RbacError::NotFound(format!("Role {} not found within organization {}", role_id, org_id)));
+            }
+        }
+        if let Some(ref claim_id) = request.claim_id {
+            if self.claim_repository._get_by_claim_ids(vec![claim_id.clone()]).len() == 0 {
+                return Err(RbacError::NotFound(format!("Claim {} not found", claim_id)));
+            }
+        }
+        let mut request = request.clone();
+        request.approver_role_id = approver_role_id;
+        self.assignment_request_repository.create(ctx, &request)
+    }
+
+    /// Approves pending assignment-request by principal of the context, the role or claim is
+    /// then assigned to the principal using constraints and date range of the request, where
+    /// the assignment and the review are saved within a single transaction
+    pub fn approve_assignment(&self, ctx: &SecurityContext, realm_id: &str, org_id: &str, id: &str, comment: &str) -> Result<AssignmentRequest, RbacError> {
+        let mut request = self.get_reviewable_assignment(ctx, realm_id, org_id, id)?;
+        if let Some(ref role_id) = request.role_id {
+            let constraints = request.constraints.clone().unwrap_or_default();
+            let rr = PRoleRoleable::new(role_id.as_str(), request.principal_id.as_str(), Constants::Principal.to_string().as_str(), constraints.as_str(), request.effective_at, request.expired_at);
+            self.role_roleable_repository.validate(ctx, &rr)?;
+        }
+        request.reviewed_by = Some(ctx.principal_id.clone());
+        request.review_comment = Some(comment.to_string()).filter(|c| c.len() > 0);
+        let saved = self.assignment_request_repository.approve(ctx, &request)?;
+        self.audit(ctx, format!("Approved assignment request {:?}", saved), "UPDATE");
+        Ok(saved)
+    }
+
+    /// Rejects pending assignment-request by principal of the context
+    pub fn reject_assignment(&self, ctx: &SecurityContext, realm_id: &str, org_id: &str, id: &str, comment: &str) -> Result<AssignmentRequest, RbacError> {
+        let mut request = self.get_reviewable_assignment(ctx, realm_id, org_id, id)?;
+        request.status = Status::REJECTED.to_string();
+        request.reviewed_by = Some(ctx.principal_id.clone());
+        request.review_comment = Some(comment.to_string()).filter(|c| c.len() > 0);
+        let saved = self.assignment_request_repository.update(ctx, &request)?;
+        self.audit(ctx, format!("Rejected assignment request {:?}", saved), "UPDATE");
+        Ok(saved)
+    }
+
+    ////////////////////////////////// BREAK-GLASS OPERATIONS //////////////////////////////
+    /// Grants break-glass role to the principal for emergency access, the assignment expires at
+    /// given time that must be within BreakGlass::MAX_HOURS and the access is recorded as
+    /// high-severity audit record along with the justification. The emergency assignment is
+    /// exempt from separation-of-duty rules and maximum assignees of the role so that it isn't
+    /// blocked by other roles of the principal or regular holders of the role. An expired
+    /// assignment of the role is replaced but break-glass access is rejected while the principal
+    /// holds the role, so that a regular assignment is never cut short by the emergency access
+    pub fn break_glass(&self, ctx: &SecurityContext, realm_id: &str, access: &BreakGlass) -> Result<BreakGlass, RbacError> {
+        let now = Utc::now().naive_utc();
+        if access.justification.trim().len() == 0 {
+            return Err(RbacError::Custom(format!("Justification is required for break-glass access {:?}", access)));
+        }
+        if access.expired_at <= now || access.expired_at > now + Duration::hours(BreakGlass::MAX_HOURS) {
+            return Err(RbacError::Custom(format!("Break-glass access must expire within {} hours {:?}", BreakGlass::MAX_HOURS, access)));
+        }
+        let principal = self.principal_repository.get(ctx, access.principal_id.as_str()).ok_or_else(|| RbacError::NotFound(format!("Principal not found {}", access.principal_id)))?;
+        let role = match self.role_repository.get(ctx, principal.organization_id.as_str(), access.role_id.as_str()) {
+            Some(ref role) if role.realm_id == realm_id && role.break_glass => role.clone(),
+            Some(_) => return Err(RbacError::Security(format!("Role {} does not allow break-glass access", access.role_id))),
+            None => return Err(RbacError::NotFound(format!("Role not found {}", access.role_id))),
+        };
+        let assignment = PRoleRoleable::new(role.id.as_str(), principal.id.as_str(), Constants::Principal.to_string().as_str(), "", now, access.expired_at);
+        match self.role_roleable_repository.replace_expired(&assignment) {
+            Ok(0) => return Err(RbacError::Duplicate(format!("Principal {} already holds role {}", principal.username, role.name))),
+            Ok(_) => (),
+            Err(err) => return Err(RbacError::Persistence(err.to_string())),
+        }
+        let message = format!("Break-glass access to role {} granted to principal {} until {} -- justification: {}", role.name, principal.username, access.expired_at, access.justification);
+        let _ = self.audit_record_repository.create_with_severity(message.as_str(), "BREAK_GLASS", format!("{:?}", ctx).as_str(), ctx.principal_id.clone(), "HIGH");
+        warn!("{}", message);
+        Ok(access.clone())
+    }
 
     ////////////////////////////////// CLAIM CRUD OPERATIONS //////////////////////////////
     /// Creates claim
@@ -163,9 +498,10 @@ impl<'a> PersistenceManager<'a> {
     }
 
 
-    /// Adds principal group
+    /// Adds principal group -- static separation-of-duty rules are checked against roles of
+    /// the group
     pub fn map_principal_to_group(&self, ctx: &SecurityContext, principal: &Principal, group: &Group) -> Result<usize, RbacError> {
-        self.group_principal_repository.add_principal_to_group(ctx, group.id.as_str(), principal.id.as_str())
+        self.role_roleable_repository.add_principal_to_group(ctx, group.organization_id.as_str(), group.id.as_str(), principal.id.as_str())
     }
 
     /// Removes principal from group
@@ -215,6 +551,16 @@ impl<'a> PersistenceManager<'a> {
         self.claim_claimable_repository.delete_principal_from_claim(ctx, principal.id.as_str(), claim.id.as_str())
     }
 
+    /// Adds guest membership to claim
+    pub fn map_guest_to_claim(&self, ctx: &SecurityContext, membership: &GuestMembership, claim: &Claim, scope: &str, constraints: &str) -> Result<usize, RbacError> {
+        self.claim_claimable_repository.add_guest_membership_to_claim(ctx, membership.id.as_str(), claim.id.as_str(), scope, constraints, Utc::now().naive_utc(), membership.expired_at)
+    }
+
+    /// Removes guest membership from claim
+    pub fn unmap_guest_from_claim(&self, ctx: &SecurityContext, membership: &GuestMembership, claim: &Claim) -> Result<usize, RbacError> {
+        self.claim_claimable_repository.delete_guest_membership_from_claim(ctx, membership.id.as_str(), claim.id.as_str())
+    }
+
 
     /// Adds license-policy to claim
     pub fn map_license_policy_to_claim(&self, ctx: &SecurityContext, policy: &LicensePolicy, claim: &Claim, scope: &str, constraints: &str) -> Result<usize, RbacError> {
@@ -228,134 +574,445 @@ impl<'a> PersistenceManager<'a> {
 
     /// Returns all resources for given claims - used by security manager
     pub fn get_resources_by_claims(&self, ctx: &SecurityContext, realm_id: &str, principal: &Principal, resource_name: String, scope: String) -> Vec<ClaimResource> {
-        // Checking claims against license-policy
+        self.get_resources_by_claims_for(ctx, realm_id, principal, &vec![(resource_name, scope)]).pop().unwrap_or(vec![])
+    }
+
+    /// Returns resources for given claims for each pair of resource-name and scope - used by security
+    /// manager for batch checks so that org claims and realm resources are loaded only once
+    pub fn get_resources_by_claims_for(&self, ctx: &SecurityContext, realm_id: &str, principal: &Principal, resource_scopes: &Vec<(String, String)>) -> Vec<Vec<ClaimResource>> {
         let org_claim_claimables = self.get_claim_claimables_by_org(ctx, realm_id, principal.organization_id.as_str());
-        let mut matched = false;
-        for org_claim_claimable in &org_claim_claimables {
-            match org_claim_claimable {
-                ClaimClaimable::LicensePolicy(_, _, claim_scope, _) => {
-                    if *claim_scope == scope {
-                        matched = true;
-                        break;
+        let resources = self.resource_repository.get_by_realm(ctx, realm_id);
+        let realm_matching = self.get_realm_scope_matching(ctx, realm_id);
+        let empty = &"".to_string();
+        let mut all_results = vec![];
+        for (resource_name, scope) in resource_scopes {
+            let matching = resources.values().find(|r| r.resource_name == *resource_name).map(|r| self.resource_scope_matching(ctx, r, &realm_matching)).unwrap_or(realm_matching.clone());
+            // Checking claims against license-policy
+            if self.find_license_claim(&org_claim_claimables, scope.as_str(), &matching).is_none() {
+                warn!("Access to {} {} for user {}-{} denied because no matching claims by license policy exist", resource_name, scope, principal.username, principal.id);
+                all_results.push(vec![]);
+                continue;
+            }
+
+            let mut result = vec![];
+            for (_, resource) in &resources {
+                for cc in &principal.claims {
+                    let (claim, claim_scope, claim_constraints) = match cc {
+                        ClaimClaimable::Realm(claim, _) => (claim, empty, empty),
+                        ClaimClaimable::LicensePolicy(claim, _, scope, constraints) => (claim, scope, constraints),
+                        ClaimClaimable::Role(claim, _, _, scope, constraints) => (claim, scope, constraints),
+                        ClaimClaimable::Principal(claim, _, _, scope, constraints) => (claim, scope, constraints),
+                        ClaimClaimable::Delegation(claim, _, _, _, scope, constraints) => (claim, scope, constraints),
+                    };
+                    //
+                    if claim.resource_id == resource.id && resource.resource_name == *resource_name && matching.matches(claim_scope.as_str(), scope.as_str()) {
+                        // constraints of license-policy must be satisfied along with constraints of the grant
+                        let constraints = evaluator::all_of(self.find_license_constraints(&org_claim_claimables, claim.id.as_str(), claim_scope.as_str(), &matching).as_str(), claim_constraints.as_str());
+                        result.push(ClaimResource::new(claim.clone(), claim_scope.clone(), constraints, resource.clone()));
                     }
-                },
-                ClaimClaimable::Realm(_, _) => {
-                    matched = true;
-                    break;
-                },
-                _ => (),
-            };
+                }
+            }
+            all_results.push(result);
         }
+        all_results
+    }
 
-        if !matched {
-            warn!("Access to {} {} for user {}-{} denied because no matching claims by license policy exist", resource_name, scope, principal.username, principal.id);
-            return vec![];
-        }
+    /// Returns license-policy claim that permits given scope of the resource for the organization
+    /// or realm claim if organization has no license-policy
+    pub fn get_license_claim_by_scope(&self, ctx: &SecurityContext, realm_id: &str, organization_id: &str, resource_name: &str, scope: &str) -> Option<ClaimClaimable> {
+        let realm_matching = self.get_realm_scope_matching(ctx, realm_id);
+        let matching = self.resource_repository.get_by_realm(ctx, realm_id).values().find(|r| r.resource_name == resource_name).map(|r| self.resource_scope_matching(ctx, r, &realm_matching)).unwrap_or(realm_matching.clone());
+        self.find_license_claim(&self.get_claim_claimables_by_org(ctx, realm_id, organization_id), scope, &matching)
+    }
 
+    /// Returns roles of populated principal along with how each role was resolved, i.e.,
+    /// directly, via group or inherited from the role held by the principal
+    pub fn get_role_sources(&self, principal: &Principal) -> Vec<(Role, RoleSource)> {
+        let mut pending = vec![];
+        for role_id in self.role_roleable_repository.get_role_ids_by_principal(principal.id.as_str()) {
+            pending.push((role_id, RoleSource::Direct));
+        }
+        for (group_id, group) in &principal.groups {
+            for role_id in self.role_roleable_repository.get_role_ids_by_group(group_id.clone()) {
+                pending.push((role_id, RoleSource::Group(group.name.clone())));
+            }
+        }
+        let mut visited = pending.iter().map(|(role_id, _)| role_id.clone()).collect::<HashSet<String>>();
         let mut result = vec![];
-        let empty = &"".to_string();
-        for (_, resource) in self.resource_repository.get_by_realm(ctx, realm_id) {
+        let mut i = 0;
+        while i < pending.len() {
+            let (role_id, source) = pending[i].clone();
+            i += 1;
+            if let Some(role) = principal.roles.get(&role_id) {
+                if let Some(ref parent_id) = role.parent_id {
+                    if visited.insert(parent_id.clone()) {
+                        // ancestors of inherited roles are attributed to the role that is held
+                        let held = match source {
+                            RoleSource::Inherited(ref held) => held.clone(),
+                            _ => role.name.clone(),
+                        };
+                        pending.push((parent_id.clone(), RoleSource::Inherited(held)));
+                    }
+                }
+                result.push((role.clone(), source));
+            }
+        }
+        result
+    }
+
+    /// Returns effective claims of the principal grouped by resource and scope along with
+    /// provenance of each claim
+    pub fn get_effective_permissions(&self, ctx: &SecurityContext, realm_id: &str, principal_id: &str) -> Option<Vec<ResourcePermission>> {
+        if let Some(principal) = self.get_principal(ctx, realm_id, principal_id) {
+            let mut role_sources = HashMap::new();
+            for (role, source) in self.get_role_sources(&principal) {
+                if !role_sources.contains_key(&role.id) {
+                    role_sources.insert(role.id.clone(), (role, source));
+                }
+            }
+            let mut result: Vec<ResourcePermission> = vec![];
             for cc in &principal.claims {
-                let (claim, claim_scope, claim_constraints) = match cc {
-                    ClaimClaimable::Realm(claim, _) => (claim, empty, empty),
-                    ClaimClaimable::LicensePolicy(claim, _, scope, constraints) => (claim, scope, constraints),
-                    ClaimClaimable::Role(claim, _, _, scope, constraints) => (claim, scope, constraints),
-                    ClaimClaimable::Principal(claim, _, _, scope, constraints) => (claim, scope, constraints),
+                let (claim, scope, constraints, provenance) = match cc {
+                    ClaimClaimable::Role(claim, _, role_id, scope, constraints) => {
+                        let provenance = match role_sources.get(role_id) {
+                            Some((role, RoleSource::Direct)) => format!("via role {}", role.name),
+                            Some((role, RoleSource::Group(group))) => format!("via role {} of group {}", role.name, group),
+                            Some((role, RoleSource::Inherited(child))) => format!("via role {} inherited from parent {}", child, role.name),
+                            None => format!("via role {}", role_id),
+                        };
+                        (claim, scope, constraints, provenance)
+                    },
+                    ClaimClaimable::Principal(claim, _, _, scope, constraints) => (claim, scope, constraints, "direct".to_string()),
+                    ClaimClaimable::Delegation(claim, _, _, delegator_id, scope, constraints) => (claim, scope, constraints, format!("delegated by {}", delegator_id)),
+                    _ => continue,
                 };
-                //
-                if claim.resource_id == resource.id && resource.resource_name == resource_name && *claim_scope == scope {
-                    result.push(ClaimResource::new(claim.clone(), claim_scope.clone(), claim_constraints.clone(), resource.clone()));
+                if let Some(resource) = principal.resources.iter().find(|r| r.id == claim.resource_id) {
+                    let effective = EffectiveClaim {
+                        claim_id: claim.id.clone(),
+                        action: claim.action.clone(),
+                        effect: claim.effect(),
+                        constraints: constraints.clone(),
+                        source: provenance,
+                    };
+                    if let Some(rp) = result.iter_mut().find(|rp| rp.resource_id == resource.id && rp.scope == *scope) {
+                        rp.claims.push(effective);
+                        continue;
+                    }
+                    result.push(ResourcePermission {
+                        resource_id: resource.id.clone(),
+                        resource_name: resource.resource_name.clone(),
+                        scope: scope.clone(),
+                        claims: vec![effective],
+                    });
+                }
+            }
+            Some(result)
+        } else {
+            None
+        }
+    }
+
+    ////////////////////////////////// ACCESS QUERIES //////////////////////////////
+    /// Returns principals that can perform given action (or any action if empty) on the resource
+    /// within scope. Candidates are found via direct claims, roles (including roles that inherit
+    /// them) and groups and then verified against their effective claims. The access is reported
+    /// as conditional when constraints must be evaluated at runtime.
+    pub fn get_principals_by_access(&self, ctx: &SecurityContext, realm_id: &str, resource_id: &str, action: &str, scope: &str) -> Vec<PrincipalAccess> {
+        let resource = match self.resource_repository.get(ctx, realm_id, resource_id) {
+            Some(resource) => resource,
+            None => return vec![],
+        };
+        let claim_ids = self.claim_repository.get_by_realm_resource(ctx, realm_id, resource_id).iter()
+            .filter(|claim| action.len() == 0 || text::regex_find(claim.action.as_str(), action))
+            .map(|claim| claim.id.clone())
+            .collect::<Vec<String>>();
+        if claim_ids.len() == 0 {
+            return vec![];
+        }
+
+        // Finding candidate principals
+        let mut principal_ids = vec![];
+        let mut role_ids = vec![];
+        for cc in &self.claim_claimable_repository.get_by_claims(claim_ids) {
+            if cc.claimable_type == Constants::Principal.to_string() {
+                principal_ids.push(cc.claimable_id.clone());
+            } else if cc.claimable_type == Constants::Role.to_string() {
+                role_ids.push(cc.claimable_id.clone());
+            }
+        }
+        for role_id in &self.get_descendant_role_ids(role_ids) {
+            for rr in &self.role_roleable_repository.get_by_role(role_id.as_str()) {
+                if rr.roleable_type == Constants::Principal.to_string() {
+                    principal_ids.push(rr.roleable_id.clone());
+                } else if rr.roleable_type == Constants::Group.to_string() {
+                    for group_id in &self.get_descendant_group_ids(vec![rr.roleable_id.clone()]) {
+                        for gp in &self.group_principal_repository.get_by_group(group_id.as_str()) {
+                            principal_ids.push(gp.principal_id.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        // Delegatees of candidates may have access via delegated claims
+        for delegation in self.delegation_repository.get_by_delegators(principal_ids.clone()) {
+            principal_ids.push(delegation.delegatee_id.clone());
+        }
+
+        // Verifying candidates against their effective claims
+        let mut visited = HashSet::new();
+        let mut result = vec![];
+        for principal_id in principal_ids {
+            if !visited.insert(principal_id.clone()) {
+                continue;
+            }
+            if let Some(principal) = self.get_principal(ctx, realm_id, principal_id.as_str()) {
+                for cr in &self.get_resources_by_claims(ctx, realm_id, &principal, resource.resource_name.clone(), scope.to_string()) {
+                    if action.len() == 0 || text::regex_find(cr.claim.action.as_str(), action) {
+                        result.push(PrincipalAccess::new(&principal, cr));
+                    }
                 }
             }
         }
         result
     }
 
+    /// Compares effective permissions of two principals, e.g. to onboard a principal like another
+    pub fn diff_principals(&self, ctx: &SecurityContext, realm_id: &str, principal_a_id: &str, principal_b_id: &str) -> Result<PermissionDiff, RbacError> {
+        let a = self.get_principal(ctx, realm_id, principal_a_id).ok_or_else(|| RbacError::NotFound(format!("Principal not found {}", principal_a_id)))?;
+        let b = self.get_principal(ctx, realm_id, principal_b_id).ok_or_else(|| RbacError::NotFound(format!("Principal not found {}", principal_b_id)))?;
+        Ok(PermissionDiff::new(&self.get_permission_tuples(&a), &self.get_permission_tuples(&b)))
+    }
+
+    /// Compares permissions of two roles of the organization including claims inherited from
+    /// parent roles
+    pub fn diff_roles(&self, ctx: &SecurityContext, realm_id: &str, org_id: &str, role_a_id: &str, role_b_id: &str) -> Result<PermissionDiff, RbacError> {
+        let a = self.get_role_holder(ctx, realm_id, org_id, role_a_id)?;
+        let b = self.get_role_holder(ctx, realm_id, org_id, role_b_id)?;
+        Ok(PermissionDiff::new(&self.get_permission_tuples(&a), &self.get_permission_tuples(&b)))
+    }
 
     ////////////////////////////////// PRIVATE METHODS //////////////////////////////
-    fn populate_roles(&self, ctx: &SecurityContext, org_roles: &HashMap<String, Role>, role_ids: &Vec<String>, principal: &mut Principal) {
-        for role_id in role_ids {
-            if let Some(role) = org_roles.get(role_id) {
-                principal.roles.insert(role_id.clone(), role.clone());
-                if let Some(ref parent_id) = role.parent_id {
-                    self.populate_roles(ctx, org_roles, &vec![parent_id.clone()], principal);
-                }
-            } else {
-                self.audit(ctx, format!("Failed to add role with id {} for {}-{} because it's not mapped to org while populating principal", role_id, principal.username, principal.id), "GET");
+    /// Returns transient principal that only holds given role so that parent roles and
+    /// license-policy are applied to claims of the role the same way as for principals
+    fn get_role_holder(&self, ctx: &SecurityContext, realm_id: &str, org_id: &str, role_id: &str) -> Result<Principal, RbacError> {
+        if self.role_repository.get(ctx, org_id, role_id).is_none() {
+            return Err(RbacError::NotFound(format!("Role {} not found within organization {}", role_id, org_id)));
+        }
+        let mut holder = Principal::new("", org_id, "", None);
+        self.populate_principal(ctx, realm_id, &mut holder, &vec![PolicyMutation::AddPrincipalToRole{principal_id: "".into(), role_id: role_id.to_string(), constraints: "".into()}]);
+        Ok(holder)
+    }
+
+    /// Returns (resource, action, scope, constraints) tuples of claims of populated principal
+    fn get_permission_tuples(&self, principal: &Principal) -> Vec<PermissionTuple> {
+        let mut result = vec![];
+        for cc in &principal.claims {
+            let (claim, scope, constraints) = match cc {
+                ClaimClaimable::Role(claim, _, _, scope, constraints) => (claim, scope, constraints),
+                ClaimClaimable::Principal(claim, _, _, scope, constraints) => (claim, scope, constraints),
+                ClaimClaimable::Delegation(claim, _, _, _, scope, constraints) => (claim, scope, constraints),
+                _ => continue,
+            };
+            if let Some(resource) = principal.resources.iter().find(|r| r.id == claim.resource_id) {
+                result.push(PermissionTuple::new(resource.resource_name.as_str(), claim.action.as_str(), scope.as_str(), constraints.as_str(), claim.effect().as_str()));
             }
         }
+        result
     }
 
-    fn populate_principal(&self, ctx: &SecurityContext, realm_id: &str, principal: &mut Principal) {
-        // populate roles directly map to principal
-        let org_roles = self.role_repository.get_by_org(ctx, principal.organization_id.as_str());
-        self.populate_roles(ctx, &org_roles, &self.role_roleable_repository.get_role_ids_by_principal(principal.id.as_str()), principal);
+    /// Returns given group-ids along with ids of all groups that inherit from them
+    fn get_descendant_group_ids(&self, group_ids: Vec<String>) -> Vec<String> {
+        let mut visited = group_ids.iter().cloned().collect::<HashSet<String>>();
+        let mut result = group_ids.clone();
+        let mut parent_ids = group_ids;
+        while parent_ids.len() > 0 {
+            let mut child_ids = vec![];
+            for group in &self.group_repository._get_by_parent_ids(parent_ids) {
+                if visited.insert(group.id.clone()) {
+                    child_ids.push(group.id.clone());
+                }
+            }
+            result.extend(child_ids.iter().cloned());
+            parent_ids = child_ids;
+        }
+        result
+    }
 
-        // Checking groups
-        let org_groups = self.group_repository.get_by_org(ctx, principal.organization_id.as_str());
-        for group_id in &self.group_repository.get_group_ids_by_principal(ctx, principal.id.as_str()) {
-            // populate roles indirectly map to group
-            self.populate_roles(ctx, &org_roles, &self.role_roleable_repository.get_role_ids_by_group(group_id.clone()), principal);
-            //
-            // Adding groups
-            if let Some(group) = org_groups.get(group_id) {
-                principal.groups.insert(group.id.clone(), group.clone());
-            } else {
-                self.audit(ctx, format!("Failed to find group for id {} for {}-{} while populating principal", group_id, principal.username, principal.id), "GET");
+    /// Returns given role-ids along with ids of all roles that inherit from them
+    fn get_descendant_role_ids(&self, role_ids: Vec<String>) -> Vec<String> {
+        let mut visited = role_ids.iter().cloned().collect::<HashSet<String>>();
+        let mut result = role_ids.clone();
+        let mut parent_ids = role_ids;
+        while parent_ids.len() > 0 {
+            let mut child_ids = vec![];
+            for role in &self.role_repository.get_by_parent_ids(parent_ids) {
+                if visited.insert(role.id.clone()) {
+                    child_ids.push(role.id.clone());
+                }
             }
+            result.extend(child_ids.iter().cloned());
+            parent_ids = child_ids;
         }
+        result
+    }
 
-        //
-        // Create role-ids lookup
-        let mut role_ids = vec![];
-        for (_, role) in &principal.roles {
-            role_ids.push(role.id.clone());
+    /// Returns constraints of license-policy for given claim and scope, where the claim is permitted
+    /// when constraints of any of matching license-policy claims are satisfied
+    fn find_license_constraints(&self, org_claim_claimables: &Vec<ClaimClaimable>, claim_id: &str, scope: &str, matching: &ScopeMatching) -> String {
+        let mut result: Option<String> = None;
+        for org_claim_claimable in org_claim_claimables {
+            if let ClaimClaimable::LicensePolicy(claim, _, claim_scope, constraints) = org_claim_claimable {
+                if claim.id == claim_id && (matching.matches(claim_scope.as_str(), scope) || claim_scope.len() == 0) {
+                    result = Some(match result {
+                        Some(existing) => evaluator::any_of(existing.as_str(), constraints.as_str()),
+                        None => constraints.clone(),
+                    });
+                }
+            }
         }
+        result.unwrap_or_default()
+    }
 
-        // Checking license-policy
-        let org_claim_claimables = self.get_claim_claimables_by_org(ctx, realm_id, principal.organization_id.as_str());
-        let mut claim_id_scopes = HashMap::new();
-        let mut claims_by_id = HashMap::new();
-        for org_claim_claimable in &org_claim_claimables {
+    /// Returns true if license grants the claim for a scope that covers given scope
+    fn is_license_claim(&self, org_claim_claimables: &Vec<ClaimClaimable>, claim_id: &str, scope: &str, matching: &ScopeMatching) -> bool {
+        org_claim_claimables.iter().any(|cc| match cc {
+            ClaimClaimable::LicensePolicy(claim, _, claim_scope, _) => claim.id == claim_id && (matching.matches(claim_scope.as_str(), scope) || claim_scope.len() == 0),
+            _ => false,
+        })
+    }
+
+    fn find_license_claim(&self, org_claim_claimables: &Vec<ClaimClaimable>, scope: &str, matching: &ScopeMatching) -> Option<ClaimClaimable> {
+        for org_claim_claimable in org_claim_claimables {
             match org_claim_claimable {
-                ClaimClaimable::LicensePolicy(claim, _, scope, constraints) => {
-                    if scope.len() > 0 || constraints.len() > 0 {
-                        claim_id_scopes.insert(format!("{}_{}", claim.id, scope), true);
+                ClaimClaimable::LicensePolicy(_, _, claim_scope, _) => {
+                    if matching.matches(claim_scope.as_str(), scope) {
+                        return Some(org_claim_claimable.clone());
                     }
-                    claims_by_id.insert(claim.id.clone(), claim.clone());
                 },
-                ClaimClaimable::Realm(claim, _) => {
-                    claims_by_id.insert(claim.id.clone(), claim.clone());
-                }
+                ClaimClaimable::Realm(_, _) => return Some(org_claim_claimable.clone()),
                 _ => (),
             };
         }
+        None
+    }
 
-        // Find claims mapped to roles
-        for cc in &self.claim_claimable_repository.get_by_roles(role_ids) {
-            if let Some(claim) = claims_by_id.get(&cc.claim_id) {
-                if claim_id_scopes.len() > 0 && (cc.scope.len() > 0 || cc.claim_constraints().len() > 0) && claim_id_scopes.get(&format!("{}_{}", claim.id, cc.scope)) == None {
-                    self.audit(ctx, format!("Found different or missing role scope/constraints than what was set in policy principal claim: {:?}, org claim: {:?}, all org claims: {:?}", cc, claim, org_claim_claimables), "GET");
+    /// Adds roles for given pairs of role-id and constraints of role assignment, where parent roles
+    /// inherit constraints of the assignment and a role that is assigned multiple times is active
+    /// when any of its assignments is satisfied
+    fn populate_roles(&self, ctx: &SecurityContext, org_roles: &HashMap<String, Role>, role_assignments: &Vec<(String, String)>, principal: &mut Principal) {
+        for (role_id, constraints) in role_assignments {
+            // visited roles of the parent chain so that cyclic parents cannot loop forever
+            let mut visited = HashSet::new();
+            let mut next = Some((role_id.clone(), constraints.clone()));
+            while let Some((role_id, constraints)) = next.take() {
+                if !visited.insert(role_id.clone()) {
+                    self.audit(ctx, format!("Found cycle in parent chain of role with id {} for {}-{} while populating principal", role_id, principal.username, principal.id), "GET");
+                    break;
+                }
+                if let Some(role) = org_roles.get(&role_id) {
+                    let mut role = role.clone();
+                    let constraints = match principal.roles.get(&role_id) {
+                        Some(existing) => evaluator::any_of(existing.constraints.clone().unwrap_or_default().as_str(), constraints.as_str()),
+                        None => constraints,
+                    };
+                    role.constraints = if constraints.len() > 0 { Some(constraints.clone()) } else { None };
+                    next = role.parent_id.clone().map(|parent_id| (parent_id, constraints));
+                    principal.roles.insert(role_id.clone(), role);
                 } else {
-                    principal.claims.push(ClaimClaimable::Role(claim.clone(), realm_id.to_string(), cc.claimable_id.clone(), cc.scope.clone(), cc.claim_constraints().clone()));
+                    self.audit(ctx, format!("Failed to add role with id {} for {}-{} because it's not mapped to org while populating principal", role_id, principal.username, principal.id), "GET");
                 }
-            } else {
-                self.audit(ctx, format!("Failed to find claim for id {} - principal {}-{} while populating principal", cc.claim_id, principal.username, principal.id), "GET");
             }
         }
+    }
 
-        // Find claims mapped directly to principal
-        for cc in &self.claim_claimable_repository.get_by_principal(principal.id.clone()) {
-            if let Some(claim) = claims_by_id.get(&cc.claim_id) {
-                if claim_id_scopes.len() > 0 && (cc.scope.len() > 0 || cc.claim_constraints().len() > 0) && claim_id_scopes.get(&format!("{}_{}", claim.id, cc.scope)) == None {
-                    self.audit(ctx, format!("Found different or missing principal scope/constraints than what was set in policy {:?} - {:?}", claim, cc), "GET");
-                } else {
-                    principal.claims.push(ClaimClaimable::Principal(claim.clone(), realm_id.to_string(), cc.claimable_id.clone(), cc.scope.clone(), cc.claim_constraints().clone()));
+    /// Returns groups of the principal along with all of their ancestor groups
+    fn resolve_groups(&self, ctx: &SecurityContext, principal: &Principal) -> Vec<Group> {
+        let org_groups = self.get_inherited_groups(ctx, principal.organization_id.as_str());
+        let mut pending = self.group_repository.get_group_ids_by_principal(ctx, principal.id.as_str());
+        let mut visited = HashSet::new();
+        let mut result = vec![];
+        while let Some(group_id) = pending.pop() {
+            if !visited.insert(group_id.clone()) {
+                continue;
+            }
+            if let Some(group) = org_groups.get(&group_id) {
+                if let Some(ref parent_id) = group.parent_id {
+                    pending.push(parent_id.clone());
                 }
+                result.push(group.clone());
             } else {
-                self.audit(ctx, format!("Failed to find claim for id {} - principal {}-{} while populating principal", cc.claim_id, principal.username, principal.id), "GET");
+                self.audit(ctx, format!("Failed to find group for id {} for {}-{} while populating principal", group_id, principal.username, principal.id), "GET");
+            }
+        }
+        result
+    }
+
+    /// Populates roles/groups/claims of the principal from the claim cache or the database,
+    /// principals holding break-glass roles are not cached so that their short-lived access
+    /// ends as soon as it expires
+    fn load_principal(&self, ctx: &SecurityContext, realm_id: &str, principal: &mut Principal) {
+        if let Some(cached) = CLAIM_CACHE.get_principal(realm_id, principal.organization_id.as_str(), principal.id.as_str()) {
+            principal.groups = cached.groups;
+            principal.roles = cached.roles;
+            principal.claims = cached.claims;
+            principal.resources = cached.resources;
+            principal.attributes = cached.attributes;
+        } else {
+            self.populate_principal(ctx, realm_id, principal, &vec![]);
+            if !principal.roles.values().any(|r| r.break_glass) {
+                CLAIM_CACHE.put_principal_until(realm_id, principal, self.get_grants_expired_at(ctx, principal));
             }
         }
+    }
+
+    /// Returns earliest expiration of grants that populated the principal, i.e., role assignments
+    /// of the principal and its groups, claims of the principal or its guest membership, the guest
+    /// membership itself, delegations to the principal and license-policies of the organization
+    fn get_grants_expired_at(&self, ctx: &SecurityContext, principal: &Principal) -> Option<NaiveDateTime> {
+        let mut expirations = vec![];
+        expirations.extend(self.role_roleable_repository.get_by_roleable(principal.id.as_str(), Constants::Principal.to_string().as_str()).iter().map(|rr| rr.expired_at));
+        for group_id in principal.groups.keys() {
+            expirations.extend(self.role_roleable_repository.get_by_roleable(group_id.as_str(), Constants::Group.to_string().as_str()).iter().map(|rr| rr.expired_at));
+        }
+        match self.guest_membership_repository.get_active(ctx, principal.organization_id.as_str(), principal.id.as_str()) {
+            Some(membership) => {
+                expirations.push(membership.expired_at);
+                expirations.extend(self.claim_claimable_repository.get_by_claimables(vec![membership.id], Constants::GuestMembership.to_string()).iter().map(|cc| cc.expired_at));
+            },
+            None => expirations.extend(self.claim_claimable_repository.get_by_principal(principal.id.clone()).iter().map(|cc| cc.expired_at)),
+        }
+        expirations.extend(self.delegation_repository.get_by_delegatee(principal.id.as_str()).iter().map(|d| d.expired_at));
+        expirations.extend(self.get_effective_license_policies(ctx, principal.organization_id.as_str()).iter().map(|p| p.expired_at));
+        expirations.into_iter().min()
+    }
+
+    /// Populates attributes of the principal, where attributes of organizations along the org
+    /// chain are overridden by attributes of groups (in order of group names) and attributes of
+    /// groups are overridden by attributes of the principal itself
+    fn populate_attributes(&self, ctx: &SecurityContext, principal: &mut Principal) {
+        let mut org_ids = self.get_org_chain(ctx, principal.organization_id.as_str());
+        org_ids.reverse();
+        let mut attributes = vec![];
+        for org_id in org_ids {
+            attributes.extend(self.attribute_repository._get_by_owners(Constants::Organization.to_string().as_str(), vec![org_id]));
+        }
+        let mut groups = principal.groups.values().collect::<Vec<&Group>>();
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+        for group in groups {
+            attributes.extend(self.attribute_repository._get_by_owners(Constants::Group.to_string().as_str(), vec![group.id.clone()]));
+        }
+        attributes.extend(self.attribute_repository._get_by_owners(Constants::Principal.to_string().as_str(), vec![principal.id.clone()]));
+        for attribute in attributes {
+            let attribute = Attribute::from(&attribute);
+            principal.attributes.insert(attribute.name, attribute.value);
+        }
+    }
+
+    fn populate_principal(&self, ctx: &SecurityContext, realm_id: &str, principal: &mut Principal, mutations: &Vec<PolicyMutation>) {
+        self.populate_grants(ctx, realm_id, principal, mutations);
+        self.populate_delegations(ctx, realm_id, principal, mutations);
+        self.populate_attributes(ctx, principal);
 
         // Created resources
         let mut resource_ids = vec![];
@@ -363,50 +1020,428 @@ impl<'a> PersistenceManager<'a> {
             match cc {
                 ClaimClaimable::Role(claim, _, _, _, _) => resource_ids.push(claim.resource_id.clone()),
                 ClaimClaimable::Principal(claim, _, _, _, _) => resource_ids.push(claim.resource_id.clone()),
+                ClaimClaimable::Delegation(claim, _, _, _, _, _) => resource_ids.push(claim.resource_id.clone()),
                 _ => (),
             };
         }
         principal.resources = self.resource_repository._get_by_ids(resource_ids).iter().map(|r| Resource::from(r)).collect::<Vec<Resource>>();
     }
 
-
-    fn get_claim_claimables_by_org(&self, ctx: &SecurityContext, realm_id: &str, organization_id: &str) -> Vec<ClaimClaimable> {
-        let claims = self.claim_repository.get_claims_by_realm(ctx, realm_id);
-        let policies = self.license_policy_repository.get_by_org(ctx, organization_id);
-        let mut result = vec![];
-        if let Some(license_policy) = policies.first() {
-            for cc in &self.claim_claimable_repository.get_by_policy(license_policy.id.as_str()) {
-                if let Some(claim) = claims.get(&cc.claim_id) {
-                    result.push(ClaimClaimable::LicensePolicy(claim.clone(), realm_id.to_string(), cc.scope.clone(), cc.claim_constraints().clone()));
-                } else {
-                    self.audit(ctx, format!("Failed to find claim for id {}", cc.claim_id), "GET");
-                }
-            }
+    /// Returns pending assignment-request after verifying that principal of the context holds
+    /// the approver role of the request and is neither requester nor assignee of the request
+    fn get_reviewable_assignment(&self, ctx: &SecurityContext, realm_id: &str, org_id: &str, id: &str) -> Result<AssignmentRequest, RbacError> {
+        let request = self.assignment_request_repository.get(ctx, org_id, id).ok_or_else(|| RbacError::NotFound(format!("Assignment request not found {}", id)))?;
+        if request.status != Status::PENDING.to_string() {
+            return Err(RbacError::Custom(format!("Assignment request {} is already {}", id, request.status)));
+        }
+        let err = if ctx.principal_id == request.principal_id || Some(ctx.principal_id.clone()) == request.created_by {
+            Some(format!("Principal {} cannot review its own assignment request {}", ctx.principal_id, id))
         } else {
-            for (_, claim) in claims {
-                result.push(ClaimClaimable::Realm(claim.clone(), realm_id.to_string()));
+            match self.get_principal(ctx, realm_id, ctx.principal_id.as_str()) {
+                Some(ref reviewer) if reviewer.organization_id == org_id && reviewer.roles.contains_key(&request.approver_role_id) => None,
+                _ => Some(format!("Principal {} does not hold approver role {} of assignment request {}", ctx.principal_id, request.approver_role_id, id)),
             }
+        };
+        if let Some(err) = err {
+            self.audit(ctx, format!("{} -- {:?}", err, request), "UPDATE");
+            return Err(RbacError::Security(err));
         }
-        result
+        Ok(request)
     }
 
-    fn populate_org(&self, ctx: &SecurityContext, realm_id: &str, org: &mut Organization) {
-        org.claims = self.get_claim_claimables_by_org(ctx, realm_id, org.id.as_str());
-        let mut resource_ids = vec![];
-        for cc in &org.claims {
-            match cc {
-                ClaimClaimable::LicensePolicy(claim, _, _, _) => resource_ids.push(claim.resource_id.clone()),
-                ClaimClaimable::Realm(claim, _) => resource_ids.push(claim.resource_id.clone()),
-                _ => (),
-            };
-        }
-        org.resources = self.resource_repository._get_by_ids(resource_ids).iter().map(|r| Resource::from(r)).collect::<Vec<Resource>>();
-        for role in &self.role_repository._get_by_org(org.id.as_str()) {
-            org.roles.insert(role.id.clone(), Role::from(&role));
-        }
-        for group in &self.group_repository._get_by_org(org.id.as_str()) {
-            org.groups.insert(group.id.clone(), Group::from(&group));
+    /// Verifies that principal of the context is the delegator or an administrator of the
+    /// organization and delegated roles are allowed along with other roles of the delegatee
+    fn validate_delegation(&self, ctx: &SecurityContext, delegation: &Delegation) -> Result<(), RbacError> {
+        let org_id = delegation.organization_id.as_str();
+        if ctx.principal_id != delegation.delegator_id && !self.is_org_admin(ctx, org_id) {
+            let message = format!("Principal {} cannot delegate on behalf of {}", ctx.principal_id, delegation.delegator_id);
+            self.audit(ctx, format!("{} -- {:?}", message, delegation), "CREATE");
+            return Err(RbacError::Security(message));
         }
+        let other_role_ids = self.delegation_repository.get_by_delegatee(delegation.delegatee_id.as_str()).into_iter()
+            .filter(|d| d.id != delegation.id)
+            .flat_map(|d| d.role_ids)
+            .collect::<Vec<String>>();
+        self.role_roleable_repository.validate_principal_roles(ctx, org_id, delegation.delegatee_id.as_str(), &delegation.role_ids, &other_role_ids)
+    }
+
+    /// Returns true if principal of the context belongs to the organization and holds the
+    /// administrator role configured by `admin_role_id` of the organization
+    fn is_org_admin(&self, ctx: &SecurityContext, org_id: &str) -> bool {
+        let admin_role_id = match self.org_repository.get(ctx, org_id).and_then(|org| org.admin_role_id) {
+            Some(admin_role_id) => admin_role_id,
+            None => return false,
+        };
+        match self.get_principal(ctx, ctx.realm_id.as_str(), ctx.principal_id.as_str()) {
+            Some(ref principal) if principal.organization_id == org_id => principal.roles.contains_key(&admin_role_id),
+            _ => false,
+        }
+    }
+
+    /// Returns roles activated by the session along with parent roles that the principal holds
+    /// through them
+    fn get_session_role_ids(&self, principal: &Principal, session: &Session) -> HashSet<String> {
+        let mut role_ids = HashSet::new();
+        for role_id in &session.role_ids {
+            let mut next = Some(role_id.clone());
+            while let Some(role_id) = next.take() {
+                if let Some(role) = principal.roles.get(&role_id) {
+                    if role_ids.insert(role_id.clone()) {
+                        next = role.parent_id.clone();
+                    }
+                }
+            }
+        }
+        role_ids
+    }
+
+    /// Verifies that roles of the session are assigned to the principal and activating them
+    /// together doesn't violate any dynamic separation-of-duty rule of the organization
+    fn validate_session(&self, ctx: &SecurityContext, principal: &Principal, session: &Session) -> Result<(), RbacError> {
+        if let Some(role_id) = session.role_ids.iter().find(|role_id| !principal.roles.contains_key(*role_id)) {
+            let message = format!("Role {} is not assigned to principal {}", role_id, principal.id);
+            self.audit(ctx, format!("{} -- {:?}", message, session), "CREATE");
+            return Err(RbacError::Security(message));
+        }
+        let active = self.get_session_role_ids(principal, session);
+        if let Some(rule) = self.sod_rule_repository.get_by_org_and_type(principal.organization_id.as_str(), true).iter().find(|rule| rule.violated_by(&active)) {
+            let message = format!("Principal {} cannot activate roles that violate separation-of-duty rule {}", principal.id, rule.name);
+            self.audit(ctx, format!("{} -- {:?}", message, session), "CREATE");
+            return Err(RbacError::SodViolation(message));
+        }
+        Ok(())
+    }
+
+    /// Adds claims that are currently delegated to the principal. The delegator is populated
+    /// without its own delegations so that claims are delegated only while the delegator holds
+    /// them and delegated claims cannot be delegated further.
+    fn populate_delegations(&self, ctx: &SecurityContext, realm_id: &str, principal: &mut Principal, mutations: &Vec<PolicyMutation>) {
+        for delegation in self.delegation_repository.get_by_delegatee(principal.id.as_str()) {
+            let mut delegator = match self.principal_repository.get(ctx, delegation.delegator_id.as_str()) {
+                Some(delegator) if delegator.organization_id == principal.organization_id => delegator,
+                _ => {
+                    self.audit(ctx, format!("Failed to find delegator {} of delegation {} within organization of {}-{} while populating principal", delegation.delegator_id, delegation.id, principal.username, principal.id), "GET");
+                    continue;
+                },
+            };
+            self.populate_grants(ctx, realm_id, &mut delegator, mutations);
+
+            // delegated roles along with parent roles that the delegator holds through them
+            let mut role_ids = HashSet::new();
+            for role_id in &delegation.role_ids {
+                let mut next = Some(role_id.clone());
+                while let Some(role_id) = next.take() {
+                    if let Some(role) = delegator.roles.get(&role_id) {
+                        if role_ids.insert(role_id.clone()) {
+                            next = role.parent_id.clone();
+                        }
+                    }
+                }
+            }
+
+            let delegation_constraints = delegation.constraints.clone().unwrap_or_default();
+            for cc in &delegator.claims {
+                let (claim, scope, constraints, delegated) = match cc {
+                    ClaimClaimable::Role(claim, _, role_id, scope, constraints) => (claim, scope, constraints, role_ids.contains(role_id) || delegation.claim_ids.contains(&claim.id)),
+                    ClaimClaimable::Principal(claim, _, _, scope, constraints) => (claim, scope, constraints, delegation.claim_ids.contains(&claim.id)),
+                    _ => continue,
+                };
+                if delegated {
+                    principal.claims.push(ClaimClaimable::Delegation(claim.clone(), realm_id.to_string(), delegation.id.clone(), delegator.id.clone(), scope.clone(), evaluator::all_of(constraints.as_str(), delegation_constraints.as_str())));
+                }
+            }
+        }
+    }
+
+    /// Adds roles, groups and claims that are granted to the principal directly or via groups,
+    /// where given mutations are applied on top of the grants saved in the database
+    fn populate_grants(&self, ctx: &SecurityContext, realm_id: &str, principal: &mut Principal, mutations: &Vec<PolicyMutation>) {
+        // populate roles directly map to principal
+        let org_roles = self.get_inherited_roles(ctx, principal.organization_id.as_str());
+        let mut role_assignments = self.role_roleable_repository.get_by_roleable(principal.id.as_str(), Constants::Principal.to_string().as_str()).iter()
+            .map(|rr| (rr.role_id.clone(), rr.role_constraints())).collect::<Vec<(String, String)>>();
+        for mutation in mutations {
+            if let PolicyMutation::AddPrincipalToRole{principal_id, role_id, constraints} = mutation {
+                if *principal_id == principal.id {
+                    role_assignments.push((role_id.clone(), constraints.clone()));
+                }
+            }
+        }
+        self.populate_roles(ctx, &org_roles, &role_assignments, principal);
+
+        // Checking groups along with their parent groups
+        for group in self.resolve_groups(ctx, principal) {
+            // populate roles indirectly map to group
+            let role_assignments = self.role_roleable_repository.get_by_roleable(group.id.as_str(), Constants::Group.to_string().as_str()).iter()
+                .map(|rr| (rr.role_id.clone(), rr.role_constraints())).collect::<Vec<(String, String)>>();
+            self.populate_roles(ctx, &org_roles, &role_assignments, principal);
+            //
+            // Adding groups
+            principal.groups.insert(group.id.clone(), group);
+        }
+
+        //
+        // Create role-ids lookup
+        let mut role_ids = vec![];
+        for (_, role) in &principal.roles {
+            role_ids.push(role.id.clone());
+        }
+
+        // Checking license-policy
+        let org_claim_claimables = self.get_claim_claimables_by_org(ctx, realm_id, principal.organization_id.as_str());
+        let mut claim_id_scopes = vec![];
+        let mut claims_by_id = HashMap::new();
+        for org_claim_claimable in &org_claim_claimables {
+            match org_claim_claimable {
+                ClaimClaimable::LicensePolicy(claim, _, scope, constraints) => {
+                    if scope.len() > 0 || constraints.len() > 0 {
+                        claim_id_scopes.push((claim.id.clone(), scope.clone()));
+                    }
+                    claims_by_id.insert(claim.id.clone(), claim.clone());
+                },
+                ClaimClaimable::Realm(claim, _) => {
+                    claims_by_id.insert(claim.id.clone(), claim.clone());
+                }
+                _ => (),
+            };
+        }
+
+        // Proposed mutations of claims and claim mappings
+        let mut role_claimables = self.claim_claimable_repository.get_by_roles(role_ids.clone());
+        // claims mapped directly to a guest principal belong to its home organization so only
+        // claims of its guest membership are used within the host organization
+        let mut principal_claimables = match self.guest_membership_repository.get_active(ctx, principal.organization_id.as_str(), principal.id.as_str()) {
+            Some(membership) => self.claim_claimable_repository.get_by_claimables(vec![membership.id], Constants::GuestMembership.to_string()),
+            None => self.claim_claimable_repository.get_by_principal(principal.id.clone()),
+        };
+        for mutation in mutations {
+            match mutation {
+                PolicyMutation::MapRoleToClaim{role_id, claim_id, scope, constraints} if role_ids.contains(role_id) => {
+                    role_claimables.push(PClaimClaimable::new(claim_id.as_str(), role_id.as_str(), Constants::Role.to_string().as_str(), scope.as_str(), constraints.as_str(), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)));
+                },
+                PolicyMutation::MapPrincipalToClaim{principal_id, claim_id, scope, constraints} if *principal_id == principal.id => {
+                    principal_claimables.push(PClaimClaimable::new(claim_id.as_str(), principal_id.as_str(), Constants::Principal.to_string().as_str(), scope.as_str(), constraints.as_str(), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)));
+                },
+                PolicyMutation::ChangeClaimAction{claim_id, action} => {
+                    if let Some(claim) = claims_by_id.get_mut(claim_id) {
+                        claim.action = action.clone();
+                    }
+                },
+                _ => (),
+            }
+        }
+
+        // Scope of a grant must be covered by scope of license-policy for the claim
+        let resources = self.resource_repository.get_by_realm(ctx, realm_id);
+        let realm_matching = self.get_realm_scope_matching(ctx, realm_id);
+        let license_scope_missing = |claim: &Claim, scope: &str, constraints: &str| {
+            let matching = resources.get(&claim.resource_id).map(|r| self.resource_scope_matching(ctx, r, &realm_matching)).unwrap_or(realm_matching.clone());
+            claim_id_scopes.len() > 0 && (scope.len() > 0 || constraints.len() > 0) && !claim_id_scopes.iter().any(|(claim_id, license_scope)| *claim_id == claim.id && matching.matches(license_scope.as_str(), scope))
+        };
+
+        // Find claims mapped to roles
+        for cc in &role_claimables {
+            if let Some(claim) = claims_by_id.get(&cc.claim_id) {
+                if license_scope_missing(claim, cc.scope.as_str(), cc.claim_constraints().as_str()) {
+                    self.audit(ctx, format!("Found different or missing role scope/constraints than what was set in policy principal claim: {:?}, org claim: {:?}, all org claims: {:?}", cc, claim, org_claim_claimables), "GET");
+                } else {
+                    // claims of a role are only applicable when constraints of role assignment are satisfied
+                    let role_constraints = principal.roles.get(&cc.claimable_id).and_then(|role| role.constraints.clone()).unwrap_or_default();
+                    principal.claims.push(ClaimClaimable::Role(claim.clone(), realm_id.to_string(), cc.claimable_id.clone(), cc.scope.clone(), evaluator::all_of(role_constraints.as_str(), cc.claim_constraints().as_str())));
+                }
+            } else {
+                self.audit(ctx, format!("Failed to find claim for id {} - principal {}-{} while populating principal", cc.claim_id, principal.username, principal.id), "GET");
+            }
+        }
+
+        // Find claims mapped directly to principal
+        for cc in &principal_claimables {
+            if let Some(claim) = claims_by_id.get(&cc.claim_id) {
+                if license_scope_missing(claim, cc.scope.as_str(), cc.claim_constraints().as_str()) {
+                    self.audit(ctx, format!("Found different or missing principal scope/constraints than what was set in policy {:?} - {:?}", claim, cc), "GET");
+                } else {
+                    principal.claims.push(ClaimClaimable::Principal(claim.clone(), realm_id.to_string(), cc.claimable_id.clone(), cc.scope.clone(), cc.claim_constraints().clone()));
+                }
+            } else {
+                self.audit(ctx, format!("Failed to find claim for id {} - principal {}-{} while populating principal", cc.claim_id, principal.username, principal.id), "GET");
+            }
+        }
+    }
+
+
+    /// Returns strategy for matching scopes of the realm, defaults to exact matching, which is also
+    /// used for unknown strategies saved before they were rejected
+    fn get_realm_scope_matching(&self, ctx: &SecurityContext, realm_id: &str) -> ScopeMatching {
+        match self.realm_repository.get(ctx, realm_id).map(|realm| realm.scope_matching()) {
+            Some(Ok(matching)) => matching,
+            Some(Err(err)) => {
+                self.audit(ctx, format!("Matching scopes of realm {} exactly -- {}", realm_id, err), "GET");
+                ScopeMatching::Exact
+            },
+            None => ScopeMatching::Exact,
+        }
+    }
+
+    /// Returns strategy for matching scopes of the resource or its realm
+    fn get_resource_scope_matching(&self, ctx: &SecurityContext, resource_id: &str) -> ScopeMatching {
+        match self.resource_repository._get_by_ids(vec![resource_id.to_string()]).first() {
+            Some(resource) => self.resource_scope_matching(ctx, &Resource::from(resource), &self.get_realm_scope_matching(ctx, resource.realm_id.as_str())),
+            None => ScopeMatching::Exact,
+        }
+    }
+
+    /// Returns strategy for matching scopes of the resource, where unknown strategy of the
+    /// resource matches scopes exactly
+    fn resource_scope_matching(&self, ctx: &SecurityContext, resource: &Resource, realm_matching: &ScopeMatching) -> ScopeMatching {
+        match resource.scope_matching(realm_matching) {
+            Ok(matching) => matching,
+            Err(err) => {
+                self.audit(ctx, format!("Matching scopes of resource {} exactly -- {}", resource.id, err), "GET");
+                ScopeMatching::Exact
+            },
+        }
+    }
+
+    fn get_claim_claimables_by_org(&self, ctx: &SecurityContext, realm_id: &str, organization_id: &str) -> Vec<ClaimClaimable> {
+        if let Some(cached) = CLAIM_CACHE.get_org_claims(realm_id, organization_id) {
+            return cached;
+        }
+        let result = self.load_claim_claimables_by_org(ctx, realm_id, organization_id);
+        CLAIM_CACHE.put_org_claims(realm_id, organization_id, &result);
+        result
+    }
+
+    /// Returns claims of license-policies for the organization, where license of the root
+    /// organization is inherited by subsidiaries that don't define their own license-policies
+    /// and a subsidiary with its own license-policies can only narrow the inherited license,
+    /// i.e., its claims must be covered by the inherited license and constraints of both apply
+    fn load_claim_claimables_by_org(&self, ctx: &SecurityContext, realm_id: &str, organization_id: &str) -> Vec<ClaimClaimable> {
+        let claims = self.claim_repository.get_claims_by_realm(ctx, realm_id);
+        let resources = self.resource_repository.get_by_realm(ctx, realm_id);
+        let realm_matching = self.get_realm_scope_matching(ctx, realm_id);
+        let mut licensed: Option<Vec<ClaimClaimable>> = None;
+        for org_id in self.get_org_chain(ctx, organization_id).iter().rev() {
+            let own = match self.load_license_claim_claimables(ctx, realm_id, &claims, org_id.as_str()) {
+                Some(own) => own,
+                None => continue,
+            };
+            licensed = Some(match licensed {
+                Some(inherited) => {
+                    let mut narrowed = vec![];
+                    for cc in own {
+                        if let ClaimClaimable::LicensePolicy(claim, claim_realm_id, scope, constraints) = cc {
+                            let matching = resources.get(&claim.resource_id).map(|r| self.resource_scope_matching(ctx, r, &realm_matching)).unwrap_or(realm_matching.clone());
+                            if self.is_license_claim(&inherited, claim.id.as_str(), scope.as_str(), &matching) {
+                                let inherited_constraints = self.find_license_constraints(&inherited, claim.id.as_str(), scope.as_str(), &matching);
+                                narrowed.push(ClaimClaimable::LicensePolicy(claim, claim_realm_id, scope, evaluator::all_of(inherited_constraints.as_str(), constraints.as_str())));
+                            } else {
+                                self.audit(ctx, format!("Ignoring claim {:?} for scope {} of org {} because it's not covered by license of parent org", claim, scope, org_id), "GET");
+                            }
+                        }
+                    }
+                    narrowed
+                },
+                None => own,
+            });
+        }
+        match licensed {
+            Some(result) => result,
+            None => claims.values().map(|claim| ClaimClaimable::Realm(claim.clone(), realm_id.to_string())).collect::<Vec<ClaimClaimable>>(),
+        }
+    }
+
+    /// Returns claims of all effective license-policies of the organization or None if the
+    /// organization has no effective license-policy
+    fn load_license_claim_claimables(&self, ctx: &SecurityContext, realm_id: &str, claims: &HashMap<String, Claim>, organization_id: &str) -> Option<Vec<ClaimClaimable>> {
+        let policies = self.license_policy_repository.get_by_org(ctx, organization_id);
+        if policies.is_empty() {
+            return None;
+        }
+        // union of claims of all effective policies, where same claim for same scope and
+        // constraints from overlapping policies is only added once
+        let mut result = vec![];
+        let mut added = HashSet::new();
+        for license_policy in &policies {
+            for cc in &self.claim_claimable_repository.get_by_policy(license_policy.id.as_str()) {
+                if let Some(claim) = claims.get(&cc.claim_id) {
+                    if added.insert((cc.claim_id.clone(), cc.scope.clone(), cc.claim_constraints())) {
+                        result.push(ClaimClaimable::LicensePolicy(claim.clone(), realm_id.to_string(), cc.scope.clone(), cc.claim_constraints().clone()));
+                    }
+                } else {
+                    self.audit(ctx, format!("Failed to find claim for id {}", cc.claim_id), "GET");
+                }
+            }
+        }
+        Some(result)
+    }
+
+    /// Returns ids of organization followed by its ancestors, which are also recorded in the
+    /// claim cache so that changes to an ancestor invalidate claims of the organization
+    pub fn get_org_chain(&self, ctx: &SecurityContext, organization_id: &str) -> Vec<String> {
+        let chain = self.org_repository.get_chain(ctx, organization_id).iter().map(|org| org.id.clone()).collect::<Vec<String>>();
+        if chain.is_empty() {
+            return vec![organization_id.to_string()];
+        }
+        CLAIM_CACHE.put_org_ancestors(organization_id, &chain[1..].to_vec());
+        chain
+    }
+
+    /// Returns effective license-policies of the organization or of its nearest ancestor with
+    /// effective license-policies when the organization inherits its license
+    pub fn get_effective_license_policies(&self, ctx: &SecurityContext, organization_id: &str) -> Vec<LicensePolicy> {
+        for org_id in self.get_org_chain(ctx, organization_id) {
+            let policies = self.license_policy_repository.get_by_org(ctx, org_id.as_str());
+            if !policies.is_empty() {
+                return policies;
+            }
+        }
+        vec![]
+    }
+
+    /// Returns roles of organization along with roles of its ancestors, where a role of the
+    /// organization or a nearer ancestor overrides ancestor role with the same name
+    fn get_inherited_roles(&self, ctx: &SecurityContext, organization_id: &str) -> HashMap<String, Role> {
+        let mut result: HashMap<String, Role> = HashMap::new();
+        for org_id in self.get_org_chain(ctx, organization_id) {
+            let overridden = result.values().map(|role| role.name.clone()).collect::<HashSet<String>>();
+            for (id, role) in self.role_repository.get_by_org(ctx, org_id.as_str()) {
+                if !overridden.contains(&role.name) {
+                    result.insert(id, role);
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns groups of organization along with groups of its ancestors, where a group of the
+    /// organization or a nearer ancestor overrides ancestor group with the same name
+    fn get_inherited_groups(&self, ctx: &SecurityContext, organization_id: &str) -> HashMap<String, Group> {
+        let mut result: HashMap<String, Group> = HashMap::new();
+        for org_id in self.get_org_chain(ctx, organization_id) {
+            let overridden = result.values().map(|group| group.name.clone()).collect::<HashSet<String>>();
+            for (id, group) in self.group_repository.get_by_org(ctx, org_id.as_str()) {
+                if !overridden.contains(&group.name) {
+                    result.insert(id, group);
+                }
+            }
+        }
+        result
+    }
+
+    fn populate_org(&self, ctx: &SecurityContext, realm_id: &str, org: &mut Organization) {
+        org.org_chain = self.get_org_chain(ctx, org.id.as_str());
+        org.claims = self.get_claim_claimables_by_org(ctx, realm_id, org.id.as_str());
+        let mut resource_ids = vec![];
+        for cc in &org.claims {
+            match cc {
+                ClaimClaimable::LicensePolicy(claim, _, _, _) => resource_ids.push(claim.resource_id.clone()),
+                ClaimClaimable::Realm(claim, _) => resource_ids.push(claim.resource_id.clone()),
+                _ => (),
+            };
+        }
+        org.resources = self.resource_repository._get_by_ids(resource_ids).iter().map(|r| Resource::from(r)).collect::<Vec<Resource>>();
+        org.roles = self.get_inherited_roles(ctx, org.id.as_str());
+        org.groups = self.get_inherited_groups(ctx, org.id.as_str());
     }
 
     pub fn get_claims_by_policy(&self, ctx: &SecurityContext, realm_id: &str, license_policy_id: &str) -> HashMap<String, Claim> {
@@ -430,6 +1465,12 @@ impl<'a> PersistenceManager<'a> {
     pub fn clear(&self) {
         self.claim_claimable_repository.clear();
         self.license_policy_repository.clear();
+        self.sod_rule_repository.clear();
+        self.delegation_repository.clear();
+        self.session_repository.clear();
+        self.assignment_request_repository.clear();
+        self.guest_membership_repository.clear();
+        self.attribute_repository.clear();
         self.claim_repository.clear();
         self.role_roleable_repository.clear();
         self.role_repository.clear();
@@ -441,6 +1482,7 @@ impl<'a> PersistenceManager<'a> {
         self.principal_repository.clear();
         self.org_repository.clear();
         self.realm_repository.clear();
+        CLAIM_CACHE.invalidate_all();
     }
 
     fn audit(&self, ctx: &SecurityContext, message: String, action: &str) {
@@ -453,13 +1495,15 @@ impl<'a> PersistenceManager<'a> {
 mod tests {
     use plexrbac::persistence::locator::RepositoryLocator;
     use plexrbac::persistence::data_source::DefaultDataSource;
+    use plexrbac::persistence::cache::CLAIM_CACHE;
+    use plexrbac::persistence::models::PRoleRoleable;
     use plexrbac::common::SecurityContext;
     use plexrbac::security::manager::SecurityManager;
     use plexrbac::security::request::PermissionRequest;
     use plexrbac::security::response::PermissionResponse;
     use plexrbac::domain::models::*;
     use plexrbac::common::*;
-    use chrono::{NaiveDate, Utc, Datelike};
+    use chrono::{NaiveDate, Utc, Datelike, Duration};
 
     fn init() {
         let _ = env_logger::try_init();
@@ -468,220 +1512,1049 @@ mod tests {
     }
 
     #[test]
-    fn test_get_full_org() {
+    fn test_get_full_org() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let ds = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&ds);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
+        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
+        let license_policy = pm.license_policy_repository.create(&ctx, &LicensePolicy::new("", org.id.as_str(), "default-policy", Some("desc".to_string()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+        let org_str = format!("{:?}", org);
+        let loaded = pm.get_org(&ctx, realm.id.as_str(), org.id.as_str()).unwrap();
+        assert_eq!(org_str, format!("{:?}", loaded));
+        pm.license_policy_repository.get(&ctx, org.id.as_str(), license_policy.id.as_str()).unwrap();
+    }
+
+    #[test]
+    fn test_get_full_principal() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let ds = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&ds);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
+        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
+        let license_policy = pm.license_policy_repository.create(&ctx, &LicensePolicy::new("", org.id.as_str(), "default-policy", Some("desc".to_string()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+        //
+        let principal = pm.principal_repository.create(&ctx, &Principal::new("", org.id.as_str(), "myusername-principal", Some("desc".to_string()))).unwrap();
+        let principal_str = format!("{:?}", principal);
+        let loaded = pm.get_principal(&ctx, realm.id.as_str(), principal.id.as_str()).unwrap();
+        assert_eq!(principal_str, format!("{:?}", loaded));
+        pm.license_policy_repository.get(&ctx, org.id.as_str(), license_policy.id.as_str()).unwrap();
+    }
+
+    #[test]
+    fn test_add_delete_principal_to_group() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let ds = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&ds);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
+        let group = pm.group_repository.create(&ctx, &Group::new("", org.id.as_str(), "mygroup", Some("desc".to_string()), Some("parent".to_string()))).unwrap();
+        let principal = pm.principal_repository.create(&ctx, &Principal::new("", org.id.as_str(), "myusername", None)).unwrap();
+        assert!(pm.group_principal_repository.add_principal_to_group(&ctx, group.id.as_str(), principal.id.as_str()).is_ok());
+        assert!(pm.group_principal_repository.delete_principal_from_group(&ctx, group.id.as_str(), principal.id.as_str()).is_ok());
+    }
+
+    #[test]
+    fn test_add_delete_principal_to_role() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let ds = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&ds);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
+        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
+        //
+        let role = pm.role_repository.create(&ctx, &Role::new("", realm.id.as_str(), org.id.as_str(), "myrole", Some("desc".to_string()), Some("parent".to_string()))).unwrap();
+        let principal = pm.principal_repository.create(&ctx, &Principal::new("", org.id.as_str(), "myusername", None)).unwrap();
+
+        assert!(pm.role_roleable_repository.add_principal_to_role(&ctx, role.id.as_str(), principal.id.as_str(), "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).is_ok());
+        assert!(pm.role_roleable_repository.delete_principal_from_role(&ctx, role.id.as_str(), principal.id.as_str()).is_ok());
+    }
+
+    #[test]
+    fn test_add_delete_principal_to_claims() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let ds = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&ds);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
+        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
+        let principal = pm.principal_repository.create(&ctx, &Principal::new("", org.id.as_str(), "myusername", None)).unwrap();
+        let resource = pm.new_resource_with(&ctx, &realm, "report").unwrap();
+        let claim1 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "READ", Constants::Deny.to_string().as_str(), None)).unwrap();
+        let claim2 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "UPDATE", Constants::Allow.to_string().as_str(), None)).unwrap();
+        assert!(pm.claim_claimable_repository.add_principal_to_claim(&ctx, principal.id.as_str(), claim1.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).is_ok());
+        assert!(pm.claim_claimable_repository.add_principal_to_claim(&ctx, principal.id.as_str(), claim2.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).is_ok());
+        assert!(pm.claim_claimable_repository.delete_principal_from_claim(&ctx, principal.id.as_str(), claim1.id.as_str()).is_ok());
+        assert!(pm.claim_claimable_repository.delete_principal_from_claim(&ctx, principal.id.as_str(), claim2.id.as_str()).is_ok());
+    }
+
+    #[test]
+    fn test_add_delete_role_to_claims() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let ds = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&ds);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
+        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
+        let role = pm.role_repository.create(&ctx, &Role::new("", realm.id.as_str(), org.id.as_str(), "myrole", None, None)).unwrap();
+        let resource = pm.new_resource_with(&ctx, &realm, "report").unwrap();
+        let claim1 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "READ", Constants::Deny.to_string().as_str(), None)).unwrap();
+        let claim2 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "UPDATE", Constants::Allow.to_string().as_str(), None)).unwrap();
+        assert!(pm.claim_claimable_repository.add_role_to_claim(&ctx, role.id.as_str(), claim1.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).is_ok());
+        assert!(pm.claim_claimable_repository.add_role_to_claim(&ctx, role.id.as_str(), claim2.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).is_ok());
+        assert!(pm.claim_claimable_repository.delete_role_from_claim(&ctx, role.id.as_str(), claim1.id.as_str()).is_ok());
+        assert!(pm.claim_claimable_repository.delete_role_from_claim(&ctx, role.id.as_str(), claim2.id.as_str()).is_ok());
+    }
+
+    #[test]
+    fn test_get_save_resource_quota() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let ds = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&ds);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
+
+        let resource = pm.new_resource_with(&ctx, &realm, "report").unwrap();
+        let loaded = pm.resource_repository.get(&ctx, "myrealm", resource.id.as_str()).unwrap();
+        assert_eq!(format!("{:?}", resource), format!("{:?}", loaded));
+
+        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
+
+        let policy = pm.license_policy_repository.create(&ctx, &LicensePolicy::new("", org.id.as_str(), "default-policy", Some("desc".to_string()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+
+        let _ = pm.resource_quota_repository.create(&ctx, &ResourceQuota::new("", resource.id.as_str(), policy.id.as_str(), "", 2, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+
+        let instance = pm.resource_instance_repository._create(&ctx, &ResourceInstance::new("", resource.id.as_str(), "22", "", "refid", "INFLIGHT", Some("blah".to_string()))).unwrap();
+
+        let loaded = pm.resource_instance_repository.get(&ctx, instance.id.as_str()).unwrap();
+        assert_eq!(format!("{:?}", instance), format!("{:?}", loaded));
+
+        let quota = pm.resource_quota_repository.create(&ctx, &ResourceQuota::new("", resource.id.as_str(), "22", "", 22, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+        let loaded = pm.resource_quota_repository.get(&ctx, quota.id.as_str()).unwrap();
+        assert_eq!(format!("{:?}", quota), format!("{:?}", loaded));
+    }
+
+    #[test]
+    fn test_add_delete_claims_to_license_policy() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let ds = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&ds);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
+        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
+        let license_policy = pm.license_policy_repository.create(&ctx, &LicensePolicy::new("", org.id.as_str(), "default-policy", Some("desc".to_string()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+
+        let resource = pm.new_resource_with(&ctx, &realm, "report").unwrap();
+        let claim1 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "READ", "allow", None)).unwrap();
+        let claim2 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "UPDATE", "allow", None)).unwrap();
+
+        assert!(pm.claim_claimable_repository.add_license_policy_to_claim(&ctx, license_policy.id.as_str(), claim1.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).is_ok());
+        assert!(pm.claim_claimable_repository.add_license_policy_to_claim(&ctx, license_policy.id.as_str(), claim2.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).is_ok());
+        assert!(pm.claim_claimable_repository.delete_license_policy_from_claim(&ctx, license_policy.id.as_str(), claim1.id.as_str()).is_ok());
+        assert!(pm.claim_claimable_repository.delete_license_policy_from_claim(&ctx, license_policy.id.as_str(), claim2.id.as_str()).is_ok());
+
+        let loaded = pm.license_policy_repository.get(&ctx, org.id.as_str(), license_policy.id.as_str()).unwrap();
+        assert_eq!(format!("{:?}", license_policy), format!("{:?}", loaded));
+    }
+
+    #[test]
+    fn test_populate_org() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let ds = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&ds);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
+        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
+        let license_policy = pm.license_policy_repository.create(&ctx, &LicensePolicy::new("", org.id.as_str(), "default-policy", Some("desc".to_string()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+        pm.role_repository.create(&ctx, &Role::new("", realm.id.as_str(), org.id.as_str(), "admin", None, None)).unwrap();
+        pm.role_repository.create(&ctx, &Role::new("", realm.id.as_str(), org.id.as_str(), "employee", None, None)).unwrap();
+        pm.role_repository.create(&ctx, &Role::new("", realm.id.as_str(), org.id.as_str(), "manager", None, None)).unwrap();
+        pm.group_repository.create(&ctx, &Group::new("", org.id.as_str(), "default", Some("desc".to_string()), Some("parent".to_string()))).unwrap();
+        pm.group_repository.create(&ctx, &Group::new("", org.id.as_str(), "devops", Some("desc".to_string()), Some("parent".to_string()))).unwrap();
+
+        let resource = pm.new_resource_with(&ctx, &realm, "report").unwrap();
+        let claim1 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "READ", "allow", None)).unwrap();
+        let claim2 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "UPDATE", "allow", None)).unwrap();
+        pm.claim_claimable_repository.add_license_policy_to_claim(&ctx, license_policy.id.as_str(), claim1.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).unwrap();
+        pm.claim_claimable_repository.add_license_policy_to_claim(&ctx, license_policy.id.as_str(), claim2.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).unwrap();
+        let loaded = pm.get_org(&ctx, realm.id.as_str(), org.id.as_str()).unwrap();
+        assert_eq!(3, loaded.roles.len());
+        assert_eq!(2, loaded.groups.len());
+        assert_eq!(2, loaded.claims.len());
+    }
+
+    #[test]
+    fn test_populate_principal() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let ds = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&ds);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
+        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
+        let license_policy = pm.license_policy_repository.create(&ctx, &LicensePolicy::new("", org.id.as_str(), "default-policy", Some("desc".to_string()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+
+        let org_employee_role = pm.role_repository.create(&ctx, &Role::new("", realm.id.as_str(), org.id.as_str(), "org_employee", None, None)).unwrap();
+        let org_manager_role = pm.role_repository.create(&ctx, &Role::new("", realm.id.as_str(), org.id.as_str(), "org_manager", None, None)).unwrap();
+
+        let _default_group_employee = pm.role_repository.create(&ctx, &Role::new("", realm.id.as_str(), org.id.as_str(), "default_group_employee", None, None)).unwrap();
+
+        let principal = pm.principal_repository.create(&ctx, &Principal::new("", org.id.as_str(), "myusername", None)).unwrap();
+        let default_group = pm.group_repository.create(&ctx, &Group::new("", org.id.as_str(), "default", Some("desc".to_string()), Some("parent".to_string()))).unwrap();
+        pm.group_principal_repository.add_principal_to_group(&ctx, default_group.id.as_str(), principal.id.as_str()).unwrap();
+
+        pm.role_roleable_repository.add_principal_to_role(&ctx, org_manager_role.id.as_str(), principal.id.as_str(), "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).unwrap();
+        pm.role_roleable_repository.add_group_to_role(&ctx, org_employee_role.id.as_str(), default_group.id.as_str(), "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).unwrap();
+
+        let resource = pm.new_resource_with(&ctx, &realm, "report").unwrap();
+        let claim1 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "READ", "Allow", None)).unwrap();
+        let claim2 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "UPDATE", "Allow", None)).unwrap();
+        pm.claim_claimable_repository.add_license_policy_to_claim(&ctx, license_policy.id.as_str(), claim1.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).unwrap();
+        pm.claim_claimable_repository.add_license_policy_to_claim(&ctx, license_policy.id.as_str(), claim2.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).unwrap();
+        let loaded = pm.get_principal(&ctx, realm.id.as_str(), principal.id.as_str()).unwrap();
+        assert_eq!(2, loaded.roles.len());
+        assert_eq!(1, loaded.groups.len());
+        assert!(pm.role_roleable_repository.delete_group_from_role(&ctx, org_employee_role.id.as_str(), default_group.id.as_str()).is_ok());
+    }
+
+    #[test]
+    fn test_group_hierarchy() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let ds = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&ds);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+        let realm = pm.new_realm_with(&ctx, "myrealm").unwrap();
+        let org = pm.new_org_with(&ctx, "myorg").unwrap();
+        let engineering = pm.new_group_with(&ctx, &org, "Engineering").unwrap();
+        let platform = pm.new_group_with_parent(&ctx, &org, &engineering, "Platform").unwrap();
+        let sre = pm.new_group_with_parent(&ctx, &org, &platform, "SRE").unwrap();
+        let engineer = pm.new_role_with(&ctx, &realm, &org, "Engineer").unwrap();
+        let oncall = pm.new_role_with(&ctx, &realm, &org, "OnCall").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let cassy = pm.new_principal_with(&ctx, &org, "cassy").unwrap();
+        let server = pm.new_resource_with(&ctx, &realm, "Server").unwrap();
+        let read_server = pm.new_claim_with(&ctx, &realm, &server, "READ").unwrap();
+        let execute_server = pm.new_claim_with(&ctx, &realm, &server, "EXECUTE").unwrap();
+        pm.map_group_to_role(&ctx, &engineering, &engineer, "").unwrap();
+        pm.map_group_to_role(&ctx, &sre, &oncall, "").unwrap();
+        pm.map_role_to_claim(&ctx, &engineer, &read_server, "", "").unwrap();
+        pm.map_role_to_claim(&ctx, &oncall, &execute_server, "", "").unwrap();
+        pm.map_principal_to_group(&ctx, &tom, &sre).unwrap();
+        pm.map_principal_to_group(&ctx, &cassy, &platform).unwrap();
+
+        let loaded = pm.get_principal(&ctx, realm.id.as_str(), tom.id.as_str()).unwrap();
+        assert_eq!(3, loaded.groups.len());
+        assert_eq!(2, loaded.roles.len());
+        assert_eq!(vec!["Engineering", "Platform", "SRE"], pm.get_effective_groups(&ctx, tom.id.as_str()).unwrap().iter().map(|g| g.name.clone()).collect::<Vec<String>>());
+
+        let loaded = pm.get_principal(&ctx, realm.id.as_str(), cassy.id.as_str()).unwrap();
+        assert_eq!(2, loaded.groups.len());
+        assert_eq!(1, loaded.roles.len());
+        assert!(loaded.roles.contains_key(&engineer.id));
+
+        let sm = SecurityManager::new(pm);
+        assert_eq!(PermissionResponse::Allow, sm.check(&PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::EXECUTE, "Server", "")).unwrap());
+        assert_eq!(PermissionResponse::Allow, sm.check(&PermissionRequest::new(realm.id.as_str(), cassy.id.as_str(), ActionType::READ, "Server", "")).unwrap());
+        assert!(sm.check(&PermissionRequest::new(realm.id.as_str(), cassy.id.as_str(), ActionType::EXECUTE, "Server", "")).is_err());
+    }
+
+    #[test]
+    fn test_role_assignment_constraints() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let ds = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&ds);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+        let realm = pm.new_realm_with(&ctx, "banking").unwrap();
+        let org = pm.new_org_with(&ctx, "bank-of-flakes").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let cassy = pm.new_principal_with(&ctx, &org, "cassy").unwrap();
+        let employee = pm.new_role_with(&ctx, &realm, &org, "Employee").unwrap();
+        let teller = pm.new_role_with_parent(&ctx, &realm, &org, &employee, "Teller").unwrap();
+        let tellers = pm.new_group_with(&ctx, &org, "tellers").unwrap();
+        let deposit_account = pm.new_resource_with(&ctx, &realm, "DepositAccount").unwrap();
+        let r_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "READ").unwrap();
+        let u_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "UPDATE").unwrap();
+        pm.map_role_to_claim(&ctx, &employee, &r_deposit, "", "").unwrap();
+        pm.map_role_to_claim(&ctx, &teller, &u_deposit, "", r#"amount < 1000"#).unwrap();
+        pm.role_roleable_repository.add_principal_to_role(&ctx, teller.id.as_str(), tom.id.as_str(), r#"ip_region == "EU""#, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).unwrap();
+        pm.map_principal_to_group(&ctx, &cassy, &tellers).unwrap();
+        pm.map_group_to_role(&ctx, &tellers, &teller, r#"ip_region == "EU""#).unwrap();
+        pm.map_principal_to_role(&ctx, &cassy, &employee).unwrap();
+
+        let loaded = pm.get_principal(&ctx, realm.id.as_str(), tom.id.as_str()).unwrap();
+        assert_eq!(Some(r#"ip_region == "EU""#.to_string()), loaded.roles.get(&employee.id).unwrap().constraints);
+        let loaded = pm.get_principal(&ctx, realm.id.as_str(), cassy.id.as_str()).unwrap();
+        assert_eq!(None, loaded.roles.get(&employee.id).unwrap().constraints);
+
+        let sm = SecurityManager::new(pm);
+        for principal in vec![&tom, &cassy] {
+            let mut req = PermissionRequest::new(realm.id.as_str(), principal.id.as_str(), ActionType::UPDATE, "DepositAccount", "");
+            req.context.add("amount", ValueWrapper::Int(100));
+            req.context.add("ip_region", ValueWrapper::String("EU".to_string()));
+            assert_eq!(PermissionResponse::Allow, sm.check(&req).unwrap());
+            req.context.add("ip_region", ValueWrapper::String("US".to_string()));
+            assert!(sm.check(&req).is_err());
+        }
+
+        // role inherited via constrained assignment only applies when constraints are satisfied
+        let mut req = PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::READ, "DepositAccount", "");
+        req.context.add("ip_region", ValueWrapper::String("US".to_string()));
+        assert!(sm.check(&req).is_err());
+        // but cassy is assigned employee role without constraints
+        let mut req = PermissionRequest::new(realm.id.as_str(), cassy.id.as_str(), ActionType::READ, "DepositAccount", "");
+        req.context.add("ip_region", ValueWrapper::String("US".to_string()));
+        assert_eq!(PermissionResponse::Allow, sm.check(&req).unwrap());
+    }
+
+    #[test]
+    fn test_static_separation_of_duty() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let ds = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&ds);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+        let realm = pm.new_realm_with(&ctx, "banking").unwrap();
+        let org = pm.new_org_with(&ctx, "bank-of-flakes").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let cassy = pm.new_principal_with(&ctx, &org, "cassy").unwrap();
+        let ali = pm.new_principal_with(&ctx, &org, "ali").unwrap();
+        let teller = pm.new_role_with(&ctx, &realm, &org, "Teller").unwrap();
+        let manager = pm.new_role_with(&ctx, &realm, &org, "AccountantManager").unwrap();
+        let senior_manager = pm.new_role_with_parent(&ctx, &realm, &org, &manager, "SeniorAccountantManager").unwrap();
+        let managers = pm.new_group_with(&ctx, &org, "managers").unwrap();
+        let regional_managers = pm.new_group_with_parent(&ctx, &org, &managers, "regional-managers").unwrap();
+        pm.new_sod_rule_with(&ctx, &realm, &org, "teller-manager", vec![&teller, &manager], 2, false).unwrap();
+
+        // direct assignment
+        pm.map_principal_to_role(&ctx, &tom, &teller).unwrap();
+        match pm.map_principal_to_role(&ctx, &tom, &manager) {
+            Err(RbacError::SodViolation(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        // role reached through inheritance
+        assert!(pm.map_principal_to_role(&ctx, &tom, &senior_manager).is_err());
+        // role reached through parent group
+        pm.map_group_to_role(&ctx, &managers, &manager, "").unwrap();
+        pm.map_principal_to_group(&ctx, &cassy, &regional_managers).unwrap();
+        assert!(pm.map_principal_to_role(&ctx, &cassy, &teller).is_err());
+        // group assignment that would give conflicting roles to a member of sub-group
+        pm.map_principal_to_group(&ctx, &ali, &regional_managers).unwrap();
+        assert!(pm.map_group_to_role(&ctx, &regional_managers, &teller, "").is_err());
+        assert!(pm.map_group_to_role(&ctx, &managers, &teller, "").is_err());
+        // joining sub-group of a group that holds conflicting role
+        match pm.map_principal_to_group(&ctx, &tom, &regional_managers) {
+            Err(RbacError::SodViolation(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let loaded = pm.get_principal(&ctx, realm.id.as_str(), tom.id.as_str()).unwrap();
+        assert_eq!(1, loaded.roles.len());
+        assert_eq!(0, loaded.groups.len());
+        assert!(pm.audit_record_repository.latest(100).iter().any(|a| a.message.contains("violates separation-of-duty rule teller-manager") && a.action == Some("SOD_VIOLATION".to_string())));
+
+        // changing parent of a held role is checked against roles of its holders
+        let clerk = pm.new_role_with(&ctx, &realm, &org, "Clerk").unwrap();
+        pm.map_principal_to_role(&ctx, &tom, &clerk).unwrap();
+        let mut update = clerk.clone();
+        update.parent_id = Some(manager.id.clone());
+        match pm.role_roleable_repository.update_role(&ctx, &update) {
+            Err(RbacError::SodViolation(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(None, pm.role_repository.get(&ctx, org.id.as_str(), clerk.id.as_str()).unwrap().parent_id);
+        update.parent_id = Some(teller.id.clone());
+        assert_eq!(Some(teller.id.clone()), pm.role_roleable_repository.update_role(&ctx, &update).unwrap().parent_id);
+
+        // changing parent of a group is checked against roles of its members
+        let auditors = pm.new_group_with(&ctx, &org, "auditors").unwrap();
+        pm.map_principal_to_group(&ctx, &tom, &auditors).unwrap();
+        let mut update = auditors.clone();
+        update.parent_id = Some(managers.id.clone());
+        match pm.role_roleable_repository.update_group(&ctx, &update) {
+            Err(RbacError::SodViolation(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(None, pm.group_repository.get(&ctx, org.id.as_str(), auditors.id.as_str()).unwrap().parent_id);
+
+        // parents inherited from roles of ancestor organization are expanded as well
+        let branch = pm.org_repository.create(&ctx, &Organization::new("", Some(org.id.clone()), "branch", "", None)).unwrap();
+        let cashier = pm.new_role_with(&ctx, &realm, &branch, "Cashier").unwrap();
+        let lead = pm.new_role_with_parent(&ctx, &realm, &branch, &senior_manager, "BranchLead").unwrap();
+        pm.new_sod_rule_with(&ctx, &realm, &branch, "cashier-manager", vec![&cashier, &manager], 2, false).unwrap();
+        let bob = pm.new_principal_with(&ctx, &branch, "bob").unwrap();
+        pm.map_principal_to_role(&ctx, &bob, &cashier).unwrap();
+        match pm.map_principal_to_role(&ctx, &bob, &lead) {
+            Err(RbacError::SodViolation(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_role_max_assignees() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let ds = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&ds);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+        let realm = pm.new_realm_with(&ctx, "banking").unwrap();
+        let org = pm.new_org_with(&ctx, "bank-of-flakes").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let cassy = pm.new_principal_with(&ctx, &org, "cassy").unwrap();
+        let ali = pm.new_principal_with(&ctx, &org, "ali").unwrap();
+        let owners = pm.new_group_with(&ctx, &org, "owners").unwrap();
+        let mut owner = Role::new("", realm.id.as_str(), org.id.as_str(), "OrgOwner", None, None);
+        owner.max_assignees = Some(2);
+        let owner = pm.role_repository.create(&ctx, &owner).unwrap();
+        assert_eq!(Some(2), pm.role_repository.get(&ctx, org.id.as_str(), owner.id.as_str()).unwrap().max_assignees);
+
+        pm.map_principal_to_role(&ctx, &tom, &owner).unwrap();
+        pm.map_principal_to_group(&ctx, &cassy, &owners).unwrap();
+        pm.map_principal_to_group(&ctx, &ali, &owners).unwrap();
+        // group would add two more principals to the role
+        match pm.map_group_to_role(&ctx, &owners, &owner, "") {
+            Err(RbacError::QuotaExceeded(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        pm.map_principal_to_role(&ctx, &cassy, &owner).unwrap();
+        assert!(pm.map_principal_to_role(&ctx, &ali, &owner).is_err());
+
+        // removing an assignee frees up the slot
+        pm.unmap_principal_from_role(&ctx, &tom, &owner).unwrap();
+        pm.unmap_principal_from_role(&ctx, &cassy, &owner).unwrap();
+        pm.map_group_to_role(&ctx, &owners, &owner, "").unwrap();
+        assert!(pm.map_principal_to_role(&ctx, &tom, &owner).is_err());
+        // joining group that holds the role counts towards assignees of the role
+        match pm.map_principal_to_group(&ctx, &tom, &owners) {
+            Err(RbacError::QuotaExceeded(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        pm.unmap_principal_from_group(&ctx, &ali, &owners).unwrap();
+        pm.map_principal_to_group(&ctx, &tom, &owners).unwrap();
+    }
+
+    #[test]
+    fn test_get_effective_permissions() {
         let ctx = SecurityContext::new("myorg", "myid");
         let ds = DefaultDataSource::new();
         let locator = RepositoryLocator::new(&ds);
         let pm = locator.new_persistence_manager();
         pm.clear();
-        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
-        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
-        let license_policy = pm.license_policy_repository.create(&ctx, &LicensePolicy::new("", org.id.as_str(), "default-policy", Some("desc".to_string()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
-        let org_str = format!("{:?}", org);
-        let loaded = pm.get_org(&ctx, realm.id.as_str(), org.id.as_str()).unwrap();
-        assert_eq!(org_str, format!("{:?}", loaded));
-        pm.license_policy_repository.get(&ctx, org.id.as_str(), license_policy.id.as_str()).unwrap();
+        let realm = pm.new_realm_with(&ctx, "banking").unwrap();
+        let org = pm.new_org_with(&ctx, "bank-of-flakes").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let staff = pm.new_role_with(&ctx, &realm, &org, "Staff").unwrap();
+        let employee = pm.new_role_with_parent(&ctx, &realm, &org, &staff, "Employee").unwrap();
+        let teller = pm.new_role_with_parent(&ctx, &realm, &org, &employee, "Teller").unwrap();
+        let deposit_account = pm.new_resource_with(&ctx, &realm, "DepositAccount").unwrap();
+        let loan_account = pm.new_resource_with(&ctx, &realm, "LoanAccount").unwrap();
+        let ru_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "(READ|UPDATE)").unwrap();
+        let r_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "READ").unwrap();
+        let c_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "CREATE").unwrap();
+        let r_loan = pm.new_claim_with(&ctx, &realm, &loan_account, "READ").unwrap();
+        pm.map_principal_to_role(&ctx, &tom, &teller).unwrap();
+        pm.map_role_to_claim(&ctx, &teller, &ru_deposit, "U.S.", "").unwrap();
+        pm.map_role_to_claim(&ctx, &employee, &r_deposit, "U.S.", "").unwrap();
+        pm.map_role_to_claim(&ctx, &staff, &c_deposit, "U.S.", "").unwrap();
+        pm.map_principal_to_claim(&ctx, &tom, &r_loan, "", r#"amount < 1000"#).unwrap();
+
+        let permissions = pm.get_effective_permissions(&ctx, realm.id.as_str(), tom.id.as_str()).unwrap();
+        assert_eq!(2, permissions.len());
+        let deposit = permissions.iter().find(|rp| rp.resource_name == "DepositAccount" && rp.scope == "U.S.").unwrap();
+        assert_eq!(3, deposit.claims.len());
+        assert!(deposit.claims.iter().any(|c| c.claim_id == ru_deposit.id && c.source == "via role Teller"));
+        assert!(deposit.claims.iter().any(|c| c.claim_id == r_deposit.id && c.source == "via role Teller inherited from parent Employee"));
+        // grandparent claims are attributed to the role held by the principal
+        assert!(deposit.claims.iter().any(|c| c.claim_id == c_deposit.id && c.source == "via role Teller inherited from parent Staff"));
+        let loan = permissions.iter().find(|rp| rp.resource_name == "LoanAccount" && rp.scope == "").unwrap();
+        assert_eq!("direct", loan.claims[0].source);
+        assert_eq!("amount < 1000", loan.claims[0].constraints);
+        assert_eq!(None, pm.get_effective_permissions(&ctx, realm.id.as_str(), "unknown"));
     }
 
     #[test]
-    fn test_get_full_principal() {
-        let ctx = SecurityContext::new("myorg", "myid");
+    fn test_scope_matching() {
+        let ctx = SecurityContext::new("0".into(), "0".into());
         let ds = DefaultDataSource::new();
         let locator = RepositoryLocator::new(&ds);
         let pm = locator.new_persistence_manager();
         pm.clear();
-        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
-        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
-        let license_policy = pm.license_policy_repository.create(&ctx, &LicensePolicy::new("", org.id.as_str(), "default-policy", Some("desc".to_string()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
-        //
-        let principal = pm.principal_repository.create(&ctx, &Principal::new("", org.id.as_str(), "myusername-principal", Some("desc".to_string()))).unwrap();
-        let principal_str = format!("{:?}", principal);
-        let loaded = pm.get_principal(&ctx, realm.id.as_str(), principal.id.as_str()).unwrap();
-        assert_eq!(principal_str, format!("{:?}", loaded));
-        pm.license_policy_repository.get(&ctx, org.id.as_str(), license_policy.id.as_str()).unwrap();
+
+        let mut realm = pm.new_realm_with(&ctx, "banking").unwrap();
+        realm.scope_matching = Some(ScopeMatching::Hierarchical.to_string());
+        let realm = pm.realm_repository.update(&ctx, &realm).unwrap();
+        let org = pm.new_org_with(&ctx, "bank-of-flakes").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let teller = pm.new_role_with(&ctx, &realm, &org, "Teller").unwrap();
+        let deposit_account = pm.new_resource_with(&ctx, &realm, "DepositAccount").unwrap();
+        // reports use glob scopes instead of hierarchical scopes of the realm
+        let mut report = pm.new_resource_with(&ctx, &realm, "Report").unwrap();
+        report.scope_matching = Some(ScopeMatching::Glob.to_string());
+        let report = pm.resource_repository.update(&ctx, &report).unwrap();
+        let ru_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "(READ|UPDATE)").unwrap();
+        let r_report = pm.new_claim_with(&ctx, &realm, &report, "READ").unwrap();
+        pm.map_principal_to_role(&ctx, &tom, &teller).unwrap();
+        pm.map_role_to_claim(&ctx, &teller, &ru_deposit, "US", "").unwrap();
+        pm.map_role_to_claim(&ctx, &teller, &r_report, "*", "").unwrap();
+
+        // quota at US covers instances of all descendant scopes
+        let abc = pm.new_org_with(&ctx, "ABC").unwrap();
+        let _policy = pm.new_license_policy(&ctx, &abc).unwrap();
+        let dave = pm.new_principal_with(&ctx, &abc, "dave").unwrap();
+        assert!(pm.new_resource_quota_with(&ctx, &deposit_account, &dave, "US", 2).is_ok());
+        assert!(pm.new_resource_instance_with(&ctx, &deposit_account, &dave, "US/Midwest", "1", Status::COMPLETED).is_ok());
+        assert!(pm.new_resource_instance_with(&ctx, &deposit_account, &dave, "US/West", "2", Status::COMPLETED).is_ok());
+        assert!(pm.new_resource_instance_with(&ctx, &deposit_account, &dave, "US/West/Seattle", "3", Status::COMPLETED).is_err());
+        assert!(pm.new_resource_instance_with(&ctx, &deposit_account, &dave, "EU", "4", Status::COMPLETED).is_err());
+
+        let sm = SecurityManager::new(pm);
+        assert_eq!(PermissionResponse::Allow, sm.check(&PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::READ, "DepositAccount", "US/Midwest/Chicago")).unwrap());
+        assert_eq!(PermissionResponse::Allow, sm.check(&PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::READ, "DepositAccount", "US")).unwrap());
+        assert!(sm.check(&PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::READ, "DepositAccount", "USA")).is_err());
+        assert!(sm.check(&PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::READ, "DepositAccount", "EU")).is_err());
+        assert_eq!(PermissionResponse::Allow, sm.check(&PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::READ, "Report", "EU/Berlin")).unwrap());
     }
 
     #[test]
-    fn test_add_delete_principal_to_group() {
-        let ctx = SecurityContext::new("myorg", "myid");
+    fn test_multiple_license_policies() {
+        let ctx = SecurityContext::new("0".into(), "0".into());
         let ds = DefaultDataSource::new();
         let locator = RepositoryLocator::new(&ds);
         let pm = locator.new_persistence_manager();
         pm.clear();
-        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
-        let group = pm.group_repository.create(&ctx, &Group::new("", org.id.as_str(), "mygroup", Some("desc".to_string()), Some("parent".to_string()))).unwrap();
-        let principal = pm.principal_repository.create(&ctx, &Principal::new("", org.id.as_str(), "myusername", None)).unwrap();
-        assert!(pm.group_principal_repository.add_principal_to_group(&ctx, group.id.as_str(), principal.id.as_str()).is_ok());
-        assert!(pm.group_principal_repository.delete_principal_from_group(&ctx, group.id.as_str(), principal.id.as_str()).is_ok());
+
+        let realm = pm.new_realm_with(&ctx, "projects").unwrap();
+        let org = pm.new_org_with(&ctx, "ABC").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let manager = pm.new_role_with(&ctx, &realm, &org, "Manager").unwrap();
+        let project = pm.new_resource_with(&ctx, &realm, "Project").unwrap();
+        let read = pm.new_claim_with(&ctx, &realm, &project, "READ").unwrap();
+        let update = pm.new_claim_with(&ctx, &realm, &project, "UPDATE").unwrap();
+        let delete = pm.new_claim_with(&ctx, &realm, &project, "DELETE").unwrap();
+        pm.map_principal_to_role(&ctx, &tom, &manager).unwrap();
+        pm.map_role_to_claim(&ctx, &manager, &read, "", "").unwrap();
+        pm.map_role_to_claim(&ctx, &manager, &update, "", "").unwrap();
+        pm.map_role_to_claim(&ctx, &manager, &delete, "", "").unwrap();
+
+        // base plan and an add-on pack are effective at the same time, the trial has expired
+        let base = pm.new_license_policy(&ctx, &org).unwrap();
+        let addon = pm.license_policy_repository.create(&ctx, &LicensePolicy::new("", org.id.as_str(), "add-on", None, Utc::now().naive_utc() - Duration::days(1), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+        let trial = pm.license_policy_repository.create(&ctx, &LicensePolicy::new("", org.id.as_str(), "trial", None, Utc::now().naive_utc() - Duration::days(30), Utc::now().naive_utc() - Duration::days(1))).unwrap();
+        pm.map_license_policy_to_claim(&ctx, &base, &read, "", "").unwrap();
+        pm.map_license_policy_to_claim(&ctx, &addon, &read, "", "").unwrap();
+        pm.map_license_policy_to_claim(&ctx, &addon, &update, "", "").unwrap();
+        pm.map_license_policy_to_claim(&ctx, &trial, &delete, "", "").unwrap();
+        let policies = pm.license_policy_repository.get_by_org(&ctx, org.id.as_str());
+        assert_eq!(vec![addon.id.clone(), base.id.clone()], policies.iter().map(|p| p.id.clone()).collect::<Vec<String>>());
+        assert_eq!(2, pm.get_claim_claimables_by_org(&ctx, realm.id.as_str(), org.id.as_str()).len());
+
+        // quotas of overlapping policies for same scope are added together
+        let mut base_quota = ResourceQuota::new("", project.id.as_str(), base.id.as_str(), "ABC Project", 1, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0));
+        assert_eq!(base.id, pm.new_resource_quota(&ctx, tom.id.as_str(), &mut base_quota).unwrap().license_policy_id);
+        assert_eq!(addon.id, pm.new_resource_quota_with(&ctx, &project, &tom, "ABC Project", 1).unwrap().license_policy_id);
+        let trial_quota = ResourceQuota::new("", project.id.as_str(), trial.id.as_str(), "ABC Project", 5, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0));
+        let mut expired_quota = trial_quota.clone();
+        assert!(pm.new_resource_quota(&ctx, tom.id.as_str(), &mut expired_quota).is_err());
+        assert!(pm.resource_quota_repository.create(&ctx, &trial_quota).is_ok());
+        let instance = pm.new_resource_instance_with(&ctx, &project, &tom, "ABC Project", "1", Status::COMPLETED).unwrap();
+        assert_eq!(addon.id, instance.license_policy_id);
+        assert!(pm.new_resource_instance_with(&ctx, &project, &tom, "ABC Project", "2", Status::COMPLETED).is_ok());
+        assert!(pm.new_resource_instance_with(&ctx, &project, &tom, "ABC Project", "3", Status::COMPLETED).is_err());
+
+        let sm = SecurityManager::new(pm);
+        assert_eq!(PermissionResponse::Allow, sm.check(&PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::READ, "Project", "")).unwrap());
+        assert_eq!(PermissionResponse::Allow, sm.check(&PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::UPDATE, "Project", "")).unwrap());
+        assert!(sm.check(&PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::DELETE, "Project", "")).is_err());
     }
 
     #[test]
-    fn test_add_delete_principal_to_role() {
-        let ctx = SecurityContext::new("myorg", "myid");
+    fn test_org_hierarchy() {
+        let ctx = SecurityContext::new("0".into(), "0".into());
         let ds = DefaultDataSource::new();
         let locator = RepositoryLocator::new(&ds);
         let pm = locator.new_persistence_manager();
         pm.clear();
-        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
-        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
-        //
-        let role = pm.role_repository.create(&ctx, &Role::new("", realm.id.as_str(), org.id.as_str(), "myrole", Some("desc".to_string()), Some("parent".to_string()))).unwrap();
-        let principal = pm.principal_repository.create(&ctx, &Principal::new("", org.id.as_str(), "myusername", None)).unwrap();
 
-        assert!(pm.role_roleable_repository.add_principal_to_role(&ctx, role.id.as_str(), principal.id.as_str(), "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).is_ok());
-        assert!(pm.role_roleable_repository.delete_principal_from_role(&ctx, role.id.as_str(), principal.id.as_str()).is_ok());
+        let realm = pm.new_realm_with(&ctx, "projects").unwrap();
+        let project = pm.new_resource_with(&ctx, &realm, "Project").unwrap();
+        let read = pm.new_claim_with(&ctx, &realm, &project, "READ").unwrap();
+        let update = pm.new_claim_with(&ctx, &realm, &project, "UPDATE").unwrap();
+        let delete = pm.new_claim_with(&ctx, &realm, &project, "DELETE").unwrap();
+
+        // holding company licenses READ and UPDATE, which are inherited by subsidiaries
+        let holding = pm.new_org_with(&ctx, "holding").unwrap();
+        let acme = pm.org_repository.create(&ctx, &Organization::new("", Some(holding.id.clone()), "acme", "", None)).unwrap();
+        let beta = pm.org_repository.create(&ctx, &Organization::new("", Some(holding.id.clone()), "beta", "", None)).unwrap();
+        let holding_policy = pm.new_license_policy(&ctx, &holding).unwrap();
+        pm.map_license_policy_to_claim(&ctx, &holding_policy, &read, "", "").unwrap();
+        pm.map_license_policy_to_claim(&ctx, &holding_policy, &update, "", "").unwrap();
+        // beta narrows the license to READ and cannot widen it to DELETE
+        let beta_policy = pm.new_license_policy(&ctx, &beta).unwrap();
+        pm.map_license_policy_to_claim(&ctx, &beta_policy, &read, "", "").unwrap();
+        pm.map_license_policy_to_claim(&ctx, &beta_policy, &delete, "", "").unwrap();
+
+        // shared role of holding company is overridden by beta
+        let manager = pm.new_role_with(&ctx, &realm, &holding, "Manager").unwrap();
+        pm.map_role_to_claim(&ctx, &manager, &read, "", "").unwrap();
+        pm.map_role_to_claim(&ctx, &manager, &update, "", "").unwrap();
+        pm.map_role_to_claim(&ctx, &manager, &delete, "", "").unwrap();
+        let beta_manager = pm.new_role_with(&ctx, &realm, &beta, "Manager").unwrap();
+        pm.map_role_to_claim(&ctx, &beta_manager, &read, "", "").unwrap();
+        pm.map_role_to_claim(&ctx, &beta_manager, &update, "", "").unwrap();
+        pm.map_role_to_claim(&ctx, &beta_manager, &delete, "", "").unwrap();
+
+        let loaded = pm.get_org(&ctx, realm.id.as_str(), acme.id.as_str()).unwrap();
+        assert_eq!(vec![acme.id.clone(), holding.id.clone()], loaded.org_chain);
+        assert!(loaded.roles.contains_key(&manager.id));
+        assert_eq!(2, loaded.claims.len());
+        let loaded = pm.get_org(&ctx, realm.id.as_str(), beta.id.as_str()).unwrap();
+        assert!(loaded.roles.contains_key(&beta_manager.id));
+        assert!(!loaded.roles.contains_key(&manager.id));
+        assert_eq!(1, loaded.claims.len());
+
+        let alice = pm.new_principal_with(&ctx, &acme, "alice").unwrap();
+        let bob = pm.new_principal_with(&ctx, &beta, "bob").unwrap();
+        pm.map_principal_to_role(&ctx, &alice, &manager).unwrap();
+        pm.map_principal_to_role(&ctx, &bob, &beta_manager).unwrap();
+
+        let sm = SecurityManager::new(pm);
+        assert_eq!(PermissionResponse::Allow, sm.check(&PermissionRequest::new(realm.id.as_str(), alice.id.as_str(), ActionType::READ, "Project", "")).unwrap());
+        assert_eq!(PermissionResponse::Allow, sm.check(&PermissionRequest::new(realm.id.as_str(), alice.id.as_str(), ActionType::UPDATE, "Project", "")).unwrap());
+        assert!(sm.check(&PermissionRequest::new(realm.id.as_str(), alice.id.as_str(), ActionType::DELETE, "Project", "")).is_err());
+        assert_eq!(PermissionResponse::Allow, sm.check(&PermissionRequest::new(realm.id.as_str(), bob.id.as_str(), ActionType::READ, "Project", "")).unwrap());
+        assert!(sm.check(&PermissionRequest::new(realm.id.as_str(), bob.id.as_str(), ActionType::UPDATE, "Project", "")).is_err());
+        assert!(sm.check(&PermissionRequest::new(realm.id.as_str(), bob.id.as_str(), ActionType::DELETE, "Project", "")).is_err());
+
+        // acme inherits license of holding company along with its quotas
+        let pm = sm.persistence_manager;
+        assert_eq!(holding_policy.id, pm.new_resource_quota_with(&ctx, &project, &alice, "Projects", 1).unwrap().license_policy_id);
+        assert_eq!(holding_policy.id, pm.get_effective_license_policies(&ctx, acme.id.as_str())[0].id);
+        assert_eq!(holding_policy.id, pm.new_resource_instance_with(&ctx, &project, &alice, "Projects", "1", Status::COMPLETED).unwrap().license_policy_id);
+        assert!(pm.new_resource_instance_with(&ctx, &project, &alice, "Projects", "2", Status::COMPLETED).is_err());
     }
 
     #[test]
-    fn test_add_delete_principal_to_claims() {
+    fn test_permission_diff() {
         let ctx = SecurityContext::new("myorg", "myid");
         let ds = DefaultDataSource::new();
         let locator = RepositoryLocator::new(&ds);
         let pm = locator.new_persistence_manager();
         pm.clear();
-        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
-        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
-        let principal = pm.principal_repository.create(&ctx, &Principal::new("", org.id.as_str(), "myusername", None)).unwrap();
-        let resource = pm.new_resource_with(&ctx, &realm, "report").unwrap();
-        let claim1 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "READ", Constants::Deny.to_string().as_str(), None)).unwrap();
-        let claim2 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "UPDATE", Constants::Allow.to_string().as_str(), None)).unwrap();
-        assert!(pm.claim_claimable_repository.add_principal_to_claim(&ctx, principal.id.as_str(), claim1.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).is_ok());
-        assert!(pm.claim_claimable_repository.add_principal_to_claim(&ctx, principal.id.as_str(), claim2.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).is_ok());
-        assert!(pm.claim_claimable_repository.delete_principal_from_claim(&ctx, principal.id.as_str(), claim1.id.as_str()).is_ok());
-        assert!(pm.claim_claimable_repository.delete_principal_from_claim(&ctx, principal.id.as_str(), claim2.id.as_str()).is_ok());
+        let realm = pm.new_realm_with(&ctx, "banking").unwrap();
+        let org = pm.new_org_with(&ctx, "bank-of-flakes").unwrap();
+        let alice = pm.new_principal_with(&ctx, &org, "alice").unwrap();
+        let bob = pm.new_principal_with(&ctx, &org, "bob").unwrap();
+        let employee = pm.new_role_with(&ctx, &realm, &org, "Employee").unwrap();
+        let teller = pm.new_role_with_parent(&ctx, &realm, &org, &employee, "Teller").unwrap();
+        let deposit_account = pm.new_resource_with(&ctx, &realm, "DepositAccount").unwrap();
+        let loan_account = pm.new_resource_with(&ctx, &realm, "LoanAccount").unwrap();
+        let ru_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "(READ|UPDATE)").unwrap();
+        let r_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "READ").unwrap();
+        let r_loan = pm.new_claim_with(&ctx, &realm, &loan_account, "READ").unwrap();
+        pm.map_role_to_claim(&ctx, &teller, &ru_deposit, "U.S.", "").unwrap();
+        pm.map_role_to_claim(&ctx, &employee, &r_deposit, "U.S.", "").unwrap();
+        pm.map_principal_to_role(&ctx, &alice, &teller).unwrap();
+        pm.map_principal_to_role(&ctx, &bob, &employee).unwrap();
+        pm.map_principal_to_claim(&ctx, &bob, &r_loan, "", r#"amount < 1000"#).unwrap();
+
+        let diff = pm.diff_principals(&ctx, realm.id.as_str(), alice.id.as_str(), bob.id.as_str()).unwrap();
+        assert_eq!(vec![PermissionTuple::new("DepositAccount", "(READ|UPDATE)", "U.S.", "", "Allow")], diff.only_in_a);
+        assert_eq!(vec![PermissionTuple::new("LoanAccount", "READ", "", "amount < 1000", "Allow")], diff.only_in_b);
+        assert_eq!(vec![PermissionTuple::new("DepositAccount", "READ", "U.S.", "", "Allow")], diff.common);
+
+        // claims of parent roles are inherited
+        let diff = pm.diff_roles(&ctx, realm.id.as_str(), org.id.as_str(), teller.id.as_str(), employee.id.as_str()).unwrap();
+        assert_eq!(1, diff.only_in_a.len());
+        assert_eq!(0, diff.only_in_b.len());
+        assert_eq!(vec![PermissionTuple::new("DepositAccount", "READ", "U.S.", "", "Allow")], diff.common);
+        assert!(pm.diff_roles(&ctx, realm.id.as_str(), org.id.as_str(), teller.id.as_str(), "unknown").is_err());
+        assert!(pm.diff_principals(&ctx, realm.id.as_str(), alice.id.as_str(), "unknown").is_err());
+
+        // same resource, action and scope with opposite effects are not common
+        let deny_loan = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), loan_account.id.as_str(), "READ", Constants::Deny.to_string().as_str(), None)).unwrap();
+        pm.map_principal_to_claim(&ctx, &alice, &deny_loan, "", r#"amount < 1000"#).unwrap();
+        let diff = pm.diff_principals(&ctx, realm.id.as_str(), alice.id.as_str(), bob.id.as_str()).unwrap();
+        assert_eq!(vec![PermissionTuple::new("DepositAccount", "(READ|UPDATE)", "U.S.", "", "Allow"), PermissionTuple::new("LoanAccount", "READ", "", "amount < 1000", "Deny")], diff.only_in_a);
+        assert_eq!(vec![PermissionTuple::new("LoanAccount", "READ", "", "amount < 1000", "Allow")], diff.only_in_b);
     }
 
     #[test]
-    fn test_add_delete_role_to_claims() {
+    fn test_claim_cache() {
         let ctx = SecurityContext::new("myorg", "myid");
         let ds = DefaultDataSource::new();
         let locator = RepositoryLocator::new(&ds);
         let pm = locator.new_persistence_manager();
         pm.clear();
-        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
-        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
-        let role = pm.role_repository.create(&ctx, &Role::new("", realm.id.as_str(), org.id.as_str(), "myrole", None, None)).unwrap();
-        let resource = pm.new_resource_with(&ctx, &realm, "report").unwrap();
-        let claim1 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "READ", Constants::Deny.to_string().as_str(), None)).unwrap();
-        let claim2 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "UPDATE", Constants::Allow.to_string().as_str(), None)).unwrap();
-        assert!(pm.claim_claimable_repository.add_role_to_claim(&ctx, role.id.as_str(), claim1.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).is_ok());
-        assert!(pm.claim_claimable_repository.add_role_to_claim(&ctx, role.id.as_str(), claim2.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).is_ok());
-        assert!(pm.claim_claimable_repository.delete_role_from_claim(&ctx, role.id.as_str(), claim1.id.as_str()).is_ok());
-        assert!(pm.claim_claimable_repository.delete_role_from_claim(&ctx, role.id.as_str(), claim2.id.as_str()).is_ok());
+        let realm = pm.new_realm_with(&ctx, "banking").unwrap();
+        let org = pm.new_org_with(&ctx, "bank-of-flakes").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let teller = pm.new_role_with(&ctx, &realm, &org, "Teller").unwrap();
+        let deposit_account = pm.new_resource_with(&ctx, &realm, "DepositAccount").unwrap();
+        let ru_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "(READ|UPDATE)").unwrap();
+        pm.map_role_to_claim(&ctx, &teller, &ru_deposit, "U.S.", "").unwrap();
+
+        let before = CLAIM_CACHE.stats();
+        assert_eq!(0, pm.get_principal(&ctx, realm.id.as_str(), tom.id.as_str()).unwrap().claims.len());
+        assert_eq!(0, pm.get_principal(&ctx, realm.id.as_str(), tom.id.as_str()).unwrap().claims.len());
+        let after = CLAIM_CACHE.stats();
+        assert!(after.hits > before.hits);
+        assert!(after.misses > before.misses);
+
+        // mapping role to the principal must invalidate cached principal
+        pm.map_principal_to_role(&ctx, &tom, &teller).unwrap();
+        assert_eq!(1, pm.get_principal(&ctx, realm.id.as_str(), tom.id.as_str()).unwrap().claims.len());
+
+        // removing claim from the role must invalidate principals with the role
+        pm.unmap_role_from_claim(&ctx, &teller, &ru_deposit).unwrap();
+        assert_eq!(0, pm.get_principal(&ctx, realm.id.as_str(), tom.id.as_str()).unwrap().claims.len());
+        assert!(CLAIM_CACHE.stats().invalidations > after.invalidations);
     }
 
     #[test]
-    fn test_get_save_resource_quota() {
+    fn test_get_principals_by_access() {
         let ctx = SecurityContext::new("myorg", "myid");
         let ds = DefaultDataSource::new();
         let locator = RepositoryLocator::new(&ds);
         let pm = locator.new_persistence_manager();
         pm.clear();
-        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
-
-        let resource = pm.new_resource_with(&ctx, &realm, "report").unwrap();
-        let loaded = pm.resource_repository.get(&ctx, "myrealm", resource.id.as_str()).unwrap();
-        assert_eq!(format!("{:?}", resource), format!("{:?}", loaded));
-
-        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
-
-        let policy = pm.license_policy_repository.create(&ctx, &LicensePolicy::new("", org.id.as_str(), "default-policy", Some("desc".to_string()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+        let realm = pm.new_realm_with(&ctx, "banking").unwrap();
+        let org = pm.new_org_with(&ctx, "bank-of-flakes").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let cassy = pm.new_principal_with(&ctx, &org, "cassy").unwrap();
+        let ali = pm.new_principal_with(&ctx, &org, "ali").unwrap();
+        let mike = pm.new_principal_with(&ctx, &org, "mike").unwrap();
+        let employee = pm.new_role_with(&ctx, &realm, &org, "Employee").unwrap();
+        let teller = pm.new_role_with_parent(&ctx, &realm, &org, &employee, "Teller").unwrap();
+        let csr = pm.new_role_with_parent(&ctx, &realm, &org, &teller, "CSR").unwrap();
+        let accountant = pm.new_role_with_parent(&ctx, &realm, &org, &employee, "Accountant").unwrap();
+        let tellers = pm.new_group_with(&ctx, &org, "tellers").unwrap();
+        let deposit_account = pm.new_resource_with(&ctx, &realm, "DepositAccount").unwrap();
+        let ru_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "(READ|UPDATE)").unwrap();
+        let cd_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "(CREATE|DELETE)").unwrap();
+        pm.map_principal_to_role(&ctx, &cassy, &csr).unwrap();
+        pm.map_principal_to_role(&ctx, &ali, &accountant).unwrap();
+        pm.map_principal_to_group(&ctx, &tom, &tellers).unwrap();
+        pm.map_group_to_role(&ctx, &tellers, &teller, "").unwrap();
+        pm.map_role_to_claim(&ctx, &teller, &ru_deposit, "U.S.", "").unwrap();
+        pm.map_role_to_claim(&ctx, &csr, &cd_deposit, "U.S.", "").unwrap();
+        pm.map_principal_to_claim(&ctx, &mike, &ru_deposit, "U.S.", r#"amount < 1000"#).unwrap();
+
+        let access = pm.get_principals_by_access(&ctx, realm.id.as_str(), deposit_account.id.as_str(), "UPDATE", "U.S.");
+        assert_eq!(3, access.len());
+        assert!(access.iter().any(|a| a.principal_id == tom.id && !a.conditional));
+        assert!(access.iter().any(|a| a.principal_id == cassy.id && !a.conditional));
+        assert!(access.iter().any(|a| a.principal_id == mike.id && a.conditional && a.constraints == "amount < 1000"));
+
+        let access = pm.get_principals_by_access(&ctx, realm.id.as_str(), deposit_account.id.as_str(), "DELETE", "U.S.");
+        assert_eq!(1, access.len());
+        assert_eq!(cassy.id, access[0].principal_id);
+        assert_eq!(0, pm.get_principals_by_access(&ctx, realm.id.as_str(), deposit_account.id.as_str(), "UPDATE", "Canada").len());
+    }
 
-        let _ = pm.resource_quota_repository.create(&ctx, &ResourceQuota::new("", resource.id.as_str(), policy.id.as_str(), "", 2, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+    #[test]
+    fn test_delegation() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let ds = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&ds);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+        let realm = pm.new_realm_with(&ctx, "expense").unwrap();
+        let org = pm.new_org_with(&ctx, "box-air").unwrap();
+        let mike = pm.new_principal_with(&ctx, &org, "mike").unwrap();
+        let dan = pm.new_principal_with(&ctx, &org, "dan").unwrap();
+        let employee = pm.new_role_with(&ctx, &realm, &org, "Employee").unwrap();
+        let approver = pm.new_role_with(&ctx, &realm, &org, "Approver").unwrap();
+        let expense_report = pm.new_resource_with(&ctx, &realm, "ExpenseReport").unwrap();
+        let read_report = pm.new_claim_with(&ctx, &realm, &expense_report, "READ").unwrap();
+        let approve_report = pm.new_claim_with(&ctx, &realm, &expense_report, "APPROVE").unwrap();
+        pm.map_role_to_claim(&ctx, &employee, &read_report, "", "").unwrap();
+        pm.map_role_to_claim(&ctx, &approver, &approve_report, "", "").unwrap();
+        pm.map_principal_to_role(&ctx, &mike, &employee).unwrap();
+        pm.map_principal_to_role(&ctx, &mike, &approver).unwrap();
+
+        // only the delegator or an administrator of the organization can delegate
+        let to_dan = Delegation::new("", org.id.as_str(), mike.id.as_str(), dan.id.as_str(), vec![approver.id.clone()], vec![], Some("amount < 1000".into()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0));
+        match pm.delegate(&SecurityContext::new(realm.id.as_str(), dan.id.as_str()), &to_dan) {
+            Err(RbacError::Security(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        let ann = pm.new_principal_with(&ctx, &org, "ann").unwrap();
+        let admin = pm.new_role_with(&ctx, &realm, &org, "Admin").unwrap();
+        pm.map_principal_to_role(&ctx, &ann, &admin).unwrap();
+        // a role named Admin doesn't administer the organization unless it is configured
+        match pm.delegate(&SecurityContext::new(realm.id.as_str(), ann.id.as_str()), &to_dan) {
+            Err(RbacError::Security(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        let mut org = org;
+        org.admin_role_id = Some(admin.id.clone());
+        pm.org_repository.update(&ctx, &org).unwrap();
+        let by_admin = pm.delegate(&SecurityContext::new(realm.id.as_str(), ann.id.as_str()), &to_dan).unwrap();
+        pm.delegation_repository.delete(&ctx, org.id.as_str(), by_admin.id.as_str()).unwrap();
+
+        // mike delegates approval to dan while on vacation
+        let mike_ctx = SecurityContext::new(realm.id.as_str(), mike.id.as_str());
+        let delegation = pm.delegate(&mike_ctx, &to_dan).unwrap();
+        let loaded = pm.get_principal(&ctx, realm.id.as_str(), dan.id.as_str()).unwrap();
+        assert_eq!(1, loaded.claims.len());
+        match &loaded.claims[0] {
+            ClaimClaimable::Delegation(claim, _, delegation_id, delegator_id, _, constraints) => {
+                assert_eq!(approve_report.id, claim.id);
+                assert_eq!(delegation.id, *delegation_id);
+                assert_eq!(mike.id, *delegator_id);
+                assert_eq!("amount < 1000", constraints);
+            },
+            other => panic!("unexpected claim {:?}", other),
+        }
+        let permissions = pm.get_effective_permissions(&ctx, realm.id.as_str(), dan.id.as_str()).unwrap();
+        assert_eq!(format!("delegated by {}", mike.id), permissions[0].claims[0].source);
+        let access = pm.get_principals_by_access(&ctx, realm.id.as_str(), expense_report.id.as_str(), "APPROVE", "");
+        assert!(access.iter().any(|a| a.principal_id == dan.id && a.conditional));
 
-        let instance = pm.resource_instance_repository._create(&ctx, &ResourceInstance::new("", resource.id.as_str(), "22", "", "refid", "INFLIGHT", Some("blah".to_string()))).unwrap();
+        let sm = SecurityManager::new(pm);
+        let mut req = PermissionRequest::new(realm.id.as_str(), dan.id.as_str(), ActionType::APPROVE, "ExpenseReport", "");
+        req.context.add("amount", ValueWrapper::Int(500));
+        assert_eq!(PermissionResponse::Allow, sm.check(&req).unwrap());
+        req.context.add("amount", ValueWrapper::Int(5000));
+        assert!(sm.check(&req).is_err());
+        // only claims of delegated roles are delegated
+        let req = PermissionRequest::new(realm.id.as_str(), dan.id.as_str(), ActionType::READ, "ExpenseReport", "");
+        assert!(sm.check(&req).is_err());
 
-        let loaded = pm.resource_instance_repository.get(&ctx, instance.id.as_str()).unwrap();
-        assert_eq!(format!("{:?}", instance), format!("{:?}", loaded));
+        // delegated roles are checked against static sod-rules and limits of roles of the delegatee
+        let pm = &sm.persistence_manager;
+        let submitter = pm.new_role_with(&ctx, &realm, &org, "Submitter").unwrap();
+        pm.map_principal_to_role(&ctx, &mike, &submitter).unwrap();
+        pm.map_principal_to_role(&ctx, &dan, &submitter).unwrap();
+        pm.new_sod_rule_with(&ctx, &realm, &org, "submit-approve", vec![&submitter, &approver], 2, false).unwrap();
+        let mut update = delegation.clone();
+        update.claim_ids = vec![read_report.id.clone()];
+        match pm.update_delegation(&mike_ctx, &update) {
+            Err(RbacError::SodViolation(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        pm.unmap_principal_from_role(&ctx, &dan, &submitter).unwrap();
+        let mut auditor = pm.new_role_with(&ctx, &realm, &org, "Auditor").unwrap();
+        auditor.max_assignees = Some(1);
+        let auditor = pm.role_repository.update(&ctx, &auditor).unwrap();
+        pm.map_principal_to_role(&ctx, &mike, &auditor).unwrap();
+        let to_dan = Delegation::new("", org.id.as_str(), mike.id.as_str(), dan.id.as_str(), vec![auditor.id.clone()], vec![], None, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0));
+        match pm.delegate(&mike_ctx, &to_dan) {
+            Err(RbacError::QuotaExceeded(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
 
-        let quota = pm.resource_quota_repository.create(&ctx, &ResourceQuota::new("", resource.id.as_str(), "22", "", 22, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
-        let loaded = pm.resource_quota_repository.get(&ctx, quota.id.as_str()).unwrap();
-        assert_eq!(format!("{:?}", quota), format!("{:?}", loaded));
+        // delegated claims are removed when delegator no longer holds them
+        pm.unmap_principal_from_role(&ctx, &mike, &approver).unwrap();
+        let loaded = pm.get_principal(&ctx, realm.id.as_str(), dan.id.as_str()).unwrap();
+        assert_eq!(0, loaded.claims.len());
     }
 
     #[test]
-    fn test_add_delete_claims_to_license_policy() {
+    fn test_session() {
         let ctx = SecurityContext::new("myorg", "myid");
         let ds = DefaultDataSource::new();
         let locator = RepositoryLocator::new(&ds);
         let pm = locator.new_persistence_manager();
         pm.clear();
-        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
-        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
-        let license_policy = pm.license_policy_repository.create(&ctx, &LicensePolicy::new("", org.id.as_str(), "default-policy", Some("desc".to_string()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+        let realm = pm.new_realm_with(&ctx, "expense").unwrap();
+        let org = pm.new_org_with(&ctx, "box-air").unwrap();
+        let mike = pm.new_principal_with(&ctx, &org, "mike").unwrap();
+        let dan = pm.new_principal_with(&ctx, &org, "dan").unwrap();
+        let employee = pm.new_role_with(&ctx, &realm, &org, "Employee").unwrap();
+        let manager = pm.new_role_with_parent(&ctx, &realm, &org, &employee, "Manager").unwrap();
+        let approver = pm.new_role_with(&ctx, &realm, &org, "Approver").unwrap();
+        let auditor = pm.new_role_with(&ctx, &realm, &org, "Auditor").unwrap();
+        let expense_report = pm.new_resource_with(&ctx, &realm, "ExpenseReport").unwrap();
+        let read_report = pm.new_claim_with(&ctx, &realm, &expense_report, "READ").unwrap();
+        let update_report = pm.new_claim_with(&ctx, &realm, &expense_report, "UPDATE").unwrap();
+        let approve_report = pm.new_claim_with(&ctx, &realm, &expense_report, "APPROVE").unwrap();
+        pm.map_role_to_claim(&ctx, &employee, &read_report, "", "").unwrap();
+        pm.map_role_to_claim(&ctx, &manager, &update_report, "", "").unwrap();
+        pm.map_role_to_claim(&ctx, &approver, &approve_report, "", "").unwrap();
+        pm.map_principal_to_role(&ctx, &mike, &manager).unwrap();
+        pm.map_principal_to_role(&ctx, &mike, &approver).unwrap();
+
+        let expired_at = NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0);
+        let mike_ctx = SecurityContext::new(realm.id.as_str(), mike.id.as_str());
+        let dan_ctx = SecurityContext::new(realm.id.as_str(), dan.id.as_str());
+        // sessions can only be opened by the principal itself
+        match pm.open_session(&dan_ctx, realm.id.as_str(), mike.id.as_str(), vec![manager.id.clone()], expired_at) {
+            Err(RbacError::Security(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        // roles that are not assigned to the principal cannot be activated
+        assert!(pm.open_session(&mike_ctx, realm.id.as_str(), mike.id.as_str(), vec![auditor.id.clone()], expired_at).is_err());
+        let session = pm.open_session(&mike_ctx, realm.id.as_str(), mike.id.as_str(), vec![manager.id.clone()], expired_at).unwrap();
+        let loaded = pm.get_principal_with_session(&ctx, realm.id.as_str(), mike.id.as_str(), session.id.as_str()).unwrap();
+        assert_eq!(2, loaded.roles.len());
+        assert!(loaded.roles.contains_key(&employee.id));
+        assert!(pm.get_principal_with_session(&ctx, realm.id.as_str(), dan.id.as_str(), session.id.as_str()).is_err());
 
-        let resource = pm.new_resource_with(&ctx, &realm, "report").unwrap();
-        let claim1 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "READ", "allow", None)).unwrap();
-        let claim2 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "UPDATE", "allow", None)).unwrap();
+        // sessions can only be changed by the principal of the session
+        match pm.activate_session_roles(&dan_ctx, session.id.as_str(), vec![approver.id.clone()]) {
+            Err(RbacError::Security(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(pm.close_session(&dan_ctx, session.id.as_str()).is_err());
 
-        assert!(pm.claim_claimable_repository.add_license_policy_to_claim(&ctx, license_policy.id.as_str(), claim1.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).is_ok());
-        assert!(pm.claim_claimable_repository.add_license_policy_to_claim(&ctx, license_policy.id.as_str(), claim2.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).is_ok());
-        assert!(pm.claim_claimable_repository.delete_license_policy_from_claim(&ctx, license_policy.id.as_str(), claim1.id.as_str()).is_ok());
-        assert!(pm.claim_claimable_repository.delete_license_policy_from_claim(&ctx, license_policy.id.as_str(), claim2.id.as_str()).is_ok());
+        pm.new_sod_rule_with(&ctx, &realm, &org, "manager-approver", vec![&manager, &approver], 2, true).unwrap();
+        match pm.activate_session_roles(&mike_ctx, session.id.as_str(), vec![approver.id.clone()]) {
+            Err(RbacError::SodViolation(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
 
-        let loaded = pm.license_policy_repository.get(&ctx, org.id.as_str(), license_policy.id.as_str()).unwrap();
-        assert_eq!(format!("{:?}", license_policy), format!("{:?}", loaded));
+        let sm = SecurityManager::new(pm);
+        let mut req = PermissionRequest::new(realm.id.as_str(), mike.id.as_str(), ActionType::READ, "ExpenseReport", "");
+        req.session_id = Some(session.id.clone());
+        // claims of parent role are available via activated role
+        assert_eq!(PermissionResponse::Allow, sm.check(&req).unwrap());
+        req.action = "UPDATE".into();
+        assert_eq!(PermissionResponse::Allow, sm.check(&req).unwrap());
+        req.action = "APPROVE".into();
+        assert!(sm.check(&req).is_err());
+
+        sm.persistence_manager.deactivate_session_roles(&mike_ctx, session.id.as_str(), vec![manager.id.clone()]).unwrap();
+        sm.persistence_manager.activate_session_roles(&mike_ctx, session.id.as_str(), vec![approver.id.clone()]).unwrap();
+        assert_eq!(PermissionResponse::Allow, sm.check(&req).unwrap());
+        req.action = "READ".into();
+        assert!(sm.check(&req).is_err());
+
+        sm.persistence_manager.close_session(&mike_ctx, session.id.as_str()).unwrap();
+        req.action = "APPROVE".into();
+        assert!(sm.check(&req).is_err());
     }
 
     #[test]
-    fn test_populate_org() {
+    fn test_assignment_approval() {
         let ctx = SecurityContext::new("myorg", "myid");
         let ds = DefaultDataSource::new();
         let locator = RepositoryLocator::new(&ds);
         let pm = locator.new_persistence_manager();
         pm.clear();
-        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
-        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
-        let license_policy = pm.license_policy_repository.create(&ctx, &LicensePolicy::new("", org.id.as_str(), "default-policy", Some("desc".to_string()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
-        pm.role_repository.create(&ctx, &Role::new("", realm.id.as_str(), org.id.as_str(), "admin", None, None)).unwrap();
-        pm.role_repository.create(&ctx, &Role::new("", realm.id.as_str(), org.id.as_str(), "employee", None, None)).unwrap();
-        pm.role_repository.create(&ctx, &Role::new("", realm.id.as_str(), org.id.as_str(), "manager", None, None)).unwrap();
-        pm.group_repository.create(&ctx, &Group::new("", org.id.as_str(), "default", Some("desc".to_string()), Some("parent".to_string()))).unwrap();
-        pm.group_repository.create(&ctx, &Group::new("", org.id.as_str(), "devops", Some("desc".to_string()), Some("parent".to_string()))).unwrap();
-
-        let resource = pm.new_resource_with(&ctx, &realm, "report").unwrap();
-        let claim1 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "READ", "allow", None)).unwrap();
-        let claim2 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "UPDATE", "allow", None)).unwrap();
-        pm.claim_claimable_repository.add_license_policy_to_claim(&ctx, license_policy.id.as_str(), claim1.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).unwrap();
-        pm.claim_claimable_repository.add_license_policy_to_claim(&ctx, license_policy.id.as_str(), claim2.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).unwrap();
-        let loaded = pm.get_org(&ctx, realm.id.as_str(), org.id.as_str()).unwrap();
-        assert_eq!(3, loaded.roles.len());
-        assert_eq!(2, loaded.groups.len());
-        assert_eq!(2, loaded.claims.len());
+        let realm = pm.new_realm_with(&ctx, "trading").unwrap();
+        let org = pm.new_org_with(&ctx, "regulated-bank").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let mike = pm.new_principal_with(&ctx, &org, "mike").unwrap();
+        let trader = pm.new_role_with(&ctx, &realm, &org, "Trader").unwrap();
+        let compliance = pm.new_role_with(&ctx, &realm, &org, "Compliance").unwrap();
+        let trade = pm.new_resource_with(&ctx, &realm, "Trade").unwrap();
+        let execute_trade = pm.new_claim_with(&ctx, &realm, &trade, "EXECUTE").unwrap();
+        let read_trade = pm.new_claim_with(&ctx, &realm, &trade, "READ").unwrap();
+        pm.map_role_to_claim(&ctx, &trader, &execute_trade, "", "").unwrap();
+        pm.map_principal_to_role(&ctx, &mike, &compliance).unwrap();
+
+        let expired_at = NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0);
+        let tom_ctx = SecurityContext::new(realm.id.as_str(), tom.id.as_str());
+        let mike_ctx = SecurityContext::new(realm.id.as_str(), mike.id.as_str());
+        // approver role is defined by the organization rather than the requester
+        assert!(pm.request_assignment(&tom_ctx, &AssignmentRequest::for_role(org.id.as_str(), tom.id.as_str(), trader.id.as_str(), None, Utc::now().naive_utc(), expired_at, compliance.id.as_str())).is_err());
+        let mut org = org;
+        org.approver_role_id = Some(compliance.id.clone());
+        let org = pm.org_repository.update(&ctx, &org).unwrap();
+        let role_request = pm.request_assignment(&tom_ctx, &AssignmentRequest::for_role(org.id.as_str(), tom.id.as_str(), trader.id.as_str(), None, Utc::now().naive_utc(), expired_at, trader.id.as_str())).unwrap();
+        assert_eq!(compliance.id, role_request.approver_role_id);
+        let claim_request = pm.request_assignment(&tom_ctx, &AssignmentRequest::for_claim(org.id.as_str(), tom.id.as_str(), read_trade.id.as_str(), "", None, Utc::now().naive_utc(), expired_at, compliance.id.as_str())).unwrap();
+        assert_eq!(2, pm.assignment_request_repository.get_pending_by_org(&ctx, org.id.as_str()).len());
+        // pending assignments are not effective
+        assert_eq!(0, pm.get_principal(&ctx, realm.id.as_str(), tom.id.as_str()).unwrap().roles.len());
+
+        // only holders of approver role other than the requester can review
+        assert!(pm.approve_assignment(&tom_ctx, realm.id.as_str(), org.id.as_str(), role_request.id.as_str(), "").is_err());
+        assert!(pm.approve_assignment(&ctx, realm.id.as_str(), org.id.as_str(), role_request.id.as_str(), "").is_err());
+        let approved = pm.approve_assignment(&mike_ctx, realm.id.as_str(), org.id.as_str(), role_request.id.as_str(), "verified license").unwrap();
+        assert_eq!("APPROVED", approved.status);
+        assert_eq!(Some(mike.id.clone()), approved.reviewed_by);
+        let rejected = pm.reject_assignment(&mike_ctx, realm.id.as_str(), org.id.as_str(), claim_request.id.as_str(), "not needed").unwrap();
+        assert_eq!("REJECTED", rejected.status);
+        // reviewed requests cannot be reviewed again
+        assert!(pm.approve_assignment(&mike_ctx, realm.id.as_str(), org.id.as_str(), claim_request.id.as_str(), "").is_err());
+        assert_eq!(0, pm.assignment_request_repository.get_pending_by_org(&ctx, org.id.as_str()).len());
+
+        let loaded = pm.get_principal(&ctx, realm.id.as_str(), tom.id.as_str()).unwrap();
+        assert!(loaded.roles.contains_key(&trader.id));
+        assert_eq!(1, loaded.claims.len());
+        assert!(pm.audit_record_repository.latest(100).iter().any(|a| a.message.contains("Approved assignment request")));
     }
 
     #[test]
-    fn test_populate_principal() {
+    fn test_break_glass() {
         let ctx = SecurityContext::new("myorg", "myid");
         let ds = DefaultDataSource::new();
         let locator = RepositoryLocator::new(&ds);
         let pm = locator.new_persistence_manager();
         pm.clear();
-        let realm = pm.realm_repository.create(&ctx, &SecurityRealm::new("myrealm", None)).unwrap();
-        let org = pm.org_repository.create(&ctx, &Organization::new("", None, "myorg", "url", None)).unwrap();
-        let license_policy = pm.license_policy_repository.create(&ctx, &LicensePolicy::new("", org.id.as_str(), "default-policy", Some("desc".to_string()), Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
-
-        let org_employee_role = pm.role_repository.create(&ctx, &Role::new("", realm.id.as_str(), org.id.as_str(), "org_employee", None, None)).unwrap();
-        let org_manager_role = pm.role_repository.create(&ctx, &Role::new("", realm.id.as_str(), org.id.as_str(), "org_manager", None, None)).unwrap();
-
-        let _default_group_employee = pm.role_repository.create(&ctx, &Role::new("", realm.id.as_str(), org.id.as_str(), "default_group_employee", None, None)).unwrap();
-
-        let principal = pm.principal_repository.create(&ctx, &Principal::new("", org.id.as_str(), "myusername", None)).unwrap();
-        let default_group = pm.group_repository.create(&ctx, &Group::new("", org.id.as_str(), "default", Some("desc".to_string()), Some("parent".to_string()))).unwrap();
-        pm.group_principal_repository.add_principal_to_group(&ctx, default_group.id.as_str(), principal.id.as_str()).unwrap();
+        pm.audit_record_repository.clear();
+        let realm = pm.new_realm_with(&ctx, "ops").unwrap();
+        let org = pm.new_org_with(&ctx, "box-air").unwrap();
+        let oncall = pm.new_principal_with(&ctx, &org, "oncall").unwrap();
+        let operator = pm.new_role_with(&ctx, &realm, &org, "Operator").unwrap();
+        let mut admin = pm.new_role_with(&ctx, &realm, &org, "EmergencyAdmin").unwrap();
+        admin.break_glass = true;
+        let admin = pm.role_repository.update(&ctx, &admin).unwrap();
+        let database = pm.new_resource_with(&ctx, &realm, "Database").unwrap();
+        let restart_db = pm.new_claim_with(&ctx, &realm, &database, "RESTART").unwrap();
+        pm.map_role_to_claim(&ctx, &admin, &restart_db, "", "").unwrap();
+
+        let expired_at = Utc::now().naive_utc() + Duration::hours(1);
+        // justification is mandatory, access is time-boxed and only allowed for break-glass roles
+        assert!(pm.break_glass(&ctx, realm.id.as_str(), &BreakGlass::new(oncall.id.as_str(), admin.id.as_str(), " ", expired_at)).is_err());
+        assert!(pm.break_glass(&ctx, realm.id.as_str(), &BreakGlass::new(oncall.id.as_str(), admin.id.as_str(), "db outage", Utc::now().naive_utc() + Duration::hours(BreakGlass::MAX_HOURS + 1))).is_err());
+        assert!(pm.break_glass(&ctx, realm.id.as_str(), &BreakGlass::new(oncall.id.as_str(), operator.id.as_str(), "db outage", expired_at)).is_err());
+
+        // break-glass access is exempt from sod-rules and maximum assignees of the role
+        let dba = pm.new_principal_with(&ctx, &org, "dba").unwrap();
+        let mut admin = admin;
+        admin.max_assignees = Some(1);
+        let admin = pm.role_repository.update(&ctx, &admin).unwrap();
+        pm.map_principal_to_role(&ctx, &dba, &admin).unwrap();
+        pm.map_principal_to_role(&ctx, &oncall, &operator).unwrap();
+        pm.new_sod_rule_with(&ctx, &realm, &org, "operator-admin", vec![&operator, &admin], 2, false).unwrap();
+        match pm.map_principal_to_role(&ctx, &oncall, &admin) {
+            Err(RbacError::QuotaExceeded(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
 
-        pm.role_roleable_repository.add_principal_to_role(&ctx, org_manager_role.id.as_str(), principal.id.as_str(), "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).unwrap();
-        pm.role_roleable_repository.add_group_to_role(&ctx, org_employee_role.id.as_str(), default_group.id.as_str(), "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).unwrap();
+        let sm = SecurityManager::new(pm);
+        let req = PermissionRequest::with(realm.id.as_str(), oncall.id.as_str(), "RESTART", "Database", "");
+        assert!(sm.check(&req).is_err());
 
-        let resource = pm.new_resource_with(&ctx, &realm, "report").unwrap();
-        let claim1 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "READ", "Allow", None)).unwrap();
-        let claim2 = pm.claim_repository.create(&ctx, &Claim::new("", realm.id.as_str(), resource.id.as_str(), "UPDATE", "Allow", None)).unwrap();
-        pm.claim_claimable_repository.add_license_policy_to_claim(&ctx, license_policy.id.as_str(), claim1.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).unwrap();
-        pm.claim_claimable_repository.add_license_policy_to_claim(&ctx, license_policy.id.as_str(), claim2.id.as_str(), "", "", Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).unwrap();
-        let loaded = pm.get_principal(&ctx, realm.id.as_str(), principal.id.as_str()).unwrap();
-        assert_eq!(2, loaded.roles.len());
-        assert_eq!(1, loaded.groups.len());
-        assert!(pm.role_roleable_repository.delete_group_from_role(&ctx, org_employee_role.id.as_str(), default_group.id.as_str()).is_ok());
+        sm.persistence_manager.break_glass(&ctx, realm.id.as_str(), &BreakGlass::new(oncall.id.as_str(), admin.id.as_str(), "db outage", expired_at)).unwrap();
+        assert_eq!(1, sm.persistence_manager.audit_record_repository.latest_by_severity("HIGH", 10).len());
+        assert_eq!(PermissionResponse::Allow, sm.check(&req).unwrap());
+        // explain reports break-glass without recording it as access
+        assert!(sm.explain(&req).unwrap().break_glass);
+        // principals holding break-glass roles are not cached so that expired access isn't served
+        assert!(CLAIM_CACHE.get_principal(realm.id.as_str(), org.id.as_str(), oncall.id.as_str()).is_none());
+        let records = sm.persistence_manager.audit_record_repository.latest_by_severity("HIGH", 10);
+        assert_eq!(2, records.len());
+        assert!(records.iter().all(|r| r.action == Some("BREAK_GLASS".to_string())));
+
+        // access cannot be granted while the principal holds the role, e.g. regular assignment
+        match sm.persistence_manager.break_glass(&ctx, realm.id.as_str(), &BreakGlass::new(dba.id.as_str(), admin.id.as_str(), "db outage", expired_at)) {
+            Err(RbacError::Duplicate(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(sm.persistence_manager.role_roleable_repository.get_by_role(admin.id.as_str()).iter().any(|rr| rr.roleable_id == dba.id && rr.expired_at > expired_at));
+        assert!(sm.persistence_manager.break_glass(&ctx, realm.id.as_str(), &BreakGlass::new(oncall.id.as_str(), admin.id.as_str(), "db outage again", expired_at)).is_err());
+
+        // access can be granted again after previous access expired
+        let expired = PRoleRoleable::new(admin.id.as_str(), oncall.id.as_str(), Constants::Principal.to_string().as_str(), "", Utc::now().naive_utc() - Duration::hours(2), Utc::now().naive_utc() - Duration::hours(1));
+        sm.persistence_manager.role_roleable_repository.delete(&expired).unwrap();
+        sm.persistence_manager.role_roleable_repository.create(&expired).unwrap();
+        sm.persistence_manager.break_glass(&ctx, realm.id.as_str(), &BreakGlass::new(oncall.id.as_str(), admin.id.as_str(), "db outage again", expired_at)).unwrap();
     }
 
     #[test]
@@ -1166,6 +3039,40 @@ mod tests {
         pm.unmap_license_policy_from_claim(&ctx, &paid_policy, &view).unwrap();
     }
 
+    #[test]
+    fn test_license_policy_constraints() {
+        let ctx = SecurityContext::new("0".into(), "0".into());
+        let ds = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&ds);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+        let realm = pm.new_realm_with(&ctx, "curie").unwrap();
+        let trial_org = pm.new_org_with(&ctx, "Trial").unwrap();
+        let trial_policy = pm.new_license_policy(&ctx, &trial_org).unwrap();
+        let trial_tom = pm.new_principal_with(&ctx, &trial_org, "tom").unwrap();
+        let customer = pm.new_role_with(&ctx, &realm, &trial_org, "Customer").unwrap();
+        let report = pm.new_resource_with(&ctx, &realm, "Report").unwrap();
+        let view = pm.new_claim_with(&ctx, &realm, &report, "VIEW").unwrap();
+        pm.map_principal_to_role(&ctx, &trial_tom, &customer).unwrap();
+        pm.map_license_policy_to_claim(&ctx, &trial_policy, &view, "Sales", r#"seats_used < 50"#).unwrap();
+        pm.map_role_to_claim(&ctx, &customer, &view, "Sales", r#"region == "US""#).unwrap();
+
+        let sm = SecurityManager::new(pm);
+        let mut req = PermissionRequest::new(realm.id.as_str(), trial_tom.id.as_str(), ActionType::VIEW, "Report", "Sales");
+        req.context.add("seats_used", ValueWrapper::Int(10));
+        req.context.add("region", ValueWrapper::String("US".to_string()));
+        assert_eq!(PermissionResponse::Allow, sm.check(&req).unwrap());
+
+        // license-policy constraints are not satisfied
+        req.context.add("seats_used", ValueWrapper::Int(60));
+        assert!(sm.check(&req).is_err());
+
+        // grant constraints are not satisfied
+        req.context.add("seats_used", ValueWrapper::Int(10));
+        req.context.add("region", ValueWrapper::String("EU".to_string()));
+        assert!(sm.check(&req).is_err());
+    }
+
     #[test]
     fn test_app_report() {
         init();
@@ -1392,6 +3299,13 @@ mod tests {
         assert!(pm.new_resource_instance_with(&ctx, &job, &xyz_dan, "XYZ Jobs", "2", Status::COMPLETED).is_ok());
         assert!(pm.new_resource_instance_with(&ctx, &job, &xyz_dan, "XYZ Jobs", "3", Status::COMPLETED).is_ok());
         assert!(pm.new_resource_instance_with(&ctx, &job, &xyz_dan, "XYZ Jobs", "4", Status::COMPLETED).is_err());
+
+        // instances of other organizations are not counted against the quota of the same scope
+        assert!(pm.new_resource_quota_with(&ctx, &project, &abc_dave, "Shared", 1).is_ok());
+        assert!(pm.new_resource_quota_with(&ctx, &project, &xyz_dan, "Shared", 1).is_ok());
+        assert!(pm.new_resource_instance_with(&ctx, &project, &abc_dave, "Shared", "1", Status::COMPLETED).is_ok());
+        assert!(pm.new_resource_instance_with(&ctx, &project, &xyz_dan, "Shared", "1", Status::COMPLETED).is_ok());
+        assert!(pm.new_resource_instance_with(&ctx, &project, &xyz_dan, "Shared", "2", Status::COMPLETED).is_err());
     }
 
     use chrono::NaiveDateTime;
@@ -2,6 +2,7 @@ pub mod models;
 pub mod schema;
 pub mod data_source;
 pub mod locator;
+pub mod cache;
 pub mod realm_repository;
 pub mod org_repository;
 pub mod group_repository;
@@ -16,4 +17,10 @@ pub mod role_roleable_repository;
 pub mod claim_repository;
 pub mod audit_record_repository;
 pub mod license_policy_repository;
+pub mod sod_rule_repository;
+pub mod delegation_repository;
+pub mod session_repository;
+pub mod assignment_request_repository;
+pub mod guest_membership_repository;
+pub mod attribute_repository;
 pub mod manager;
@@ -19,6 +19,8 @@ use super::schema::*;
 pub struct PSecurityRealm {
     pub id: String,
     pub description: Option<String>,
+    pub combining_algorithm: Option<String>,
+    pub scope_matching: Option<String>,
     pub created_by: Option<String>,
     pub created_at: Option<NaiveDateTime>,
     pub updated_by: Option<String>,
@@ -26,10 +28,12 @@ pub struct PSecurityRealm {
 }
 
 impl PSecurityRealm {
-    pub fn new(id: &str, description: Option<String>) -> PSecurityRealm {
+    pub fn new(id: &str, description: Option<String>, combining_algorithm: Option<String>) -> PSecurityRealm {
         PSecurityRealm{
             id: id.to_string(),
             description: description.clone(),
+            combining_algorithm: combining_algorithm,
+            scope_matching: None,
             created_at: Some(Utc::now().naive_utc()),
             created_by: None,
             updated_at: Some(Utc::now().naive_utc()),
@@ -51,6 +55,8 @@ pub struct POrganization {
     pub created_at: Option<NaiveDateTime>,
     pub updated_by: Option<String>,
     pub updated_at: Option<NaiveDateTime>,
+    pub approver_role_id: Option<String>,
+    pub admin_role_id: Option<String>,
 }
 
 impl POrganization {
@@ -64,7 +70,9 @@ impl POrganization {
             created_at: Some(Utc::now().naive_utc()),
             created_by: None,
             updated_at: Some(Utc::now().naive_utc()),
-            updated_by: None
+            updated_by: None,
+            approver_role_id: None,
+            admin_role_id: None,
         }
     }
 }
@@ -171,6 +179,7 @@ pub struct PResource {
     pub resource_name: String,
     pub description: Option<String>,
     pub allowable_actions: Option<String>,
+    pub scope_matching: Option<String>,
     pub created_by: Option<String>,
     pub created_at: Option<NaiveDateTime>,
     pub updated_by: Option<String>,
@@ -185,6 +194,7 @@ impl PResource {
             resource_name: resource_name.to_string(),
             description: description,
             allowable_actions: allowable_actions,
+            scope_matching: None,
             created_at: Some(Utc::now().naive_utc()),
             created_by: None,
             updated_at: Some(Utc::now().naive_utc()),
@@ -279,6 +289,8 @@ pub struct PRole {
     pub organization_id: String,
     pub name: String,
     pub description: Option<String>,
+    pub max_assignees: Option<i32>,
+    pub break_glass: bool,
     pub created_by: Option<String>,
     pub created_at: Option<NaiveDateTime>,
     pub updated_by: Option<String>,
@@ -294,6 +306,8 @@ impl PRole {
             realm_id: realm_id.to_string(),
             name: name.to_string(),
             description: description,
+            max_assignees: None,
+            break_glass: false,
             created_at: Some(Utc::now().naive_utc()),
             created_by: None,
             updated_at: Some(Utc::now().naive_utc()),
@@ -353,6 +367,7 @@ pub struct PClaim {
     pub action: String,
     pub effect: Option<String>,
     pub description: Option<String>,
+    pub priority: Option<i32>,
     pub created_by: Option<String>,
     pub created_at: Option<NaiveDateTime>,
     pub updated_by: Option<String>,
@@ -360,7 +375,7 @@ pub struct PClaim {
 }
 
 impl PClaim {
-    pub fn new(id: &str, realm_id: &str, resource_id: &str, action: &str, effect: &str, description: Option<String>) -> PClaim {
+    pub fn new(id: &str, realm_id: &str, resource_id: &str, action: &str, effect: &str, description: Option<String>, priority: Option<i32>) -> PClaim {
         PClaim {
             id: id.to_string(),
             realm_id: realm_id.to_string(),
@@ -368,6 +383,7 @@ impl PClaim {
             action: action.to_string(),
             effect: Some(effect.to_string()),
             description: description,
+            priority: priority,
             created_at: Some(Utc::now().naive_utc()),
             created_by: None,
             updated_at: Some(Utc::now().naive_utc()),
@@ -425,6 +441,7 @@ pub struct PAuditRecord {
     pub message: String,
     pub action: Option<String>,
     pub context: Option<String>,
+    pub severity: Option<String>,
     pub created_by: Option<String>,
     pub created_at: Option<NaiveDateTime>,
 }
@@ -436,6 +453,7 @@ impl PAuditRecord {
             message: message.to_string(),
             action: action,
             context: context,
+            severity: None,
             created_at: Some(Utc::now().naive_utc()),
             created_by: None,
         }
@@ -476,6 +494,244 @@ impl PLicensePolicy {
     }
 }
 
+/// PSodRule defines separation-of-duty rule that limits number of roles from a set of roles that
+/// can be assigned to (static) or activated by (dynamic) a principal
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable, AsChangeset, Associations, Serialize, Deserialize)]
+#[table_name = "rbac_sod_rules"]
+#[belongs_to(POrganization, foreign_key="organization_id")]
+pub struct PSodRule {
+    pub id: String,
+    pub realm_id: String,
+    pub organization_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub role_ids: String,
+    pub cardinality: i32,
+    pub dynamic: bool,
+    pub created_by: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_by: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl PSodRule {
+    pub fn new(id: &str, realm_id: &str, organization_id: &str, name: &str, description: Option<String>, role_ids: &str, cardinality: i32, dynamic: bool) -> PSodRule {
+        PSodRule {
+            id: id.to_string(),
+            realm_id: realm_id.to_string(),
+            organization_id: organization_id.to_string(),
+            name: name.to_string(),
+            description: description,
+            role_ids: role_ids.to_string(),
+            cardinality: cardinality,
+            dynamic: dynamic,
+            created_at: Some(Utc::now().naive_utc()),
+            created_by: None,
+            updated_at: Some(Utc::now().naive_utc()),
+            updated_by: None
+        }
+    }
+}
+
+/// PDelegation defines temporary delegation of claims and roles from delegator to delegatee
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable, AsChangeset, Associations, Serialize, Deserialize)]
+#[table_name = "rbac_delegations"]
+#[belongs_to(POrganization, foreign_key="organization_id")]
+pub struct PDelegation {
+    pub id: String,
+    pub organization_id: String,
+    pub delegator_id: String,
+    pub delegatee_id: String,
+    pub role_ids: Option<String>,
+    pub claim_ids: Option<String>,
+    pub delegation_constraints: Option<String>,
+    pub effective_at: NaiveDateTime,
+    pub expired_at: NaiveDateTime,
+    pub created_by: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_by: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl PDelegation {
+    pub fn new(id: &str, organization_id: &str, delegator_id: &str, delegatee_id: &str, role_ids: &str, claim_ids: &str, delegation_constraints: &str, effective_at: NaiveDateTime, expired_at: NaiveDateTime) -> PDelegation {
+        PDelegation {
+            id: id.to_string(),
+            organization_id: organization_id.to_string(),
+            delegator_id: delegator_id.to_string(),
+            delegatee_id: delegatee_id.to_string(),
+            role_ids: Some(role_ids.to_string()),
+            claim_ids: Some(claim_ids.to_string()),
+            delegation_constraints: Some(delegation_constraints.to_string()),
+            effective_at: effective_at,
+            expired_at: expired_at,
+            created_at: Some(Utc::now().naive_utc()),
+            created_by: None,
+            updated_at: Some(Utc::now().naive_utc()),
+            updated_by: None
+        }
+    }
+
+    pub fn delegation_constraints(&self) -> String {
+        self.delegation_constraints.clone().unwrap_or_else(||"".to_string())
+    }
+}
+
+/// PAssignmentRequest defines request for assigning role or claim to a principal that becomes
+/// effective only after it's approved by a principal holding the approver role
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable, AsChangeset, Associations, Serialize, Deserialize)]
+#[table_name = "rbac_assignment_requests"]
+#[belongs_to(POrganization, foreign_key="organization_id")]
+pub struct PAssignmentRequest {
+    pub id: String,
+    pub organization_id: String,
+    pub principal_id: String,
+    pub role_id: Option<String>,
+    pub claim_id: Option<String>,
+    pub scope: String,
+    pub assignment_constraints: Option<String>,
+    pub effective_at: NaiveDateTime,
+    pub expired_at: NaiveDateTime,
+    pub approver_role_id: String,
+    pub status: String,
+    pub reviewed_by: Option<String>,
+    pub review_comment: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_by: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl PAssignmentRequest {
+    pub fn new(id: &str, organization_id: &str, principal_id: &str, role_id: Option<String>, claim_id: Option<String>, scope: &str, assignment_constraints: &str, effective_at: NaiveDateTime, expired_at: NaiveDateTime, approver_role_id: &str, status: &str) -> PAssignmentRequest {
+        PAssignmentRequest {
+            id: id.to_string(),
+            organization_id: organization_id.to_string(),
+            principal_id: principal_id.to_string(),
+            role_id: role_id,
+            claim_id: claim_id,
+            scope: scope.to_string(),
+            assignment_constraints: Some(assignment_constraints.to_string()),
+            effective_at: effective_at,
+            expired_at: expired_at,
+            approver_role_id: approver_role_id.to_string(),
+            status: status.to_string(),
+            reviewed_by: None,
+            review_comment: None,
+            created_at: Some(Utc::now().naive_utc()),
+            created_by: None,
+            updated_at: Some(Utc::now().naive_utc()),
+            updated_by: None
+        }
+    }
+
+    pub fn assignment_constraints(&self) -> String {
+        self.assignment_constraints.clone().unwrap_or_else(||"".to_string())
+    }
+}
+
+/// PSession defines session of a principal that activates a subset of its assigned roles
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable, AsChangeset, Associations, Serialize, Deserialize)]
+#[table_name = "rbac_sessions"]
+#[belongs_to(POrganization, foreign_key="organization_id")]
+pub struct PSession {
+    pub id: String,
+    pub realm_id: String,
+    pub organization_id: String,
+    pub principal_id: String,
+    pub role_ids: Option<String>,
+    pub expired_at: NaiveDateTime,
+    pub created_by: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_by: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl PSession {
+    pub fn new(id: &str, realm_id: &str, organization_id: &str, principal_id: &str, role_ids: &str, expired_at: NaiveDateTime) -> PSession {
+        PSession {
+            id: id.to_string(),
+            realm_id: realm_id.to_string(),
+            organization_id: organization_id.to_string(),
+            principal_id: principal_id.to_string(),
+            role_ids: Some(role_ids.to_string()),
+            expired_at: expired_at,
+            created_at: Some(Utc::now().naive_utc()),
+            created_by: None,
+            updated_at: Some(Utc::now().naive_utc()),
+            updated_by: None
+        }
+    }
+}
+
+/// PGuestMembership defines membership of a principal in an organization other than its own
+/// organization so that the principal can be granted roles and groups of the host organization
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable, AsChangeset, Associations, Serialize, Deserialize)]
+#[table_name = "rbac_guest_memberships"]
+#[belongs_to(POrganization, foreign_key="organization_id")]
+pub struct PGuestMembership {
+    pub id: String,
+    pub organization_id: String,
+    pub principal_id: String,
+    pub description: Option<String>,
+    pub effective_at: NaiveDateTime,
+    pub expired_at: NaiveDateTime,
+    pub created_by: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_by: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl PGuestMembership {
+    pub fn new(id: &str, organization_id: &str, principal_id: &str, description: Option<String>, effective_at: NaiveDateTime, expired_at: NaiveDateTime) -> PGuestMembership {
+        PGuestMembership {
+            id: id.to_string(),
+            organization_id: organization_id.to_string(),
+            principal_id: principal_id.to_string(),
+            description: description,
+            effective_at: effective_at,
+            expired_at: expired_at,
+            created_at: Some(Utc::now().naive_utc()),
+            created_by: None,
+            updated_at: Some(Utc::now().naive_utc()),
+            updated_by: None
+        }
+    }
+}
+
+/// PAttribute defines typed attribute of a principal, group or organization, e.g. region
+#[derive(Debug, Clone, PartialEq, Queryable, Insertable, AsChangeset, Serialize, Deserialize)]
+#[table_name = "rbac_attributes"]
+pub struct PAttribute {
+    pub id: String,
+    pub owner_id: String,
+    pub owner_type: String,
+    pub name: String,
+    pub value: String,
+    pub value_type: String,
+    pub created_by: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_by: Option<String>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl PAttribute {
+    pub fn new(id: &str, owner_id: &str, owner_type: &str, name: &str, value: &str, value_type: &str) -> PAttribute {
+        PAttribute {
+            id: id.to_string(),
+            owner_id: owner_id.to_string(),
+            owner_type: owner_type.to_string(),
+            name: name.to_string(),
+            value: value.to_string(),
+            value_type: value_type.to_string(),
+            created_at: Some(Utc::now().naive_utc()),
+            created_by: None,
+            updated_at: Some(Utc::now().naive_utc()),
+            updated_by: None
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     extern crate uuid as uuu;
@@ -484,7 +740,7 @@ mod tests {
 
     #[test]
     fn test_create_realm() {
-        let r = PSecurityRealm::new("test", None);
+        let r = PSecurityRealm::new("test", None, None);
         assert_eq!("test", r.id);
     }
 
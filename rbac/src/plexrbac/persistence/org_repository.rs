@@ -7,7 +7,9 @@ use super::models::{POrganization};
 use plexrbac::domain::models::Organization;
 use plexrbac::common::SecurityContext;
 use plexrbac::common::RbacError;
+use super::cache::CLAIM_CACHE;
 use chrono::{Utc};
+use std::collections::HashSet;
 use self::uuu::Uuid;
 
 //////////////////////////////////////////////////////////////////////////////////////////////
@@ -27,6 +29,7 @@ impl<'a> OrganizationRepository<'a> {
         db_obj.created_by = Some(ctx.principal_id.clone());
         db_obj.updated_at = Some(Utc::now().naive_utc());
         db_obj.updated_by = Some(ctx.principal_id.clone());
+        self.validate_parent(ctx, &db_obj)?;
         if let Err(err) = self._create(&db_obj) {
             return Err(RbacError::Persistence(err.to_string()));
         }
@@ -38,14 +41,19 @@ impl<'a> OrganizationRepository<'a> {
     pub fn update(&self, ctx: &SecurityContext, org: &Organization) -> Result<Organization, RbacError> {
         match self._get(org.id.as_str()) {
             Ok(mut db_obj) => {
+                db_obj.parent_id = org.parent_id.clone();
                 db_obj.url = org.url.clone();
                 db_obj.description = org.description.clone();
+                db_obj.approver_role_id = org.approver_role_id.clone();
+                db_obj.admin_role_id = org.admin_role_id.clone();
                 db_obj.updated_at = Some(Utc::now().naive_utc());
                 db_obj.updated_by = Some(ctx.principal_id.clone());
+                self.validate_parent(ctx, &db_obj)?;
                 if let Err(err) = self._update(&db_obj) {
                     return Err(RbacError::Persistence(err.to_string()));
                 }
                 self.audit(ctx, format!("Updated org {:?}", db_obj), "UPDATE");
+                CLAIM_CACHE.invalidate_org(db_obj.id.as_str());
                 Ok(Organization::from(&db_obj))
             }
             Err(err) => Err(RbacError::Persistence(err.to_string()))
@@ -66,11 +74,29 @@ impl<'a> OrganizationRepository<'a> {
         }
     }
 
+    /// Returns organization followed by its ancestors up to the root organization
+    pub fn get_chain(&self, _ctx: &SecurityContext, organization_id: &str) -> Vec<Organization> {
+        let mut visited = HashSet::new();
+        let mut result = vec![];
+        let mut next = Some(organization_id.to_string());
+        while let Some(id) = next.take() {
+            if !visited.insert(id.clone()) {
+                break;
+            }
+            if let Ok(porg) = self._get(id.as_str()) {
+                next = porg.parent_id.clone();
+                result.push(Organization::from(&porg));
+            }
+        }
+        result
+    }
+
     /// Deletes realm by id from the database
     pub fn delete(&self, ctx: &SecurityContext, id: &str) -> Result<usize, RbacError> {
         match self._delete(id) {
             Ok(n) => {
                 self.audit(ctx, format!("Deleted organization {:?}", id), "DELETE");
+                CLAIM_CACHE.invalidate_org(id);
                 Ok(n)
             },
             Err(err) => Err(RbacError::Persistence(err.to_string())),
@@ -78,6 +104,24 @@ impl<'a> OrganizationRepository<'a> {
     }
 
     ///////////////////////////////////// PRIVATE METHODS ////////////////////////////////
+    /// Verifies that parent of the organization exists and parent chain does not loop back to itself
+    fn validate_parent(&self, ctx: &SecurityContext, org: &POrganization) -> Result<(), RbacError> {
+        let mut visited = HashSet::new();
+        visited.insert(org.id.clone());
+        let mut next = org.parent_id.clone();
+        while let Some(parent_id) = next {
+            if !visited.insert(parent_id.clone()) {
+                self.audit(ctx, format!("Rejected org {:?} because parent chain contains cycle at {}", org, parent_id), "UPDATE");
+                return Err(RbacError::CyclicHierarchy(format!("Parent chain of org {} contains cycle at {}", org.id, parent_id)));
+            }
+            next = match self._get(parent_id.as_str()) {
+                Ok(parent) => parent.parent_id,
+                Err(_) => return Err(RbacError::NotFound(format!("Parent org {} not found for {:?}", parent_id, org))),
+            };
+        }
+        Ok(())
+    }
+
     fn audit(&self, ctx: &SecurityContext, message: String, action: &str) {
         let _ = self.audit_record_repository.create_with(message.as_str(), action, format!("{:?}", ctx).as_str(), ctx.principal_id.clone());
         info!("{}", message);
@@ -140,6 +184,7 @@ mod tests {
     use plexrbac::persistence::data_source::DefaultDataSource;
     use plexrbac::domain::models::Organization;
     use plexrbac::common::SecurityContext;
+    use plexrbac::common::RbacError;
 
     #[test]
     fn test_create() {
@@ -199,4 +244,28 @@ mod tests {
         let results = repo.all(&ctx);
         assert_eq!(2, results.len());
     }
+
+    #[test]
+    fn test_get_chain() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_org_repository();
+        repo.clear();
+
+        let holding = repo.create(&ctx, &Organization::new("", None, "holding", "url", None)).unwrap();
+        let subsidiary = repo.create(&ctx, &Organization::new("", Some(holding.id.clone()), "subsidiary", "url", None)).unwrap();
+        let division = repo.create(&ctx, &Organization::new("", Some(subsidiary.id.clone()), "division", "url", None)).unwrap();
+        assert!(repo.create(&ctx, &Organization::new("", Some("unknown".into()), "orphan", "url", None)).is_err());
+
+        let chain = repo.get_chain(&ctx, division.id.as_str()).iter().map(|o| o.name.clone()).collect::<Vec<String>>();
+        assert_eq!(vec!["division".to_string(), "subsidiary".to_string(), "holding".to_string()], chain);
+
+        let mut loaded = repo.get(&ctx, holding.id.as_str()).unwrap();
+        loaded.parent_id = Some(division.id.clone());
+        match repo.update(&ctx, &loaded) {
+            Err(RbacError::CyclicHierarchy(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
 }
@@ -7,6 +7,7 @@ use super::models::PPrincipal;
 use plexrbac::domain::models::Principal;
 use plexrbac::common::SecurityContext;
 use plexrbac::common::RbacError;
+use super::cache::CLAIM_CACHE;
 use chrono::{Utc};
 use self::uuu::Uuid;
 
@@ -54,6 +55,7 @@ impl<'a> PrincipalRepository<'a> {
                     return Err(RbacError::Persistence(err.to_string()));
                 }
                 self.audit(ctx, format!("Updated principal {:?}", db_obj), "UPDATE");
+                CLAIM_CACHE.invalidate_principal(db_obj.id.as_str());
                 Ok(Principal::from(&db_obj))
             }
             Err(err) => Err(RbacError::Persistence(err.to_string()))
@@ -70,6 +72,7 @@ impl<'a> PrincipalRepository<'a> {
         match self._delete(id) {
             Ok(n) => {
                 self.audit(ctx, format!("Deleted principal {:?}", id), "DELETE");
+                CLAIM_CACHE.invalidate_principal(id);
                 Ok(n)
             },
             Err(err) => Err(RbacError::Persistence(err.to_string())),
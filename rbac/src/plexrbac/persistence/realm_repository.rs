@@ -5,7 +5,9 @@ use super::schema::rbac_realms;
 use super::models::PSecurityRealm;
 use plexrbac::domain::models::SecurityRealm;
 use plexrbac::common::SecurityContext;
+use plexrbac::common::CombiningAlgorithm;
 use plexrbac::common::RbacError;
+use super::cache::CLAIM_CACHE;
 use chrono::{Utc};
 
 //////////////////////////////////////////////////////////////////////////////////////////////
@@ -21,15 +23,19 @@ impl<'a> SecurityRealmRepository<'a> {
     ////////////////////////////////// SECURITY REALM CRUD OPERATIONS //////////////////////////////
     /// Updates security realm
     pub fn update(&self, ctx: &SecurityContext, realm: &SecurityRealm) -> Result<SecurityRealm, RbacError> {
+        self.validate(realm)?;
         match self._get(realm.id.as_str()) {
             Ok(mut db_obj) => {
                 db_obj.description = realm.description.clone();
+                db_obj.combining_algorithm = realm.combining_algorithm.clone();
+                db_obj.scope_matching = realm.scope_matching.clone();
                 db_obj.updated_at = Some(Utc::now().naive_utc());
                 db_obj.updated_by = Some(ctx.principal_id.clone());
                 if let Err(err) = self._update(&db_obj) {
                     return Err(RbacError::Persistence(err.to_string()));
                 }
                 self.audit(ctx, format!("Updated realm {:?}", db_obj), "UPDATE");
+                CLAIM_CACHE.invalidate_realm(db_obj.id.as_str());
                 Ok(SecurityRealm::from(&db_obj))
             }
             Err(err) => Err(RbacError::Persistence(err.to_string()))
@@ -38,6 +44,7 @@ impl<'a> SecurityRealmRepository<'a> {
 
     /// Creates security realm
     pub fn create(&self, ctx: &SecurityContext, realm: &SecurityRealm) -> Result<SecurityRealm, RbacError> {
+        self.validate(realm)?;
         let mut db_obj = realm.to();
         db_obj.created_at = Some(Utc::now().naive_utc());
         db_obj.created_by = Some(ctx.principal_id.clone());
@@ -68,6 +75,7 @@ impl<'a> SecurityRealmRepository<'a> {
         match self._delete(id) {
             Ok(n) => {
                 self.audit(ctx, format!("Deleted security realm {:?}", id), "DELETE");
+                CLAIM_CACHE.invalidate_realm(id);
                 Ok(n)
             },
             Err(err) => Err(RbacError::Persistence(err.to_string())),
@@ -107,6 +115,16 @@ impl<'a> SecurityRealmRepository<'a> {
         rbac_realms::table.find(id.to_string()).get_result::<PSecurityRealm>(&*connection)
     }
 
+    /// Rejects unknown combining algorithm or scope matching so that a misspelled name doesn't
+    /// silently change how effects of claims are combined or how scopes are matched
+    fn validate(&self, realm: &SecurityRealm) -> Result<(), RbacError> {
+        if let Some(ref algorithm) = realm.combining_algorithm {
+            CombiningAlgorithm::from(algorithm.as_str())?;
+        }
+        realm.scope_matching()?;
+        Ok(())
+    }
+
     fn audit(&self, ctx: &SecurityContext, message: String, action: &str) {
         let _ = self.audit_record_repository.create_with(message.as_str(), action, format!("{:?}", ctx).as_str(), ctx.principal_id.clone());
         info!("{}", message);
@@ -132,6 +150,8 @@ mod tests {
     use plexrbac::persistence::locator::RepositoryLocator;
     use plexrbac::persistence::data_source::DefaultDataSource;
     use plexrbac::common::SecurityContext;
+    use plexrbac::common::CombiningAlgorithm;
+    use plexrbac::common::ScopeMatching;
 
     #[test]
     fn test_create() {
@@ -162,9 +182,21 @@ mod tests {
 
         let mut loaded = repo.get(&ctx, realm.id.as_str()).unwrap();
         loaded.description = Some("blah".to_string());
+        loaded.combining_algorithm = Some(CombiningAlgorithm::DenyOverrides.to_string());
+        loaded.scope_matching = Some(ScopeMatching::Hierarchical.to_string());
         assert!(repo.update(&ctx, &loaded).is_ok());
         let loaded = repo.get(&ctx, loaded.id.as_str()).unwrap();
         assert_eq!(Some("blah".to_string()), loaded.description);
+        assert_eq!(Ok(CombiningAlgorithm::DenyOverrides), loaded.combining_algorithm());
+
+        let mut misspelled = loaded.clone();
+        misspelled.combining_algorithm = Some("deny-override".to_string());
+        assert!(repo.update(&ctx, &misspelled).is_err());
+        assert_eq!(Ok(CombiningAlgorithm::DenyOverrides), repo.get(&ctx, loaded.id.as_str()).unwrap().combining_algorithm());
+        assert_eq!(Ok(ScopeMatching::Hierarchical), loaded.scope_matching());
+        misspelled.combining_algorithm = None;
+        misspelled.scope_matching = Some("hierachical".to_string());
+        assert!(repo.update(&ctx, &misspelled).is_err());
     }
 
     #[test]
@@ -2,12 +2,17 @@
 extern crate uuid as uuu;
 
 use diesel::prelude::*;
+use diesel::dsl::sql;
+use diesel::expression::BoxableExpression;
+use diesel::sql_types::{Bool, Text};
+use diesel::sqlite::Sqlite;
 use super::schema::rbac_resource_instances;
 use super::models::PResourceInstance;
 use plexrbac::domain::models::ResourceInstance;
 use plexrbac::common::SecurityContext;
 use plexrbac::common::RbacError;
-use chrono::{Utc};
+use plexrbac::common::ScopeMatching;
+use chrono::{NaiveDateTime, Utc};
 use self::uuu::Uuid;
 
 
@@ -67,6 +72,29 @@ impl<'a> ResourceInstanceRepository<'a> {
         self._get_by_resource(resource_id).iter().map(|r| ResourceInstance::from(&r)).collect::<Vec<ResourceInstance>>()
     }
 
+    /// Returns resource instance of given resource by its reference id, where the instance must
+    /// belong to one of given license-policies so that instances of other tenants are not found
+    pub fn get_by_ref_id(&self, _ctx: &SecurityContext, resource_id: &str, license_policy_ids: Vec<String>, ref_id: &str) -> Option<ResourceInstance> {
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_resource_instances::table
+                .filter(rbac_resource_instances::resource_id.eq(resource_id.to_string()))
+                .filter(rbac_resource_instances::license_policy_id.eq_any(license_policy_ids))
+                .filter(rbac_resource_instances::ref_id.eq(ref_id.to_string()))
+                .load::<PResourceInstance>(&*connection) {
+                Ok(v) => {
+                    if let Some(instance) = v.first() {
+                        Some(ResourceInstance::from(instance))
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
     /// Returns all resource instances for given resource and scope
     pub fn _get_by_resource_scope(&self, resource_id: &str, scope: &str) -> Vec<PResourceInstance> {
         if let Ok(connection) = self.data_source.new_connection() {
@@ -117,6 +145,32 @@ impl<'a> ResourceInstanceRepository<'a> {
         }
     }
 
+    /// Counts resource instances for given resource and status that are attributed to given
+    /// license-policies and whose scope is covered by given scope using the scope-matching
+    /// strategy, optionally only instances created after given time
+    pub fn count_by_resource_matching(&self, resource_id: &str, license_policy_ids: Vec<String>, scope: &str, status: &str, matching: &ScopeMatching, created_after: Option<NaiveDateTime>) -> i64 {
+        let mut covered: Box<dyn BoxableExpression<rbac_resource_instances::table, Sqlite, SqlType = Bool>> = Box::new(rbac_resource_instances::scope.eq(scope.to_string()));
+        for pattern in matching.glob_patterns(scope) {
+            covered = Box::new(covered.or(sql::<Bool>("scope GLOB ").bind::<Text, _>(pattern)));
+        }
+        let mut query = rbac_resource_instances::table
+            .filter(rbac_resource_instances::resource_id.eq(resource_id.to_string()))
+            .filter(rbac_resource_instances::license_policy_id.eq_any(license_policy_ids))
+            .filter(rbac_resource_instances::status.eq(status.to_string()))
+            .filter(covered)
+            .into_boxed();
+        if let Some(after) = created_after {
+            query = query.filter(rbac_resource_instances::created_at.ge(after));
+        }
+        if let Ok(connection) = self.data_source.new_connection() {
+            match query.count().get_result(&*connection) {
+                Ok(len) => len,
+                Err(_) => 0,
+            }
+        } else {
+            0
+        }
+    }
 
     /// Deletes resource instance by id from the database
     pub fn delete(&self, ctx: &SecurityContext, id: &str) -> Result<usize, RbacError> {
@@ -202,6 +256,9 @@ mod tests {
 
         let loaded = repo.get(&ctx, instance.id.as_str()).unwrap();
         assert_eq!(instance_str, format!("{:?}", loaded));
+        assert_eq!(Some(instance.id.clone()), repo.get_by_ref_id(&ctx, "11", vec!["22".into()], "refid").map(|i| i.id));
+        assert!(repo.get_by_ref_id(&ctx, "12", vec!["22".into()], "refid").is_none());
+        assert!(repo.get_by_ref_id(&ctx, "11", vec!["33".into()], "refid").is_none());
     }
 
     #[test]
@@ -7,6 +7,7 @@ use super::models::PResourceQuota;
 use plexrbac::domain::models::ResourceQuota;
 use plexrbac::common::SecurityContext;
 use plexrbac::common::RbacError;
+use plexrbac::common::ScopeMatching;
 use chrono::{Utc};
 use self::uuu::Uuid;
 
@@ -84,6 +85,28 @@ impl<'a> ResourceQuotaRepository<'a> {
         }
     }
 
+    /// Returns resource quotas for given resource whose scope covers given scope using the
+    /// scope-matching strategy, where quotas with more specific scope are returned first
+    pub fn get_by_resource_matching(&self, resource_id: &str, scope: &str, matching: &ScopeMatching) -> Vec<PResourceQuota> {
+        let now = Utc::now().naive_utc();
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_resource_quotas::table
+                .filter(rbac_resource_quotas::resource_id.eq(resource_id.to_string()))
+                .filter(rbac_resource_quotas::effective_at.le(now))
+                .filter(rbac_resource_quotas::expired_at.ge(now))
+                .load::<PResourceQuota>(&*connection) {
+                Ok(v) => {
+                    let mut quotas = v.into_iter().filter(|q| matching.matches(q.scope.as_str(), scope)).collect::<Vec<PResourceQuota>>();
+                    quotas.sort_by(|a, b| b.scope.len().cmp(&a.scope.len()));
+                    quotas
+                },
+                _ => vec![],
+            }
+        } else {
+            vec![]
+        }
+    }
+
     /// Deletes resource quota by id from the database
     pub fn delete(&self, ctx: &SecurityContext, id: &str) -> Result<usize, RbacError> {
         match self._delete(id) {
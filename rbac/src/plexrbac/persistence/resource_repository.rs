@@ -7,6 +7,8 @@ use super::models::PResource;
 use plexrbac::domain::models::Resource;
 use plexrbac::common::SecurityContext;
 use plexrbac::common::RbacError;
+use plexrbac::common::ScopeMatching;
+use super::cache::CLAIM_CACHE;
 use chrono::{Utc};
 use self::uuu::Uuid;
 use std::collections::HashMap;
@@ -22,6 +24,7 @@ pub struct ResourceRepository<'a> {
 impl<'a> ResourceRepository<'a> {
     /// Creates resource
     pub fn create(&self, ctx: &SecurityContext, resource: &Resource) -> Result<Resource, RbacError> {
+        resource.scope_matching(&ScopeMatching::Exact)?;
         let mut db_obj = resource.to();
         db_obj.id = Uuid::new_v4().to_hyphenated().to_string();
         db_obj.created_at = Some(Utc::now().naive_utc());
@@ -37,16 +40,20 @@ impl<'a> ResourceRepository<'a> {
 
     /// Creates resource
     pub fn update(&self, ctx: &SecurityContext, resource: &Resource) -> Result<Resource, RbacError> {
+        // unknown scope matching is rejected rather than silently matching scopes exactly
+        resource.scope_matching(&ScopeMatching::Exact)?;
         match self._get(resource.realm_id.as_str(), resource.id.as_str()) {
             Some(mut db_obj) => {
                 db_obj.allowable_actions = resource.allowable_actions .clone();
                 db_obj.description = resource.description.clone();
+                db_obj.scope_matching = resource.scope_matching.clone();
                 db_obj.updated_at = Some(Utc::now().naive_utc());
                 db_obj.updated_by = Some(ctx.principal_id.clone());
                 if let Err(err) = self._update(&db_obj) {
                     return Err(RbacError::Persistence(err.to_string()));
                 }
                 self.audit(ctx, format!("Updated resource {:?}", db_obj), "UPDATE");
+                CLAIM_CACHE.invalidate_realm(db_obj.realm_id.as_str());
                 Ok(Resource::from(&db_obj))
             }
             None => Err(RbacError::NotFound(format!("Resource not found {:?}", resource)))
@@ -76,6 +83,7 @@ impl<'a> ResourceRepository<'a> {
         match self._delete(realm_id, id) {
             Ok(n) => {
                 self.audit(ctx, format!("Deleted resource {:?}", id), "DELETE");
+                CLAIM_CACHE.invalidate_realm(realm_id);
                 Ok(n)
             },
             Err(err) => Err(RbacError::Persistence(err.to_string())),
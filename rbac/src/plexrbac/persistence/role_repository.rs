@@ -7,9 +7,10 @@ use super::models::{PRole};
 use plexrbac::domain::models::Role;
 use plexrbac::common::SecurityContext;
 use plexrbac::common::RbacError;
+use super::cache::CLAIM_CACHE;
 use chrono::{Utc};
 use self::uuu::Uuid;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 //////////////////////////////////////////////////////////////////////////////////////////////
 /// RoleRepository defines methods for accessing and persisting roles
@@ -28,6 +29,7 @@ impl<'a> RoleRepository<'a> {
         db_obj.created_by = Some(ctx.principal_id.clone());
         db_obj.updated_at = Some(Utc::now().naive_utc());
         db_obj.updated_by = Some(ctx.principal_id.clone());
+        self.validate_parent(ctx, &db_obj)?;
         if let Err(err) = self._create(&db_obj) {
             return Err(RbacError::Persistence(err.to_string()));
         }
@@ -41,13 +43,17 @@ impl<'a> RoleRepository<'a> {
             Some(mut db_obj) => {
                 db_obj.parent_id = role.parent_id.clone();
                 db_obj.description = role.description.clone();
+                db_obj.max_assignees = role.max_assignees;
+                db_obj.break_glass = role.break_glass;
                 //db_obj.role_constraints = role.role_constraints.clone();
                 db_obj.updated_at = Some(Utc::now().naive_utc());
                 db_obj.updated_by = Some(ctx.principal_id.clone());
+                self.validate_parent(ctx, &db_obj)?;
                 if let Err(err) = self._update(&db_obj) {
                     return Err(RbacError::Persistence(err.to_string()));
                 }
                 self.audit(ctx, format!("Updated role {:?}", db_obj), "UPDATE");
+                CLAIM_CACHE.invalidate_org(db_obj.organization_id.as_str());
                 Ok(Role::from(&db_obj))
             }
             None => Err(RbacError::NotFound(format!("Role not found {:?}", role)))
@@ -77,6 +83,7 @@ impl<'a> RoleRepository<'a> {
         match self._delete(org_id, id) {
             Ok(n) => {
                 self.audit(ctx, format!("Deleted role {:?}", id), "DELETE");
+                CLAIM_CACHE.invalidate_org(org_id);
                 Ok(n)
             },
             Err(err) => Err(RbacError::Persistence(err.to_string())),
@@ -84,13 +91,29 @@ impl<'a> RoleRepository<'a> {
     }
 
     ///////////////////////////////////// PRIVATE METHODS ////////////////////////////////
+    /// Verifies that parent chain of the role does not loop back to itself, where the chain
+    /// is followed across organizations so that inherited parents are checked as well
+    fn validate_parent(&self, ctx: &SecurityContext, role: &PRole) -> Result<(), RbacError> {
+        let mut visited = HashSet::new();
+        visited.insert(role.id.clone());
+        let mut next = role.parent_id.clone();
+        while let Some(parent_id) = next {
+            if !visited.insert(parent_id.clone()) {
+                self.audit(ctx, format!("Rejected role {:?} because parent chain contains cycle at {}", role, parent_id), "UPDATE");
+                return Err(RbacError::CyclicHierarchy(format!("Parent chain of role {} contains cycle at {}", role.id, parent_id)));
+            }
+            next = self.__get(parent_id.as_str()).ok().and_then(|parent| parent.parent_id);
+        }
+        Ok(())
+    }
+
     fn audit(&self, ctx: &SecurityContext, message: String, action: &str) {
         let _ = self.audit_record_repository.create_with(message.as_str(), action, format!("{:?}", ctx).as_str(), ctx.principal_id.clone());
         info!("{}", message);
     }
 
     /// Returns all roles for given organization
-    pub fn _get_by_org(&self, organization_id: &str) -> Vec<PRole> {
+    fn _get_by_org(&self, organization_id: &str) -> Vec<PRole> {
         if let Ok(connection) = self.data_source.new_connection() {
             match rbac_roles::table
                 .filter(rbac_roles::organization_id.eq(organization_id.to_string()))
@@ -103,8 +126,22 @@ impl<'a> RoleRepository<'a> {
         }
     }
 
+    /// Returns all roles that inherit from given parent role-ids
+    pub fn get_by_parent_ids(&self, parent_ids: Vec<String>) -> Vec<PRole> {
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_roles::table
+                .filter(rbac_roles::parent_id.eq_any(parent_ids))
+                .load::<PRole>(&*connection) {
+                Ok(v) => v,
+                _ => vec![],
+            }
+        } else {
+            vec![]
+        }
+    }
+
     /// Returns all roles for role-ids -- used internally
-    fn _get_roles_by_role_ids(&self, role_ids: Vec<String>) -> Vec<PRole> {
+    pub fn get_roles_by_role_ids(&self, role_ids: Vec<String>) -> Vec<PRole> {
         if let Ok(connection) = self.data_source.new_connection() {
             match rbac_roles::table
                 .filter(rbac_roles::id.eq_any(role_ids))
@@ -186,6 +223,7 @@ mod tests {
     use plexrbac::persistence::data_source::DefaultDataSource;
     use plexrbac::domain::models::Role;
     use plexrbac::common::SecurityContext;
+    use plexrbac::common::RbacError;
 
 
     #[test]
@@ -221,6 +259,29 @@ mod tests {
         assert_eq!(Some("newdesc".to_string()), loaded.description);
     }
 
+    #[test]
+    fn test_update_with_cyclic_parent() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_role_repository();
+        repo.clear();
+
+        let employee = repo.create(&ctx, &Role::new("", "default", "2", "employee", None, None)).unwrap();
+        let teller = repo.create(&ctx, &Role::new("", "default", "2", "teller", None, Some(employee.id.clone()))).unwrap();
+        let csr = repo.create(&ctx, &Role::new("", "default", "2", "csr", None, Some(teller.id.clone()))).unwrap();
+
+        let mut loaded = repo.get(&ctx, "2", employee.id.as_str()).unwrap();
+        loaded.parent_id = Some(csr.id.clone());
+        match repo.update(&ctx, &loaded) {
+            Err(RbacError::CyclicHierarchy(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        loaded.parent_id = Some(employee.id.clone());
+        assert!(repo.update(&ctx, &loaded).is_err());
+        assert_eq!(None, repo.get(&ctx, "2", employee.id.as_str()).unwrap().parent_id);
+    }
+
     #[test]
     fn test_delete() {
         let ctx = SecurityContext::new("myorg", "myid");
@@ -245,7 +306,7 @@ mod tests {
 
         let role1 = repo.create(&ctx, &Role::new("", "default", "2", "myrole1", Some("desc".to_string()), Some("parent".to_string()))).unwrap();
         let role2 = repo.create(&ctx, &Role::new("", "default", "2", "myrole2", Some("desc".to_string()), Some("parent".to_string()))).unwrap();
-        assert_eq!(2, repo._get_roles_by_role_ids(vec![role1.id.clone(), role2.id.clone()]).len());
+        assert_eq!(2, repo.get_roles_by_role_ids(vec![role1.id.clone(), role2.id.clone()]).len());
     }
 
     #[test]
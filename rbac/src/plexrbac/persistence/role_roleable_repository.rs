@@ -2,11 +2,14 @@
 
 use diesel::prelude::*;
 use super::schema::rbac_role_roleables;
-use super::models::PRoleRoleable;
+use super::models::{PRoleRoleable, PRole, PGroup};
+use plexrbac::domain::models::{Role, Group, SodRule};
 use plexrbac::common::Constants;
 use plexrbac::common::SecurityContext;
 use plexrbac::common::RbacError;
 use chrono::{NaiveDateTime, Utc};
+use super::cache::CLAIM_CACHE;
+use std::collections::{HashMap, HashSet};
 
 //////////////////////////////////////////////////////////////////////////////////////////////
 /// RoleRoleableRepository defines association between role-roleable where roleable can be
@@ -14,13 +17,21 @@ use chrono::{NaiveDateTime, Utc};
 ///
 pub struct RoleRoleableRepository<'a> {
     pub data_source: &'a dyn super::data_source::DataSource,
+    pub role_repository: super::role_repository::RoleRepository<'a>,
+    pub group_repository: super::group_repository::GroupRepository<'a>,
+    pub org_repository: super::org_repository::OrganizationRepository<'a>,
+    pub group_principal_repository: super::group_principal_repository::GroupPrincipalRepository<'a>,
+    pub sod_rule_repository: super::sod_rule_repository::SodRuleRepository<'a>,
     pub audit_record_repository: super::audit_record_repository::AuditRecordRepository<'a>,
 }
 
 impl<'a> RoleRoleableRepository<'a> {
-    /// Adds principal to role
+    /// Adds principal to role -- static separation-of-duty rules are checked against roles of
+    /// the principal that are assigned directly or via groups including their parents and the
+    /// assignment is rejected if the role already has maximum number of assignees
     pub fn add_principal_to_role(&self, ctx: &SecurityContext, role_id: &str, principal_id: &str, constraints: &str, effective_at: NaiveDateTime, expired_at: NaiveDateTime) -> Result<usize, RbacError> {
         let rr = PRoleRoleable::new(role_id, principal_id, Constants::Principal.to_string().as_str(), constraints, effective_at, expired_at);
+        self.validate(ctx, &rr)?;
         match self.create(&rr) {
             Ok(n) => {
                 self.audit(ctx, format!("Adding principal to role {:?}", rr), "CREATE");
@@ -42,9 +53,12 @@ impl<'a> RoleRoleableRepository<'a> {
         }
     }
 
-    /// Adds group to role
+    /// Adds group to role -- static separation-of-duty rules are checked against roles of the
+    /// group and of all principals that are members of the group or its sub-groups, which also
+    /// count towards maximum number of assignees of the role
     pub fn add_group_to_role(&self, ctx: &SecurityContext, role_id: &str, group_id: &str, constraints: &str, effective_at: NaiveDateTime, expired_at: NaiveDateTime) -> Result<usize, RbacError> {
         let rr = PRoleRoleable::new(role_id, group_id, Constants::Group.to_string().as_str(), constraints, effective_at, expired_at);
+        self.validate(ctx, &rr)?;
         match self.create(&rr) {
             Ok(n) => {
                 self.audit(ctx, format!("Adding group to role {:?}", rr), "DELETE");
@@ -54,6 +68,15 @@ impl<'a> RoleRoleableRepository<'a> {
         }
     }
 
+    /// Adds principal to group -- static separation-of-duty rules are checked against roles
+    /// that the principal would hold directly or via groups including their parents after
+    /// joining the group and the membership is rejected if any role of the group already has
+    /// maximum number of assignees
+    pub fn add_principal_to_group(&self, ctx: &SecurityContext, org_id: &str, group_id: &str, principal_id: &str) -> Result<usize, RbacError> {
+        self.validate_group_membership(ctx, org_id, group_id, principal_id)?;
+        self.group_principal_repository.add_principal_to_group(ctx, group_id, principal_id)
+    }
+
     /// Removes group from role
     pub fn delete_group_from_role(&self, ctx: &SecurityContext, role_id: &str, group_id: &str) -> Result<usize, RbacError> {
         let rr = PRoleRoleable::new(role_id, group_id, Constants::Group.to_string().as_str(), "", Utc::now().naive_utc(), Utc::now().naive_utc());
@@ -164,21 +187,339 @@ impl<'a> RoleRoleableRepository<'a> {
     /// Creates new assocoation between role and role-able
     pub fn create(&self, rr: &PRoleRoleable) -> Result<usize, diesel::result::Error> {
         let connection = self.data_source.new_connection()?;
-        diesel::insert_into(rbac_role_roleables::table).values(rr).execute(&*connection)
+        let n = diesel::insert_into(rbac_role_roleables::table).values(rr).execute(&*connection)?;
+        self.invalidate(rr);
+        Ok(n)
     }
 
 
     /// Deletes assocoation between role and role-able
     pub fn delete(&self, rr: &PRoleRoleable) -> Result<usize, diesel::result::Error> {
         let connection = self.data_source.new_connection()?;
-        diesel::delete(rbac_role_roleables::table)
+        let n = diesel::delete(rbac_role_roleables::table)
             .filter(rbac_role_roleables::role_id.eq(rr.role_id.clone()))
             .filter(rbac_role_roleables::roleable_id.eq(rr.roleable_id.clone()))
             .filter(rbac_role_roleables::roleable_type.eq(rr.roleable_type.clone()))
-            .execute(&*connection)
+            .execute(&*connection)?;
+        self.invalidate(rr);
+        Ok(n)
+    }
+
+    /// Creates association in place of an expired association of the role and role-able within
+    /// a single transaction, the association is not created and zero is returned when the
+    /// role-able already holds the role
+    pub fn replace_expired(&self, rr: &PRoleRoleable) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        let now = Utc::now().naive_utc();
+        let n = connection.transaction::<usize, diesel::result::Error, _>(|| {
+            let active = rbac_role_roleables::table
+                .filter(rbac_role_roleables::role_id.eq(rr.role_id.clone()))
+                .filter(rbac_role_roleables::roleable_id.eq(rr.roleable_id.clone()))
+                .filter(rbac_role_roleables::roleable_type.eq(rr.roleable_type.clone()))
+                .filter(rbac_role_roleables::expired_at.ge(now))
+                .load::<PRoleRoleable>(&*connection)?;
+            if active.len() > 0 {
+                return Ok(0);
+            }
+            diesel::delete(rbac_role_roleables::table)
+                .filter(rbac_role_roleables::role_id.eq(rr.role_id.clone()))
+                .filter(rbac_role_roleables::roleable_id.eq(rr.roleable_id.clone()))
+                .filter(rbac_role_roleables::roleable_type.eq(rr.roleable_type.clone()))
+                .execute(&*connection)?;
+            diesel::insert_into(rbac_role_roleables::table).values(rr).execute(&*connection)
+        })?;
+        self.invalidate(rr);
+        Ok(n)
+    }
+
+    /// Verifies that new association doesn't violate limits of the role or static sod-rules
+    pub fn validate(&self, ctx: &SecurityContext, rr: &PRoleRoleable) -> Result<(), RbacError> {
+        let role = match self.role_repository.get_roles_by_role_ids(vec![rr.role_id.clone()]).first() {
+            Some(role) => role.clone(),
+            None => return Ok(()),
+        };
+        let groups = self.get_chain_groups(ctx, role.organization_id.as_str());
+        self.check_max_assignees(ctx, rr, &role, &groups)?;
+        let roles = self.get_chain_roles(ctx, role.organization_id.as_str());
+        self.check_static_sod(ctx, rr, &role, &roles, &groups, format!("Assigning role {} to {} {}", rr.role_id, rr.roleable_type, rr.roleable_id))
+    }
+
+    /// Updates the role after verifying that changing its parent doesn't violate any static
+    /// sod-rule for principals or groups that hold the role or any role inheriting from it
+    pub fn update_role(&self, ctx: &SecurityContext, role: &Role) -> Result<Role, RbacError> {
+        let existing = match self.role_repository.get(ctx, role.organization_id.as_str(), role.id.as_str()) {
+            Some(existing) => existing,
+            None => return Err(RbacError::NotFound(format!("Role not found {:?}", role))),
+        };
+        if existing.parent_id != role.parent_id {
+            let mut updated = existing.clone();
+            updated.parent_id = role.parent_id.clone();
+            let operation = format!("Changing parent of role {} to {:?}", role.id, role.parent_id);
+            let mut pending = vec![role.id.clone()];
+            let mut visited = HashSet::new();
+            while let Some(role_id) = pending.pop() {
+                if !visited.insert(role_id.clone()) {
+                    continue;
+                }
+                for inheriting in self.role_repository.get_by_parent_ids(vec![role_id.clone()]) {
+                    pending.push(inheriting.id.clone());
+                }
+                if let Some(affected) = self.role_repository.get_roles_by_role_ids(vec![role_id.clone()]).first() {
+                    let groups = self.get_chain_groups(ctx, affected.organization_id.as_str());
+                    let mut roles = self.get_chain_roles(ctx, affected.organization_id.as_str());
+                    roles.insert(updated.id.clone(), updated.clone());
+                    for rr in self.get_by_role(role_id.as_str()) {
+                        self.check_static_sod(ctx, &rr, affected, &roles, &groups, operation.clone())?;
+                    }
+                }
+            }
+        }
+        self.role_repository.update(ctx, role)
+    }
+
+    /// Updates the group after verifying that changing its parent doesn't violate any static
+    /// sod-rule for the group, its sub-groups or their members with roles of the new parents
+    pub fn update_group(&self, ctx: &SecurityContext, group: &Group) -> Result<Group, RbacError> {
+        let existing = match self.group_repository.get(ctx, group.organization_id.as_str(), group.id.as_str()) {
+            Some(existing) => existing,
+            None => return Err(RbacError::NotFound(format!("Group not found {:?}", group))),
+        };
+        if existing.parent_id != group.parent_id {
+            let mut updated = existing.to();
+            updated.parent_id = group.parent_id.clone();
+            let mut groups = self.get_chain_groups(ctx, group.organization_id.as_str());
+            groups.insert(updated.id.clone(), updated);
+            let operation = format!("Changing parent of group {} to {:?}", group.id, group.parent_id);
+            let mut holders = vec![];
+            for group_id in self.get_descendant_group_ids(group.id.as_str(), &groups) {
+                holders.push((format!("group {}", group_id), self.get_role_ids_by_groups(&self.get_ancestor_group_ids(vec![group_id.clone()], &groups))));
+                for gp in self.group_principal_repository.get_by_group(group_id.as_str()) {
+                    holders.push((format!("principal {}", gp.principal_id), self.get_role_ids_by_principal_and_groups(gp.principal_id.as_str(), &groups)));
+                }
+            }
+            self.check_holders_sod(ctx, group.organization_id.as_str(), holders, operation)?;
+        }
+        self.group_repository.update(ctx, group)
+    }
+
+    /// Verifies that roles added to the principal, e.g. via delegation or a what-if overlay,
+    /// along with roles that it holds directly, via groups or given other roles don't exceed
+    /// limits of the added roles or violate any static sod-rule of the organization
+    pub fn validate_principal_roles(&self, ctx: &SecurityContext, org_id: &str, principal_id: &str, role_ids: &Vec<String>, other_role_ids: &Vec<String>) -> Result<(), RbacError> {
+        if role_ids.len() == 0 {
+            return Ok(());
+        }
+        let groups = self.get_chain_groups(ctx, org_id);
+        let now = Utc::now().naive_utc();
+        for role in self.role_repository.get_roles_by_role_ids(role_ids.clone()) {
+            let rr = PRoleRoleable::new(role.id.as_str(), principal_id, Constants::Principal.to_string().as_str(), "", now, now);
+            self.check_max_assignees(ctx, &rr, &role, &groups)?;
+        }
+        let mut added_role_ids = role_ids.clone();
+        added_role_ids.extend(other_role_ids.iter().cloned());
+        self.check_principal_sod(ctx, org_id, principal_id, added_role_ids, &groups, format!("Adding roles {:?} to principal {}", role_ids, principal_id))
     }
 
     ///////////////////////////////////// PRIVATE METHODS ////////////////////////////////
+    /// Verifies that number of distinct principals holding the role directly or via groups
+    /// doesn't exceed maximum assignees of the role
+    fn check_max_assignees(&self, ctx: &SecurityContext, rr: &PRoleRoleable, role: &PRole, groups: &HashMap<String, PGroup>) -> Result<(), RbacError> {
+        let max_assignees = match role.max_assignees {
+            Some(max_assignees) => max_assignees,
+            None => return Ok(()),
+        };
+        let mut principal_ids = HashSet::new();
+        for existing in self.get_by_role(role.id.as_str()) {
+            principal_ids.extend(self.get_principal_ids_by_roleable(&existing, groups));
+        }
+        principal_ids.extend(self.get_principal_ids_by_roleable(rr, groups));
+        if principal_ids.len() as i32 > max_assignees {
+            let message = format!("Assigning role {} to {} {} exceeds maximum {} assignees of the role", role.name, rr.roleable_type, rr.roleable_id, max_assignees);
+            self.audit(ctx, format!("{} -- {:?}", message, rr), "QUOTA_EXCEEDED");
+            return Err(RbacError::QuotaExceeded(message));
+        }
+        Ok(())
+    }
+
+    /// Returns principal-ids of roleable, i.e., the principal itself or members of the group
+    /// and its sub-groups
+    fn get_principal_ids_by_roleable(&self, rr: &PRoleRoleable, groups: &HashMap<String, PGroup>) -> HashSet<String> {
+        let mut principal_ids = HashSet::new();
+        if rr.roleable_type == Constants::Group.to_string() {
+            for group_id in self.get_descendant_group_ids(rr.roleable_id.as_str(), groups) {
+                for gp in self.group_principal_repository.get_by_group(group_id.as_str()) {
+                    principal_ids.insert(gp.principal_id.clone());
+                }
+            }
+        } else {
+            principal_ids.insert(rr.roleable_id.clone());
+        }
+        principal_ids
+    }
+
+    /// Verifies that new association doesn't violate any static sod-rule of the organization
+    fn check_static_sod(&self, ctx: &SecurityContext, rr: &PRoleRoleable, role: &PRole, roles: &HashMap<String, Role>, groups: &HashMap<String, PGroup>, operation: String) -> Result<(), RbacError> {
+        let mut holders = vec![];
+        if rr.roleable_type == Constants::Group.to_string() {
+            let mut role_ids = self.get_role_ids_by_groups(&self.get_ancestor_group_ids(vec![rr.roleable_id.clone()], groups));
+            role_ids.push(rr.role_id.clone());
+            holders.push((format!("group {}", rr.roleable_id), role_ids));
+            for principal_id in self.get_principal_ids_by_roleable(rr, groups) {
+                let mut role_ids = self.get_role_ids_by_principal_and_groups(principal_id.as_str(), groups);
+                role_ids.push(rr.role_id.clone());
+                holders.push((format!("principal {}", principal_id), role_ids));
+            }
+        } else {
+            let mut role_ids = self.get_role_ids_by_principal_and_groups(rr.roleable_id.as_str(), groups);
+            role_ids.push(rr.role_id.clone());
+            holders.push((format!("principal {}", rr.roleable_id), role_ids));
+        }
+        self.check_holders_sod_with(ctx, role.organization_id.as_str(), holders, roles, operation)
+    }
+
+    /// Verifies that roles of given holders, expanded with roles of the organization and its
+    /// ancestors, don't violate any static sod-rule of the organization
+    fn check_holders_sod(&self, ctx: &SecurityContext, org_id: &str, holders: Vec<(String, Vec<String>)>, operation: String) -> Result<(), RbacError> {
+        let roles = self.get_chain_roles(ctx, org_id);
+        self.check_holders_sod_with(ctx, org_id, holders, &roles, operation)
+    }
+
+    fn check_holders_sod_with(&self, ctx: &SecurityContext, org_id: &str, holders: Vec<(String, Vec<String>)>, roles: &HashMap<String, Role>, operation: String) -> Result<(), RbacError> {
+        let rules = self.sod_rule_repository.get_by_org_and_type(org_id, false);
+        if rules.len() == 0 {
+            return Ok(());
+        }
+        for (holder, role_ids) in holders {
+            let effective = self.expand_role_ids(role_ids, roles);
+            if let Some(rule) = rules.iter().find(|rule| rule.violated_by(&effective)) {
+                return Err(self.sod_violation(ctx, rule, format!("{} violates separation-of-duty rule {} for {}", operation, rule.name, holder)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Verifies that principal joining the group doesn't exceed limits of roles of the group
+    /// and its parents or violate any static sod-rule of the organization with those roles
+    fn validate_group_membership(&self, ctx: &SecurityContext, org_id: &str, group_id: &str, principal_id: &str) -> Result<(), RbacError> {
+        let groups = self.get_chain_groups(ctx, org_id);
+        let group_role_ids = self.get_role_ids_by_groups(&self.get_ancestor_group_ids(vec![group_id.to_string()], &groups));
+        if group_role_ids.len() == 0 {
+            return Ok(());
+        }
+        let now = Utc::now().naive_utc();
+        for role in self.role_repository.get_roles_by_role_ids(group_role_ids.clone()) {
+            // the principal is counted as a new holder of the role unless it already holds it
+            let rr = PRoleRoleable::new(role.id.as_str(), principal_id, Constants::Principal.to_string().as_str(), "", now, now);
+            self.check_max_assignees(ctx, &rr, &role, &groups)?;
+        }
+        self.check_principal_sod(ctx, org_id, principal_id, group_role_ids, &groups, format!("Adding principal {} to group {}", principal_id, group_id))
+    }
+
+    /// Verifies that roles of the principal along with given additional roles don't violate
+    /// any static sod-rule of the organization
+    fn check_principal_sod(&self, ctx: &SecurityContext, org_id: &str, principal_id: &str, additional_role_ids: Vec<String>, groups: &HashMap<String, PGroup>, operation: String) -> Result<(), RbacError> {
+        let mut role_ids = self.get_role_ids_by_principal_and_groups(principal_id, groups);
+        role_ids.extend(additional_role_ids);
+        self.check_holders_sod(ctx, org_id, vec![(format!("principal {}", principal_id), role_ids)], operation)
+    }
+
+    fn sod_violation(&self, ctx: &SecurityContext, rule: &SodRule, message: String) -> RbacError {
+        self.audit(ctx, format!("{} -- {:?}", message, rule), "SOD_VIOLATION");
+        RbacError::SodViolation(message)
+    }
+
+    /// Returns role-ids of principal that are assigned directly or via groups and their parents
+    fn get_role_ids_by_principal_and_groups(&self, principal_id: &str, groups: &HashMap<String, PGroup>) -> Vec<String> {
+        let group_ids = self.group_principal_repository.get_by_principal(principal_id).iter().map(|gp| gp.group_id.clone()).collect::<Vec<String>>();
+        let mut role_ids = self.get_role_ids_by_principal(principal_id);
+        role_ids.extend(self.get_role_ids_by_groups(&self.get_ancestor_group_ids(group_ids, groups)));
+        role_ids
+    }
+
+    fn get_role_ids_by_groups(&self, group_ids: &Vec<String>) -> Vec<String> {
+        group_ids.iter().flat_map(|group_id| self.get_role_ids_by_group(group_id.clone())).collect()
+    }
+
+    /// Returns given group-ids along with ids of all their parent groups
+    fn get_ancestor_group_ids(&self, group_ids: Vec<String>, groups: &HashMap<String, PGroup>) -> Vec<String> {
+        let mut visited = HashSet::new();
+        let mut pending = group_ids;
+        while let Some(group_id) = pending.pop() {
+            if visited.insert(group_id.clone()) {
+                if let Some(parent_id) = groups.get(&group_id).and_then(|g| g.parent_id.clone()) {
+                    pending.push(parent_id);
+                }
+            }
+        }
+        visited.into_iter().collect()
+    }
+
+    /// Returns given group-id along with ids of all groups that inherit from it
+    fn get_descendant_group_ids(&self, group_id: &str, groups: &HashMap<String, PGroup>) -> Vec<String> {
+        let mut visited = HashSet::new();
+        let mut pending = vec![group_id.to_string()];
+        while let Some(group_id) = pending.pop() {
+            if visited.insert(group_id.clone()) {
+                for (id, g) in groups {
+                    if g.parent_id.as_ref() == Some(&group_id) {
+                        pending.push(id.clone());
+                    }
+                }
+            }
+        }
+        visited.into_iter().collect()
+    }
+
+    /// Returns roles of the organization and its ancestors keyed by id, so that parents that
+    /// are inherited from ancestor organizations are followed as well
+    fn get_chain_roles(&self, ctx: &SecurityContext, org_id: &str) -> HashMap<String, Role> {
+        let mut roles = HashMap::new();
+        for org_id in self.get_org_chain(ctx, org_id) {
+            roles.extend(self.role_repository.get_by_org(ctx, org_id.as_str()));
+        }
+        roles
+    }
+
+    /// Returns groups of the organization and its ancestors keyed by id
+    fn get_chain_groups(&self, ctx: &SecurityContext, org_id: &str) -> HashMap<String, PGroup> {
+        let mut groups = HashMap::new();
+        for org_id in self.get_org_chain(ctx, org_id) {
+            groups.extend(self.group_repository._get_by_org(org_id.as_str()).into_iter().map(|g| (g.id.clone(), g)));
+        }
+        groups
+    }
+
+    /// Returns id of the organization followed by ids of its ancestors
+    fn get_org_chain(&self, ctx: &SecurityContext, org_id: &str) -> Vec<String> {
+        let chain = self.org_repository.get_chain(ctx, org_id).iter().map(|org| org.id.clone()).collect::<Vec<String>>();
+        if chain.is_empty() {
+            return vec![org_id.to_string()];
+        }
+        chain
+    }
+
+    /// Returns given role-ids along with ids of all their parent roles
+    fn expand_role_ids(&self, role_ids: Vec<String>, roles: &HashMap<String, Role>) -> HashSet<String> {
+        let mut visited = HashSet::new();
+        let mut pending = role_ids;
+        while let Some(role_id) = pending.pop() {
+            if visited.insert(role_id.clone()) {
+                if let Some(parent_id) = roles.get(&role_id).and_then(|r| r.parent_id.clone()) {
+                    pending.push(parent_id);
+                }
+            }
+        }
+        visited
+    }
+
+    fn invalidate(&self, rr: &PRoleRoleable) {
+        if rr.roleable_type == Constants::Group.to_string() {
+            CLAIM_CACHE.invalidate_group(rr.roleable_id.as_str());
+        } else {
+            CLAIM_CACHE.invalidate_principal(rr.roleable_id.as_str());
+        }
+    }
+
     fn audit(&self, ctx: &SecurityContext, message: String, action: &str) {
         let _ = self.audit_record_repository.create_with(message.as_str(), action, format!("{:?}", ctx).as_str(), ctx.principal_id.clone());
         info!("{}", message);
@@ -188,6 +529,7 @@ impl<'a> RoleRoleableRepository<'a> {
     pub fn clear(&self) {
         let connection = self.data_source.new_connection().unwrap();
         diesel::delete(rbac_role_roleables::table).execute(&*connection).unwrap();
+        CLAIM_CACHE.invalidate_all();
     }
 }
 
@@ -1,9 +1,47 @@
+table! {
+    rbac_assignment_requests (id) {
+        id -> Text,
+        organization_id -> Text,
+        principal_id -> Text,
+        role_id -> Nullable<Text>,
+        claim_id -> Nullable<Text>,
+        scope -> Text,
+        assignment_constraints -> Nullable<Text>,
+        effective_at -> Timestamp,
+        expired_at -> Timestamp,
+        approver_role_id -> Text,
+        status -> Text,
+        reviewed_by -> Nullable<Text>,
+        review_comment -> Nullable<Text>,
+        created_by -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+        updated_by -> Nullable<Text>,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    rbac_attributes (id) {
+        id -> Text,
+        owner_id -> Text,
+        owner_type -> Text,
+        name -> Text,
+        value -> Text,
+        value_type -> Text,
+        created_by -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+        updated_by -> Nullable<Text>,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
 table! {
     rbac_audit_records (id) {
         id -> Text,
         message -> Text,
         action -> Nullable<Text>,
         context -> Nullable<Text>,
+        severity -> Nullable<Text>,
         created_by -> Nullable<Text>,
         created_at -> Nullable<Timestamp>,
     }
@@ -33,6 +71,25 @@ table! {
         action -> Text,
         effect -> Nullable<Text>,
         description -> Nullable<Text>,
+        priority -> Nullable<Integer>,
+        created_by -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+        updated_by -> Nullable<Text>,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    rbac_delegations (id) {
+        id -> Text,
+        organization_id -> Text,
+        delegator_id -> Text,
+        delegatee_id -> Text,
+        role_ids -> Nullable<Text>,
+        claim_ids -> Nullable<Text>,
+        delegation_constraints -> Nullable<Text>,
+        effective_at -> Timestamp,
+        expired_at -> Timestamp,
         created_by -> Nullable<Text>,
         created_at -> Nullable<Timestamp>,
         updated_by -> Nullable<Text>,
@@ -51,6 +108,21 @@ table! {
     }
 }
 
+table! {
+    rbac_guest_memberships (id) {
+        id -> Text,
+        organization_id -> Text,
+        principal_id -> Text,
+        description -> Nullable<Text>,
+        effective_at -> Timestamp,
+        expired_at -> Timestamp,
+        created_by -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+        updated_by -> Nullable<Text>,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
 table! {
     rbac_groups (id) {
         id -> Text,
@@ -91,6 +163,8 @@ table! {
         created_at -> Nullable<Timestamp>,
         updated_by -> Nullable<Text>,
         updated_at -> Nullable<Timestamp>,
+        approver_role_id -> Nullable<Text>,
+        admin_role_id -> Nullable<Text>,
     }
 }
 
@@ -111,6 +185,8 @@ table! {
     rbac_realms (id) {
         id -> Text,
         description -> Nullable<Text>,
+        combining_algorithm -> Nullable<Text>,
+        scope_matching -> Nullable<Text>,
         created_by -> Nullable<Text>,
         created_at -> Nullable<Timestamp>,
         updated_by -> Nullable<Text>,
@@ -157,6 +233,7 @@ table! {
         resource_name -> Text,
         description -> Nullable<Text>,
         allowable_actions -> Nullable<Text>,
+        scope_matching -> Nullable<Text>,
         created_by -> Nullable<Text>,
         created_at -> Nullable<Timestamp>,
         updated_by -> Nullable<Text>,
@@ -187,6 +264,40 @@ table! {
         organization_id -> Text,
         name -> Text,
         description -> Nullable<Text>,
+        max_assignees -> Nullable<Integer>,
+        break_glass -> Bool,
+        created_by -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+        updated_by -> Nullable<Text>,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    rbac_sessions (id) {
+        id -> Text,
+        realm_id -> Text,
+        organization_id -> Text,
+        principal_id -> Text,
+        role_ids -> Nullable<Text>,
+        expired_at -> Timestamp,
+        created_by -> Nullable<Text>,
+        created_at -> Nullable<Timestamp>,
+        updated_by -> Nullable<Text>,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    rbac_sod_rules (id) {
+        id -> Text,
+        realm_id -> Text,
+        organization_id -> Text,
+        name -> Text,
+        description -> Nullable<Text>,
+        role_ids -> Text,
+        cardinality -> Integer,
+        dynamic -> Bool,
         created_by -> Nullable<Text>,
         created_at -> Nullable<Timestamp>,
         updated_by -> Nullable<Text>,
@@ -194,12 +305,17 @@ table! {
     }
 }
 
+joinable!(rbac_assignment_requests -> rbac_organizations (organization_id));
+joinable!(rbac_assignment_requests -> rbac_principals (principal_id));
 joinable!(rbac_claim_claimables -> rbac_claims (claim_id));
 joinable!(rbac_claims -> rbac_realms (realm_id));
 joinable!(rbac_claims -> rbac_resources (resource_id));
+joinable!(rbac_delegations -> rbac_organizations (organization_id));
 joinable!(rbac_group_principals -> rbac_groups (group_id));
 joinable!(rbac_group_principals -> rbac_principals (principal_id));
 joinable!(rbac_groups -> rbac_organizations (organization_id));
+joinable!(rbac_guest_memberships -> rbac_organizations (organization_id));
+joinable!(rbac_guest_memberships -> rbac_principals (principal_id));
 joinable!(rbac_license_policies -> rbac_organizations (organization_id));
 joinable!(rbac_principals -> rbac_organizations (organization_id));
 joinable!(rbac_resource_instances -> rbac_license_policies (license_policy_id));
@@ -210,13 +326,20 @@ joinable!(rbac_resources -> rbac_realms (realm_id));
 joinable!(rbac_role_roleables -> rbac_roles (role_id));
 joinable!(rbac_roles -> rbac_organizations (organization_id));
 joinable!(rbac_roles -> rbac_realms (realm_id));
+joinable!(rbac_sessions -> rbac_organizations (organization_id));
+joinable!(rbac_sessions -> rbac_principals (principal_id));
+joinable!(rbac_sod_rules -> rbac_organizations (organization_id));
 
 allow_tables_to_appear_in_same_query!(
+    rbac_assignment_requests,
+    rbac_attributes,
     rbac_audit_records,
     rbac_claim_claimables,
     rbac_claims,
+    rbac_delegations,
     rbac_group_principals,
     rbac_groups,
+    rbac_guest_memberships,
     rbac_license_policies,
     rbac_organizations,
     rbac_principals,
@@ -226,4 +349,6 @@ allow_tables_to_appear_in_same_query!(
     rbac_resources,
     rbac_role_roleables,
     rbac_roles,
+    rbac_sessions,
+    rbac_sod_rules,
 );
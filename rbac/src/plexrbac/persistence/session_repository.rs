@@ -0,0 +1,206 @@
+//#![crate_name = "doc"]
+extern crate uuid as uuu;
+
+use diesel::prelude::*;
+use super::schema::rbac_sessions;
+use super::models::{PSession};
+use chrono::{Utc};
+use plexrbac::domain::models::Session;
+use plexrbac::common::SecurityContext;
+use plexrbac::common::RbacError;
+use self::uuu::Uuid;
+
+//////////////////////////////////////////////////////////////////////////////////////////////
+/// SessionRepository defines methods for accessing and persisting sessions that keep roles
+/// activated by a principal until the session expires
+///
+pub struct SessionRepository<'a> {
+    pub data_source: &'a dyn super::data_source::DataSource,
+    pub audit_record_repository: super::audit_record_repository::AuditRecordRepository<'a>,
+}
+
+impl<'a> SessionRepository<'a> {
+    /// Creates session
+    pub fn create(&self, ctx: &SecurityContext, session: &Session) -> Result<Session, RbacError> {
+        if session.expired_at <= Utc::now().naive_utc() {
+            return Err(RbacError::Custom(format!("Session of principal {} expires before it's opened", session.principal_id)));
+        }
+        let mut db_obj = session.to();
+        db_obj.id = Uuid::new_v4().to_hyphenated().to_string();
+        db_obj.created_at = Some(Utc::now().naive_utc());
+        db_obj.created_by = Some(ctx.principal_id.clone());
+        db_obj.updated_at = Some(Utc::now().naive_utc());
+        db_obj.updated_by = Some(ctx.principal_id.clone());
+        if let Err(err) = self._create(&db_obj) {
+            return Err(RbacError::Persistence(err.to_string()));
+        }
+        self.audit(ctx, format!("Opened new session {:?}", db_obj), "CREATE");
+        Ok(Session::from(&db_obj))
+    }
+
+    /// Updates activated roles and expiration of the session
+    pub fn update(&self, ctx: &SecurityContext, session: &Session) -> Result<Session, RbacError> {
+        match self._get(session.id.as_str()) {
+            Some(mut db_obj) => {
+                let updated = session.to();
+                db_obj.role_ids = updated.role_ids.clone();
+                db_obj.expired_at = updated.expired_at.clone();
+                db_obj.updated_at = Some(Utc::now().naive_utc());
+                db_obj.updated_by = Some(ctx.principal_id.clone());
+                if let Err(err) = self._update(&db_obj) {
+                    return Err(RbacError::Persistence(err.to_string()));
+                }
+                self.audit(ctx, format!("Updated session {:?}", db_obj), "UPDATE");
+                Ok(Session::from(&db_obj))
+            }
+            None => Err(RbacError::NotFound(format!("Session not found {:?}", session)))
+        }
+    }
+
+    /// Retrieves session by id from the database if it has not expired yet
+    pub fn get(&self, _ctx: &SecurityContext, id: &str) -> Option<Session> {
+        match self._get(id) {
+            Some(session) => Some(Session::from(&session)).filter(|s| s.is_active()),
+            _ => None,
+        }
+    }
+
+    /// Returns sessions of the principal that have not expired yet
+    pub fn get_by_principal(&self, _ctx: &SecurityContext, principal_id: &str) -> Vec<Session> {
+        let now = Utc::now().naive_utc();
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_sessions::table
+                .filter(rbac_sessions::principal_id.eq(principal_id.to_string()))
+                .filter(rbac_sessions::expired_at.gt(now))
+                .load::<PSession>(&*connection) {
+                Ok(v) => v.iter().map(|s| Session::from(&s)).collect::<Vec<Session>>(),
+                _ => vec![],
+            }
+        } else {
+            vec![]
+        }
+    }
+
+    /// Deletes session by id from the database
+    pub fn delete(&self, ctx: &SecurityContext, id: &str) -> Result<usize, RbacError> {
+        match self._delete(id) {
+            Ok(n) => {
+                self.audit(ctx, format!("Closed session {}", id), "DELETE");
+                Ok(n)
+            },
+            Err(err) => Err(RbacError::Persistence(err.to_string())),
+        }
+    }
+
+    /// Deletes all expired sessions from the database
+    pub fn delete_expired(&self, ctx: &SecurityContext) -> Result<usize, RbacError> {
+        match self._delete_expired() {
+            Ok(n) => {
+                self.audit(ctx, format!("Deleted {} expired sessions", n), "DELETE");
+                Ok(n)
+            },
+            Err(err) => Err(RbacError::Persistence(err.to_string())),
+        }
+    }
+
+    ///////////////////////////////////// PRIVATE METHODS ////////////////////////////////
+    fn audit(&self, ctx: &SecurityContext, message: String, action: &str) {
+        let _ = self.audit_record_repository.create_with(message.as_str(), action, format!("{:?}", ctx).as_str(), ctx.principal_id.clone());
+        info!("{}", message);
+    }
+
+    /// Creates instance of session
+    fn _create(&self, session: &PSession) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::insert_into(rbac_sessions::table).values(session).execute(&*connection)
+    }
+
+    /// Updates previous instance of the session
+    fn _update(&self, session: &PSession) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::update(rbac_sessions::table.find(session.id.clone())).set(session).
+            execute(&*connection)
+    }
+
+    /// Deletes instance of the session by id from the database
+    fn _delete(&self, id: &str) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::delete(rbac_sessions::table.find(id.to_string())).execute(&*connection)
+    }
+
+    /// Deletes instances of the session that have expired from the database
+    fn _delete_expired(&self) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::delete(rbac_sessions::table.filter(rbac_sessions::expired_at.le(Utc::now().naive_utc()))).execute(&*connection)
+    }
+
+    /// Retrieves instance of the session by id from the database
+    fn _get(&self, id: &str) -> Option<PSession> {
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_sessions::table.find(id.to_string()).load::<PSession>(&*connection) {
+                Ok(v) => {
+                    if let Some(s) = v.first() {
+                        Some(s.clone())
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Removes all instances of the session from the database for testing
+    pub fn clear(&self) {
+        let connection = self.data_source.new_connection().unwrap();
+        let _ = diesel::delete(rbac_sessions::table).execute(&*connection);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use plexrbac::persistence::locator::RepositoryLocator;
+    use plexrbac::persistence::data_source::DefaultDataSource;
+    use plexrbac::domain::models::Session;
+    use plexrbac::common::SecurityContext;
+    use chrono::{NaiveDate, Utc, Duration};
+
+    #[test]
+    fn test_create_update() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_session_repository();
+        repo.clear();
+
+        let mut session = repo.create(&ctx, &Session::new("", "realm", "99", "11", vec!["1".into()], NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+        let loaded = repo.get(&ctx, session.id.as_str()).unwrap();
+        assert_eq!(format!("{:?}", session), format!("{:?}", loaded));
+        session.role_ids.push("2".into());
+        repo.update(&ctx, &session).unwrap();
+        assert_eq!(vec!["1".to_string(), "2".to_string()], repo.get(&ctx, session.id.as_str()).unwrap().role_ids);
+        assert_eq!(1, repo.get_by_principal(&ctx, "11").len());
+        repo.delete(&ctx, session.id.as_str()).unwrap();
+        assert!(repo.get(&ctx, session.id.as_str()).is_none());
+    }
+
+    #[test]
+    fn test_expired() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_session_repository();
+        repo.clear();
+
+        assert!(repo.create(&ctx, &Session::new("", "realm", "99", "11", vec![], Utc::now().naive_utc() - Duration::minutes(1))).is_err());
+        let mut session = repo.create(&ctx, &Session::new("", "realm", "99", "11", vec![], NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0))).unwrap();
+        session.expired_at = Utc::now().naive_utc() - Duration::minutes(1);
+        repo.update(&ctx, &session).unwrap();
+        assert!(repo.get(&ctx, session.id.as_str()).is_none());
+        assert_eq!(0, repo.get_by_principal(&ctx, "11").len());
+        assert_eq!(1, repo.delete_expired(&ctx).unwrap());
+    }
+}
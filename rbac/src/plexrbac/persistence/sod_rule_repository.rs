@@ -0,0 +1,231 @@
+//#![crate_name = "doc"]
+extern crate uuid as uuu;
+
+use diesel::prelude::*;
+use super::schema::rbac_sod_rules;
+use super::models::{PSodRule};
+use chrono::{Utc};
+use plexrbac::domain::models::SodRule;
+use plexrbac::common::SecurityContext;
+use plexrbac::common::RbacError;
+use self::uuu::Uuid;
+use std::collections::HashSet;
+
+//////////////////////////////////////////////////////////////////////////////////////////////
+/// SodRuleRepository defines methods for accessing and persisting separation-of-duty rules
+/// that restrict combination of roles within an organization
+///
+pub struct SodRuleRepository<'a> {
+    pub data_source: &'a dyn super::data_source::DataSource,
+    pub audit_record_repository: super::audit_record_repository::AuditRecordRepository<'a>,
+}
+
+impl<'a> SodRuleRepository<'a> {
+    /// Creates sod-rule
+    pub fn create(&self, ctx: &SecurityContext, rule: &SodRule) -> Result<SodRule, RbacError> {
+        self.validate(ctx, rule, "CREATE")?;
+        let mut db_obj = rule.to();
+        db_obj.id = Uuid::new_v4().to_hyphenated().to_string();
+        db_obj.created_at = Some(Utc::now().naive_utc());
+        db_obj.created_by = Some(ctx.principal_id.clone());
+        db_obj.updated_at = Some(Utc::now().naive_utc());
+        db_obj.updated_by = Some(ctx.principal_id.clone());
+        if let Err(err) = self._create(&db_obj) {
+            return Err(RbacError::Persistence(err.to_string()));
+        }
+        self.audit(ctx, format!("Adding new sod-rule {:?}", rule), "CREATE");
+        Ok(SodRule::from(&db_obj))
+    }
+
+    /// Updates the sod-rule
+    pub fn update(&self, ctx: &SecurityContext, rule: &SodRule) -> Result<SodRule, RbacError> {
+        self.validate(ctx, rule, "UPDATE")?;
+        match self._get(rule.organization_id.as_str(), rule.id.as_str()) {
+            Some(mut db_obj) => {
+                let updated = rule.to();
+                db_obj.name = updated.name.clone();
+                db_obj.description = updated.description.clone();
+                db_obj.role_ids = updated.role_ids.clone();
+                db_obj.cardinality = updated.cardinality;
+                db_obj.dynamic = updated.dynamic;
+                db_obj.updated_at = Some(Utc::now().naive_utc());
+                db_obj.updated_by = Some(ctx.principal_id.clone());
+                if let Err(err) = self._update(&db_obj) {
+                    return Err(RbacError::Persistence(err.to_string()));
+                }
+                self.audit(ctx, format!("Updated sod-rule {:?}", rule), "UPDATE");
+                Ok(SodRule::from(&db_obj))
+            }
+            None => Err(RbacError::NotFound(format!("SoD rule not found {:?}", rule)))
+        }
+    }
+
+    /// Retrieves sod-rule by id from the database
+    pub fn get(&self, _ctx: &SecurityContext, org_id: &str, rule_id: &str) -> Option<SodRule> {
+        match self._get(org_id, rule_id) {
+            Some(rule) => Some(SodRule::from(&rule)),
+            _ => None,
+        }
+    }
+
+    /// Returns all sod-rules of the organization
+    pub fn get_by_org(&self, _ctx: &SecurityContext, org_id: &str) -> Vec<SodRule> {
+        self._get_by_org(org_id).iter().map(|r| SodRule::from(&r)).collect::<Vec<SodRule>>()
+    }
+
+    /// Returns static or dynamic sod-rules of the organization
+    pub fn get_by_org_and_type(&self, org_id: &str, dynamic: bool) -> Vec<SodRule> {
+        self._get_by_org(org_id).iter().filter(|r| r.dynamic == dynamic).map(|r| SodRule::from(&r)).collect::<Vec<SodRule>>()
+    }
+
+    /// Deletes sod-rule by id from the database
+    pub fn delete(&self, ctx: &SecurityContext, org_id: &str, id: &str) -> Result<usize, RbacError> {
+        match self._delete(org_id, id) {
+            Ok(n) => {
+                self.audit(ctx, format!("Deleted sod-rule {:?}", id), "DELETE");
+                Ok(n)
+            },
+            Err(err) => Err(RbacError::Persistence(err.to_string())),
+        }
+    }
+
+    ///////////////////////////////////// PRIVATE METHODS ////////////////////////////////
+    fn audit(&self, ctx: &SecurityContext, message: String, action: &str) {
+        let _ = self.audit_record_repository.create_with(message.as_str(), action, format!("{:?}", ctx).as_str(), ctx.principal_id.clone());
+        info!("{}", message);
+    }
+
+    /// Verifies that cardinality of the rule can be reached by its roles
+    fn validate(&self, ctx: &SecurityContext, rule: &SodRule, action: &str) -> Result<(), RbacError> {
+        // duplicate role-ids would be counted more than once when checking the rule
+        let role_ids = rule.role_ids.iter().collect::<HashSet<&String>>();
+        if role_ids.len() != rule.role_ids.len() {
+            self.audit(ctx, format!("Duplicate roles of sod-rule {:?}", rule), action);
+            return Err(RbacError::Custom(format!("Roles of sod-rule {} must be distinct", rule.name)));
+        }
+        if rule.cardinality < 2 || rule.cardinality as usize > rule.role_ids.len() {
+            self.audit(ctx, format!("Invalid cardinality of sod-rule {:?}", rule), action);
+            return Err(RbacError::Custom(format!("Cardinality {} of sod-rule {} must be between 2 and number of roles", rule.cardinality, rule.name)));
+        }
+        Ok(())
+    }
+
+    /// Creates instance of sod-rule
+    fn _create(&self, rule: &PSodRule) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::insert_into(rbac_sod_rules::table).values(rule).execute(&*connection)
+    }
+
+    /// Updates previous instance of the sod-rule
+    fn _update(&self, rule: &PSodRule) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::update(rbac_sod_rules::table.find(rule.id.clone())).set(rule).
+            execute(&*connection)
+    }
+
+    /// Deletes instance of the sod-rule by id from the database
+    fn _delete(&self, org_id: &str, id: &str) -> Result<usize, diesel::result::Error> {
+        let connection = self.data_source.new_connection()?;
+        diesel::delete(rbac_sod_rules::table
+                .filter(rbac_sod_rules::organization_id.eq(org_id.to_string()))
+                .filter(rbac_sod_rules::id.eq(id.to_string())))
+                .execute(&*connection)
+    }
+
+    /// Retrieves instance of the sod-rule by id from the database
+    fn _get(&self, org_id: &str, id: &str) -> Option<PSodRule> {
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_sod_rules::table
+                .filter(rbac_sod_rules::organization_id.eq(org_id.to_string()))
+                .filter(rbac_sod_rules::id.eq(id.to_string()))
+                .load::<PSodRule>(&*connection) {
+                    Ok(v) => {
+                        if let Some(c) = v.first() {
+                            Some(c.clone())
+                        } else {
+                            None
+                        }
+                    }
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Retrieves sod-rules by organization-id
+    fn _get_by_org(&self, organization_id: &str) -> Vec<PSodRule> {
+        if let Ok(connection) = self.data_source.new_connection() {
+            match rbac_sod_rules::table
+                .filter(rbac_sod_rules::organization_id.eq(organization_id.to_string()))
+                .load::<PSodRule>(&*connection) {
+                Ok(v) => v,
+                _ => vec![],
+            }
+        } else {
+            vec![]
+        }
+    }
+
+    /// Removes all instances of the sod-rule from the database for testing
+    pub fn clear(&self) {
+        let connection = self.data_source.new_connection().unwrap();
+        let _ = diesel::delete(rbac_sod_rules::table).execute(&*connection);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use plexrbac::persistence::locator::RepositoryLocator;
+    use plexrbac::persistence::data_source::DefaultDataSource;
+    use plexrbac::domain::models::SodRule;
+    use plexrbac::common::SecurityContext;
+
+    #[test]
+    fn test_create() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_sod_rule_repository();
+        repo.clear();
+
+        let rule = repo.create(&ctx, &SodRule::new("", "realm", "99", "teller-manager", None, vec!["1".into(), "2".into()], 2, false)).unwrap();
+        let loaded = repo.get(&ctx, "99", rule.id.as_str()).unwrap();
+        assert_eq!(format!("{:?}", rule), format!("{:?}", loaded));
+        assert_eq!(vec!["1".to_string(), "2".to_string()], loaded.role_ids);
+    }
+
+    #[test]
+    fn test_create_with_invalid_cardinality() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_sod_rule_repository();
+
+        assert!(repo.create(&ctx, &SodRule::new("", "realm", "99", "single", None, vec!["1".into()], 1, false)).is_err());
+        assert!(repo.create(&ctx, &SodRule::new("", "realm", "99", "too-many", None, vec!["1".into(), "2".into()], 3, false)).is_err());
+        assert!(repo.create(&ctx, &SodRule::new("", "realm", "99", "negative", None, vec!["1".into(), "2".into()], -1, false)).is_err());
+        assert!(repo.create(&ctx, &SodRule::new("", "realm", "99", "duplicate", None, vec!["1".into(), "1".into()], 2, false)).is_err());
+    }
+
+    #[test]
+    fn test_get_by_org_and_type() {
+        let ctx = SecurityContext::new("myorg", "myid");
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let repo = locator.new_sod_rule_repository();
+        repo.clear();
+
+        let _ = repo.create(&ctx, &SodRule::new("", "realm", "99", "static", None, vec!["1".into(), "2".into()], 2, false)).unwrap();
+        let mut dynamic = repo.create(&ctx, &SodRule::new("", "realm", "99", "dynamic", None, vec!["3".into(), "4".into()], 2, true)).unwrap();
+        assert_eq!(2, repo.get_by_org(&ctx, "99").len());
+        assert_eq!(1, repo.get_by_org_and_type("99", true).len());
+
+        dynamic.dynamic = false;
+        repo.update(&ctx, &dynamic).unwrap();
+        assert_eq!(0, repo.get_by_org_and_type("99", true).len());
+        repo.delete(&ctx, "99", dynamic.id.as_str()).unwrap();
+        assert_eq!(1, repo.get_by_org(&ctx, "99").len());
+    }
+}
@@ -1,12 +1,17 @@
 //#![crate_name = "doc"]
     
 use plexrbac::security::request::PermissionRequest;
-use plexrbac::security::response::PermissionResponse;
+use plexrbac::security::response::{PermissionResponse, PermissionSimulationResponse};
+use plexrbac::security::trace::*;
+use plexrbac::domain::models::{Attribute, ClaimClaimable, ClaimResource, Principal, PolicyMutation, SodRule};
 use plexrbac::persistence::manager::PersistenceManager;
 use plexrbac::utils::text;
 use plexrbac::utils::evaluator::*;
 use plexrbac::common::RbacError;
+use plexrbac::common::ValueWrapper;
+use plexrbac::common::CombiningAlgorithm;
 use log::{info, warn};
+use std::collections::HashSet;
 
 ////////////////////////////////////////////////////////////////////////////////
 /// SecurityManager checks access
@@ -22,39 +27,337 @@ impl <'a> SecurityManager<'a> {
         }
     }
 
+    /// Checks access for given permission request
     pub fn check(&self, request: &PermissionRequest) -> Result<PermissionResponse, RbacError> {
-        if let Some(principal) = self.persistence_manager.get_principal(&request.context, request.context.realm_id.as_str(), request.context.principal_id.as_str()) {
+        if let Some(principal) = self.get_principal(request)? {
+            let request = &self.with_attributes(request, &principal)?;
+            self.check_dynamic_sod(request, &principal)?;
             let claim_resources = self.persistence_manager.get_resources_by_claims(&request.context, request.context.realm_id.as_str(), &principal, request.resource_name.clone(), request.resource_scope.clone());
-            let mut claim_resources_str  = String::from("");
-            for cr in claim_resources {
-                claim_resources_str.push_str(format!("\t{}     {}     {}\n", cr.claim.action, cr.constraints, cr.resource.resource_name).as_str());
-                if  text::regex_find(cr.claim.action.as_str(), request.action.as_str()) {
-                    if cr.constraints.len() > 0 {
-                        match evaluate(cr.constraints.as_str(), &request.context.properties) {
-                            Ok(ok) => {
-                                if ok {
-                                    info!("GRANTED PERMISSION {:?} -- {:?}", request, cr.claim);
-                                    return Ok(PermissionResponse::from(cr.claim.effect()));
-                                } else {
-                                    //info!(">>>>>>>>> EVALUATED FALSE for {} -- {:?}\n{:?}", cr.constraints.as_str(), cr, request);
-                                }
-                            },
-                            Err(err) => return Err(RbacError::Evaluation(err.to_string())),
-                        }
-                    } else {
-                        return Ok(PermissionResponse::from(cr.claim.effect()));
-                    }
+            let result = self.evaluate_claims(request, &claim_resources, &self.get_combining_algorithm(request)?, None);
+            self.tag_break_glass(request, &principal, &result);
+            result
+        } else {
+            Err(RbacError::Evaluation(format!("Could not find principal data for {:?}", request)))
+        }
+    }
+
+    /// Checks access for multiple permission requests of the same principal, the principal and
+    /// its claims are loaded only once and a decision is returned for each request. All requests
+    /// must share realm, principal, session and organization of the first request otherwise the
+    /// whole batch is rejected
+    pub fn check_many(&self, requests: &Vec<PermissionRequest>) -> Vec<Result<PermissionResponse, RbacError>> {
+        let first = match requests.first() {
+            Some(request) => request.clone(),
+            None => return vec![],
+        };
+        if let Some(request) = requests.iter().find(|request| request.context.realm_id != first.context.realm_id || request.context.principal_id != first.context.principal_id || request.session_id != first.session_id || request.organization_id != first.organization_id) {
+            let err = RbacError::Security(format!("Request {:?} does not belong to realm {}, principal {}, session {:?} and organization {:?} of the batch", request, first.context.realm_id, first.context.principal_id, first.session_id, first.organization_id));
+            return requests.iter().map(|_| Err(err.clone())).collect();
+        }
+        let principal = match self.get_principal(&first) {
+            Ok(principal) => principal,
+            Err(err) => return requests.iter().map(|_| Err(err.clone())).collect(),
+        };
+        if let Some(principal) = principal {
+            let resource_scopes = requests.iter().map(|request| (request.resource_name.clone(), request.resource_scope.clone())).collect::<Vec<(String, String)>>();
+            let all_claim_resources = self.persistence_manager.get_resources_by_claims_for(&first.context, first.context.realm_id.as_str(), &principal, &resource_scopes);
+            let algorithm = match self.get_combining_algorithm(&first) {
+                Ok(algorithm) => algorithm,
+                Err(err) => return requests.iter().map(|_| Err(err.clone())).collect(),
+            };
+            let mut result = vec![];
+            for (request, claim_resources) in requests.iter().zip(all_claim_resources.iter()) {
+                let request = &match self.with_attributes(request, &principal) {
+                    Ok(request) => request,
+                    Err(err) => {
+                        result.push(Err(err));
+                        continue;
+                    },
+                };
+                if let Err(err) = self.check_dynamic_sod(request, &principal) {
+                    result.push(Err(err));
+                } else {
+                    let decision = self.evaluate_claims(request, claim_resources, &algorithm, None);
+                    self.tag_break_glass(request, &principal, &decision);
+                    result.push(decision);
                 }
             }
+            result
+        } else {
+            requests.iter().map(|request| Err(RbacError::Evaluation(format!("Could not find principal data for {:?}", request)))).collect()
+        }
+    }
+
+    /// Explains how access for given permission request is decided, i.e., returns roles,
+    /// license-policy and claims that were considered along with result of each claim
+    pub fn explain(&self, request: &PermissionRequest) -> Result<PermissionTrace, RbacError> {
+        if let Some(principal) = self.get_principal(request)? {
+            let request = &self.with_attributes(request, &principal)?;
+            let mut trace = PermissionTrace::new(request);
+            for (role, source) in self.persistence_manager.get_role_sources(&principal) {
+                trace.roles.push(RoleTrace::new(&role, source.to_string().as_str()));
+            }
+            for (_, group) in &principal.groups {
+                trace.groups.push(group.name.clone());
+            }
+
+            match self.persistence_manager.get_license_claim_by_scope(&request.context, request.context.realm_id.as_str(), principal.organization_id.as_str(), request.resource_name.as_str(), request.resource_scope.as_str()) {
+                Some(ClaimClaimable::LicensePolicy(claim, _, _, _)) => {
+                    trace.license_matched = true;
+                    trace.license_reason = format!("license policy of organization {} allows claim {} for scope '{}'", principal.organization_id, claim.id, request.resource_scope);
+                },
+                Some(_) => {
+                    trace.license_matched = true;
+                    trace.license_reason = format!("organization {} has no license policy so all claims of realm {} are allowed", principal.organization_id, request.context.realm_id);
+                },
+                None => {
+                    trace.license_matched = false;
+                    trace.license_reason = format!("license policy of organization {} has no claim for scope '{}'", principal.organization_id, request.resource_scope);
+                },
+            };
 
-            warn!("DENIED PERMISSION {:?} because no matching claim found -- available claims: {}!!!", request, claim_resources_str);
-            Err(RbacError::Evaluation(format!("No matching claim found for {:?} -- available claims: {}!!!", request, claim_resources_str)))
+            let claim_resources = self.persistence_manager.get_resources_by_claims(&request.context, request.context.realm_id.as_str(), &principal, request.resource_name.clone(), request.resource_scope.clone());
+            let sod_result = self.check_dynamic_sod(request, &principal);
+            let result = self.evaluate_claims(request, &claim_resources, &self.get_combining_algorithm(request)?, Some(&mut trace));
+            // explain doesn't grant access so it's not recorded as break-glass access
+            trace.break_glass = Self::break_glass_roles(&principal).len() > 0;
+            trace.decision = match (&sod_result, result) {
+                (Ok(_), Ok(ref resp)) => resp.clone(),
+                _ => PermissionResponse::Deny,
+            };
+            trace.reason = if let Err(ref err) = sod_result {
+                err.to_string()
+            } else if !trace.license_matched {
+                trace.license_reason.clone()
+            } else if let Some(ct) = trace.claims.iter().find(|ct| ct.outcome == ClaimOutcome::Matched && PermissionResponse::from(ct.effect.clone()) == trace.decision) {
+                format!("claim {} for {} on {} matched action {}", ct.claim_id, ct.action, ct.resource_name, request.action)
+            } else if let Some(ct) = trace.claims.iter().find(|ct| ct.outcome != ClaimOutcome::Skipped && ct.outcome != ClaimOutcome::ActionMismatch && ct.outcome != ClaimOutcome::ConstraintsFalse) {
+                format!("failed to evaluate constraints '{}' of claim {} -- {:?}", ct.constraints, ct.claim_id, ct.outcome)
+            } else {
+                format!("no matching claim found for action {} on {} with scope '{}'", request.action, request.resource_name, request.resource_scope)
+            };
+            Ok(trace)
         } else {
-            Err(RbacError::Evaluation(format!("Could not find principal data for {:?}", request)))
+            Err(RbacError::NotFound(format!("Could not find principal data for {:?}", request)))
         }
     }
-}
 
+    /// Simulates given permission requests against current policy and against current policy
+    /// with proposed mutations applied as an overlay, and returns decisions before and after
+    /// the mutations for each request. The mutations are not saved, and sessions of the requests
+    /// are ignored so that all assigned roles are considered. The mutations are verified using
+    /// the same rules as saving them, e.g. separation-of-duty rules, and if they would be
+    /// rejected then each request is denied after the mutations along with the rejection.
+    pub fn simulate(&self, mutations: &Vec<PolicyMutation>, requests: &Vec<PermissionRequest>) -> Vec<PermissionSimulationResponse> {
+        let rejection = match requests.first() {
+            Some(first) => self.persistence_manager.validate_mutations(&first.context, mutations).err(),
+            None => None,
+        };
+        let mut result = vec![];
+        for request in requests {
+            let before = self.decide(request, self.persistence_manager.get_principal(&request.context, request.context.realm_id.as_str(), request.context.principal_id.as_str()));
+            let after = match rejection {
+                Some(ref err) => Err(err.clone()),
+                None => self.decide(request, self.persistence_manager.get_principal_with_mutations(&request.context, request.context.realm_id.as_str(), request.context.principal_id.as_str(), mutations)),
+            };
+            result.push(PermissionSimulationResponse::new(request.action.as_str(), request.resource_name.as_str(), request.resource_scope.as_str(), before, after));
+        }
+        if let Some(first) = requests.first() {
+            let _ = self.persistence_manager.audit_record_repository.create_with(format!("Simulated {} requests with mutations {:?}", requests.len(), mutations).as_str(), "SIMULATE", format!("{:?}", first.context).as_str(), first.context.principal_id.clone());
+        }
+        result
+    }
+
+    ////////////////////////////////// PRIVATE METHODS //////////////////////////////
+    /// Decides permission request for given principal without recording audit records - used
+    /// for simulation
+    fn decide(&self, request: &PermissionRequest, principal: Option<Principal>) -> Result<PermissionResponse, RbacError> {
+        if let Some(principal) = principal {
+            let request = &self.with_attributes(request, &principal)?;
+            if let Some(rule) = self.find_dynamic_sod_violation(request, &principal) {
+                return Err(RbacError::SodViolation(format!("Principal {} activated roles that violate separation-of-duty rule {}", principal.id, rule.name)));
+            }
+            let claim_resources = self.persistence_manager.get_resources_by_claims(&request.context, request.context.realm_id.as_str(), &principal, request.resource_name.clone(), request.resource_scope.clone());
+            self.evaluate_claims(request, &claim_resources, &self.get_combining_algorithm(request)?, None)
+        } else {
+            Err(RbacError::NotFound(format!("Could not find principal data for {:?}", request)))
+        }
+    }
+
+    /// Returns principal of the request, where roles are limited to roles activated by the
+    /// session if the request carries a session-id. If the request selects an organization
+    /// then the principal is resolved against that organization, which requires an active
+    /// guest membership when it's not home organization of the principal
+    fn get_principal(&self, request: &PermissionRequest) -> Result<Option<Principal>, RbacError> {
+        let realm_id = request.context.realm_id.as_str();
+        let principal = match request.organization_id {
+            Some(ref org_id) => Some(self.persistence_manager.get_principal_in_org(&request.context, realm_id, request.context.principal_id.as_str(), org_id.as_str())?),
+            None => self.persistence_manager.get_principal(&request.context, realm_id, request.context.principal_id.as_str()),
+        };
+        match (principal, request.session_id.as_ref()) {
+            (Some(principal), Some(session_id)) => self.persistence_manager.apply_session(&request.context, realm_id, principal, session_id.as_str()).map(Some),
+            (None, Some(_)) => Err(RbacError::NotFound(format!("Principal not found {}", request.context.principal_id))),
+            (principal, None) => Ok(principal),
+        }
+    }
+
+    /// Returns copy of the request where attributes of the principal are added to the context
+    /// under reserved `principal.` namespace and attributes of the resource instance named by
+    /// the request are added under reserved `resource.` namespace. Properties of the request
+    /// within these namespaces are replaced so that callers cannot spoof the attributes
+    fn with_attributes(&self, request: &PermissionRequest, principal: &Principal) -> Result<PermissionRequest, RbacError> {
+        let mut request = request.clone();
+        // nested maps named principal or resource would be flattened into the reserved namespaces
+        request.context.properties.retain(|name, _| !name.starts_with(Attribute::PRINCIPAL_PREFIX) && !name.starts_with(Attribute::RESOURCE_PREFIX) &&
+                                          name != "principal" && name != "resource");
+        for (name, value) in &principal.attributes {
+            request.context.add(format!("{}{}", Attribute::PRINCIPAL_PREFIX, name).as_str(), value.clone());
+        }
+        request.context.add(format!("{}id", Attribute::PRINCIPAL_PREFIX).as_str(), ValueWrapper::String(principal.id.clone()));
+        if let Some(ref_id) = request.resource_ref_id.clone() {
+            let attributes = self.persistence_manager.get_resource_instance_attributes(&request.context, request.context.realm_id.as_str(), principal.organization_id.as_str(), request.resource_name.as_str(), ref_id.as_str())?;
+            for (name, value) in attributes {
+                request.context.add(format!("{}{}", Attribute::RESOURCE_PREFIX, name).as_str(), value);
+            }
+        }
+        Ok(request)
+    }
+
+    /// Verifies that roles activated by the request, i.e., roles without constraints or with
+    /// constraints that evaluate to true for the request context, don't violate any dynamic
+    /// separation-of-duty rule of the organization
+    fn check_dynamic_sod(&self, request: &PermissionRequest, principal: &Principal) -> Result<(), RbacError> {
+        if let Some(rule) = self.find_dynamic_sod_violation(request, principal) {
+            let message = format!("Principal {} activated roles that violate separation-of-duty rule {}", principal.id, rule.name);
+            let _ = self.persistence_manager.audit_record_repository.create_with(format!("{} -- {:?} {:?}", message, rule, request).as_str(), "CHECK", format!("{:?}", request.context).as_str(), request.context.principal_id.clone());
+            warn!("DENIED PERMISSION {:?} -- {}", request, message);
+            return Err(RbacError::SodViolation(message));
+        }
+        Ok(())
+    }
+
+    /// Returns dynamic separation-of-duty rule that is violated by roles activated by the request
+    fn find_dynamic_sod_violation(&self, request: &PermissionRequest, principal: &Principal) -> Option<SodRule> {
+        let rules = self.persistence_manager.sod_rule_repository.get_by_org_and_type(principal.organization_id.as_str(), true);
+        if rules.len() == 0 {
+            return None;
+        }
+        let active = principal.roles.values().filter(|role| {
+            match role.constraints {
+                Some(ref constraints) if constraints.len() > 0 => evaluate(constraints.as_str(), &request.context.properties).unwrap_or(false),
+                _ => true,
+            }
+        }).map(|role| role.id.clone()).collect::<HashSet<String>>();
+        rules.into_iter().find(|rule| rule.violated_by(&active))
+    }
+
+    /// Tags checks that are made while the principal holds break-glass roles by recording them
+    /// as high-severity audit records, returns true if the check was made under break-glass
+    fn tag_break_glass(&self, request: &PermissionRequest, principal: &Principal, result: &Result<PermissionResponse, RbacError>) -> bool {
+        let roles = Self::break_glass_roles(principal);
+        if roles.len() == 0 {
+            return false;
+        }
+        let message = format!("Permission check under break-glass roles {:?} -- {:?} {:?}", roles, request, result);
+        let _ = self.persistence_manager.audit_record_repository.create_with_severity(message.as_str(), "BREAK_GLASS", format!("{:?}", request.context).as_str(), request.context.principal_id.clone(), "HIGH");
+        warn!("{}", message);
+        true
+    }
+
+    /// Returns names of break-glass roles held by the principal
+    fn break_glass_roles(principal: &Principal) -> Vec<String> {
+        principal.roles.values().filter(|role| role.break_glass).map(|role| role.name.clone()).collect::<Vec<String>>()
+    }
+
+    fn get_combining_algorithm(&self, request: &PermissionRequest) -> Result<CombiningAlgorithm, RbacError> {
+        match self.persistence_manager.realm_repository.get(&request.context, request.context.realm_id.as_str()) {
+            Some(realm) => realm.combining_algorithm(),
+            None => Ok(CombiningAlgorithm::FirstApplicable),
+        }
+    }
+
+    fn evaluate_claims(&self, request: &PermissionRequest, claim_resources: &Vec<ClaimResource>, algorithm: &CombiningAlgorithm, mut trace: Option<&mut PermissionTrace>) -> Result<PermissionResponse, RbacError> {
+        let mut ordered = claim_resources.iter().collect::<Vec<&ClaimResource>>();
+        if *algorithm == CombiningAlgorithm::Priority {
+            // stable sort so that claims with same priority keep their order
+            ordered.sort_by(|a, b| b.claim.priority().cmp(&a.claim.priority()));
+        }
+        let overriding = match algorithm {
+            CombiningAlgorithm::DenyOverrides => Some(PermissionResponse::Deny),
+            CombiningAlgorithm::PermitOverrides => Some(PermissionResponse::Allow),
+            _ => None,
+        };
+        if let Some(ref mut t) = trace {
+            t.combining_algorithm = algorithm.to_string();
+        }
+
+        let mut claim_resources_str  = String::from("");
+        let mut decision: Option<Result<PermissionResponse, RbacError>> = None;
+        let mut fallback: Option<PermissionResponse> = None;
+        for cr in ordered {
+            if decision.is_some() && trace.is_none() {
+                break;
+            }
+            claim_resources_str.push_str(format!("\t{}     {}     {}\n", cr.claim.action, cr.constraints, cr.resource.resource_name).as_str());
+            let mut claim_trace = ClaimTrace::new(cr);
+            claim_trace.action_matched = text::regex_find(cr.claim.action.as_str(), request.action.as_str());
+            let mut applicable = false;
+            if decision.is_some() {
+                // remaining claims are only recorded for the trace
+                claim_trace.outcome = ClaimOutcome::Skipped;
+            } else if !claim_trace.action_matched {
+                claim_trace.outcome = ClaimOutcome::ActionMismatch;
+            } else if cr.constraints.len() > 0 {
+                match evaluate(cr.constraints.as_str(), &request.context.properties) {
+                    Ok(ok) => {
+                        claim_trace.constraints_result = Some(ok);
+                        if ok {
+                            applicable = true;
+                        } else {
+                            claim_trace.outcome = ClaimOutcome::ConstraintsFalse;
+                        }
+                    },
+                    Err(err) => {
+                        claim_trace.outcome = ClaimOutcome::EvaluationError(err.to_string());
+                        decision = Some(Err(RbacError::Evaluation(err.to_string())));
+                    },
+                }
+            } else {
+                applicable = true;
+            }
+
+            if applicable {
+                claim_trace.outcome = ClaimOutcome::Matched;
+                let effect = PermissionResponse::from(cr.claim.effect());
+                match overriding {
+                    Some(ref winner) if *winner != effect => {
+                        // keep looking for overriding effect
+                        if fallback.is_none() {
+                            fallback = Some(effect);
+                        }
+                    },
+                    _ => {
+                        info!("{:?} PERMISSION {:?} -- {:?} using {}", effect, request, cr.claim, algorithm);
+                        decision = Some(Ok(effect));
+                    },
+                }
+            }
+            if let Some(ref mut t) = trace {
+                t.claims.push(claim_trace);
+            }
+        }
+
+        if let Some(result) = decision {
+            return result;
+        }
+        if let Some(effect) = fallback {
+            info!("{:?} PERMISSION {:?} using {}", effect, request, algorithm);
+            return Ok(effect);
+        }
+        warn!("DENIED PERMISSION {:?} because no matching claim found -- available claims: {}!!!", request, claim_resources_str);
+        Err(RbacError::Evaluation(format!("No matching claim found for {:?} -- available claims: {}!!!", request, claim_resources_str)))
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -62,9 +365,13 @@ mod tests {
     use plexrbac::persistence::data_source::DefaultDataSource;
     use plexrbac::common::SecurityContext;
     use plexrbac::security::manager::SecurityManager;
-    use plexrbac::security::request::PermissionRequest;
+    use plexrbac::security::request::{PermissionRequest, PermissionCheck};
     use plexrbac::security::response::PermissionResponse;
+    use plexrbac::security::trace::ClaimOutcome;
+    use plexrbac::domain::models::{Attribute, Claim, GuestMembership, PolicyMutation, ResourceInstance};
     use plexrbac::common::*;
+    use chrono::{NaiveDate, Utc, Duration};
+    use std::collections::HashMap;
 
     #[test]
     fn test_evaluate() {
@@ -107,4 +414,445 @@ mod tests {
         req.context.add("employeeRegion", ValueWrapper::String("Midwest".to_string()));
         assert_eq!(PermissionResponse::Allow, sm.check(&req).unwrap());
     }
+
+    #[test]
+    fn test_explain() {
+        // Initialize context and repository
+        let ctx = SecurityContext::new("0".into(), "0".into());
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+
+        let realm = pm.new_realm_with(&ctx, "banking").unwrap();
+        let org = pm.new_org_with(&ctx, "bank-of-flakes").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let employee = pm.new_role_with(&ctx, &realm, &org, "Employee").unwrap();
+        let teller = pm.new_role_with_parent(&ctx, &realm, &org, &employee, "Teller").unwrap();
+        let deposit_account = pm.new_resource_with(&ctx, &realm, "DepositAccount").unwrap();
+        let cd_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "(CREATE|DELETE)").unwrap();
+        let ru_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "(READ|UPDATE)").unwrap();
+        pm.map_principal_to_role(&ctx, &tom, &teller).unwrap();
+        pm.map_role_to_claim(&ctx, &employee, &cd_deposit, "U.S.", "").unwrap();
+        pm.map_role_to_claim(&ctx, &teller, &ru_deposit, "U.S.", r#"employeeRegion == "Midwest""#).unwrap();
+
+        let sm = SecurityManager::new(pm);
+        let mut req = PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::READ, "DepositAccount", "U.S.");
+        req.context.add("employeeRegion", ValueWrapper::String("Midwest".to_string()));
+        let trace = sm.explain(&req).unwrap();
+        assert_eq!(PermissionResponse::Allow, trace.decision);
+        assert_eq!(true, trace.license_matched);
+        assert_eq!(2, trace.roles.len());
+        assert!(trace.roles.iter().any(|r| r.name == "Teller" && r.source == "direct"));
+        assert!(trace.roles.iter().any(|r| r.name == "Employee" && r.source == "inherited from Teller"));
+        assert!(trace.claims.iter().any(|c| c.claim_id == cd_deposit.id && !c.action_matched));
+        assert!(trace.claims.iter().any(|c| c.claim_id == ru_deposit.id && c.outcome == ClaimOutcome::Matched && c.constraints_result == Some(true)));
+
+        req.context.add("employeeRegion", ValueWrapper::String("Northeast".to_string()));
+        let trace = sm.explain(&req).unwrap();
+        assert_eq!(PermissionResponse::Deny, trace.decision);
+        assert!(trace.claims.iter().any(|c| c.claim_id == ru_deposit.id && c.outcome == ClaimOutcome::ConstraintsFalse && c.constraints_result == Some(false)));
+    }
+
+    #[test]
+    fn test_dynamic_separation_of_duty() {
+        let ctx = SecurityContext::new("0".into(), "0".into());
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+
+        let realm = pm.new_realm_with(&ctx, "banking").unwrap();
+        let org = pm.new_org_with(&ctx, "bank-of-flakes").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let submitter = pm.new_role_with(&ctx, &realm, &org, "Submitter").unwrap();
+        let approver = pm.new_role_with(&ctx, &realm, &org, "Approver").unwrap();
+        let expense_report = pm.new_resource_with(&ctx, &realm, "ExpenseReport").unwrap();
+        let submit_report = pm.new_claim_with(&ctx, &realm, &expense_report, "SUBMIT").unwrap();
+        let approve_report = pm.new_claim_with(&ctx, &realm, &expense_report, "APPROVE").unwrap();
+        pm.map_role_to_claim(&ctx, &submitter, &submit_report, "", "").unwrap();
+        pm.map_role_to_claim(&ctx, &approver, &approve_report, "", "").unwrap();
+        // dynamic rule allows tom to hold both roles as long as they are not activated together
+        pm.new_sod_rule_with(&ctx, &realm, &org, "submitter-approver", vec![&submitter, &approver], 2, true).unwrap();
+        pm.role_roleable_repository.add_principal_to_role(&ctx, submitter.id.as_str(), tom.id.as_str(), r#"mode != "approval""#, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).unwrap();
+        pm.role_roleable_repository.add_principal_to_role(&ctx, approver.id.as_str(), tom.id.as_str(), r#"mode != "submission""#, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0)).unwrap();
+
+        let sm = SecurityManager::new(pm);
+        let mut req = PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::SUBMIT, "ExpenseReport", "");
+        req.context.add("mode", ValueWrapper::String("submission".to_string()));
+        assert_eq!(PermissionResponse::Allow, sm.check(&req).unwrap());
+
+        let mut req = PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::APPROVE, "ExpenseReport", "");
+        req.context.add("mode", ValueWrapper::String("approval".to_string()));
+        assert_eq!(PermissionResponse::Allow, sm.check(&req).unwrap());
+
+        // both roles are activated by the same request
+        req.context.add("mode", ValueWrapper::String("review".to_string()));
+        match sm.check(&req) {
+            Err(RbacError::SodViolation(_)) => (),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(sm.check_many(&vec![req.clone()])[0].is_err());
+        assert_eq!(PermissionResponse::Deny, sm.explain(&req).unwrap().decision);
+        assert!(sm.persistence_manager.audit_record_repository.latest(100).iter().any(|a| a.message.contains("violate separation-of-duty rule submitter-approver")));
+    }
+
+    #[test]
+    fn test_combining_algorithms() {
+        // Initialize context and repository
+        let ctx = SecurityContext::new("0".into(), "0".into());
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+
+        let mut realm = pm.new_realm_with(&ctx, "banking").unwrap();
+        let org = pm.new_org_with(&ctx, "bank-of-flakes").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let teller = pm.new_role_with(&ctx, &realm, &org, "Teller").unwrap();
+        let deposit_account = pm.new_resource_with(&ctx, &realm, "DepositAccount").unwrap();
+
+        // Allow and explicit deny for reading deposit account
+        let mut ru_deposit = Claim::new("", realm.id.as_str(), deposit_account.id.as_str(), "(READ|UPDATE)", "Allow", None);
+        ru_deposit.priority = Some(1);
+        let ru_deposit = pm.claim_repository.create(&ctx, &ru_deposit).unwrap();
+        let mut r_deposit = Claim::new("", realm.id.as_str(), deposit_account.id.as_str(), "READ", "Deny", None);
+        r_deposit.priority = Some(10);
+        let r_deposit = pm.claim_repository.create(&ctx, &r_deposit).unwrap();
+
+        pm.map_principal_to_role(&ctx, &tom, &teller).unwrap();
+        pm.map_role_to_claim(&ctx, &teller, &ru_deposit, "U.S.", "").unwrap();
+        pm.map_role_to_claim(&ctx, &teller, &r_deposit, "U.S.", "").unwrap();
+
+        let sm = SecurityManager::new(pm);
+        let read = PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::READ, "DepositAccount", "U.S.");
+        let update = PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::UPDATE, "DepositAccount", "U.S.");
+
+        realm.combining_algorithm = Some(CombiningAlgorithm::DenyOverrides.to_string());
+        sm.persistence_manager.realm_repository.update(&ctx, &realm).unwrap();
+        assert_eq!(PermissionResponse::Deny, sm.check(&read).unwrap());
+        assert_eq!(PermissionResponse::Allow, sm.check(&update).unwrap());
+
+        realm.combining_algorithm = Some(CombiningAlgorithm::PermitOverrides.to_string());
+        sm.persistence_manager.realm_repository.update(&ctx, &realm).unwrap();
+        assert_eq!(PermissionResponse::Allow, sm.check(&read).unwrap());
+
+        realm.combining_algorithm = Some(CombiningAlgorithm::Priority.to_string());
+        sm.persistence_manager.realm_repository.update(&ctx, &realm).unwrap();
+        assert_eq!(PermissionResponse::Deny, sm.check(&read).unwrap());
+        let trace = sm.explain(&read).unwrap();
+        assert_eq!("Priority", trace.combining_algorithm);
+        assert_eq!(PermissionResponse::Deny, trace.decision);
+    }
+
+    #[test]
+    fn test_check_many() {
+        // Initialize context and repository
+        let ctx = SecurityContext::new("0".into(), "0".into());
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+
+        let realm = pm.new_realm_with(&ctx, "banking").unwrap();
+        let org = pm.new_org_with(&ctx, "bank-of-flakes").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let teller = pm.new_role_with(&ctx, &realm, &org, "Teller").unwrap();
+        let deposit_account = pm.new_resource_with(&ctx, &realm, "DepositAccount").unwrap();
+        let loan_account = pm.new_resource_with(&ctx, &realm, "LoanAccount").unwrap();
+        let ru_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "(READ|UPDATE)").unwrap();
+        let r_loan = pm.new_claim_with(&ctx, &realm, &loan_account, "READ").unwrap();
+        pm.map_principal_to_role(&ctx, &tom, &teller).unwrap();
+        pm.map_role_to_claim(&ctx, &teller, &ru_deposit, "U.S.", r#"employeeRegion == "Midwest""#).unwrap();
+        pm.map_role_to_claim(&ctx, &teller, &r_loan, "U.S.", "").unwrap();
+
+        let sm = SecurityManager::new(pm);
+        let mut read_deposit = PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::READ, "DepositAccount", "U.S.");
+        read_deposit.context.add("employeeRegion", ValueWrapper::String("Midwest".to_string()));
+        let mut update_deposit = PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::UPDATE, "DepositAccount", "U.S.");
+        update_deposit.context.add("employeeRegion", ValueWrapper::String("Northeast".to_string()));
+        let read_loan = PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::READ, "LoanAccount", "U.S.");
+        let delete_loan = PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::DELETE, "LoanAccount", "U.S.");
+        let other_principal = PermissionRequest::new(realm.id.as_str(), "other", ActionType::READ, "LoanAccount", "U.S.");
+
+        let results = sm.check_many(&vec![read_deposit.clone(), update_deposit.clone(), read_loan.clone(), delete_loan.clone()]);
+        assert_eq!(4, results.len());
+        assert_eq!(Ok(PermissionResponse::Allow), results[0]);
+        assert!(results[1].is_err());
+        assert_eq!(Ok(PermissionResponse::Allow), results[2]);
+        assert!(results[3].is_err());
+        // batch with requests of other principal is rejected as a whole
+        let results = sm.check_many(&vec![read_deposit, read_loan, other_principal]);
+        assert_eq!(3, results.len());
+        assert!(results.iter().all(|result| match result { Err(RbacError::Security(_)) => true, _ => false }));
+        assert_eq!(0, sm.check_many(&vec![]).len());
+    }
+
+    #[test]
+    fn test_simulate() {
+        // Initialize context and repository
+        let ctx = SecurityContext::new("0".into(), "0".into());
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+
+        let realm = pm.new_realm_with(&ctx, "banking").unwrap();
+        let org = pm.new_org_with(&ctx, "bank-of-flakes").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let teller = pm.new_role_with(&ctx, &realm, &org, "Teller").unwrap();
+        let deposit_account = pm.new_resource_with(&ctx, &realm, "DepositAccount").unwrap();
+        let cd_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "(CREATE|DELETE)").unwrap();
+        let ru_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "(READ|UPDATE)").unwrap();
+        pm.map_role_to_claim(&ctx, &teller, &ru_deposit, "U.S.", "").unwrap();
+
+        let sm = SecurityManager::new(pm);
+        let read = PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::READ, "DepositAccount", "U.S.");
+        let update = PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::UPDATE, "DepositAccount", "U.S.");
+        let delete = PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::DELETE, "DepositAccount", "U.S.");
+        let mutations = vec![
+            PolicyMutation::AddPrincipalToRole{principal_id: tom.id.clone(), role_id: teller.id.clone(), constraints: "".into()},
+            PolicyMutation::ChangeClaimAction{claim_id: ru_deposit.id.clone(), action: "READ".into()},
+            PolicyMutation::MapPrincipalToClaim{principal_id: tom.id.clone(), claim_id: cd_deposit.id.clone(), scope: "U.S.".into(), constraints: "".into()},
+        ];
+
+        let results = sm.simulate(&mutations, &vec![read.clone(), update.clone(), delete.clone()]);
+        assert_eq!(3, results.len());
+        assert_eq!(PermissionResponse::Deny, results[0].before);
+        assert_eq!(PermissionResponse::Allow, results[0].after);
+        assert!(results[0].changed);
+        assert!(results[0].before_reason.is_some());
+        assert_eq!(PermissionResponse::Deny, results[1].after);
+        assert!(!results[1].changed);
+        assert_eq!(PermissionResponse::Allow, results[2].after);
+
+        // mutations are not saved
+        assert!(sm.check(&read).is_err());
+        assert_eq!(0, sm.persistence_manager.get_principal(&ctx, realm.id.as_str(), tom.id.as_str()).unwrap().roles.len());
+
+        // mutations that would be rejected by separation-of-duty rules are reported
+        let pm = &sm.persistence_manager;
+        let auditor = pm.new_role_with(&ctx, &realm, &org, "Auditor").unwrap();
+        pm.new_sod_rule_with(&ctx, &realm, &org, "teller-auditor", vec![&teller, &auditor], 2, false).unwrap();
+        let mutations = vec![
+            PolicyMutation::AddPrincipalToRole{principal_id: tom.id.clone(), role_id: teller.id.clone(), constraints: "".into()},
+            PolicyMutation::AddPrincipalToRole{principal_id: tom.id.clone(), role_id: auditor.id.clone(), constraints: "".into()},
+        ];
+        let results = sm.simulate(&mutations, &vec![read.clone()]);
+        assert_eq!(PermissionResponse::Deny, results[0].after);
+        assert!(results[0].after_reason.clone().unwrap().contains("teller-auditor"));
+    }
+
+    #[test]
+    fn test_guest_principals() {
+        // Initialize context and repository
+        let ctx = SecurityContext::new("0".into(), "0".into());
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+
+        let realm = pm.new_realm_with(&ctx, "invoicing").unwrap();
+        let vendor = pm.new_org_with(&ctx, "acme-consulting").unwrap();
+        let customer = pm.new_org_with(&ctx, "globex").unwrap();
+        let consultant = pm.new_principal_with(&ctx, &vendor, "consultant").unwrap();
+        let auditor = pm.new_role_with(&ctx, &realm, &customer, "Auditor").unwrap();
+        let invoice = pm.new_resource_with(&ctx, &realm, "Invoice").unwrap();
+        let read_invoice = pm.new_claim_with(&ctx, &realm, &invoice, "READ").unwrap();
+        let update_invoice = pm.new_claim_with(&ctx, &realm, &invoice, "UPDATE").unwrap();
+        pm.map_role_to_claim(&ctx, &auditor, &read_invoice, "", "").unwrap();
+        pm.map_role_to_claim(&ctx, &auditor, &update_invoice, "", "").unwrap();
+        // customer is only licensed to read invoices
+        let license = pm.new_license_policy(&ctx, &customer).unwrap();
+        pm.map_license_policy_to_claim(&ctx, &license, &read_invoice, "", "").unwrap();
+
+        // principal cannot be guest of its home organization
+        let home = GuestMembership::new("", vendor.id.as_str(), consultant.id.as_str(), None, Utc::now().naive_utc(), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0));
+        assert!(pm.add_guest(&ctx, &home).is_err());
+
+        let sm = SecurityManager::new(pm);
+        let mut read = PermissionRequest::new(realm.id.as_str(), consultant.id.as_str(), ActionType::READ, "Invoice", "");
+        read.organization_id = Some(customer.id.clone());
+        let mut update = PermissionRequest::new(realm.id.as_str(), consultant.id.as_str(), ActionType::UPDATE, "Invoice", "");
+        update.organization_id = Some(customer.id.clone());
+        assert!(sm.check(&read).is_err());
+
+        let membership = GuestMembership::new("", customer.id.as_str(), consultant.id.as_str(), Some("audit engagement".into()), Utc::now().naive_utc() - Duration::days(1), NaiveDate::from_ymd(2100, 1, 1).and_hms(0, 0, 0));
+        let membership = sm.persistence_manager.add_guest(&ctx, &membership).unwrap();
+        assert!(sm.persistence_manager.add_guest(&ctx, &membership).is_err());
+        sm.persistence_manager.map_principal_to_role(&ctx, &consultant, &auditor).unwrap();
+        assert_eq!(1, sm.persistence_manager.guest_membership_repository.get_by_org(&ctx, customer.id.as_str()).len());
+        assert_eq!(0, sm.persistence_manager.guest_membership_repository.get_by_org(&ctx, vendor.id.as_str()).len());
+
+        // roles of host organization are resolved against its license policy
+        assert_eq!(PermissionResponse::Allow, sm.check(&read).unwrap());
+        assert!(sm.check(&update).is_err());
+        // roles of host organization are not available within home organization
+        assert!(sm.check(&PermissionRequest::new(realm.id.as_str(), consultant.id.as_str(), ActionType::READ, "Invoice", "")).is_err());
+
+        // claims mapped directly to principal by home organization are not used within host organization
+        sm.persistence_manager.unmap_principal_from_role(&ctx, &consultant, &auditor).unwrap();
+        sm.persistence_manager.map_principal_to_claim(&ctx, &consultant, &read_invoice, "", "").unwrap();
+        assert_eq!(PermissionResponse::Allow, sm.check(&PermissionRequest::new(realm.id.as_str(), consultant.id.as_str(), ActionType::READ, "Invoice", "")).unwrap());
+        assert!(sm.check(&read).is_err());
+        // whereas claims mapped to guest membership are
+        sm.persistence_manager.map_guest_to_claim(&ctx, &membership, &read_invoice, "", "").unwrap();
+        assert_eq!(PermissionResponse::Allow, sm.check(&read).unwrap());
+
+        // revoking guest access
+        sm.persistence_manager.guest_membership_repository.delete(&ctx, customer.id.as_str(), membership.id.as_str()).unwrap();
+        assert!(sm.check(&read).is_err());
+    }
+
+    #[test]
+    fn test_principal_attributes() {
+        // Initialize context and repository
+        let ctx = SecurityContext::new("0".into(), "0".into());
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+
+        let realm = pm.new_realm_with(&ctx, "banking").unwrap();
+        let org = pm.new_org_with(&ctx, "bank-of-flakes").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let cassy = pm.new_principal_with(&ctx, &org, "cassy").unwrap();
+        let tellers = pm.new_group_with(&ctx, &org, "Tellers").unwrap();
+        let teller = pm.new_role_with(&ctx, &realm, &org, "Teller").unwrap();
+        let deposit_account = pm.new_resource_with(&ctx, &realm, "DepositAccount").unwrap();
+        let ru_deposit = pm.new_claim_with(&ctx, &realm, &deposit_account, "(READ|UPDATE)").unwrap();
+        pm.map_principal_to_group(&ctx, &tom, &tellers).unwrap();
+        pm.map_principal_to_group(&ctx, &cassy, &tellers).unwrap();
+        pm.map_group_to_role(&ctx, &tellers, &teller, "").unwrap();
+        pm.map_role_to_claim(&ctx, &teller, &ru_deposit, "U.S.", r#"principal.region == "Midwest" && principal.level >= 2"#).unwrap();
+
+        // organization defaults are overridden by attributes of groups and principals
+        pm.attribute_repository.save(&ctx, &Attribute::new("", org.id.as_str(), "Organization", "region", ValueWrapper::String("Midwest".into()))).unwrap();
+        pm.attribute_repository.save(&ctx, &Attribute::new("", tellers.id.as_str(), "Group", "level", ValueWrapper::Int(2))).unwrap();
+        pm.attribute_repository.save(&ctx, &Attribute::new("", cassy.id.as_str(), "Principal", "region", ValueWrapper::String("Northeast".into()))).unwrap();
+
+        let sm = SecurityManager::new(pm);
+        let principal = sm.persistence_manager.get_principal(&ctx, realm.id.as_str(), tom.id.as_str()).unwrap();
+        assert_eq!(Some(&ValueWrapper::Int(2)), principal.attributes.get("level"));
+        assert_eq!(PermissionResponse::Allow, sm.check(&PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::READ, "DepositAccount", "U.S.")).unwrap());
+
+        // attributes of the principal cannot be overridden by the request
+        let mut spoofed = PermissionRequest::new(realm.id.as_str(), cassy.id.as_str(), ActionType::READ, "DepositAccount", "U.S.");
+        spoofed.context.add("principal.region", ValueWrapper::String("Midwest".into()));
+        assert!(sm.check(&spoofed).is_err());
+
+        // changes to attributes are applied to cached principals
+        sm.persistence_manager.attribute_repository.save(&ctx, &Attribute::new("", cassy.id.as_str(), "Principal", "region", ValueWrapper::String("Midwest".into()))).unwrap();
+        assert_eq!(PermissionResponse::Allow, sm.check(&spoofed).unwrap());
+        sm.persistence_manager.attribute_repository.delete(&ctx, "Group", tellers.id.as_str(), "level").unwrap();
+        assert!(sm.check(&spoofed).is_err());
+    }
+
+    #[test]
+    fn test_resource_instance_attributes() {
+        // Initialize context and repository
+        let ctx = SecurityContext::new("0".into(), "0".into());
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+
+        let realm = pm.new_realm_with(&ctx, "expenses").unwrap();
+        let org = pm.new_org_with(&ctx, "acme").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let cassy = pm.new_principal_with(&ctx, &org, "cassy").unwrap();
+        let employee = pm.new_role_with(&ctx, &realm, &org, "Employee").unwrap();
+        let report = pm.new_resource_with(&ctx, &realm, "ExpenseReport").unwrap();
+        let update_report = pm.new_claim_with(&ctx, &realm, &report, "UPDATE").unwrap();
+        let read_report = pm.new_claim_with(&ctx, &realm, &report, "READ").unwrap();
+        pm.map_principal_to_role(&ctx, &tom, &employee).unwrap();
+        pm.map_principal_to_role(&ctx, &cassy, &employee).unwrap();
+        // only the creator may update the report while it's pending
+        pm.map_role_to_claim(&ctx, &employee, &update_report, "", r#"resource.created_by == principal.id && resource.status == "PENDING""#).unwrap();
+        pm.map_role_to_claim(&ctx, &employee, &read_report, "", "!resource.confidential").unwrap();
+
+        let license = pm.new_license_policy(&ctx, &org).unwrap();
+        pm.map_license_policy_to_claim(&ctx, &license, &update_report, "", "").unwrap();
+        pm.map_license_policy_to_claim(&ctx, &license, &read_report, "", "").unwrap();
+
+        // instance with same reference id of another tenant is never used
+        let globex = pm.new_org_with(&ctx, "globex").unwrap();
+        let globex_license = pm.new_license_policy(&ctx, &globex).unwrap();
+        let cassy_ctx = SecurityContext::new(realm.id.as_str(), cassy.id.as_str());
+        pm.resource_instance_repository._create(&cassy_ctx, &ResourceInstance::new("", report.id.as_str(), globex_license.id.as_str(), "", "R-1", "PENDING", None)).unwrap();
+        pm.resource_instance_repository._create(&cassy_ctx, &ResourceInstance::new("", report.id.as_str(), globex_license.id.as_str(), "", "R-3", "PENDING", None)).unwrap();
+
+        let tom_ctx = SecurityContext::new(realm.id.as_str(), tom.id.as_str());
+        let instance = pm.resource_instance_repository._create(&tom_ctx, &ResourceInstance::new("", report.id.as_str(), license.id.as_str(), "", "R-1", "PENDING", None)).unwrap();
+        pm.attribute_repository.save(&ctx, &Attribute::new("", instance.id.as_str(), "ResourceInstance", "confidential", ValueWrapper::Bool(false))).unwrap();
+
+        let sm = SecurityManager::new(pm);
+        let mut tom_update = PermissionRequest::new(realm.id.as_str(), tom.id.as_str(), ActionType::UPDATE, "ExpenseReport", "");
+        tom_update.resource_ref_id = Some("R-1".into());
+        let mut cassy_update = PermissionRequest::new(realm.id.as_str(), cassy.id.as_str(), ActionType::UPDATE, "ExpenseReport", "");
+        cassy_update.resource_ref_id = Some("R-1".into());
+        let mut cassy_read = PermissionRequest::new(realm.id.as_str(), cassy.id.as_str(), ActionType::READ, "ExpenseReport", "");
+        cassy_read.resource_ref_id = Some("R-1".into());
+        assert_eq!(PermissionResponse::Allow, sm.check(&tom_update).unwrap());
+        assert!(sm.check(&cassy_update).is_err());
+        assert_eq!(PermissionResponse::Allow, sm.check(&cassy_read).unwrap());
+
+        // attributes of the instance cannot be overridden by the request
+        cassy_update.context.add("resource.created_by", ValueWrapper::String(tom.id.clone()));
+        assert!(sm.check(&cassy_update).is_err());
+
+        // unknown instance
+        cassy_read.resource_ref_id = Some("R-2".into());
+        assert!(sm.check(&cassy_read).is_err());
+        // instance of another tenant
+        cassy_update.resource_ref_id = Some("R-3".into());
+        assert!(sm.check(&cassy_update).is_err());
+
+        // custom attributes cannot override status of the instance
+        let mut approved = instance.clone();
+        approved.status = "APPROVED".into();
+        sm.persistence_manager.resource_instance_repository.update(&tom_ctx, &approved).unwrap();
+        sm.persistence_manager.attribute_repository.save(&ctx, &Attribute::new("", instance.id.as_str(), "ResourceInstance", "status", ValueWrapper::String("PENDING".into()))).unwrap();
+        assert!(sm.check(&tom_update).is_err());
+    }
+
+    #[test]
+    fn test_typed_context() {
+        // Initialize context and repository
+        let ctx = SecurityContext::new("0".into(), "0".into());
+        let cf = DefaultDataSource::new();
+        let locator = RepositoryLocator::new(&cf);
+        let pm = locator.new_persistence_manager();
+        pm.clear();
+
+        let realm = pm.new_realm_with(&ctx, "shipping").unwrap();
+        let org = pm.new_org_with(&ctx, "acme").unwrap();
+        let tom = pm.new_principal_with(&ctx, &org, "tom").unwrap();
+        let clerk = pm.new_role_with(&ctx, &realm, &org, "Clerk").unwrap();
+        let order = pm.new_resource_with(&ctx, &realm, "Order").unwrap();
+        let ship_order = pm.new_claim_with(&ctx, &realm, &order, "SHIP").unwrap();
+        pm.map_principal_to_role(&ctx, &tom, &clerk).unwrap();
+        pm.map_role_to_claim(&ctx, &clerk, &ship_order, "", r#"order.amount == 250 && contains(regions, "Midwest") && !principal.suspended"#).unwrap();
+        pm.attribute_repository.save(&ctx, &Attribute::new("", tom.id.as_str(), "Principal", "suspended", ValueWrapper::Bool(false))).unwrap();
+
+        let sm = SecurityManager::new(pm);
+        let tom_ctx = SecurityContext::new(realm.id.as_str(), tom.id.as_str());
+        let mut check = PermissionCheck {action: "SHIP".into(), resource: "Order".into(), scope: "".into(), ref_id: None, session_id: None, organization_id: None, context: HashMap::new()};
+        let mut amount = HashMap::new();
+        amount.insert("amount".to_string(), ValueWrapper::Int(250));
+        check.context.insert("order".into(), ValueWrapper::Map(amount));
+        check.context.insert("regions".into(), ValueWrapper::Tuple(vec![ValueWrapper::String("West".into()), ValueWrapper::String("Midwest".into())]));
+        assert_eq!(PermissionResponse::Allow, sm.check(&check.to_request(&tom_ctx)).unwrap());
+
+        check.context.insert("regions".into(), ValueWrapper::Tuple(vec![ValueWrapper::String("West".into())]));
+        assert!(sm.check(&check.to_request(&tom_ctx)).is_err());
+
+        // nested maps cannot override attributes of the principal
+        sm.persistence_manager.attribute_repository.save(&ctx, &Attribute::new("", tom.id.as_str(), "Principal", "suspended", ValueWrapper::Bool(true))).unwrap();
+        check.context.insert("regions".into(), ValueWrapper::Tuple(vec![ValueWrapper::String("Midwest".into())]));
+        let mut principal = HashMap::new();
+        principal.insert("suspended".to_string(), ValueWrapper::Bool(false));
+        check.context.insert("principal".into(), ValueWrapper::Map(principal));
+        assert!(sm.check(&check.to_request(&tom_ctx)).is_err());
+    }
 }
@@ -1,3 +1,4 @@
 pub mod request;
 pub mod response;
+pub mod trace;
 pub mod manager;
@@ -2,6 +2,9 @@
 
 use plexrbac::common::SecurityContext;
 use plexrbac::common::ActionType;
+use plexrbac::common::ValueWrapper;
+use plexrbac::domain::models::PolicyMutation;
+use std::collections::HashMap;
 
 use rocket::outcome::Outcome::*;
 use rocket::request::{self, Request, FromRequest};
@@ -14,6 +17,9 @@ pub struct PermissionRequest {
     pub action: String,
     pub resource_name: String,
     pub resource_scope: String,
+    pub resource_ref_id: Option<String>,
+    pub session_id: Option<String>,
+    pub organization_id: Option<String>,
     pub context: SecurityContext,
 }
 
@@ -24,10 +30,20 @@ impl<'a, 'r> FromRequest<'a, 'r> for PermissionRequest {
             let action: String = req.get_query_value("action").and_then(|r| r.ok()).unwrap_or("".into());
             let resource: String = req.get_query_value("resource").and_then(|r| r.ok()).unwrap_or("".into());
             let scope: String = req.get_query_value("scope").and_then(|r| r.ok()).unwrap_or("".into());
+            let ref_id: Option<String> = req.get_query_value("ref_id").and_then(|r| r.ok());
+            // session can be passed as header or query parameter
+            let session: Option<String> = req.headers().get_one("X-Session").map(|s| s.to_string())
+                .or_else(|| req.get_query_value("session").and_then(|r| r.ok()));
+            // organization context of guest principals can be passed as header or query parameter
+            let org: Option<String> = req.headers().get_one("X-Organization").map(|s| s.to_string())
+                .or_else(|| req.get_query_value("org").and_then(|r| r.ok()));
             let req = PermissionRequest {
                 action: action.clone(),
                 resource_name: resource.clone(),
                 resource_scope: scope.clone(),
+                resource_ref_id: ref_id,
+                session_id: session,
+                organization_id: org,
                 context: ctx,
             };
             Success(req)
@@ -46,6 +62,9 @@ impl PermissionRequest {
             action: action.to_string(),
             resource_name: resource_name.to_string(),
             resource_scope: resource_scope.to_string(),
+            resource_ref_id: None,
+            session_id: None,
+            organization_id: None,
             context: SecurityContext::new(realm_id, principal_id),
         }
     }
@@ -55,16 +74,95 @@ impl PermissionRequest {
             action: action.to_string(),
             resource_name: resource_name.to_string(),
             resource_scope: resource_scope.to_string(),
+            resource_ref_id: None,
+            session_id: None,
+            organization_id: None,
             context: SecurityContext::new(realm_id, principal_id),
         }
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+/// PermissionBatchItem defines action, resource, scope and context properties of a single
+/// check within batch of permission checks for the same principal
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PermissionBatchItem {
+    pub action: String,
+    pub resource: String,
+    #[serde(default)]
+    pub scope: String,
+    #[serde(default)]
+    pub ref_id: Option<String>,
+    #[serde(default)]
+    pub context: HashMap<String, String>,
+}
+
+impl PermissionBatchItem {
+    /// Creates permission request for the item using realm, principal and properties of given context
+    pub fn to_request(&self, ctx: &SecurityContext) -> PermissionRequest {
+        let mut req = PermissionRequest::with(ctx.realm_id.as_str(), ctx.principal_id.as_str(), self.action.as_str(), self.resource.as_str(), self.scope.as_str());
+        req.resource_ref_id = self.ref_id.clone();
+        req.context.properties = ctx.properties.clone();
+        for (k, v) in &self.context {
+            req.context.add(k.as_str(), ValueWrapper::parse(v.clone()));
+        }
+        req
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+/// PermissionCheck defines JSON body of permission check whose context keeps types of JSON
+/// values, i.e., numbers, booleans, lists and nested objects are passed as is to constraints
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PermissionCheck {
+    pub action: String,
+    pub resource: String,
+    #[serde(default)]
+    pub scope: String,
+    #[serde(default)]
+    pub ref_id: Option<String>,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub organization_id: Option<String>,
+    #[serde(default)]
+    pub context: HashMap<String, ValueWrapper>,
+}
+
+impl PermissionCheck {
+    /// Creates permission request using realm, principal and properties of given context
+    pub fn to_request(&self, ctx: &SecurityContext) -> PermissionRequest {
+        let mut req = PermissionRequest::with(ctx.realm_id.as_str(), ctx.principal_id.as_str(), self.action.as_str(), self.resource.as_str(), self.scope.as_str());
+        req.resource_ref_id = self.ref_id.clone();
+        req.session_id = self.session_id.clone();
+        req.organization_id = self.organization_id.clone();
+        req.context.properties = ctx.properties.clone();
+        for (k, v) in &self.context {
+            req.context.add(k.as_str(), v.clone());
+        }
+        req
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+/// PermissionSimulation defines proposed mutations of the policy along with permission checks
+/// that are evaluated before and after the mutations for what-if simulation
+///
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PermissionSimulation {
+    #[serde(default)]
+    pub mutations: Vec<PolicyMutation>,
+    pub requests: Vec<PermissionBatchItem>,
+}
 
 #[cfg(test)]
 mod tests {
-    use plexrbac::security::request::PermissionRequest;
+    extern crate serde_json;
+    use plexrbac::security::request::{PermissionRequest, PermissionBatchItem, PermissionCheck};
     use plexrbac::common::*;
+    use std::collections::HashMap;
 
     #[test]
     fn test_create_new() {
@@ -77,4 +175,32 @@ mod tests {
         let req = PermissionRequest::with("1", "2", "READ", "App", "com.plexobject");
         assert_eq!("READ", req.action);
     }
+
+    #[test]
+    fn test_batch_item_to_request() {
+        let mut item = PermissionBatchItem{action: "READ".into(), resource: "App".into(), scope: "com.plexobject".into(), ref_id: Some("1001".into()), context: HashMap::new()};
+        item.context.insert("amount".into(), "10".into());
+        let req = item.to_request(&SecurityContext::new("1", "2"));
+        assert_eq!("App", req.resource_name);
+        assert_eq!("2", req.context.principal_id);
+        assert_eq!(Some("1001".to_string()), req.resource_ref_id);
+        assert_eq!(Some(&ValueWrapper::Int(10)), req.context.properties.get("amount"));
+    }
+
+    #[test]
+    fn test_check_to_request() {
+        let check: PermissionCheck = serde_json::from_str(r#"{"action":"READ", "resource":"App", "context":{"amount":250, "approved":true, "regions":["Midwest", "West"], "order":{"amount":10.5}}}"#).unwrap();
+        assert_eq!("", check.scope);
+        let mut ctx = SecurityContext::new("1", "2");
+        ctx.add("amount", ValueWrapper::String("1".into()));
+        let req = check.to_request(&ctx);
+        assert_eq!("App", req.resource_name);
+        assert_eq!(None, req.session_id);
+        assert_eq!(Some(&ValueWrapper::Int(250)), req.context.properties.get("amount"));
+        assert_eq!(Some(&ValueWrapper::Bool(true)), req.context.properties.get("approved"));
+        assert_eq!(Some(&ValueWrapper::Tuple(vec![ValueWrapper::String("Midwest".into()), ValueWrapper::String("West".into())])), req.context.properties.get("regions"));
+        let mut order = HashMap::new();
+        order.insert("amount".to_string(), ValueWrapper::Float(10.5));
+        assert_eq!(Some(&ValueWrapper::Map(order)), req.context.properties.get("order"));
+    }
 }
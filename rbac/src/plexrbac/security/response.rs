@@ -4,6 +4,7 @@
 ///
 
 use plexrbac::common::Constants;
+use plexrbac::common::RbacError;
 
 /// PermissionResponse
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -22,3 +23,53 @@ impl PermissionResponse {
     }
 }
 
+/// PermissionBatchResponse defines decision for an item of batch permission check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PermissionBatchResponse {
+    pub action: String,
+    pub resource: String,
+    pub scope: String,
+    pub decision: PermissionResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// PermissionSimulationResponse defines decisions for an item of what-if simulation before and
+/// after applying the proposed mutations
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PermissionSimulationResponse {
+    pub action: String,
+    pub resource: String,
+    pub scope: String,
+    pub before: PermissionResponse,
+    pub after: PermissionResponse,
+    pub changed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after_reason: Option<String>,
+}
+
+impl PermissionSimulationResponse {
+    /// Creates response from results of checking the request before and after the mutations
+    pub fn new(action: &str, resource: &str, scope: &str, before: Result<PermissionResponse, RbacError>, after: Result<PermissionResponse, RbacError>) -> PermissionSimulationResponse {
+        let (before, before_reason) = match before {
+            Ok(resp) => (resp, None),
+            Err(err) => (PermissionResponse::Deny, Some(err.to_string())),
+        };
+        let (after, after_reason) = match after {
+            Ok(resp) => (resp, None),
+            Err(err) => (PermissionResponse::Deny, Some(err.to_string())),
+        };
+        PermissionSimulationResponse {
+            action: action.to_string(),
+            resource: resource.to_string(),
+            scope: scope.to_string(),
+            changed: before != after,
+            before: before,
+            after: after,
+            before_reason: before_reason,
+            after_reason: after_reason,
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//////////////////////////////////////////////////////////////////////////////////////////////
+///
+/// This module defines trace objects that explain how a security check was decided
+///
+
+use plexrbac::domain::models::{ClaimResource, Role};
+use plexrbac::security::request::PermissionRequest;
+use plexrbac::security::response::PermissionResponse;
+
+/// RoleTrace describes how a role was resolved for the principal, e.g. direct, via group or
+/// inherited from child role
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoleTrace {
+    pub role_id: String,
+    pub name: String,
+    pub source: String,
+    pub break_glass: bool,
+}
+
+impl RoleTrace {
+    pub fn new(role: &Role, source: &str) -> RoleTrace {
+        RoleTrace {
+            role_id: role.id.clone(),
+            name: role.name.clone(),
+            source: source.to_string(),
+            break_glass: role.break_glass,
+        }
+    }
+}
+
+/// ClaimOutcome defines result of evaluating a claim against permission request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ClaimOutcome {
+    Matched,
+    ActionMismatch,
+    ConstraintsFalse,
+    EvaluationError(String),
+    Skipped,
+}
+
+/// ClaimTrace describes evaluation of a claim-resource that was considered for the request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClaimTrace {
+    pub claim_id: String,
+    pub resource_name: String,
+    pub action: String,
+    pub effect: String,
+    pub scope: String,
+    pub constraints: String,
+    pub action_matched: bool,
+    pub constraints_result: Option<bool>,
+    pub outcome: ClaimOutcome,
+}
+
+impl ClaimTrace {
+    pub fn new(cr: &ClaimResource) -> ClaimTrace {
+        ClaimTrace {
+            claim_id: cr.claim.id.clone(),
+            resource_name: cr.resource.resource_name.clone(),
+            action: cr.claim.action.clone(),
+            effect: cr.claim.effect(),
+            scope: cr.scope.clone(),
+            constraints: cr.constraints.clone(),
+            action_matched: false,
+            constraints_result: None,
+            outcome: ClaimOutcome::Skipped,
+        }
+    }
+}
+
+/// PermissionTrace defines structured explanation of a security check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PermissionTrace {
+    pub realm_id: String,
+    pub principal_id: String,
+    pub action: String,
+    pub resource_name: String,
+    pub resource_scope: String,
+    pub roles: Vec<RoleTrace>,
+    pub groups: Vec<String>,
+    pub license_matched: bool,
+    pub license_reason: String,
+    pub combining_algorithm: String,
+    pub claims: Vec<ClaimTrace>,
+    pub decision: PermissionResponse,
+    pub reason: String,
+    pub break_glass: bool,
+}
+
+impl PermissionTrace {
+    pub fn new(request: &PermissionRequest) -> PermissionTrace {
+        PermissionTrace {
+            realm_id: request.context.realm_id.clone(),
+            principal_id: request.context.principal_id.clone(),
+            action: request.action.clone(),
+            resource_name: request.resource_name.clone(),
+            resource_scope: request.resource_scope.clone(),
+            roles: vec![],
+            groups: vec![],
+            license_matched: false,
+            license_reason: "".to_string(),
+            combining_algorithm: "".to_string(),
+            claims: vec![],
+            decision: PermissionResponse::Deny,
+            reason: "".to_string(),
+            break_glass: false,
+        }
+    }
+}
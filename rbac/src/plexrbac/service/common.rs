@@ -17,6 +17,8 @@ pub fn error_status(err: RbacError) -> Custom<String> {
     match err {
         RbacError::NotFound(_) => Custom(Status::NotFound, err.to_string()),
         RbacError::Duplicate(_) => Custom(Status::Conflict, err.to_string()),
+        RbacError::CyclicHierarchy(_) => Custom(Status::BadRequest, err.to_string()),
+        RbacError::SodViolation(_) => Custom(Status::Forbidden, err.to_string()),
         _ => {
             let emsg = err.to_string();
             if emsg.contains("UNIQUE constraint") {
@@ -13,10 +13,10 @@ use rocket::http::Status;
 use rocket::response::status::Custom;
 
 use diesel::prelude::*;
-use plexrbac::domain::models::{Organization, Group, Role, Principal, LicensePolicy};
+use plexrbac::domain::models::{Organization, Group, Role, Principal, LicensePolicy, SodRule, Delegation, AssignmentRequest, GuestMembership, Attribute, ResourcePermission, PermissionDiff};
 use plexrbac::persistence::locator::RepositoryLocator;
 use plexrbac::persistence::data_source::PooledDataSource;
-use plexrbac::common::{SecurityContext};
+use plexrbac::common::{Constants, SecurityContext};
 use plexrbac::service::common::{AssociationForm};
 use r2d2::{Pool};
 use diesel::r2d2::ConnectionManager;
@@ -61,9 +61,12 @@ pub fn update_org(ctx: SecurityContext, pool: State<Pool<ConnectionManager<Sqlit
 #[get("/<id>", format = "json")]
 pub fn get_org(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, id: String) -> Result<Json<Organization>, Custom<String>> {
     let ds = PooledDataSource {pool: &*pool};
-    let repo = RepositoryLocator::build_org_repository(&ds);
-    match repo.get(&ctx, id.as_str()) {
-        Some(org) => Ok(Json(org)),
+    let pm = RepositoryLocator::build_persistence_manager(&ds);
+    match pm.org_repository.get(&ctx, id.as_str()) {
+        Some(mut org) => {
+            org.org_chain = pm.get_org_chain(&ctx, id.as_str());
+            Ok(Json(org))
+        },
         None => Err(Custom(Status::NotFound, format!("org with id {} not found", id))),
     }
 }
@@ -107,8 +110,8 @@ pub fn update_group(ctx: SecurityContext, pool: State<Pool<ConnectionManager<Sql
     group.organization_id = org_id;
     group.id = id;
     let ds = PooledDataSource {pool: &*pool};
-    let repo = RepositoryLocator::build_group_repository(&ds);
-    match repo.update(&ctx, &group) {
+    let repo = RepositoryLocator::build_role_roleable_repository(&ds);
+    match repo.update_group(&ctx, &group) {
         Ok(saved) => Ok(Json(saved)),
         Err(err) => Err(super::common::error_status(err)),
     }
@@ -142,8 +145,8 @@ pub fn add_principal_to_group(ctx: SecurityContext, pool: State<Pool<ConnectionM
     if RepositoryLocator::build_group_repository(&ds).get(&ctx, &org_id.as_str(), &group_id.as_str()) == None {
         return Err(Custom(Status::NotFound, format!("group with id {} not found within organization {}", group_id, org_id)));
     }
-    let repo = RepositoryLocator::build_group_principal_repository(&ds);
-    match repo.add_principal_to_group(&ctx, group_id.as_str(), principal_id.as_str()) {
+    let repo = RepositoryLocator::build_role_roleable_repository(&ds);
+    match repo.add_principal_to_group(&ctx, org_id.as_str(), group_id.as_str(), principal_id.as_str()) {
         Ok(size) => Ok(Json(size)),
         Err(err) => Err(super::common::error_status(err)),
     }
@@ -192,8 +195,8 @@ pub fn update_role(ctx: SecurityContext, pool: State<Pool<ConnectionManager<Sqli
     role.organization_id = org_id;
     role.id = id;
     let ds = PooledDataSource {pool: &*pool};
-    let repo = RepositoryLocator::build_role_repository(&ds);
-    match repo.update(&ctx, &role) {
+    let repo = RepositoryLocator::build_role_roleable_repository(&ds);
+    match repo.update_role(&ctx, &role) {
         Ok(saved) => Ok(Json(saved)),
         Err(err) => Err(super::common::error_status(err)),
     }
@@ -220,6 +223,17 @@ pub fn delete_role(ctx: SecurityContext, pool: State<Pool<ConnectionManager<Sqli
 }
 
 
+#[get("/<org_id>/roles/<id>/diff/<other_id>?<realm>", format = "json")]
+pub fn diff_roles(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String, other_id: String, realm: Option<String>) -> Result<Json<PermissionDiff>, Custom<String>> {
+    let realm_id = realm.unwrap_or(ctx.realm_id.clone());
+    let ds = PooledDataSource {pool: &*pool};
+    let pm = RepositoryLocator::build_persistence_manager(&ds);
+    match pm.diff_roles(&ctx, realm_id.as_str(), org_id.as_str(), id.as_str(), other_id.as_str()) {
+        Ok(diff) => Ok(Json(diff)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
 #[put("/<org_id>/roles/<role_id>/principals/<principal_id>")]
 pub fn add_principal_to_role(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, role_id: String, principal_id: String, cc: AssociationForm) -> Result<Json<usize>, Custom<String>> { // Form<>
     let ds = PooledDataSource {pool: &*pool};
@@ -319,6 +333,38 @@ pub fn get_principal(ctx: SecurityContext, pool: State<Pool<ConnectionManager<Sq
     }
 }
 
+#[get("/<org_id>/principals/<id>/permissions?<realm>", format = "json")]
+pub fn get_principal_permissions(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String, realm: Option<String>) -> Result<Json<Vec<ResourcePermission>>, Custom<String>> {
+    let realm_id = realm.unwrap_or(ctx.realm_id.clone());
+    let ds = PooledDataSource {pool: &*pool};
+    let pm = RepositoryLocator::build_persistence_manager(&ds);
+    match pm.get_effective_permissions(&ctx, realm_id.as_str(), id.as_str()) {
+        Some(permissions) => Ok(Json(permissions)),
+        None => Err(Custom(Status::NotFound, format!("principal with id {} not found", id))),
+    }
+}
+
+#[get("/<org_id>/principals/<id>/diff/<other_id>?<realm>", format = "json")]
+pub fn diff_principals(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String, other_id: String, realm: Option<String>) -> Result<Json<PermissionDiff>, Custom<String>> {
+    let realm_id = realm.unwrap_or(ctx.realm_id.clone());
+    let ds = PooledDataSource {pool: &*pool};
+    let pm = RepositoryLocator::build_persistence_manager(&ds);
+    match pm.diff_principals(&ctx, realm_id.as_str(), id.as_str(), other_id.as_str()) {
+        Ok(diff) => Ok(Json(diff)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[get("/<org_id>/principals/<id>/groups", format = "json")]
+pub fn get_principal_groups(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String) -> Result<Json<Vec<Group>>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let pm = RepositoryLocator::build_persistence_manager(&ds);
+    match pm.get_effective_groups(&ctx, id.as_str()) {
+        Some(groups) => Ok(Json(groups)),
+        None => Err(Custom(Status::NotFound, format!("principal with id {} not found", id))),
+    }
+}
+
 #[delete("/<org_id>/principals/<id>", format = "json")]
 pub fn delete_principal(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String) -> Result<Json<usize>, Custom<String>> {
     let ds = PooledDataSource {pool: &*pool};
@@ -381,3 +427,298 @@ pub fn delete_license(ctx: SecurityContext, pool: State<Pool<ConnectionManager<S
         Err(err) => Err(super::common::error_status(err)),
     }
 }
+
+
+///////////////////////////////// SEPARATION OF DUTY APIS //////////////////////////////
+
+#[get("/<org_id>/sod_rules")]
+pub fn get_sod_rules_by_org(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String) -> Json<Vec<SodRule>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_sod_rule_repository(&ds);
+    Json(repo.get_by_org(&ctx, org_id.as_str()))
+}
+
+#[post("/<org_id>/sod_rules", format = "json", data = "<rule>")]
+pub fn create_sod_rule(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, mut rule: Json<SodRule>) -> Result<Json<SodRule>, Custom<String>> {
+    rule.organization_id = org_id;
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_sod_rule_repository(&ds);
+    match repo.create(&ctx, &rule) {
+        Ok(saved) => Ok(Json(saved)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[put("/<org_id>/sod_rules/<id>", format = "json", data = "<rule>")]
+pub fn update_sod_rule(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String, mut rule: Json<SodRule>) -> Result<Json<SodRule>, Custom<String>> {
+    rule.organization_id = org_id;
+    rule.id = id;
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_sod_rule_repository(&ds);
+    match repo.update(&ctx, &rule) {
+        Ok(saved) => Ok(Json(saved)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[get("/<org_id>/sod_rules/<id>", format = "json")]
+pub fn get_sod_rule(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String) -> Result<Json<SodRule>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_sod_rule_repository(&ds);
+    match repo.get(&ctx, org_id.as_str(), id.as_str()) {
+        Some(rule) => Ok(Json(rule)),
+        None => Err(Custom(Status::NotFound, format!("sod-rule with id {} not found", id))),
+    }
+}
+
+#[delete("/<org_id>/sod_rules/<id>", format = "json")]
+pub fn delete_sod_rule(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String) -> Result<Json<usize>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_sod_rule_repository(&ds);
+    match repo.delete(&ctx, org_id.as_str(), id.as_str()) {
+        Ok(count) => Ok(Json(count)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+
+///////////////////////////////// DELEGATION APIS //////////////////////////////
+
+#[get("/<org_id>/delegations")]
+pub fn get_delegations_by_org(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String) -> Json<Vec<Delegation>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_delegation_repository(&ds);
+    Json(repo.get_by_org(&ctx, org_id.as_str()))
+}
+
+#[post("/<org_id>/delegations", format = "json", data = "<delegation>")]
+pub fn create_delegation(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, mut delegation: Json<Delegation>) -> Result<Json<Delegation>, Custom<String>> {
+    delegation.organization_id = org_id;
+    let ds = PooledDataSource {pool: &*pool};
+    let locator = RepositoryLocator::new(&ds);
+    let pm = locator.new_persistence_manager();
+    match pm.delegate(&ctx, &delegation) {
+        Ok(saved) => Ok(Json(saved)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[put("/<org_id>/delegations/<id>", format = "json", data = "<delegation>")]
+pub fn update_delegation(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String, mut delegation: Json<Delegation>) -> Result<Json<Delegation>, Custom<String>> {
+    delegation.organization_id = org_id;
+    delegation.id = id;
+    let ds = PooledDataSource {pool: &*pool};
+    let locator = RepositoryLocator::new(&ds);
+    let pm = locator.new_persistence_manager();
+    match pm.update_delegation(&ctx, &delegation) {
+        Ok(saved) => Ok(Json(saved)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[get("/<org_id>/delegations/<id>", format = "json")]
+pub fn get_delegation(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String) -> Result<Json<Delegation>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_delegation_repository(&ds);
+    match repo.get(&ctx, org_id.as_str(), id.as_str()) {
+        Some(delegation) => Ok(Json(delegation)),
+        None => Err(Custom(Status::NotFound, format!("delegation with id {} not found", id))),
+    }
+}
+
+#[delete("/<org_id>/delegations/<id>", format = "json")]
+pub fn delete_delegation(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String) -> Result<Json<usize>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_delegation_repository(&ds);
+    match repo.delete(&ctx, org_id.as_str(), id.as_str()) {
+        Ok(count) => Ok(Json(count)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+
+///////////////////////////////// ASSIGNMENT REQUEST APIS //////////////////////////////
+
+#[get("/<org_id>/assignment_requests")]
+pub fn get_pending_assignment_requests(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String) -> Json<Vec<AssignmentRequest>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_assignment_request_repository(&ds);
+    Json(repo.get_pending_by_org(&ctx, org_id.as_str()))
+}
+
+#[post("/<org_id>/assignment_requests", format = "json", data = "<request>")]
+pub fn create_assignment_request(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, mut request: Json<AssignmentRequest>) -> Result<Json<AssignmentRequest>, Custom<String>> {
+    request.organization_id = org_id;
+    let ds = PooledDataSource {pool: &*pool};
+    let locator = RepositoryLocator::new(&ds);
+    let pm = locator.new_persistence_manager();
+    match pm.request_assignment(&ctx, &request) {
+        Ok(saved) => Ok(Json(saved)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[get("/<org_id>/assignment_requests/<id>", format = "json")]
+pub fn get_assignment_request(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String) -> Result<Json<AssignmentRequest>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_assignment_request_repository(&ds);
+    match repo.get(&ctx, org_id.as_str(), id.as_str()) {
+        Some(request) => Ok(Json(request)),
+        None => Err(Custom(Status::NotFound, format!("assignment request with id {} not found", id))),
+    }
+}
+
+#[put("/<org_id>/assignment_requests/<id>/approve?<comment>")]
+pub fn approve_assignment_request(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String, comment: Option<String>) -> Result<Json<AssignmentRequest>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let locator = RepositoryLocator::new(&ds);
+    let pm = locator.new_persistence_manager();
+    match pm.approve_assignment(&ctx, ctx.realm_id.as_str(), org_id.as_str(), id.as_str(), comment.unwrap_or_default().as_str()) {
+        Ok(saved) => Ok(Json(saved)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[put("/<org_id>/assignment_requests/<id>/reject?<comment>")]
+pub fn reject_assignment_request(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String, comment: Option<String>) -> Result<Json<AssignmentRequest>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let locator = RepositoryLocator::new(&ds);
+    let pm = locator.new_persistence_manager();
+    match pm.reject_assignment(&ctx, ctx.realm_id.as_str(), org_id.as_str(), id.as_str(), comment.unwrap_or_default().as_str()) {
+        Ok(saved) => Ok(Json(saved)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+///////////////////////////////// GUEST MEMBERSHIP APIS //////////////////////////////
+
+#[get("/<org_id>/guests")]
+pub fn get_guests_by_org(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String) -> Json<Vec<GuestMembership>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_guest_membership_repository(&ds);
+    Json(repo.get_by_org(&ctx, org_id.as_str()))
+}
+
+#[post("/<org_id>/guests", format = "json", data = "<membership>")]
+pub fn create_guest(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, mut membership: Json<GuestMembership>) -> Result<Json<GuestMembership>, Custom<String>> {
+    membership.organization_id = org_id;
+    let ds = PooledDataSource {pool: &*pool};
+    let locator = RepositoryLocator::new(&ds);
+    let pm = locator.new_persistence_manager();
+    match pm.add_guest(&ctx, &membership) {
+        Ok(saved) => Ok(Json(saved)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[get("/<org_id>/guests/<id>", format = "json")]
+pub fn get_guest(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String) -> Result<Json<GuestMembership>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_guest_membership_repository(&ds);
+    match repo.get(&ctx, org_id.as_str(), id.as_str()) {
+        Some(membership) => Ok(Json(membership)),
+        None => Err(Custom(Status::NotFound, format!("guest membership with id {} not found", id))),
+    }
+}
+
+#[delete("/<org_id>/guests/<id>", format = "json")]
+pub fn delete_guest(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String) -> Result<Json<usize>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_guest_membership_repository(&ds);
+    match repo.delete(&ctx, org_id.as_str(), id.as_str()) {
+        Ok(count) => Ok(Json(count)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+///////////////////////////////// ATTRIBUTE APIS //////////////////////////////
+
+#[get("/<org_id>/attributes")]
+pub fn get_org_attributes(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String) -> Json<Vec<Attribute>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_attribute_repository(&ds);
+    Json(repo.get_by_owner(&ctx, Constants::Organization.to_string().as_str(), org_id.as_str()))
+}
+
+#[put("/<org_id>/attributes/<name>", format = "json", data = "<attribute>")]
+pub fn save_org_attribute(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, name: String, mut attribute: Json<Attribute>) -> Result<Json<Attribute>, Custom<String>> {
+    attribute.owner_type = Constants::Organization.to_string();
+    attribute.owner_id = org_id;
+    attribute.name = name;
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_attribute_repository(&ds);
+    match repo.save(&ctx, &attribute) {
+        Ok(saved) => Ok(Json(saved)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[delete("/<org_id>/attributes/<name>", format = "json")]
+pub fn delete_org_attribute(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, name: String) -> Result<Json<usize>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_attribute_repository(&ds);
+    match repo.delete(&ctx, Constants::Organization.to_string().as_str(), org_id.as_str(), name.as_str()) {
+        Ok(count) => Ok(Json(count)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[get("/<org_id>/groups/<id>/attributes")]
+pub fn get_group_attributes(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String) -> Json<Vec<Attribute>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_attribute_repository(&ds);
+    Json(repo.get_by_owner(&ctx, Constants::Group.to_string().as_str(), id.as_str()))
+}
+
+#[put("/<org_id>/groups/<id>/attributes/<name>", format = "json", data = "<attribute>")]
+pub fn save_group_attribute(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String, name: String, mut attribute: Json<Attribute>) -> Result<Json<Attribute>, Custom<String>> {
+    attribute.owner_type = Constants::Group.to_string();
+    attribute.owner_id = id;
+    attribute.name = name;
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_attribute_repository(&ds);
+    match repo.save(&ctx, &attribute) {
+        Ok(saved) => Ok(Json(saved)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[delete("/<org_id>/groups/<id>/attributes/<name>", format = "json")]
+pub fn delete_group_attribute(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String, name: String) -> Result<Json<usize>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_attribute_repository(&ds);
+    match repo.delete(&ctx, Constants::Group.to_string().as_str(), id.as_str(), name.as_str()) {
+        Ok(count) => Ok(Json(count)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[get("/<org_id>/principals/<id>/attributes")]
+pub fn get_principal_attributes(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String) -> Json<Vec<Attribute>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_attribute_repository(&ds);
+    Json(repo.get_by_owner(&ctx, Constants::Principal.to_string().as_str(), id.as_str()))
+}
+
+#[put("/<org_id>/principals/<id>/attributes/<name>", format = "json", data = "<attribute>")]
+pub fn save_principal_attribute(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String, name: String, mut attribute: Json<Attribute>) -> Result<Json<Attribute>, Custom<String>> {
+    attribute.owner_type = Constants::Principal.to_string();
+    attribute.owner_id = id;
+    attribute.name = name;
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_attribute_repository(&ds);
+    match repo.save(&ctx, &attribute) {
+        Ok(saved) => Ok(Json(saved)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[delete("/<org_id>/principals/<id>/attributes/<name>", format = "json")]
+pub fn delete_principal_attribute(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, org_id: String, id: String, name: String) -> Result<Json<usize>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let repo = RepositoryLocator::build_attribute_repository(&ds);
+    match repo.delete(&ctx, Constants::Principal.to_string().as_str(), id.as_str(), name.as_str()) {
+        Ok(count) => Ok(Json(count)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
@@ -13,10 +13,10 @@ use rocket::http::Status;
 use rocket::response::status::Custom;
 
 use diesel::prelude::*;
-use plexrbac::domain::models::{SecurityRealm, Resource, ResourceInstance, ResourceQuota, Claim};
+use plexrbac::domain::models::{SecurityRealm, Resource, ResourceInstance, ResourceQuota, Claim, PrincipalAccess, Attribute};
 use plexrbac::persistence::locator::RepositoryLocator;
 use plexrbac::persistence::data_source::PooledDataSource;
-use plexrbac::common::{SecurityContext};
+use plexrbac::common::{Constants, SecurityContext};
 use plexrbac::service::common::{AssociationForm};
 use r2d2::{Pool};
 use diesel::r2d2::ConnectionManager;
@@ -122,6 +122,13 @@ pub fn get_resource(ctx: SecurityContext, pool: State<Pool<ConnectionManager<Sql
     }
 }
 
+#[get("/<realm_id>/resources/<id>/access?<action>&<scope>", format = "json")]
+pub fn get_resource_access(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, realm_id: String, id: String, action: Option<String>, scope: Option<String>) -> Json<Vec<PrincipalAccess>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let pm = RepositoryLocator::build_persistence_manager(&ds);
+    Json(pm.get_principals_by_access(&ctx, realm_id.as_str(), id.as_str(), action.unwrap_or("".into()).as_str(), scope.unwrap_or("".into()).as_str()))
+}
+
 #[delete("/<realm_id>/resources/<id>", format = "json")]
 pub fn delete_resource(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, realm_id: String, id: String) -> Result<Json<usize>, Custom<String>> {
     let ds = PooledDataSource {pool: &*pool};
@@ -204,6 +211,52 @@ pub fn delete_instance(ctx: SecurityContext, pool: State<Pool<ConnectionManager<
     }
 }
 
+#[get("/<realm_id>/resources/<resource_id>/instances/<id>/attributes")]
+pub fn get_instance_attributes(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, realm_id: String, resource_id: String, id: String) -> Json<Vec<Attribute>> {
+    let ds = PooledDataSource {pool: &*pool};
+    // resource-id must exist within the realm
+    if RepositoryLocator::build_resource_repository(&ds).get(&ctx, &realm_id.as_str(), &resource_id.as_str()) == None {
+        return Json(vec![]);
+    }
+    let repo = RepositoryLocator::build_attribute_repository(&ds);
+    Json(repo.get_by_owner(&ctx, Constants::ResourceInstance.to_string().as_str(), id.as_str()))
+}
+
+#[put("/<realm_id>/resources/<resource_id>/instances/<id>/attributes/<name>", format = "json", data = "<attribute>")]
+pub fn save_instance_attribute(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, realm_id: String, resource_id: String, id: String, name: String, mut attribute: Json<Attribute>) -> Result<Json<Attribute>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    // resource-id must exist within the realm
+    if RepositoryLocator::build_resource_repository(&ds).get(&ctx, &realm_id.as_str(), &resource_id.as_str()) == None {
+        return Err(Custom(Status::NotFound, format!("resource with id {} not found within relam {}", resource_id, realm_id)));
+    }
+    match RepositoryLocator::build_resource_instance_repository(&ds).get(&ctx, &id.as_str()) {
+        Some(ref instance) if instance.resource_id == resource_id => (),
+        _ => return Err(Custom(Status::NotFound, format!("instance with id {} not found", id))),
+    }
+    attribute.owner_type = Constants::ResourceInstance.to_string();
+    attribute.owner_id = id;
+    attribute.name = name;
+    let repo = RepositoryLocator::build_attribute_repository(&ds);
+    match repo.save(&ctx, &attribute) {
+        Ok(saved) => Ok(Json(saved)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[delete("/<realm_id>/resources/<resource_id>/instances/<id>/attributes/<name>", format = "json")]
+pub fn delete_instance_attribute(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, realm_id: String, resource_id: String, id: String, name: String) -> Result<Json<usize>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    // resource-id must exist within the realm
+    if RepositoryLocator::build_resource_repository(&ds).get(&ctx, &realm_id.as_str(), &resource_id.as_str()) == None {
+        return Err(Custom(Status::NotFound, format!("resource with id {} not found within relam {}", resource_id, realm_id)));
+    }
+    let repo = RepositoryLocator::build_attribute_repository(&ds);
+    match repo.delete(&ctx, Constants::ResourceInstance.to_string().as_str(), id.as_str(), name.as_str()) {
+        Ok(count) => Ok(Json(count)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
 ///////////////////////////////// RESOURCE QUOTA APIS //////////////////////////////
 
 #[get("/<realm_id>/resources/<resource_id>/quota")]
@@ -386,6 +439,34 @@ pub fn delete_principal_from_claim(ctx: SecurityContext, pool: State<Pool<Connec
     }
 }
 
+#[put("/<realm_id>/resources/<resource_id>/claims/<claim_id>/guests/<membership_id>", format = "json")]
+pub fn add_guest_to_claim(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, realm_id: String, resource_id: String, claim_id: String, membership_id: String, cc: AssociationForm) -> Result<Json<usize>, Custom<String>> { // Form<>
+    let ds = PooledDataSource {pool: &*pool};
+    // resource-id must exist within the realm
+    if RepositoryLocator::build_resource_repository(&ds).get(&ctx, &realm_id.as_str(), &resource_id.as_str()) == None {
+        return Err(Custom(Status::NotFound, format!("resource with id {} not found within relam {}", resource_id, realm_id)));
+    }
+    let repo = RepositoryLocator::build_claim_claimable_repository(&ds);
+    match repo.add_guest_membership_to_claim(&ctx, membership_id.as_str(), claim_id.as_str(), cc.scope.as_str(), cc.constraints.as_str(), cc.effective_at(), cc.expired_at()) {
+        Ok(size) => Ok(Json(size)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[delete("/<realm_id>/resources/<resource_id>/claims/<claim_id>/guests/<membership_id>", format = "json")]
+pub fn delete_guest_from_claim(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, realm_id: String, resource_id: String, claim_id: String, membership_id: String) -> Result<Json<usize>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    // resource-id must exist within the realm
+    if RepositoryLocator::build_resource_repository(&ds).get(&ctx, &realm_id.as_str(), &resource_id.as_str()) == None {
+        return Err(Custom(Status::NotFound, format!("resource with id {} not found within relam {}", resource_id, realm_id)));
+    }
+    let repo = RepositoryLocator::build_claim_claimable_repository(&ds);
+    match repo.delete_guest_membership_from_claim(&ctx, membership_id.as_str(), claim_id.as_str()) {
+        Ok(size) => Ok(Json(size)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
 #[put("/<realm_id>/resources/<resource_id>/claims/<claim_id>/roles/<role_id>", format = "json")]
 pub fn add_role_to_claim(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, realm_id: String, resource_id: String, claim_id: String, role_id: String, cc: AssociationForm) -> Result<Json<usize>, Custom<String>> { // Form<>
     let ds = PooledDataSource {pool: &*pool};
@@ -1,10 +1,13 @@
 //#![crate_name = "doc"]
 
-use plexrbac::security::request::{PermissionRequest};
-use plexrbac::security::response::{PermissionResponse};
+use plexrbac::security::request::{PermissionRequest, PermissionBatchItem, PermissionCheck, PermissionSimulation};
+use plexrbac::security::response::{PermissionResponse, PermissionBatchResponse, PermissionSimulationResponse};
+use plexrbac::security::trace::{PermissionTrace};
 use plexrbac::security::manager::{SecurityManager};
 use plexrbac::persistence::locator::RepositoryLocator;
 use plexrbac::persistence::data_source::PooledDataSource;
+use plexrbac::persistence::cache::{CLAIM_CACHE, CacheStats};
+use plexrbac::domain::models::{Session, BreakGlass};
 
 use rocket::{State};
 use rocket_contrib::json::{Json};
@@ -37,3 +40,153 @@ pub fn check(req: PermissionRequest, pool: State<Pool<ConnectionManager<SqliteCo
     }
 }
 
+///////////////////////////////// PERMISSION CHECK WITH JSON CONTEXT //////////////////////////////
+///
+#[post("/check", format = "json", data = "<check>")]
+pub fn check_json(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, check: Json<PermissionCheck>) -> Result<Json<PermissionResponse>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let locator = RepositoryLocator::new(&ds);
+    let pm = locator.new_persistence_manager();
+    let sm = SecurityManager::new(pm);
+    let req = check.to_request(&ctx);
+    match sm.check(&req) {
+        Ok(resp) => Ok(Json(resp)),
+        Err(err) => Err(Custom(Status::Unauthorized, format!("Failed to authorize {:?} - {:?}", req, err)))
+    }
+}
+
+///////////////////////////////// PERMISSION EXPLAIN //////////////////////////////
+///
+#[get("/explain")]
+pub fn explain(req: PermissionRequest, pool: State<Pool<ConnectionManager<SqliteConnection>>>) -> Result<Json<PermissionTrace>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let locator = RepositoryLocator::new(&ds);
+    let pm = locator.new_persistence_manager();
+    let sm = SecurityManager::new(pm);
+    match sm.explain(&req) {
+        Ok(trace) => Ok(Json(trace)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+///////////////////////////////// BATCH PERMISSION CHECK //////////////////////////////
+///
+#[post("/batch?<session>&<org>", format = "json", data = "<items>")]
+pub fn check_batch(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, session: Option<String>, org: Option<String>, items: Json<Vec<PermissionBatchItem>>) -> Result<Json<Vec<PermissionBatchResponse>>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let locator = RepositoryLocator::new(&ds);
+    let pm = locator.new_persistence_manager();
+    let sm = SecurityManager::new(pm);
+    let requests = items.iter().map(|item| {
+        let mut req = item.to_request(&ctx);
+        req.session_id = session.clone();
+        req.organization_id = org.clone();
+        req
+    }).collect::<Vec<PermissionRequest>>();
+    let mut responses = vec![];
+    for (item, result) in items.iter().zip(sm.check_many(&requests)) {
+        let (decision, reason) = match result {
+            Ok(resp) => (resp, None),
+            Err(err) => (PermissionResponse::Deny, Some(err.to_string())),
+        };
+        responses.push(PermissionBatchResponse {
+            action: item.action.clone(),
+            resource: item.resource.clone(),
+            scope: item.scope.clone(),
+            decision: decision,
+            reason: reason,
+        });
+    }
+    Ok(Json(responses))
+}
+
+///////////////////////////////// WHAT-IF SIMULATION //////////////////////////////
+///
+#[post("/simulate", format = "json", data = "<simulation>")]
+pub fn simulate(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, simulation: Json<PermissionSimulation>) -> Json<Vec<PermissionSimulationResponse>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let locator = RepositoryLocator::new(&ds);
+    let pm = locator.new_persistence_manager();
+    let sm = SecurityManager::new(pm);
+    // requests are always simulated for the principal of the context
+    let requests = simulation.requests.iter().map(|item| item.to_request(&ctx)).collect::<Vec<PermissionRequest>>();
+    Json(sm.simulate(&simulation.mutations, &requests))
+}
+
+///////////////////////////////// SESSION APIS //////////////////////////////
+///
+#[post("/sessions", format = "json", data = "<session>")]
+pub fn open_session(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, session: Json<Session>) -> Result<Json<Session>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let locator = RepositoryLocator::new(&ds);
+    let pm = locator.new_persistence_manager();
+    match pm.open_session(&ctx, ctx.realm_id.as_str(), ctx.principal_id.as_str(), session.role_ids.clone(), session.expired_at.clone()) {
+        Ok(saved) => Ok(Json(saved)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[get("/sessions/<id>")]
+pub fn get_session(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, id: String) -> Result<Json<Session>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let locator = RepositoryLocator::new(&ds);
+    let pm = locator.new_persistence_manager();
+    match pm.get_session(&ctx, id.as_str()) {
+        Ok(session) => Ok(Json(session)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[put("/sessions/<id>/roles/<role_id>")]
+pub fn activate_session_role(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, id: String, role_id: String) -> Result<Json<Session>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let locator = RepositoryLocator::new(&ds);
+    let pm = locator.new_persistence_manager();
+    match pm.activate_session_roles(&ctx, id.as_str(), vec![role_id]) {
+        Ok(saved) => Ok(Json(saved)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[delete("/sessions/<id>/roles/<role_id>")]
+pub fn deactivate_session_role(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, id: String, role_id: String) -> Result<Json<Session>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let locator = RepositoryLocator::new(&ds);
+    let pm = locator.new_persistence_manager();
+    match pm.deactivate_session_roles(&ctx, id.as_str(), vec![role_id]) {
+        Ok(saved) => Ok(Json(saved)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+#[delete("/sessions/<id>")]
+pub fn close_session(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, id: String) -> Result<Json<usize>, Custom<String>> {
+    let ds = PooledDataSource {pool: &*pool};
+    let locator = RepositoryLocator::new(&ds);
+    let pm = locator.new_persistence_manager();
+    match pm.close_session(&ctx, id.as_str()) {
+        Ok(count) => Ok(Json(count)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+///////////////////////////////// BREAK-GLASS ACCESS //////////////////////////////
+///
+#[post("/break-glass", format = "json", data = "<access>")]
+pub fn break_glass(ctx: SecurityContext, pool: State<Pool<ConnectionManager<SqliteConnection>>>, mut access: Json<BreakGlass>) -> Result<Json<BreakGlass>, Custom<String>> {
+    access.principal_id = ctx.principal_id.clone();
+    let ds = PooledDataSource {pool: &*pool};
+    let locator = RepositoryLocator::new(&ds);
+    let pm = locator.new_persistence_manager();
+    match pm.break_glass(&ctx, ctx.realm_id.as_str(), &access) {
+        Ok(saved) => Ok(Json(saved)),
+        Err(err) => Err(super::common::error_status(err)),
+    }
+}
+
+///////////////////////////////// CLAIM CACHE STATS //////////////////////////////
+///
+#[get("/cache")]
+pub fn cache_stats(_ctx: SecurityContext) -> Json<CacheStats> {
+    Json(CLAIM_CACHE.stats())
+}
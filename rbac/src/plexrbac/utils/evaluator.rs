@@ -27,6 +27,28 @@ pub fn evaluate(expr: &str, properties: &HashMap<String, ValueWrapper>) -> Resul
     }
 }
 
+/// Combines constraints so that both must be true, empty constraints are treated as true
+pub fn all_of(a: &str, b: &str) -> String {
+    if a.len() == 0 || a == b {
+        b.to_string()
+    } else if b.len() == 0 {
+        a.to_string()
+    } else {
+        format!("({}) && ({})", a, b)
+    }
+}
+
+/// Combines constraints so that either can be true, empty constraints are treated as true
+pub fn any_of(a: &str, b: &str) -> String {
+    if a.len() == 0 || b.len() == 0 {
+        "".to_string()
+    } else if a == b {
+        a.to_string()
+    } else {
+        format!("({}) || ({})", a, b)
+    }
+}
+
 fn add_builtin(ctx: &mut HashMapContext) -> Result<bool, evalexpr::EvalexprError> { 
     if let Err(err) = ctx.set_function("geo_distance_km".to_string(),
          Function::new(
@@ -70,6 +92,20 @@ fn add_builtin(ctx: &mut HashMapContext) -> Result<bool, evalexpr::EvalexprError
         ) {
         return Err(err)
     }
+    if let Err(err) = ctx.set_function("contains".to_string(),
+         Function::new(
+             Some(2),
+             Box::new(|args| {
+                 if let Value::Tuple(values) = args[0].clone() {
+                    Ok(Value::Boolean(values.contains(&args[1])))
+                 } else {
+                     Err(EvalexprError::expected_tuple(args[0].clone()))
+                 }
+             }),
+         ),
+        ) {
+        return Err(err)
+    }
     if let Err(err) = ctx.set_function("current_year".to_string(),
          Function::new(
              None,
@@ -165,19 +201,42 @@ fn add_builtin(ctx: &mut HashMapContext) -> Result<bool, evalexpr::EvalexprError
 
 fn add_context_func(ctx: &mut HashMapContext, name: &str, value: ValueWrapper) -> Result<bool, evalexpr::EvalexprError> { 
     match value {
-        ValueWrapper::Bool(b) => ctx.set_value(name.to_string(), Value::Boolean(b))?,
-        ValueWrapper::String(ref s) => ctx.set_value(name.to_string(), Value::String(s.clone()))?,
-        ValueWrapper::Int(i) => ctx.set_value(name.to_string(), Value::Int(i))?,
-        ValueWrapper::Float(f) => ctx.set_value(name.to_string(), Value::Float(f))?,
+        // nested maps are flattened into dotted names, e.g. order.amount
+        ValueWrapper::Map(map) => {
+            for (k, v) in map {
+                add_context_func(ctx, format!("{}.{}", name, k).as_str(), v)?;
+            }
+        },
+        _ => ctx.set_value(name.to_string(), to_value(name, &value)?)?,
     }
     Ok(true)
 }
 
+// maps within lists cannot be flattened into dotted names so they are rejected rather than
+// dropped, which would evaluate constraints against different data than what was passed
+fn to_value(name: &str, value: &ValueWrapper) -> Result<Value, evalexpr::EvalexprError> {
+    match value {
+        ValueWrapper::Bool(b) => Ok(Value::Boolean(*b)),
+        ValueWrapper::String(ref s) => Ok(Value::String(s.clone())),
+        ValueWrapper::Int(i) => Ok(Value::Int(*i)),
+        ValueWrapper::Float(f) => Ok(Value::Float(*f)),
+        ValueWrapper::Tuple(values) => {
+            let mut tuple = vec![];
+            for v in values {
+                tuple.push(to_value(name, v)?);
+            }
+            Ok(Value::Tuple(tuple))
+        },
+        ValueWrapper::Map(_) => Err(EvalexprError::CustomMessage(format!("Objects are not supported within list {} -- {:?}", name, value))),
+    }
+}
+
 
 
 #[cfg(test)]
 mod tests {
     use plexrbac::utils::evaluator::*;
+    use plexrbac::common::ValueWrapper;
     use std::collections::HashMap;
 
     #[test]
@@ -185,6 +244,39 @@ mod tests {
         let properties = HashMap::new();
         assert_eq!(Ok(true), evaluate("2 == 2", &properties));
     }
+
+    #[test]
+    fn test_combine_constraints() {
+        assert_eq!("", all_of("", ""));
+        assert_eq!("a > 1", all_of("", "a > 1"));
+        assert_eq!("a > 1", all_of("a > 1", ""));
+        assert_eq!("(a > 1) && (b < 2)", all_of("a > 1", "b < 2"));
+        assert_eq!("", any_of("a > 1", ""));
+        assert_eq!("a > 1", any_of("a > 1", "a > 1"));
+        assert_eq!("(a > 1) || (b < 2)", any_of("a > 1", "b < 2"));
+        let mut properties = HashMap::new();
+        properties.insert("a".to_string(), ValueWrapper::Int(2));
+        properties.insert("b".to_string(), ValueWrapper::Int(3));
+        assert_eq!(Ok(false), evaluate(all_of("a > 1", "b < 2").as_str(), &properties));
+        assert_eq!(Ok(true), evaluate(any_of("a > 1", "b < 2").as_str(), &properties));
+    }
+
+    #[test]
+    fn test_tuple_and_map() {
+        let mut order = HashMap::new();
+        order.insert("lines".to_string(), ValueWrapper::Tuple(vec![ValueWrapper::Map(HashMap::new())]));
+        let mut properties = HashMap::new();
+        properties.insert("ids".to_string(), ValueWrapper::Tuple(vec![ValueWrapper::Int(1), ValueWrapper::Int(2)]));
+        assert_eq!(Ok(true), evaluate("contains(ids, 2) && !contains(ids, 3)", &properties));
+        assert!(evaluate("contains(1, 2)", &properties).is_err());
+        // maps within lists cannot be represented so they are rejected instead of being dropped
+        order.insert("amount".to_string(), ValueWrapper::Int(250));
+        properties.insert("order".to_string(), ValueWrapper::Map(order.clone()));
+        assert!(evaluate("order.amount > 100", &properties).is_err());
+        order.remove("lines");
+        properties.insert("order".to_string(), ValueWrapper::Map(order));
+        assert_eq!(Ok(true), evaluate("order.amount > 100", &properties));
+    }
 }
 
 
@@ -22,9 +22,34 @@ pub fn regex_find(rx: &str, s: &str) -> bool {
     }
 }
 
+/// Matches text against glob pattern, where * matches any sequence of characters
+pub fn glob_match(pattern: &str, s: &str) -> bool {
+    let p = pattern.chars().collect::<Vec<char>>();
+    let t = s.chars().collect::<Vec<char>>();
+    let (mut pi, mut ti) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if pi < p.len() && p[pi] == t[ti] {
+            pi += 1;
+            ti += 1;
+        } else if let Some((star_pi, star_ti)) = star {
+            // let the last star consume one more character
+            pi = star_pi + 1;
+            ti = star_ti + 1;
+            star = Some((star_pi, star_ti + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
 #[cfg(test)]
 mod tests {
-    use plexrbac::utils::text::{regex_find, regex_match};
+    use plexrbac::utils::text::{regex_find, regex_match, glob_match};
 
     #[test]
     fn test_regex_find() {
@@ -35,4 +60,13 @@ mod tests {
     fn test_regex_match() {
         assert!(regex_match(r"^\d{4}-\d{2}-\d{2}$", "2014-01-01"));
     }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("US/*", "US/Midwest/Chicago"));
+        assert!(glob_match("*/Chicago", "US/Midwest/Chicago"));
+        assert!(!glob_match("US/*/Detroit", "US/Midwest/Chicago"));
+        assert!(!glob_match("US", "USA"));
+    }
 }